@@ -13,6 +13,7 @@ extern crate std;
 #[allow(unused)]
 mod common;
 
+use card_terminal_adapter::text_page::*;
 use card_terminal_adapter::types::*;
 use card_terminal_adapter::*;
 
@@ -25,6 +26,10 @@ impl CardTerminalConst for KiccEd785Plug {
     fn is_nda() -> bool {
         false
     }
+
+    fn support_payment_cancel() -> bool {
+        false
+    }
 }
 
 impl CardTerminalRxParse for KiccEd785Plug {
@@ -114,6 +119,20 @@ impl CardTerminalTxGen for KiccEd785Plug {
         &buffer[0..0]
     }
 
+    fn request_date_time<'a>(&self, buffer: &'a mut [u8]) -> &'a [u8] {
+        // implement me for actual usage
+        &buffer[0..0]
+    }
+
+    fn request_payment_cancel<'a>(
+        &self,
+        buffer: &'a mut [u8],
+        _income: RawU24IncomeArcade,
+    ) -> &'a [u8] {
+        // implement me for actual usage
+        &buffer[0..0]
+    }
+
     fn display_rom<'a>(
         &self,
         buffer: &'a mut [u8],
@@ -128,25 +147,36 @@ impl CardTerminalTxGen for KiccEd785Plug {
         &buffer[0..0]
     }
 
-    fn display_hw_info<'a, 'b>(
+    fn display_hw_info<'a>(&self, buffer: &'a mut [u8], _info: &HwInfo) -> &'a [u8] {
+        // implement me for actual usage
+        &buffer[0..0]
+    }
+
+    fn display_warning<'a>(
         &self,
         buffer: &'a mut [u8],
-        _model_version: &'b [u8; FW_VER_LEN],
-        _serial_number: &'b [u8; DEV_SN_LEN],
-        _terminal_id: &[u8; TID_LEN],
-        _hw_boot_cnt: u32,
-        _uptime_minutes: u32,
+        _warn_kind: CardTerminalDisplayWarning,
     ) -> &'a [u8] {
         // implement me for actual usage
         &buffer[0..0]
     }
 
-    fn display_warning<'a>(
+    fn push_payout_report<'a>(&self, buffer: &'a mut [u8], _report: &PayoutReport) -> &'a [u8] {
+        // implement me for actual usage
+        &buffer[0..0]
+    }
+
+    fn display_inhibit_reason<'a>(
         &self,
         buffer: &'a mut [u8],
-        _warn_kind: CardTerminalDisplayWarning,
+        _reason: &RawPlayersInhibitReason,
     ) -> &'a [u8] {
         // implement me for actual usage
         &buffer[0..0]
     }
+
+    fn display_text_page<'a>(&self, buffer: &'a mut [u8], _page: &TextPage) -> &'a [u8] {
+        // implement me for actual usage
+        &buffer[0..0]
+    }
 }
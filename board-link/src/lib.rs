@@ -0,0 +1,371 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Board-to-board link protocol, chaining two BillMock boards behind single card terminal.
+//! Primary board owns the card terminal and forwards payment of player 3 and 4 to secondary board,
+//! secondary board reports its inhibit status and counters back to primary board.
+//! The protocol is independent from hardware, thus it can be tested on host with loopback.
+//!
+//! +-------+-------+-------+--------------------+-------+
+//! | STX   | LEN   | CMD   | PAYLOAD            | CHK   |
+//! +-------+-------+-------+--------------------+-------+
+//! | 0x02  | 1 + n | 1byte | n bytes, LE        | 1byte |
+//! +-------+-------+-------+--------------------+-------+
+//! - `CHK` is two's complement of byte sum of `LEN`, `CMD` and `PAYLOAD`.
+
+#![no_std]
+
+#[cfg(test)] // for the loopback test code
+extern crate std;
+
+pub const LINK_STX: u8 = 0x02;
+/// Maximum length of payload
+pub const LINK_PAYLOAD_MAX: usize = 16;
+/// Maximum length of whole frame, STX + LEN + CMD + PAYLOAD + CHK
+pub const LINK_FRAME_MAX: usize = LINK_PAYLOAD_MAX + 4;
+/// Receive stream can hold few frames
+pub const LINK_STREAM_SIZE: usize = LINK_FRAME_MAX * 4;
+
+const LINK_CMD_ACK: u8 = 0x06;
+const LINK_CMD_NACK: u8 = 0x15;
+const LINK_CMD_FORWARD_PAYMENT: u8 = 0x10;
+const LINK_CMD_INHIBIT: u8 = 0x20;
+const LINK_CMD_REQUEST_COUNTERS: u8 = 0x30;
+const LINK_CMD_COUNTERS: u8 = 0x31;
+
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub enum LinkError {
+    /// Frame is not completed yet, or given buffer is too short to generate
+    BadLength,
+    /// Invalid Frame, STX is missing or length is out of spec
+    InvalidFrame,
+    /// Given checksum is not matched
+    BadChecksum,
+    /// Frame is correct, but command or its payload length is unknown
+    UnsupportedCmd,
+}
+
+/// Payment forwarded from primary board, player is local player of secondary board
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub struct LinkPayment {
+    /// Increased by primary board for every forward, answered by `Ack` or `Nack`
+    pub seq: u8,
+    /// 1 or 2, player of secondary board
+    pub player: u8,
+    pub pulse_count: u16,
+    pub pulse_duration: u16,
+}
+
+/// Card and coin counters of secondary board, index 0 is player 1
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq, Default)]
+pub struct LinkCounters {
+    pub card: [u32; 2],
+    pub coin: [u32; 2],
+}
+
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub enum LinkCmd {
+    /// Secondary -> Primary, forwarded payment of the sequence is credited
+    Ack(u8),
+    /// Secondary -> Primary, forwarded payment of the sequence is refused
+    Nack(u8),
+    /// Primary -> Secondary, payment for player of secondary board
+    ForwardPayment(LinkPayment),
+    /// Secondary -> Primary, inhibit status bit mask, b0 is player 1
+    Inhibit(u8),
+    /// Primary -> Secondary, request counters
+    RequestCounters,
+    /// Secondary -> Primary, counters of secondary board
+    Counters(LinkCounters),
+}
+
+fn checksum(raw: &[u8]) -> u8 {
+    raw.iter()
+        .fold(0u8, |acc, x| acc.wrapping_add(*x))
+        .wrapping_neg()
+}
+
+/// Generate frame of the command on given buffer
+pub fn encode<'a>(cmd: &LinkCmd, buf: &'a mut [u8]) -> Result<&'a [u8], LinkError> {
+    let mut payload = [0u8; LINK_PAYLOAD_MAX];
+
+    let (code, len) = match cmd {
+        LinkCmd::Ack(seq) => {
+            payload[0] = *seq;
+            (LINK_CMD_ACK, 1)
+        }
+        LinkCmd::Nack(seq) => {
+            payload[0] = *seq;
+            (LINK_CMD_NACK, 1)
+        }
+        LinkCmd::ForwardPayment(x) => {
+            payload[0] = x.seq;
+            payload[1] = x.player;
+            payload[2..4].copy_from_slice(&x.pulse_count.to_le_bytes());
+            payload[4..6].copy_from_slice(&x.pulse_duration.to_le_bytes());
+            (LINK_CMD_FORWARD_PAYMENT, 6)
+        }
+        LinkCmd::Inhibit(x) => {
+            payload[0] = *x;
+            (LINK_CMD_INHIBIT, 1)
+        }
+        LinkCmd::RequestCounters => (LINK_CMD_REQUEST_COUNTERS, 0),
+        LinkCmd::Counters(x) => {
+            for (idx, value) in x.card.iter().chain(x.coin.iter()).enumerate() {
+                payload[idx * 4..(idx + 1) * 4].copy_from_slice(&value.to_le_bytes());
+            }
+            (LINK_CMD_COUNTERS, 16)
+        }
+    };
+
+    let frame_len = len + 4;
+    if buf.len() < frame_len {
+        return Err(LinkError::BadLength);
+    }
+
+    buf[0] = LINK_STX;
+    buf[1] = (len + 1) as u8;
+    buf[2] = code;
+    buf[3..3 + len].copy_from_slice(&payload[..len]);
+    buf[3 + len] = checksum(&buf[1..3 + len]);
+
+    Ok(&buf[..frame_len])
+}
+
+fn u16_at(raw: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([raw[offset], raw[offset + 1]])
+}
+
+fn u32_at(raw: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        raw[offset],
+        raw[offset + 1],
+        raw[offset + 2],
+        raw[offset + 3],
+    ])
+}
+
+/// Parse first frame of given bytes, leading bytes before STX are skipped.
+/// Returns the command and consumed length including skipped bytes.
+pub fn decode(raw: &[u8]) -> Result<(LinkCmd, usize), LinkError> {
+    let Some(start) = raw.iter().position(|x| *x == LINK_STX) else {
+        return Err(LinkError::InvalidFrame);
+    };
+    let frame = &raw[start..];
+
+    let Some(&len) = frame.get(1) else {
+        return Err(LinkError::BadLength);
+    };
+    let len = len as usize;
+    if len == 0 || (LINK_PAYLOAD_MAX + 1) < len {
+        return Err(LinkError::InvalidFrame);
+    }
+
+    let end = len + 3;
+    if frame.len() < end {
+        return Err(LinkError::BadLength);
+    }
+
+    if checksum(&frame[1..end - 1]) != frame[end - 1] {
+        return Err(LinkError::BadChecksum);
+    }
+
+    let payload = &frame[3..end - 1];
+    let cmd = match (frame[2], payload.len()) {
+        (LINK_CMD_ACK, 1) => LinkCmd::Ack(payload[0]),
+        (LINK_CMD_NACK, 1) => LinkCmd::Nack(payload[0]),
+        (LINK_CMD_FORWARD_PAYMENT, 6) => LinkCmd::ForwardPayment(LinkPayment {
+            seq: payload[0],
+            player: payload[1],
+            pulse_count: u16_at(payload, 2),
+            pulse_duration: u16_at(payload, 4),
+        }),
+        (LINK_CMD_INHIBIT, 1) => LinkCmd::Inhibit(payload[0]),
+        (LINK_CMD_REQUEST_COUNTERS, 0) => LinkCmd::RequestCounters,
+        (LINK_CMD_COUNTERS, 16) => LinkCmd::Counters(LinkCounters {
+            card: [u32_at(payload, 0), u32_at(payload, 4)],
+            coin: [u32_at(payload, 8), u32_at(payload, 12)],
+        }),
+        _ => return Err(LinkError::UnsupportedCmd),
+    };
+
+    Ok((cmd, start + end))
+}
+
+/// Receive stream that reassembles frames from fragmented bytes
+pub struct LinkStream {
+    buf: [u8; LINK_STREAM_SIZE],
+    len: usize,
+}
+
+impl LinkStream {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0u8; LINK_STREAM_SIZE],
+            len: 0,
+        }
+    }
+
+    /// Append received bytes, stale bytes are discarded when the stream is overflowed.
+    pub fn push(&mut self, raw: &[u8]) {
+        if LINK_STREAM_SIZE < self.len + raw.len() {
+            self.len = 0;
+        }
+
+        let raw = &raw[raw.len().saturating_sub(LINK_STREAM_SIZE)..];
+        self.buf[self.len..self.len + raw.len()].copy_from_slice(raw);
+        self.len += raw.len();
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    fn consume(&mut self, len: usize) {
+        self.buf.copy_within(len..self.len, 0);
+        self.len -= len;
+    }
+
+    /// Pop next frame, `None` when there's no more completed frame.
+    /// Broken frame is dropped and reported as error.
+    pub fn pop(&mut self) -> Option<Result<LinkCmd, LinkError>> {
+        if self.len == 0 {
+            return None;
+        }
+
+        match decode(&self.buf[..self.len]) {
+            Ok((cmd, used)) => {
+                self.consume(used);
+                Some(Ok(cmd))
+            }
+            Err(LinkError::BadLength) => None,
+            Err(e) => {
+                // drop until STX of the broken frame, next frame can be found later
+                let skip = self.buf[..self.len]
+                    .iter()
+                    .position(|x| *x == LINK_STX)
+                    .map_or(self.len, |x| x + 1);
+                self.consume(skip);
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl Default for LinkStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+
+    const SAMPLES: [LinkCmd; 6] = [
+        LinkCmd::Ack(7),
+        LinkCmd::Nack(0xFF),
+        LinkCmd::ForwardPayment(LinkPayment {
+            seq: 7,
+            player: 2,
+            pulse_count: 5,
+            pulse_duration: 100,
+        }),
+        LinkCmd::Inhibit(0b10),
+        LinkCmd::RequestCounters,
+        LinkCmd::Counters(LinkCounters {
+            card: [1, 0x12345678],
+            coin: [u32::MAX, 0],
+        }),
+    ];
+
+    /// Both boards share a wire, each board has own receive stream
+    struct Loopback {
+        wire: Vec<u8>,
+        stream: LinkStream,
+    }
+
+    impl Loopback {
+        fn new() -> Self {
+            Self {
+                wire: Vec::new(),
+                stream: LinkStream::new(),
+            }
+        }
+
+        fn send(&mut self, cmd: &LinkCmd) {
+            let mut buf = [0u8; LINK_FRAME_MAX];
+            self.wire
+                .extend_from_slice(encode(cmd, &mut buf).expect("encode"));
+        }
+
+        /// Deliver the wire with given fragment size, like UART DMA does
+        fn deliver(&mut self, chunk: usize) -> Vec<Result<LinkCmd, LinkError>> {
+            let mut ret = Vec::new();
+
+            for fragment in self.wire.chunks(chunk) {
+                self.stream.push(fragment);
+                while let Some(x) = self.stream.pop() {
+                    ret.push(x);
+                }
+            }
+            self.wire.clear();
+
+            ret
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        for cmd in SAMPLES.iter() {
+            let mut buf = [0u8; LINK_FRAME_MAX];
+            let frame = encode(cmd, &mut buf).unwrap();
+
+            assert_eq!(decode(frame), Ok((*cmd, frame.len())));
+        }
+    }
+
+    #[test]
+    fn loopback_fragmented() {
+        for chunk in 1..=LINK_FRAME_MAX {
+            let mut link = Loopback::new();
+            for cmd in SAMPLES.iter() {
+                link.send(cmd);
+            }
+
+            let received = link.deliver(chunk);
+            let expected: Vec<_> = SAMPLES.iter().map(|x| Ok(*x)).collect();
+            assert_eq!(received, expected, "chunk size {}", chunk);
+        }
+    }
+
+    #[test]
+    fn loopback_recovers_from_noise() {
+        let mut link = Loopback::new();
+        link.wire.extend_from_slice(&[0xFF, 0x00, 0x55]);
+        link.send(&LinkCmd::Inhibit(0b01));
+        // corrupt checksum of the frame
+        let last = link.wire.len() - 1;
+        link.wire[last] ^= 0xA5;
+        link.send(&SAMPLES[2]);
+
+        let received = link.deliver(LINK_FRAME_MAX);
+        assert_eq!(received.first(), Some(&Err(LinkError::BadChecksum)));
+        assert_eq!(received.last(), Some(&Ok(SAMPLES[2])));
+    }
+
+    #[test]
+    fn short_buffer() {
+        let mut buf = [0u8; 8];
+        assert_eq!(encode(&SAMPLES[5], &mut buf), Err(LinkError::BadLength),);
+        assert_eq!(
+            decode(&[LINK_STX, 6, LINK_CMD_FORWARD_PAYMENT]),
+            Err(LinkError::BadLength)
+        );
+    }
+}
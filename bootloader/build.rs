@@ -0,0 +1,42 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+use std::path::PathBuf;
+
+fn main() {
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    std::fs::copy("memory.x", out_dir.join("memory.x")).expect("Failed to copy memory.x");
+    println!("cargo:rustc-link-search={}", out_dir.display());
+    println!("cargo:rerun-if-changed=memory.x");
+
+    println!("cargo:rustc-link-arg-bins=--nmagic");
+    println!("cargo:rustc-link-arg-bins=-Tlink.x");
+
+    // Public key of `fw-sign`, bootloader accepts only the image signed by its secret key
+    let key_path = std::env::var("BILLMOCK_FW_PUBLIC_KEY")
+        .expect("Bootloader needs BILLMOCK_FW_PUBLIC_KEY, path of `fw-sign keygen` public key");
+    let key_hex = std::fs::read_to_string(&key_path).expect("Failed to read public key");
+    let key_hex = key_hex.trim();
+
+    let key: Vec<u8> = (0..key_hex.len())
+        .step_by(2)
+        .filter_map(|i| key_hex.get(i..i + 2))
+        .map(|x| u8::from_str_radix(x, 16).expect("Public key is not hex"))
+        .collect();
+    if key.len() != 32 {
+        panic!("{} is not 32 bytes hex public key", key_path);
+    }
+    std::fs::write(out_dir.join("public_key.rs"), format!("{:?}", key))
+        .expect("Failed to write public key");
+    println!("cargo:rerun-if-env-changed=BILLMOCK_FW_PUBLIC_KEY");
+    println!("cargo:rerun-if-changed={}", key_path);
+
+    // `model_ver` of `.mp_fingerprint` of application, e.g. "MINI-0V5" or "AUTO" for `hw_auto`
+    let model_ver = std::env::var("BILLMOCK_MODEL_VER")
+        .expect("Bootloader needs BILLMOCK_MODEL_VER, `model_ver` of application image");
+    println!("cargo:rustc-env=BILLMOCK_MODEL_VER={}", model_ver);
+    println!("cargo:rerun-if-env-changed=BILLMOCK_MODEL_VER");
+}
@@ -0,0 +1,23 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+/*
+ * STM32G030C8, bootloader of `fw_update` feature.
+ * Should be same with `memory/fw-update.x` of the application.
+ */
+MEMORY
+{
+  FLASH            : ORIGIN = 0x08000000, LENGTH = 20K
+  BOOTLOADER_STATE : ORIGIN = 0x08005000, LENGTH = 2K
+  ACTIVE           : ORIGIN = 0x08005800, LENGTH = 42K
+  RAM              : ORIGIN = 0x20000000, LENGTH = 8K
+}
+
+__bootloader_state_start = ORIGIN(BOOTLOADER_STATE) - ORIGIN(FLASH);
+__bootloader_state_end = ORIGIN(BOOTLOADER_STATE) + LENGTH(BOOTLOADER_STATE) - ORIGIN(FLASH);
+
+__bootloader_active_start = ORIGIN(ACTIVE) - ORIGIN(FLASH);
+__bootloader_active_end = ORIGIN(ACTIVE) + LENGTH(ACTIVE) - ORIGIN(FLASH);
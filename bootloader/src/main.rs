@@ -0,0 +1,328 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Bootloader for serial firmware update (`fw_update` feature of application).
+//! New image is received on card reader UART and written directly on active partition,
+//! there's no DFU partition for swap thus the application can take most of 64K flash.
+//! Update mode is entered when application programs update request on boot state page,
+//! or active partition doesn't have complete image, e.g. previous update was interrupted.
+//! Signature of the image is verified before every boot, not only after update.
+
+#![no_std]
+#![no_main]
+
+use cortex_m::peripheral::syst::SystClkSource;
+use cortex_m::peripheral::SYST;
+use cortex_m_rt::{entry, exception};
+use embassy_stm32::dma::NoDma;
+use embassy_stm32::flash::{Blocking, Flash, BANK1_REGION, WRITE_SIZE};
+use embassy_stm32::usart::{Config as UsartConfig, Uart, UartRx, UartTx};
+use embassy_stm32::{bind_interrupts, peripherals};
+use embedded_storage::nor_flash::NorFlash;
+use fw_update::*;
+use sha2::{Digest, Sha512};
+
+bind_interrupts!(struct Irqs {
+    USART2 => embassy_stm32::usart::InterruptHandler<peripherals::USART2>;
+});
+
+/// STM32G030 flash page size, erase unit
+const FLASH_PAGE_SIZE: u32 = 2048;
+/// HSI16, default clock of `embassy_stm32::init`
+const CORE_CLOCK_HZ: u32 = 16_000_000;
+/// Update mode is left when uploader doesn't start a session,
+/// only when active partition has complete image.
+const UPDATE_IDLE_TIMEOUT_MS: u32 = 30_000;
+/// Incompleted frame is dropped, uploader sends it again
+const FRAME_TIMEOUT_MS: u32 = 200;
+
+/// Ed25519 public key of release signing, given by `BILLMOCK_FW_PUBLIC_KEY` on build
+const FW_UPDATE_PUBLIC_KEY: [u8; 32] = include!(concat!(env!("OUT_DIR"), "/public_key.rs"));
+/// `model_ver` of application image, given by `BILLMOCK_MODEL_VER` on build
+const MODEL_VER: &str = env!("BILLMOCK_MODEL_VER");
+
+// Defined in `memory.x`, offsets from start of flash
+extern "C" {
+    static __bootloader_state_start: u32;
+    static __bootloader_state_end: u32;
+    static __bootloader_active_start: u32;
+    static __bootloader_active_end: u32;
+}
+
+type BlFlash = Flash<'static, Blocking>;
+
+/// (offset, size) of linker script partition
+fn partition(start: &u32, end: &u32) -> (u32, u32) {
+    let start = start as *const u32 as u32;
+    let end = end as *const u32 as u32;
+    (start, end - start)
+}
+
+/// Flash is memory mapped, read without flash driver
+fn mapped(offset: u32, len: usize) -> &'static [u8] {
+    unsafe { core::slice::from_raw_parts((BANK1_REGION.base + offset) as *const u8, len) }
+}
+
+fn read_boot_state(state_offset: u32) -> BootState {
+    let mut raw = [0u8; BOOT_STATE_LEN];
+    raw.copy_from_slice(mapped(state_offset, BOOT_STATE_LEN));
+    BootState::from_bytes(&raw)
+}
+
+fn write_boot_state(flash: &mut BlFlash, state_offset: u32, state: &BootState) -> bool {
+    flash
+        .erase(state_offset, state_offset + FLASH_PAGE_SIZE)
+        .and_then(|_| flash.write(state_offset, &state.to_bytes()))
+        .is_ok()
+}
+
+/// Signature over SHA-512 of image and trailer, same with `fw-sign`
+fn verify(active_offset: u32, len: u32, signature: &[u8; SIGNATURE_LEN]) -> bool {
+    let Ok(public_key) = salty::PublicKey::try_from(&FW_UPDATE_PUBLIC_KEY) else {
+        return false;
+    };
+    let digest = Sha512::digest(mapped(active_offset, len as usize));
+
+    public_key
+        .verify(&digest, &salty::Signature::from(signature))
+        .is_ok()
+}
+
+/// Image on active partition is booted only when it's signed for this hardware.
+/// Trailer on boot state is a copy, the one on active partition is covered by signature.
+fn verify_installed(state: &BootState, active_offset: u32, active_size: u32) -> bool {
+    let Some(image) = state.image else {
+        return false;
+    };
+    let len = image.trailer.image_size + TRAILER_LEN as u32;
+    if active_size < len {
+        return false;
+    }
+
+    let trailer = mapped(active_offset + image.trailer.image_size, TRAILER_LEN);
+
+    (trailer == image.trailer.to_bytes())
+        && (image.trailer.model_ver == model_ver_field(MODEL_VER))
+        && verify(active_offset, len, &image.signature)
+}
+
+/// 1ms tick of SysTick, there's no timer driver in bootloader.
+/// It counts only while polled, thus flash erase doesn't move it forward.
+struct Ticker {
+    syst: SYST,
+    now_ms: u32,
+}
+
+impl Ticker {
+    fn new(mut syst: SYST) -> Self {
+        syst.set_clock_source(SystClkSource::Core);
+        syst.set_reload(CORE_CLOCK_HZ / 1000 - 1);
+        syst.clear_current();
+        syst.enable_counter();
+        Self { syst, now_ms: 0 }
+    }
+
+    fn now(&mut self) -> u32 {
+        if self.syst.has_wrapped() {
+            self.now_ms = self.now_ms.wrapping_add(1);
+        }
+        self.now_ms
+    }
+}
+
+/// Serve update session on card reader UART.
+/// Returns when new image is finished, or the session is aborted or uploader is gone
+/// while verified image is left untouched.
+fn run_update(
+    tx: &mut UartTx<'static, peripherals::USART2, NoDma>,
+    rx: &mut UartRx<'static, peripherals::USART2, NoDma>,
+    flash: &mut BlFlash,
+    ticker: &mut Ticker,
+    (state_offset, active_offset, active_size): (u32, u32, u32),
+    is_bootable: bool,
+) {
+    let mut receiver = UpdateReceiver::new(MODEL_VER, active_size);
+    let mut stream = UpdateStream::new();
+    let mut rx_frame = [0u8; UPDATE_FRAME_MAX];
+    let mut tx_buf = [0u8; UPDATE_FRAME_MAX];
+    let mut last_rx = ticker.now();
+
+    loop {
+        let now = ticker.now();
+        let idle = now.wrapping_sub(last_rx);
+
+        match rx.nb_read() {
+            Ok(byte) => {
+                last_rx = now;
+                stream.push(&[byte]);
+            }
+            Err(nb::Error::WouldBlock) => {
+                if FRAME_TIMEOUT_MS < idle {
+                    stream.clear();
+                }
+                // boot state is erased when the session is started
+                if (UPDATE_IDLE_TIMEOUT_MS < idle)
+                    && is_bootable
+                    && read_boot_state(state_offset).image.is_some()
+                {
+                    return;
+                }
+                continue;
+            }
+            Err(nb::Error::Other(_)) => {
+                stream.clear();
+                continue;
+            }
+        }
+
+        while let Some(result) = stream.pop(&mut rx_frame) {
+            let Ok((request, _)) = result.and_then(|len| decode(&rx_frame[..len])) else {
+                continue;
+            };
+
+            let (reply, is_finished) = match receiver.handle(&request) {
+                Ok(ReceiverAction::Reply(reply)) => (reply, false),
+                Ok(ReceiverAction::Prepare(info)) => {
+                    // Old image is invalid from here, stays in update mode until new one is finished
+                    let erase_end = active_offset + info.size.next_multiple_of(FLASH_PAGE_SIZE);
+                    let result = flash
+                        .erase(state_offset, state_offset + FLASH_PAGE_SIZE)
+                        .and_then(|_| flash.erase(active_offset, erase_end));
+
+                    match result {
+                        Ok(()) => (UpdateFrame::Ack(0), false),
+                        Err(_) => {
+                            receiver.reset();
+                            (UpdateFrame::Nack(UpdateError::Flash), false)
+                        }
+                    }
+                }
+                Ok(ReceiverAction::Write { offset, data, next }) => {
+                    // last block is padded to flash write size
+                    let mut block = [0xFFu8; UPDATE_BLOCK_SIZE];
+                    let padded_len = data.len().next_multiple_of(WRITE_SIZE);
+                    block[..data.len()].copy_from_slice(data);
+
+                    match flash.write(active_offset + offset, &block[..padded_len]) {
+                        Ok(()) => (UpdateFrame::Ack(next), false),
+                        Err(_) => {
+                            receiver.reset();
+                            (UpdateFrame::Nack(UpdateError::Flash), false)
+                        }
+                    }
+                }
+                Ok(ReceiverAction::Finish {
+                    signature,
+                    len,
+                    trailer,
+                }) => {
+                    let state = BootState {
+                        image: Some(InstalledImage { trailer, signature }),
+                        is_update_requested: false,
+                    };
+
+                    // verified here to answer uploader, and again before load on next boot
+                    if !verify(active_offset, len, &signature) {
+                        (UpdateFrame::Nack(UpdateError::BadSignature), false)
+                    } else if !write_boot_state(flash, state_offset, &state) {
+                        (UpdateFrame::Nack(UpdateError::Flash), false)
+                    } else {
+                        (UpdateFrame::Ack(0), true)
+                    }
+                }
+                Ok(ReceiverAction::Abort) => {
+                    // previous image is left only when the session didn't erase it
+                    let is_left = is_bootable && read_boot_state(state_offset).image.is_some();
+                    (UpdateFrame::Ack(0), is_left)
+                }
+                Err(e) => (UpdateFrame::Nack(e), false),
+            };
+
+            if let Ok(send_source) = encode(&reply, &mut tx_buf) {
+                tx.blocking_write(send_source).ok();
+                tx.blocking_flush().ok();
+            }
+
+            if is_finished {
+                return;
+            }
+            last_rx = ticker.now();
+        }
+    }
+}
+
+#[entry]
+fn main() -> ! {
+    let p = embassy_stm32::init(Default::default());
+    let mut flash = Flash::new_blocking(p.FLASH);
+
+    let (active_offset, active_size) =
+        unsafe { partition(&__bootloader_active_start, &__bootloader_active_end) };
+    let (state_offset, _) =
+        unsafe { partition(&__bootloader_state_start, &__bootloader_state_end) };
+
+    let state = read_boot_state(state_offset);
+    let is_bootable = verify_installed(&state, active_offset, active_size);
+
+    if !is_bootable || state.is_update_requested {
+        let mut ticker = Ticker::new(cortex_m::Peripherals::take().unwrap().SYST);
+
+        // Same UART and baudrate with card reader of application
+        let usart2_config = {
+            let mut ret: UsartConfig = UsartConfig::default();
+            ret.baudrate = 115200;
+            ret.assume_noise_free = false;
+            ret.detect_previous_overrun = true;
+            ret
+        };
+        let (mut tx, mut rx) = Uart::new(p.USART2, p.PA3, p.PA2, Irqs, NoDma, NoDma, usart2_config)
+            .unwrap()
+            .split();
+
+        run_update(
+            &mut tx,
+            &mut rx,
+            &mut flash,
+            &mut ticker,
+            (state_offset, active_offset, active_size),
+            is_bootable,
+        );
+
+        let state = read_boot_state(state_offset);
+        if state.is_update_requested {
+            let state = BootState {
+                is_update_requested: false,
+                ..state
+            };
+            write_boot_state(&mut flash, state_offset, &state);
+        }
+
+        // peripherals of bootloader are reset before the application starts
+        cortex_m::peripheral::SCB::sys_reset();
+    }
+
+    unsafe {
+        let start = BANK1_REGION.base + active_offset;
+        cortex_m::Peripherals::steal().SCB.vtor.write(start);
+        cortex_m::asm::bootload(start as *const u32)
+    }
+}
+
+#[no_mangle]
+#[cfg_attr(target_os = "none", link_section = ".HardFault.user")]
+unsafe extern "C" fn HardFault() {
+    cortex_m::peripheral::SCB::sys_reset();
+}
+
+#[exception]
+unsafe fn DefaultHandler(_: i16) -> ! {
+    cortex_m::peripheral::SCB::sys_reset();
+}
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    cortex_m::asm::udf();
+}
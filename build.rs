@@ -4,6 +4,7 @@
  * SPDX-License-Identifier: MIT OR Apache-2.0
  */
 
+use std::path::PathBuf;
 use std::process::Command;
 
 use card_terminal_adapter::CardTerminalConst;
@@ -18,6 +19,19 @@ fn main() -> Result<(), Error> {
     println!("cargo:rustc-link-arg-bins=-Tlink.x");
     println!("cargo:rustc-link-arg-bins=-Tdefmt.x");
 
+    // `memory.x` includes `memory-layout.x`, application is placed behind bootloader on `fw_update`
+    let layout = match (
+        std::env::var("CARGO_FEATURE_FW_UPDATE").is_ok(),
+        std::env::var("CARGO_FEATURE_HW_QUAD_0V1").is_ok(),
+    ) {
+        (true, _) => "memory/fw-update.x",
+        (false, true) => "memory/quad.x",
+        (false, false) => "memory/standalone.x",
+    };
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    std::fs::copy(layout, out_dir.join("memory-layout.x")).expect("Failed to copy memory layout");
+    println!("cargo:rustc-link-search={}", out_dir.display());
+
     // Get project name and version
     let metadata = MetadataCommand::new().no_deps().exec()?;
 
@@ -13,8 +13,13 @@
 #![no_std]
 #![feature(const_trait_impl)]
 
+#[cfg(test)] // for the text page test code
+extern crate std;
+
+pub mod text_page;
 pub mod types;
 
+use text_page::TextPage;
 use types::*;
 
 #[derive(Debug, defmt::Format, Clone, Eq, PartialEq, Ord, PartialOrd)]
@@ -74,6 +79,14 @@ pub enum CardTerminalRxCmd {
     ResponseTerminalInfo(TidStatus, TerminalVersion),
     /// Set Pulse State
     RequestKeepPulseState(PulseStateRequest),
+    /// Request payout of tickets or tokens though hopper / ticket dispenser
+    RequestPayout(PayoutRequest),
+    /// Local wall-clock time for soft RTC
+    ResponseDateTime(RawDateTime),
+    /// Write an item of install config (schedule and etc)
+    RequestWriteConfig(RawConfigItem),
+    /// Give credits without payment (refund, tournament and etc)
+    RequestServiceCredit(ServiceCreditRequest),
 }
 
 #[derive(PartialEq, Eq, Clone, defmt::Format)]
@@ -107,6 +120,18 @@ pub enum CardTerminalTxCmd {
     DisplayHwInfo,
     /// Display Warnings
     DisplayWarning(CardTerminalDisplayWarning),
+    /// Report result of payout (hopper / ticket dispenser)
+    PushPayoutReport(PayoutReport),
+    /// Display active inhibit sources of each player
+    DisplayInhibitReason(RawPlayersInhibitReason),
+    /// Request local wall-clock time for soft RTC
+    RequestDateTime,
+    /// Cancel (refund) held payment that is not credited to any player
+    /// Only valid when `CardTerminalConst::support_payment_cancel` is true
+    RequestPaymentCancel(RawU24IncomeArcade),
+    /// Display text page that application composed
+    /// The page is kept on application side to keep the command small
+    DisplayTextPage,
 }
 
 #[derive(PartialEq, Eq, Clone, Copy, defmt::Format)]
@@ -116,6 +141,8 @@ pub enum CardTerminalDisplayWarning {
     WarnExperimentalVesion,
     WarnUnknown,
     WarnEepromFactoryReset,
+    WarnPayoutEmpty,
+    WarnPayoutJam,
 }
 
 pub const TID_LEN: usize = 10;
@@ -123,9 +150,23 @@ pub const FW_VER_LEN: usize = 5;
 pub const DEV_SN_LEN: usize = 12;
 pub const GIT_HASH_LEN: usize = 9;
 
+/// Hardware information for `DisplayHwInfo`
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub struct HwInfo<'a> {
+    pub model_version: &'a [u8; FW_VER_LEN],
+    pub serial_number: &'a [u8; DEV_SN_LEN],
+    pub terminal_id: &'a [u8; TID_LEN],
+    pub hw_boot_cnt: u32,
+    pub uptime_minutes: u32,
+    /// Non-zero when previous boot was reset by fault (e.g. watchdog)
+    pub last_fault_code: u16,
+}
+
 // #[const_trait]
 pub trait CardTerminalConst {
     fn is_nda() -> bool;
+    /// Terminal protocol can cancel (refund) payment that is already approved
+    fn support_payment_cancel() -> bool;
 }
 
 pub trait CardTerminalRxParse {
@@ -195,6 +236,16 @@ pub trait CardTerminalTxGen {
     /// Generate RequestTerminalInfo signal to send
     fn request_terminal_info<'a>(&self, buffer: &'a mut [u8]) -> &'a [u8];
 
+    /// Generate RequestDateTime signal to send
+    fn request_date_time<'a>(&self, buffer: &'a mut [u8]) -> &'a [u8];
+
+    /// Generate RequestPaymentCancel signal to send
+    fn request_payment_cancel<'a>(
+        &self,
+        buffer: &'a mut [u8],
+        income: RawU24IncomeArcade,
+    ) -> &'a [u8];
+
     /// Generate DisplayRom signal to send
     /// Display card / coin count for player 1 and 2 on LCD of card terminal.
     fn display_rom<'a>(
@@ -210,15 +261,7 @@ pub trait CardTerminalTxGen {
 
     /// Generate DisplayHwInfo signal to send
     /// Display hardware information, boot count, uptime and etc.
-    fn display_hw_info<'a, 'b>(
-        &self,
-        buffer: &'a mut [u8],
-        model_version: &'b [u8; FW_VER_LEN],
-        serial_number: &'b [u8; DEV_SN_LEN],
-        terminal_id: &[u8; TID_LEN],
-        hw_boot_cnt: u32,
-        uptime_minutes: u32,
-    ) -> &'a [u8];
+    fn display_hw_info<'a>(&self, buffer: &'a mut [u8], info: &HwInfo) -> &'a [u8];
 
     /// Generate DisplayWarning signal to send
     /// Display warning that need to update to latest terminal version firmware or something
@@ -227,4 +270,21 @@ pub trait CardTerminalTxGen {
         buffer: &'a mut [u8],
         warn_kind: CardTerminalDisplayWarning,
     ) -> &'a [u8];
+
+    /// Generate PushPayoutReport signal to send
+    /// Report dispensed count and result of hopper / ticket dispenser
+    fn push_payout_report<'a>(&self, buffer: &'a mut [u8], report: &PayoutReport) -> &'a [u8];
+
+    /// Generate DisplayInhibitReason signal to send
+    /// Display which sources hold inhibit on each player
+    fn display_inhibit_reason<'a>(
+        &self,
+        buffer: &'a mut [u8],
+        reason: &RawPlayersInhibitReason,
+    ) -> &'a [u8];
+
+    /// Generate DisplayTextPage signal to send
+    /// Display generic text page, `TextPage::render` gives lines in LCD width of the terminal
+    /// Code page (font) of the terminal should be selected by `TextPage::encoding`
+    fn display_text_page<'a>(&self, buffer: &'a mut [u8], page: &TextPage) -> &'a [u8];
 }
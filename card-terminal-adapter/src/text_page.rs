@@ -0,0 +1,325 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! # Generic text page for LCD of card terminal.
+//! Application composes a page of fixed-width lines, plug renders it into its display packet.
+//! Thus new screen (config menu, fault list, period meter ...) doesn't need new method
+//! on `CardTerminalTxGen` and every plug.
+//!
+//! Text is raw bytes, ASCII or double-byte encoding given by `TextEncoding` of the page.
+//! Width is counted in bytes, it's same with display cells for ASCII and double-byte encodings.
+//! Double-byte character is never split when the text is cut.
+
+/// Maximum lines of a page
+pub const TEXT_PAGE_LINES: usize = 4;
+/// Longest rendered value, `u32::MAX` in decimal or `0x` with 8 hex digits
+const TEXT_VALUE_LEN_MAX: usize = 10;
+
+/// Character encoding of the page, plug selects code page (font) of terminal LCD by this hint
+#[derive(Debug, Clone, Copy, Eq, PartialEq, defmt::Format)]
+pub enum TextEncoding {
+    /// ASCII only
+    Ascii,
+    /// Korean, KS X 1001 in EUC-KR
+    EucKr,
+    /// Japanese, JIS X 0208 in Shift_JIS, single-byte half-width katakana is allowed
+    ShiftJis,
+}
+
+impl TextEncoding {
+    /// Byte length of the character that starts with `lead`
+    pub const fn char_len(&self, lead: u8) -> usize {
+        match (self, lead) {
+            (Self::EucKr, 0xA1..=0xFE) => 2,
+            (Self::ShiftJis, 0x81..=0x9F | 0xE0..=0xFC) => 2,
+            _ => 1,
+        }
+    }
+
+    /// Longest head of `text` within `max` bytes, without splitting double-byte character
+    pub fn truncate<'a>(&self, text: &'a [u8], max: usize) -> &'a [u8] {
+        let mut len = 0;
+        while let Some(&lead) = text.get(len) {
+            let next = len + self.char_len(lead);
+            if (max < next) || (text.len() < next) {
+                break;
+            }
+            len = next;
+        }
+
+        &text[..len]
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, defmt::Format)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+/// Value shown on right end of the line
+#[derive(Debug, Clone, Copy, Eq, PartialEq, defmt::Format)]
+pub enum TextValue {
+    /// Label only line
+    None,
+    /// Decimal number, e.g. counter or price
+    Number(u32),
+    /// Hexadecimal number with `0x`, e.g. fault code
+    Hex(u32),
+    /// Short text, e.g. "ON" / "OFF"
+    Text(&'static [u8]),
+}
+
+impl TextValue {
+    fn render<'a>(
+        &self,
+        buf: &'a mut [u8; TEXT_VALUE_LEN_MAX],
+        encoding: TextEncoding,
+    ) -> &'a [u8] {
+        let (mut value, radix, prefix): (u32, u32, &[u8]) = match *self {
+            Self::None => return &buf[0..0],
+            Self::Text(text) => {
+                let text = encoding.truncate(text, TEXT_VALUE_LEN_MAX);
+                buf[..text.len()].copy_from_slice(text);
+                return &buf[..text.len()];
+            }
+            Self::Number(value) => (value, 10, b""),
+            Self::Hex(value) => (value, 16, b"0x"),
+        };
+
+        // digits are filled from the end of buffer
+        let mut pos = TEXT_VALUE_LEN_MAX;
+        loop {
+            pos -= 1;
+            buf[pos] = b"0123456789ABCDEF"[(value % radix) as usize];
+            value /= radix;
+
+            if value == 0 {
+                break;
+            }
+        }
+        pos -= prefix.len();
+        buf[pos..pos + prefix.len()].copy_from_slice(prefix);
+
+        &buf[pos..]
+    }
+}
+
+/// A line of text page.
+/// Label is placed by `align`, but with value the label is left and value is right aligned.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, defmt::Format)]
+pub struct TextLine {
+    pub label: &'static [u8],
+    pub value: TextValue,
+    pub align: TextAlign,
+}
+
+impl TextLine {
+    pub const EMPTY: Self = Self::text(b"");
+
+    pub const fn text(label: &'static [u8]) -> Self {
+        Self {
+            label,
+            value: TextValue::None,
+            align: TextAlign::Left,
+        }
+    }
+
+    pub const fn centered(label: &'static [u8]) -> Self {
+        Self {
+            label,
+            value: TextValue::None,
+            align: TextAlign::Center,
+        }
+    }
+
+    pub const fn number(label: &'static [u8], value: u32) -> Self {
+        Self {
+            label,
+            value: TextValue::Number(value),
+            align: TextAlign::Left,
+        }
+    }
+
+    pub const fn hex(label: &'static [u8], value: u32) -> Self {
+        Self {
+            label,
+            value: TextValue::Hex(value),
+            align: TextAlign::Left,
+        }
+    }
+
+    pub const fn value(label: &'static [u8], value: &'static [u8]) -> Self {
+        Self {
+            label,
+            value: TextValue::Text(value),
+            align: TextAlign::Left,
+        }
+    }
+
+    /// Render the line on `out` padded with space, width is length of `out`.
+    /// Control characters are replaced to `?`, too long label is cut.
+    pub fn render(&self, out: &mut [u8], encoding: TextEncoding) {
+        let width = out.len();
+        let mut value_buf = [0u8; TEXT_VALUE_LEN_MAX];
+        let value = self.value.render(&mut value_buf, encoding);
+        let value = encoding.truncate(value, width);
+
+        out.fill(b' ');
+        out[width - value.len()..].copy_from_slice(value);
+
+        // a space between label and value
+        let room = match value.len() {
+            0 => width,
+            len => width.saturating_sub(len + 1),
+        };
+        let label = encoding.truncate(self.label, room);
+        let start = match (value.is_empty(), self.align) {
+            (false, _) | (true, TextAlign::Left) => 0,
+            (true, TextAlign::Center) => (room - label.len()) / 2,
+            (true, TextAlign::Right) => room - label.len(),
+        };
+
+        for (dst, &src) in out[start..start + label.len()].iter_mut().zip(label) {
+            *dst = match src {
+                0x00..=0x1F | 0x7F => b'?',
+                x => x,
+            };
+        }
+    }
+}
+
+/// Page shown on LCD of card terminal
+#[derive(Debug, Clone, Copy, Eq, PartialEq, defmt::Format)]
+pub struct TextPage {
+    pub lines: [TextLine; TEXT_PAGE_LINES],
+    /// Seconds to show the page before terminal goes back to its idle screen,
+    /// zero keeps the page until next screen.
+    pub timeout_secs: u16,
+    /// Encoding of every line in the page
+    pub encoding: TextEncoding,
+}
+
+impl TextPage {
+    pub const fn new(timeout_secs: u16) -> Self {
+        Self {
+            lines: [TextLine::EMPTY; TEXT_PAGE_LINES],
+            timeout_secs,
+            encoding: TextEncoding::Ascii,
+        }
+    }
+
+    /// Set encoding of the page, default is ASCII
+    pub const fn encoding(mut self, encoding: TextEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Set a line, index over `TEXT_PAGE_LINES` is ignored
+    pub const fn line(mut self, index: usize, line: TextLine) -> Self {
+        if index < TEXT_PAGE_LINES {
+            self.lines[index] = line;
+        }
+        self
+    }
+
+    /// Render whole page for plug, `W` is width of terminal LCD
+    pub fn render<const W: usize>(&self, out: &mut [[u8; W]; TEXT_PAGE_LINES]) {
+        for (line, row) in self.lines.iter().zip(out.iter_mut()) {
+            line.render(row, self.encoding);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render<const W: usize>(line: TextLine) -> [u8; W] {
+        let mut out = [0u8; W];
+        line.render(&mut out, TextEncoding::Ascii);
+        out
+    }
+
+    #[test]
+    fn alignment() {
+        assert_eq!(&render::<8>(TextLine::text(b"ABC")), b"ABC     ");
+        assert_eq!(&render::<8>(TextLine::centered(b"ABC")), b"  ABC   ");
+        let right = TextLine {
+            align: TextAlign::Right,
+            ..TextLine::text(b"ABC")
+        };
+        assert_eq!(&render::<8>(right), b"     ABC");
+        assert_eq!(&render::<4>(TextLine::centered(b"ABCDEF")), b"ABCD");
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(
+            &render::<12>(TextLine::number(b"P1 CARD", 1234)),
+            b"P1 CARD 1234"
+        );
+        assert_eq!(&render::<12>(TextLine::number(b"P1", 0)), b"P1         0");
+        assert_eq!(
+            &render::<12>(TextLine::hex(b"FAULT", 0x1F)),
+            b"FAULT   0x1F"
+        );
+        assert_eq!(
+            &render::<12>(TextLine::value(b"FREE", b"ON")),
+            b"FREE      ON"
+        );
+        // label is cut for the value
+        assert_eq!(
+            &render::<12>(TextLine::number(b"LONG LABEL", u32::MAX)),
+            b"L 4294967295"
+        );
+        assert_eq!(&render::<4>(TextLine::number(b"X", 123456)), b"1234");
+    }
+
+    #[test]
+    fn control_characters() {
+        assert_eq!(&render::<6>(TextLine::text(b"A\nB\x7F")), b"A?B?  ");
+    }
+
+    #[test]
+    fn double_byte() {
+        // "한글" in EUC-KR, second character doesn't fit
+        let mut out = [0u8; 3];
+        TextLine::text(b"\xC7\xD1\xB1\xDB").render(&mut out, TextEncoding::EucKr);
+        assert_eq!(&out, b"\xC7\xD1 ");
+
+        // "ｱ" half-width katakana is single byte, "ア" is double byte in Shift_JIS
+        let mut out = [0u8; 4];
+        TextLine::value(b"\xB1\x83\x41", b"\x83\x41").render(&mut out, TextEncoding::ShiftJis);
+        assert_eq!(&out, b"\xB1 \x83\x41");
+
+        // same bytes are single byte characters in ASCII
+        let mut out = [0u8; 3];
+        TextLine::text(b"\xC7\xD1\xB1\xDB").render(&mut out, TextEncoding::Ascii);
+        assert_eq!(&out, b"\xC7\xD1\xB1");
+    }
+
+    #[test]
+    fn page() {
+        let page = TextPage::new(10)
+            .line(0, TextLine::centered(b"METER"))
+            .line(2, TextLine::number(b"COIN", 7))
+            .line(TEXT_PAGE_LINES, TextLine::text(b"IGNORED"));
+        let mut out = [[0u8; 10]; TEXT_PAGE_LINES];
+        page.render(&mut out);
+
+        assert_eq!(
+            out,
+            [
+                *b"  METER   ",
+                *b"          ",
+                *b"COIN     7",
+                *b"          "
+            ]
+        );
+    }
+}
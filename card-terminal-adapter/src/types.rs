@@ -89,6 +89,14 @@ pub struct RawPlayersInhibit {
     pub p2: bool,
 }
 
+/// Active inhibit sources of each player, bit mask of inhibit source defined on application.
+/// Zero means the player is not inhibited.
+#[derive(PartialEq, Eq, Clone, Copy, defmt::Format)]
+pub struct RawPlayersInhibitReason {
+    pub p1: u8,
+    pub p2: u8,
+}
+
 #[repr(C)]
 #[derive(Clone, Zeroable, PartialEq, PartialOrd)]
 pub struct RawTerminalId {
@@ -206,49 +214,48 @@ impl CardReaderPortBackup {
         true
     }
 
-    // 0 is player 1, <- this is temporary decide,
-    // 1 is player 2, <- this is temporary decide,
-    pub fn guess_player_by_port_num(&self, port_num: u8) -> u8 {
+    /// Game number of card terminal is assigned to players in turn, game 3 is player 1
+    /// on two player board but player 3 on four player board. Game number 0 is no one's.
+    const fn is_game_of_player(game_num: u16, player: u8, player_num: u8) -> bool {
+        let player_num = if player_num == 0 { 1 } else { player_num } as u16;
+
+        (game_num != 0) && (player != 0) && ((game_num - 1) % player_num == (player as u16 - 1))
+    }
+
+    /// Player index (0 is player 1) of the port, unknown port or game number 0 is player 1.
+    pub fn guess_player_by_port_num(&self, port_num: u8, player_num: u8) -> u8 {
         for backup in &self.raw_card_port_backup {
             if backup.raw_minimum.get_port_num() == port_num {
-                let game_num = backup.raw_extended.get_game_num();
+                let player_num = if player_num == 0 { 1 } else { player_num } as u16;
 
-                return match (game_num, (game_num & 0x1) == 0x1) {
-                    (0, _) => 0,
-                    (_, true) => 0,
-                    (_, false) => 1,
+                return match backup.raw_extended.get_game_num() {
+                    0 => 0,
+                    x => ((x - 1) % player_num) as u8,
                 };
             }
         }
         0
     }
 
-    // player 1 port is generally 1
-    // player 2 port is generally 2
-    pub fn guess_raw_income_by_player(&self, player: u8) -> Option<&RawU24IncomeArcade> {
-        for backup in &self.raw_card_port_backup {
-            if backup.property != SlotProperty::Enabled {
-                continue;
-            }
-
-            let game_num = backup.raw_extended.get_game_num();
-            if (game_num == player as u16) || (game_num == (player + 2) as u16) {
-                return Some(&backup.raw_minimum);
-            }
-        }
-
-        None
+    /// Income of enabled sale slot of the player (1 is player 1) on board has `player_num` players.
+    pub fn guess_raw_income_by_player(
+        &self,
+        player: u8,
+        player_num: u8,
+    ) -> Option<&RawU24IncomeArcade> {
+        self.guess_raw_income_index_by_player(player, player_num)
+            .map(|x| &self.raw_card_port_backup[x as usize].raw_minimum)
     }
 
     // index should be u8 but to reduce size use u8. Index gurantee less than 256.
-    pub fn guess_raw_income_index_by_player(&self, player: u8) -> Option<u8> {
+    pub fn guess_raw_income_index_by_player(&self, player: u8, player_num: u8) -> Option<u8> {
         for (pos, backup) in self.raw_card_port_backup.iter().enumerate() {
             if backup.property != SlotProperty::Enabled {
                 continue;
             }
 
             let game_num = backup.raw_extended.get_game_num();
-            if (game_num == player as u16) || (game_num == (player + 2) as u16) {
+            if Self::is_game_of_player(game_num, player, player_num) {
                 return Some(pos as u8);
             }
         }
@@ -256,6 +263,16 @@ impl CardReaderPortBackup {
         None
     }
 
+    /// Every sale slot of the player is disabled on card terminal, player without slot is not.
+    pub fn is_player_disabled(&self, player: u8, player_num: u8) -> bool {
+        let mut slots = self
+            .raw_card_port_backup
+            .iter()
+            .filter(|x| Self::is_game_of_player(x.raw_extended.get_game_num(), player, player_num));
+
+        slots.clone().next().is_some() && slots.all(|x| x.property == SlotProperty::Disabled)
+    }
+
     pub fn set_inhibit(&mut self, inhibit: RawPlayersInhibit) {
         for i in 0..self.raw_card_port_backup.len() {
             let is_disabled = self.raw_card_port_backup[i].property == SlotProperty::Disabled;
@@ -279,4 +296,140 @@ pub struct PulseStateRequest {
     pub state: bool,
 }
 
+/// Payout request for hopper or ticket dispenser, port is same as `IncomeArcadeRequest`
+#[derive(Debug, Zeroable, defmt::Format, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub struct PayoutRequest {
+    pub port: u8,
+    pub count: u16,
+}
+
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+pub enum PayoutResult {
+    /// Whole requested items are dispensed
+    Done,
+    /// No item came out until timeout, hopper or ticket bin is assumed empty
+    Empty,
+    /// Feedback sensor stuck on active state
+    Jam,
+}
+
+#[derive(Debug, defmt::Format, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub struct PayoutReport {
+    pub port: u8,
+    pub requested: u16,
+    pub dispensed: u16,
+    pub result: PayoutResult,
+}
+
 assert_eq_size!(CardReaderPortBackup, [u8; 32]);
+
+/// Local wall-clock time from card terminal, year is offset from 2000.
+#[derive(Debug, Zeroable, defmt::Format, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+pub struct RawDateTime {
+    pub year: u8,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl RawDateTime {
+    pub fn is_valid(&self) -> bool {
+        (1..=12).contains(&self.month)
+            && (1..=31).contains(&self.day)
+            && (self.hour < 24)
+            && (self.minute < 60)
+            && (self.second < 60)
+    }
+
+    /// Day of week, 0 is Monday and 6 is Sunday.
+    pub fn weekday(&self) -> u8 {
+        // Sakamoto's method, 0 is Sunday
+        const T: [u16; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+        let month = self.month.clamp(1, 12) as usize;
+        let year = 2000 + self.year as u16 - (month < 3) as u16;
+        let sunday_based =
+            (year + year / 4 - year / 100 + year / 400 + T[month - 1] + self.day as u16) % 7;
+
+        ((sunday_based + 6) % 7) as u8
+    }
+
+    /// Elapsed seconds since midnight
+    pub fn seconds_of_day(&self) -> u32 {
+        (self.hour as u32) * 3600 + (self.minute as u32) * 60 + (self.second as u32)
+    }
+}
+
+/// Install config item write request, key and value are defined on application side.
+#[derive(Debug, Zeroable, defmt::Format, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+pub struct RawConfigItem {
+    pub key: u8,
+    pub value: u32,
+}
+
+/// Service credit request (credit without payment), port is same as `IncomeArcadeRequest`
+#[derive(Debug, Zeroable, defmt::Format, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub struct ServiceCreditRequest {
+    pub port: u8,
+    pub count: u8,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backup_of_games(games: [u16; 4]) -> CardReaderPortBackup {
+        let mut ret = CardReaderPortBackup::empty_slot();
+
+        for (idx, game_num) in games.into_iter().enumerate() {
+            ret.raw_card_port_backup[idx] = RawCardPortBackup::from((
+                SlotPriceGameNum {
+                    price: 1000,
+                    game_num,
+                },
+                IncomeArcadeRequest {
+                    port: idx as u8 + 1,
+                    pulse_count: idx as u16 + 1,
+                    pulse_duration: 100,
+                },
+            ));
+        }
+
+        ret
+    }
+
+    #[test]
+    fn test_player_of_game_num() {
+        let backup = backup_of_games([1, 2, 3, 4]);
+
+        // two player board folds game 3 and 4 into player 1 and 2
+        assert_eq!(backup.guess_player_by_port_num(3, 2), 0);
+        assert_eq!(backup.guess_player_by_port_num(4, 2), 1);
+        assert_eq!(backup.guess_raw_income_index_by_player(1, 2), Some(0));
+        assert_eq!(backup.guess_raw_income_index_by_player(2, 2), Some(1));
+
+        // four player board has own slot for each player
+        assert_eq!(backup.guess_player_by_port_num(3, 4), 2);
+        assert_eq!(backup.guess_player_by_port_num(4, 4), 3);
+        assert_eq!(backup.guess_raw_income_index_by_player(3, 4), Some(2));
+        assert_eq!(
+            backup
+                .guess_raw_income_by_player(4, 4)
+                .map(|x| x.get_pulse_count()),
+            Some(4)
+        );
+        assert_eq!(backup.guess_raw_income_index_by_player(3, 2), None);
+    }
+
+    #[test]
+    fn test_player_disabled() {
+        let backup = backup_of_games([1, 0, 3, 0]);
+
+        assert!(!backup.is_player_disabled(1, 4));
+        // player without slot is not disabled
+        assert!(!backup.is_player_disabled(2, 4));
+        assert!(!backup.is_player_disabled(4, 4));
+        assert_eq!(backup.guess_raw_income_index_by_player(3, 4), Some(2));
+    }
+}
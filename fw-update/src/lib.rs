@@ -0,0 +1,1210 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Serial firmware update protocol, the image is sent through card reader UART.
+//! Host uploader sends `Hello`, `Begin`, sequential `Block`s and `Finish`,
+//! the board answers each frame with `Ready`, `Ack` or `Nack`.
+//! Both sides are sans-IO state machines, thus it can be tested on host with loopback.
+//!
+//! Uploaded payload is signed image, built by `fw-sign` of host tools.
+//! +------------------+----------------------+---------------------+
+//! | image (bin)      | `ImageTrailer`       | Ed25519 signature   |
+//! +------------------+----------------------+---------------------+
+//! | n bytes          | 64 bytes             | 64 bytes            |
+//! +------------------+----------------------+---------------------+
+//! - Image and trailer are written on active partition by bootloader, signature is given by `Finish`.
+//! - Signature is Ed25519 over SHA-512 of image and trailer.
+//! - Trailer is fingerprint of the image, board checks `model_ver` after signature covers it.
+//!
+//! +-------+-----------+-------+--------------------+-----------+
+//! | SOF   | LEN       | CMD   | PAYLOAD            | CRC       |
+//! +-------+-----------+-------+--------------------+-----------+
+//! | 0xB1  | 1 + n, LE | 1byte | n bytes, LE        | 2byte, LE |
+//! +-------+-----------+-------+--------------------+-----------+
+//! - `CRC` is CRC-16/CCITT-FALSE of `LEN`, `CMD` and `PAYLOAD`.
+//! - `SOF` is not valid byte of card terminal protocol (STX 0x02),
+//!   thus normal firmware can recognize `Hello` frame on card reader UART.
+
+#![no_std]
+
+#[cfg(test)] // for the loopback test code
+extern crate std;
+
+pub const UPDATE_SOF: u8 = 0xB1;
+/// Maximum length of image data in a `Block`, multiple of flash write size
+pub const UPDATE_BLOCK_SIZE: usize = 128;
+/// Maximum length of payload, `Block` has offset and data
+pub const UPDATE_PAYLOAD_MAX: usize = 4 + UPDATE_BLOCK_SIZE;
+/// Maximum length of whole frame, SOF + LEN + CMD + PAYLOAD + CRC
+pub const UPDATE_FRAME_MAX: usize = UPDATE_PAYLOAD_MAX + 6;
+/// Receive stream can hold two frames
+pub const UPDATE_STREAM_SIZE: usize = UPDATE_FRAME_MAX * 2;
+/// Same length with `model_ver` field of `.mp_fingerprint`, zero padded
+pub const MODEL_VER_LEN: usize = 16;
+/// `firmware_ver` of `.mp_fingerprint`, zero padded
+pub const FIRMWARE_VER_LEN: usize = 16;
+/// `firmware_git_hash` of `.mp_fingerprint` as binary SHA-1
+pub const GIT_HASH_LEN: usize = 20;
+pub const TRAILER_MAGIC: [u8; 4] = *b"BMFW";
+/// Length of `ImageTrailer` appended to image
+pub const TRAILER_LEN: usize = 64;
+/// Ed25519 signature
+pub const SIGNATURE_LEN: usize = 64;
+
+const UPDATE_CMD_HELLO: u8 = 0x01;
+const UPDATE_CMD_BEGIN: u8 = 0x02;
+const UPDATE_CMD_BLOCK: u8 = 0x03;
+const UPDATE_CMD_FINISH: u8 = 0x04;
+const UPDATE_CMD_ABORT: u8 = 0x05;
+const UPDATE_CMD_READY: u8 = 0x81;
+const UPDATE_CMD_ACK: u8 = 0x82;
+const UPDATE_CMD_NACK: u8 = 0x83;
+
+#[repr(u8)]
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub enum UpdateError {
+    /// Frame is not completed yet, or given buffer is too short to generate
+    BadLength = 0x01,
+    /// Invalid Frame, SOF is missing or length is out of spec
+    InvalidFrame = 0x02,
+    /// Given CRC is not matched
+    BadCrc = 0x03,
+    /// Frame is correct, but command or its payload length is unknown
+    UnsupportedCmd = 0x04,
+    /// Image is built for other hardware, `model_ver` is not matched
+    ModelMismatch = 0x10,
+    /// Image is bigger than active partition
+    TooLarge = 0x11,
+    /// `Block` is not continued from previous one
+    BadOffset = 0x12,
+    /// Whole image is received but CRC-32 is not matched
+    BadImageCrc = 0x13,
+    /// `Block` or `Finish` is received before `Begin`
+    NotStarted = 0x14,
+    /// Image is not signed by the key of running firmware
+    BadSignature = 0x15,
+    /// `ImageTrailer` is missing or broken
+    BadTrailer = 0x16,
+    /// Failed to erase or write flash
+    Flash = 0x20,
+    /// Unknown reason of `Nack`
+    Unknown = 0xFF,
+}
+
+impl From<u8> for UpdateError {
+    fn from(value: u8) -> Self {
+        match value {
+            0x01 => Self::BadLength,
+            0x02 => Self::InvalidFrame,
+            0x03 => Self::BadCrc,
+            0x04 => Self::UnsupportedCmd,
+            0x10 => Self::ModelMismatch,
+            0x11 => Self::TooLarge,
+            0x12 => Self::BadOffset,
+            0x13 => Self::BadImageCrc,
+            0x14 => Self::NotStarted,
+            0x15 => Self::BadSignature,
+            0x16 => Self::BadTrailer,
+            0x20 => Self::Flash,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// Image information given by `Begin`
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub struct ImageInfo {
+    /// Length of image and trailer, signature is not included
+    pub size: u32,
+    /// CRC-32 (ISO-HDLC) of image and trailer
+    pub crc: u32,
+    /// `model_ver` of `.mp_fingerprint` in the image, e.g. "MINI-0V5"
+    pub model_ver: [u8; MODEL_VER_LEN],
+}
+
+/// Board information answered to `Hello`
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub struct BoardInfo {
+    /// Maximum image size, size of active partition
+    pub capacity: u32,
+    /// `model_ver` of running firmware
+    pub model_ver: [u8; MODEL_VER_LEN],
+}
+
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub enum UpdateFrame<'a> {
+    /// Host -> Board, enter update mode and request board information
+    Hello,
+    /// Host -> Board, start new update session
+    Begin(ImageInfo),
+    /// Host -> Board, image data at offset
+    Block { offset: u32, data: &'a [u8] },
+    /// Host -> Board, whole image is sent, verify signature and boot new image
+    Finish { signature: [u8; SIGNATURE_LEN] },
+    /// Host -> Board, cancel update session and back to normal operation
+    Abort,
+    /// Board -> Host, answer of `Hello`
+    Ready(BoardInfo),
+    /// Board -> Host, frame is processed, next offset to be sent
+    Ack(u32),
+    /// Board -> Host, frame is rejected
+    Nack(UpdateError),
+}
+
+/// Convert `model_ver` string to fixed length field
+pub const fn model_ver_field(model_ver: &str) -> [u8; MODEL_VER_LEN] {
+    let src = model_ver.as_bytes();
+    let mut ret = [0u8; MODEL_VER_LEN];
+    let mut i = 0;
+    while (i < src.len()) && (i < MODEL_VER_LEN) {
+        ret[i] = src[i];
+        i += 1;
+    }
+    ret
+}
+
+/// Fingerprint of signed image, appended after image and covered by signature.
+/// Fields are taken from `.mp_fingerprint` of the ELF when the image is signed.
+///
+/// +-------+-----------+-----------+--------------+-------+----------+----------+
+/// | MAGIC | IMG SIZE  | MODEL VER | FIRMWARE VER | FLAGS | RESERVED | GIT HASH |
+/// +-------+-----------+-----------+--------------+-------+----------+----------+
+/// | 4     | 4, LE     | 16        | 16           | 1     | 3        | 20       |
+/// +-------+-----------+-----------+--------------+-------+----------+----------+
+/// - `FLAGS` bit 0 is `is_nda`, bit 1 is dirty working tree.
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub struct ImageTrailer {
+    /// Length of image without trailer
+    pub image_size: u32,
+    pub model_ver: [u8; MODEL_VER_LEN],
+    pub firmware_ver: [u8; FIRMWARE_VER_LEN],
+    pub git_hash: [u8; GIT_HASH_LEN],
+    pub is_nda: bool,
+    pub is_dirty: bool,
+}
+
+impl ImageTrailer {
+    const FLAG_NDA: u8 = 1 << 0;
+    const FLAG_DIRTY: u8 = 1 << 1;
+
+    pub fn to_bytes(&self) -> [u8; TRAILER_LEN] {
+        let mut ret = [0u8; TRAILER_LEN];
+        ret[0..4].copy_from_slice(&TRAILER_MAGIC);
+        ret[4..8].copy_from_slice(&self.image_size.to_le_bytes());
+        ret[8..24].copy_from_slice(&self.model_ver);
+        ret[24..40].copy_from_slice(&self.firmware_ver);
+        ret[40] = (self.is_nda as u8 * Self::FLAG_NDA) | (self.is_dirty as u8 * Self::FLAG_DIRTY);
+        ret[44..64].copy_from_slice(&self.git_hash);
+        ret
+    }
+
+    pub fn from_bytes(raw: &[u8; TRAILER_LEN]) -> Result<Self, UpdateError> {
+        if raw[0..4] != TRAILER_MAGIC {
+            return Err(UpdateError::BadTrailer);
+        }
+
+        let mut firmware_ver = [0u8; FIRMWARE_VER_LEN];
+        firmware_ver.copy_from_slice(&raw[24..40]);
+        let mut git_hash = [0u8; GIT_HASH_LEN];
+        git_hash.copy_from_slice(&raw[44..64]);
+
+        Ok(Self {
+            image_size: u32_at(raw, 4),
+            model_ver: model_ver_at(raw, 8),
+            firmware_ver,
+            git_hash,
+            is_nda: (raw[40] & Self::FLAG_NDA) != 0,
+            is_dirty: (raw[40] & Self::FLAG_DIRTY) != 0,
+        })
+    }
+}
+
+/// Output of `fw-sign`, image and trailer followed by signature
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub struct SignedImage<'a> {
+    /// Image and trailer, written on active partition
+    pub payload: &'a [u8],
+    pub trailer: ImageTrailer,
+    pub signature: [u8; SIGNATURE_LEN],
+}
+
+impl<'a> SignedImage<'a> {
+    /// Split signed image, signature is not verified here
+    pub fn parse(raw: &'a [u8]) -> Result<Self, UpdateError> {
+        if raw.len() <= (TRAILER_LEN + SIGNATURE_LEN) {
+            return Err(UpdateError::BadTrailer);
+        }
+
+        let (payload, signature_raw) = raw.split_at(raw.len() - SIGNATURE_LEN);
+        let mut trailer_raw = [0u8; TRAILER_LEN];
+        trailer_raw.copy_from_slice(&payload[payload.len() - TRAILER_LEN..]);
+        let trailer = ImageTrailer::from_bytes(&trailer_raw)?;
+        if trailer.image_size as usize != (payload.len() - TRAILER_LEN) {
+            return Err(UpdateError::BadTrailer);
+        }
+
+        let mut signature = [0u8; SIGNATURE_LEN];
+        signature.copy_from_slice(signature_raw);
+
+        Ok(Self {
+            payload,
+            trailer,
+            signature,
+        })
+    }
+}
+
+/// Flash write size of STM32G0, update request is programmed at once
+pub const UPDATE_REQUEST_LEN: usize = 8;
+pub const UPDATE_REQUEST_MAGIC: [u8; UPDATE_REQUEST_LEN] = *b"BMUPDATE";
+/// Offset of update request in boot state page
+pub const UPDATE_REQUEST_OFFSET: usize = TRAILER_LEN + SIGNATURE_LEN;
+/// Used part of boot state page
+pub const BOOT_STATE_LEN: usize = UPDATE_REQUEST_OFFSET + UPDATE_REQUEST_LEN;
+
+/// Trailer and signature of the image on active partition
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub struct InstalledImage {
+    pub trailer: ImageTrailer,
+    pub signature: [u8; SIGNATURE_LEN],
+}
+
+/// Boot state page between bootloader and active partition, owned by bootloader.
+///
+/// +----------------+---------------------+----------------+
+/// | `ImageTrailer` | Ed25519 signature   | UPDATE REQUEST |
+/// +----------------+---------------------+----------------+
+/// | 64 bytes       | 64 bytes            | 8 bytes        |
+/// +----------------+---------------------+----------------+
+/// - Trailer and signature are written after new image is verified,
+///   erased trailer means active partition doesn't have complete image.
+/// - `UPDATE REQUEST` is `UPDATE_REQUEST_MAGIC` programmed by application on erased flash,
+///   thus application enters update mode of bootloader without erasing the page.
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub struct BootState {
+    pub image: Option<InstalledImage>,
+    pub is_update_requested: bool,
+}
+
+impl BootState {
+    pub fn to_bytes(&self) -> [u8; BOOT_STATE_LEN] {
+        let mut ret = [0xFFu8; BOOT_STATE_LEN];
+        if let Some(image) = self.image {
+            ret[..TRAILER_LEN].copy_from_slice(&image.trailer.to_bytes());
+            ret[TRAILER_LEN..UPDATE_REQUEST_OFFSET].copy_from_slice(&image.signature);
+        }
+        if self.is_update_requested {
+            ret[UPDATE_REQUEST_OFFSET..].copy_from_slice(&UPDATE_REQUEST_MAGIC);
+        }
+        ret
+    }
+
+    /// Erased or broken trailer is treated as no image
+    pub fn from_bytes(raw: &[u8; BOOT_STATE_LEN]) -> Self {
+        let mut trailer_raw = [0u8; TRAILER_LEN];
+        trailer_raw.copy_from_slice(&raw[..TRAILER_LEN]);
+        let mut signature = [0u8; SIGNATURE_LEN];
+        signature.copy_from_slice(&raw[TRAILER_LEN..UPDATE_REQUEST_OFFSET]);
+
+        Self {
+            image: ImageTrailer::from_bytes(&trailer_raw)
+                .ok()
+                .map(|trailer| InstalledImage { trailer, signature }),
+            is_update_requested: raw[UPDATE_REQUEST_OFFSET..] == UPDATE_REQUEST_MAGIC,
+        }
+    }
+}
+
+/// CRC-16/CCITT-FALSE, for frame
+pub fn crc16(raw: &[u8]) -> u16 {
+    raw.iter().fold(0xFFFF, |mut crc: u16, &byte| {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = match crc & 0x8000 {
+                0 => crc << 1,
+                _ => (crc << 1) ^ 0x1021,
+            };
+        }
+        crc
+    })
+}
+
+/// CRC-32/ISO-HDLC for whole image, can be updated block by block
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub struct Crc32(u32);
+
+impl Crc32 {
+    pub const fn new() -> Self {
+        Self(0xFFFF_FFFF)
+    }
+
+    pub fn update(&mut self, raw: &[u8]) {
+        for &byte in raw {
+            self.0 ^= byte as u32;
+            for _ in 0..8 {
+                self.0 = match self.0 & 1 {
+                    0 => self.0 >> 1,
+                    _ => (self.0 >> 1) ^ 0xEDB8_8320,
+                };
+            }
+        }
+    }
+
+    pub const fn finish(&self) -> u32 {
+        !self.0
+    }
+
+    pub fn checksum(raw: &[u8]) -> u32 {
+        let mut crc = Self::new();
+        crc.update(raw);
+        crc.finish()
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn u32_at(raw: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        raw[offset],
+        raw[offset + 1],
+        raw[offset + 2],
+        raw[offset + 3],
+    ])
+}
+
+fn model_ver_at(raw: &[u8], offset: usize) -> [u8; MODEL_VER_LEN] {
+    let mut ret = [0u8; MODEL_VER_LEN];
+    ret.copy_from_slice(&raw[offset..offset + MODEL_VER_LEN]);
+    ret
+}
+
+/// Generate frame to given buffer, returns slice of generated frame
+pub fn encode<'a>(frame: &UpdateFrame, buf: &'a mut [u8]) -> Result<&'a [u8], UpdateError> {
+    let mut payload = [0u8; UPDATE_PAYLOAD_MAX];
+
+    let (cmd, payload_len) = match frame {
+        UpdateFrame::Hello => (UPDATE_CMD_HELLO, 0),
+        UpdateFrame::Begin(info) => {
+            payload[0..4].copy_from_slice(&info.size.to_le_bytes());
+            payload[4..8].copy_from_slice(&info.crc.to_le_bytes());
+            payload[8..8 + MODEL_VER_LEN].copy_from_slice(&info.model_ver);
+            (UPDATE_CMD_BEGIN, 8 + MODEL_VER_LEN)
+        }
+        UpdateFrame::Block { offset, data } => {
+            if UPDATE_BLOCK_SIZE < data.len() {
+                return Err(UpdateError::BadLength);
+            }
+            payload[0..4].copy_from_slice(&offset.to_le_bytes());
+            payload[4..4 + data.len()].copy_from_slice(data);
+            (UPDATE_CMD_BLOCK, 4 + data.len())
+        }
+        UpdateFrame::Finish { signature } => {
+            payload[0..SIGNATURE_LEN].copy_from_slice(signature);
+            (UPDATE_CMD_FINISH, SIGNATURE_LEN)
+        }
+        UpdateFrame::Abort => (UPDATE_CMD_ABORT, 0),
+        UpdateFrame::Ready(info) => {
+            payload[0..4].copy_from_slice(&info.capacity.to_le_bytes());
+            payload[4..4 + MODEL_VER_LEN].copy_from_slice(&info.model_ver);
+            (UPDATE_CMD_READY, 4 + MODEL_VER_LEN)
+        }
+        UpdateFrame::Ack(next_offset) => {
+            payload[0..4].copy_from_slice(&next_offset.to_le_bytes());
+            (UPDATE_CMD_ACK, 4)
+        }
+        UpdateFrame::Nack(reason) => {
+            payload[0] = *reason as u8;
+            (UPDATE_CMD_NACK, 1)
+        }
+    };
+
+    let frame_len = payload_len + 6;
+    if buf.len() < frame_len {
+        return Err(UpdateError::BadLength);
+    }
+
+    buf[0] = UPDATE_SOF;
+    buf[1..3].copy_from_slice(&((payload_len + 1) as u16).to_le_bytes());
+    buf[3] = cmd;
+    buf[4..4 + payload_len].copy_from_slice(&payload[..payload_len]);
+    let crc = crc16(&buf[1..4 + payload_len]);
+    buf[4 + payload_len..frame_len].copy_from_slice(&crc.to_le_bytes());
+
+    Ok(&buf[..frame_len])
+}
+
+/// Length of whole frame at the head of raw, the frame is not validated yet
+fn frame_len(raw: &[u8]) -> Result<usize, UpdateError> {
+    if raw.is_empty() {
+        return Err(UpdateError::BadLength);
+    }
+    if raw[0] != UPDATE_SOF {
+        return Err(UpdateError::InvalidFrame);
+    }
+    if raw.len() < 3 {
+        return Err(UpdateError::BadLength);
+    }
+
+    let len = u16::from_le_bytes([raw[1], raw[2]]) as usize;
+    if (len == 0) || ((UPDATE_PAYLOAD_MAX + 1) < len) {
+        return Err(UpdateError::InvalidFrame);
+    }
+
+    Ok(len + 5)
+}
+
+/// Parse a frame at the head of raw, returns frame and consumed length
+pub fn decode(raw: &[u8]) -> Result<(UpdateFrame<'_>, usize), UpdateError> {
+    let frame_len = frame_len(raw)?;
+    if raw.len() < frame_len {
+        return Err(UpdateError::BadLength);
+    }
+
+    let given_crc = u16::from_le_bytes([raw[frame_len - 2], raw[frame_len - 1]]);
+    if crc16(&raw[1..frame_len - 2]) != given_crc {
+        return Err(UpdateError::BadCrc);
+    }
+
+    let cmd = raw[3];
+    let payload = &raw[4..frame_len - 2];
+
+    let frame = match (cmd, payload.len()) {
+        (UPDATE_CMD_HELLO, 0) => UpdateFrame::Hello,
+        (UPDATE_CMD_BEGIN, len) if len == 8 + MODEL_VER_LEN => UpdateFrame::Begin(ImageInfo {
+            size: u32_at(payload, 0),
+            crc: u32_at(payload, 4),
+            model_ver: model_ver_at(payload, 8),
+        }),
+        (UPDATE_CMD_BLOCK, len) if 4 < len => UpdateFrame::Block {
+            offset: u32_at(payload, 0),
+            data: &payload[4..],
+        },
+        (UPDATE_CMD_FINISH, SIGNATURE_LEN) => {
+            let mut signature = [0u8; SIGNATURE_LEN];
+            signature.copy_from_slice(payload);
+            UpdateFrame::Finish { signature }
+        }
+        (UPDATE_CMD_ABORT, 0) => UpdateFrame::Abort,
+        (UPDATE_CMD_READY, len) if len == 4 + MODEL_VER_LEN => UpdateFrame::Ready(BoardInfo {
+            capacity: u32_at(payload, 0),
+            model_ver: model_ver_at(payload, 4),
+        }),
+        (UPDATE_CMD_ACK, 4) => UpdateFrame::Ack(u32_at(payload, 0)),
+        (UPDATE_CMD_NACK, 1) => UpdateFrame::Nack(payload[0].into()),
+        _ => return Err(UpdateError::UnsupportedCmd),
+    };
+
+    Ok((frame, frame_len))
+}
+
+/// Find `Hello` frame in bytes received by other protocol, to enter update mode
+pub fn contains_hello(raw: &[u8]) -> bool {
+    raw.iter()
+        .enumerate()
+        .filter(|(_, &x)| x == UPDATE_SOF)
+        .any(|(idx, _)| matches!(decode(&raw[idx..]), Ok((UpdateFrame::Hello, _))))
+}
+
+/// Accumulate fragmented bytes from UART and split them into frames.
+/// Garbage bytes before `SOF` are dropped.
+pub struct UpdateStream {
+    buf: [u8; UPDATE_STREAM_SIZE],
+    len: usize,
+}
+
+impl UpdateStream {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0u8; UPDATE_STREAM_SIZE],
+            len: 0,
+        }
+    }
+
+    /// Push received bytes, bytes over capacity are dropped
+    pub fn push(&mut self, raw: &[u8]) {
+        let room = UPDATE_STREAM_SIZE - self.len;
+        let take = raw.len().min(room);
+        self.buf[self.len..self.len + take].copy_from_slice(&raw[..take]);
+        self.len += take;
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    fn consume(&mut self, len: usize) {
+        let len = len.min(self.len);
+        self.buf.copy_within(len..self.len, 0);
+        self.len -= len;
+    }
+
+    /// Copy next completed frame to `out`, returns length of the frame.
+    /// Frame is copied out because `Block` data is borrowed while flash is written.
+    pub fn pop(&mut self, out: &mut [u8; UPDATE_FRAME_MAX]) -> Option<Result<usize, UpdateError>> {
+        // skip garbage until SOF
+        match self.buf[..self.len].iter().position(|&x| x == UPDATE_SOF) {
+            Some(0) => {}
+            Some(pos) => self.consume(pos),
+            None => {
+                self.clear();
+                return None;
+            }
+        }
+
+        let len = match frame_len(&self.buf[..self.len]) {
+            Ok(len) => len,
+            Err(UpdateError::BadLength) => return None,
+            Err(e) => {
+                self.consume(1);
+                return Some(Err(e));
+            }
+        };
+        if self.len < len {
+            return None;
+        }
+
+        match decode(&self.buf[..len]) {
+            Ok(_) => {
+                out[..len].copy_from_slice(&self.buf[..len]);
+                self.consume(len);
+                Some(Ok(len))
+            }
+            Err(e) => {
+                // resync from next byte, SOF could be in the data
+                self.consume(1);
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl Default for UpdateStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What board should do for received frame
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub enum ReceiverAction<'a> {
+    /// Send the frame back without flash access
+    Reply(UpdateFrame<'a>),
+    /// Invalidate boot state and erase active partition for new image, reply `Ack(0)` after erase
+    Prepare(ImageInfo),
+    /// Write data at offset of active partition, reply `Ack(next)` after write
+    Write {
+        offset: u32,
+        data: &'a [u8],
+        next: u32,
+    },
+    /// Image and trailer are received, verify `len` bytes of active partition with signature.
+    /// Write `BootState` when the signature is valid, reply `Ack` and boot new image.
+    Finish {
+        signature: [u8; SIGNATURE_LEN],
+        len: u32,
+        trailer: ImageTrailer,
+    },
+    /// Update is cancelled, reply `Ack` and back to normal operation
+    Abort,
+}
+
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+struct Session {
+    info: ImageInfo,
+    /// Offset of last written block, for retransmission
+    last_offset: u32,
+    next_offset: u32,
+    crc: Crc32,
+    /// Copy of trailer, it's the last part of image
+    trailer: [u8; TRAILER_LEN],
+}
+
+/// Board side state machine of update session.
+pub struct UpdateReceiver {
+    board: BoardInfo,
+    session: Option<Session>,
+}
+
+impl UpdateReceiver {
+    pub const fn new(model_ver: &str, capacity: u32) -> Self {
+        Self {
+            board: BoardInfo {
+                capacity,
+                model_ver: model_ver_field(model_ver),
+            },
+            session: None,
+        }
+    }
+
+    /// Drop current session, e.g. flash access is failed
+    pub fn reset(&mut self) {
+        self.session = None;
+    }
+
+    pub fn handle<'a>(
+        &mut self,
+        frame: &UpdateFrame<'a>,
+    ) -> Result<ReceiverAction<'a>, UpdateError> {
+        match *frame {
+            UpdateFrame::Hello => Ok(ReceiverAction::Reply(UpdateFrame::Ready(self.board))),
+            UpdateFrame::Begin(info) => {
+                self.session = None;
+
+                if info.model_ver != self.board.model_ver {
+                    return Err(UpdateError::ModelMismatch);
+                }
+                if info.size <= TRAILER_LEN as u32 {
+                    return Err(UpdateError::BadTrailer);
+                }
+                if self.board.capacity < info.size {
+                    return Err(UpdateError::TooLarge);
+                }
+
+                self.session = Some(Session {
+                    info,
+                    last_offset: 0,
+                    next_offset: 0,
+                    crc: Crc32::new(),
+                    trailer: [0u8; TRAILER_LEN],
+                });
+
+                Ok(ReceiverAction::Prepare(info))
+            }
+            UpdateFrame::Block { offset, data } => {
+                let session = self.session.as_mut().ok_or(UpdateError::NotStarted)?;
+                let next = offset + data.len() as u32;
+
+                if (offset == session.last_offset) && (next == session.next_offset) && (0 < next) {
+                    // Ack was lost and host sent same block again, already written
+                    return Ok(ReceiverAction::Reply(UpdateFrame::Ack(next)));
+                }
+                if offset != session.next_offset {
+                    return Err(UpdateError::BadOffset);
+                }
+                if session.info.size < next {
+                    return Err(UpdateError::TooLarge);
+                }
+
+                session.crc.update(data);
+                session.last_offset = offset;
+                session.next_offset = next;
+
+                let trailer_start = session.info.size - TRAILER_LEN as u32;
+                for (pos, &byte) in (offset..next).zip(data) {
+                    if trailer_start <= pos {
+                        session.trailer[(pos - trailer_start) as usize] = byte;
+                    }
+                }
+
+                Ok(ReceiverAction::Write { offset, data, next })
+            }
+            UpdateFrame::Finish { signature } => {
+                let session = self.session.take().ok_or(UpdateError::NotStarted)?;
+
+                if session.next_offset != session.info.size {
+                    return Err(UpdateError::BadOffset);
+                }
+                if session.crc.finish() != session.info.crc {
+                    return Err(UpdateError::BadImageCrc);
+                }
+
+                // `Begin` is not signed, trailer is checked again after signature is verified
+                let trailer = ImageTrailer::from_bytes(&session.trailer)?;
+                if trailer.image_size != (session.info.size - TRAILER_LEN as u32) {
+                    return Err(UpdateError::BadTrailer);
+                }
+                if trailer.model_ver != self.board.model_ver {
+                    return Err(UpdateError::ModelMismatch);
+                }
+
+                Ok(ReceiverAction::Finish {
+                    signature,
+                    len: session.info.size,
+                    trailer,
+                })
+            }
+            UpdateFrame::Abort => {
+                self.session = None;
+                Ok(ReceiverAction::Abort)
+            }
+            UpdateFrame::Ready(_) | UpdateFrame::Ack(_) | UpdateFrame::Nack(_) => {
+                Err(UpdateError::UnsupportedCmd)
+            }
+        }
+    }
+}
+
+/// What host should do next
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub enum UploaderStep<'a> {
+    /// Send the frame and wait answer
+    Send(UpdateFrame<'a>),
+    /// Update is done, board will reset and boot new image
+    Done,
+}
+
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+enum UploaderState {
+    Hello,
+    Begin,
+    Block(u32),
+    Finish,
+    Done,
+}
+
+/// Host side state machine of update session, signed image is borrowed whole.
+pub struct Uploader<'a> {
+    image: &'a [u8],
+    signature: [u8; SIGNATURE_LEN],
+    info: ImageInfo,
+    state: UploaderState,
+}
+
+impl<'a> Uploader<'a> {
+    pub fn new(signed: &SignedImage<'a>) -> Self {
+        Self {
+            image: signed.payload,
+            signature: signed.signature,
+            info: ImageInfo {
+                size: signed.payload.len() as u32,
+                crc: Crc32::checksum(signed.payload),
+                model_ver: signed.trailer.model_ver,
+            },
+            state: UploaderState::Hello,
+        }
+    }
+
+    /// Sent bytes of image, for progress indication
+    pub fn progress(&self) -> (u32, u32) {
+        match self.state {
+            UploaderState::Hello | UploaderState::Begin => (0, self.info.size),
+            UploaderState::Block(offset) => (offset, self.info.size),
+            UploaderState::Finish | UploaderState::Done => (self.info.size, self.info.size),
+        }
+    }
+
+    /// Frame to be sent on current state, same frame is given again for retransmission
+    pub fn step(&self) -> UploaderStep<'a> {
+        match self.state {
+            UploaderState::Hello => UploaderStep::Send(UpdateFrame::Hello),
+            UploaderState::Begin => UploaderStep::Send(UpdateFrame::Begin(self.info)),
+            UploaderState::Block(offset) => {
+                let start = offset as usize;
+                let end = (start + UPDATE_BLOCK_SIZE).min(self.image.len());
+                UploaderStep::Send(UpdateFrame::Block {
+                    offset,
+                    data: &self.image[start..end],
+                })
+            }
+            UploaderState::Finish => UploaderStep::Send(UpdateFrame::Finish {
+                signature: self.signature,
+            }),
+            UploaderState::Done => UploaderStep::Done,
+        }
+    }
+
+    /// Apply answer of board, `Nack` or unexpected answer stops the session
+    pub fn answer(&mut self, frame: &UpdateFrame) -> Result<(), UpdateError> {
+        self.state = match (self.state, frame) {
+            (_, UpdateFrame::Nack(reason)) => return Err(*reason),
+            (UploaderState::Hello, UpdateFrame::Ready(board)) => {
+                if board.model_ver != self.info.model_ver {
+                    return Err(UpdateError::ModelMismatch);
+                }
+                if board.capacity < self.info.size {
+                    return Err(UpdateError::TooLarge);
+                }
+                UploaderState::Begin
+            }
+            (UploaderState::Begin, UpdateFrame::Ack(0)) => UploaderState::Block(0),
+            (UploaderState::Block(_), UpdateFrame::Ack(next)) => match *next {
+                next if next == self.info.size => UploaderState::Finish,
+                next if next < self.info.size => UploaderState::Block(next),
+                _ => return Err(UpdateError::BadOffset),
+            },
+            (UploaderState::Finish, UpdateFrame::Ack(_)) => UploaderState::Done,
+            _ => return Err(UpdateError::UnsupportedCmd),
+        };
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+
+    /// Stand-in of Ed25519 for loopback, crypto is tested with host tools
+    fn fake_sign(payload: &[u8]) -> [u8; SIGNATURE_LEN] {
+        let mut ret = [0u8; SIGNATURE_LEN];
+        ret[..4].copy_from_slice(&Crc32::checksum(payload).to_le_bytes());
+        ret
+    }
+
+    /// Board side simulation, active partition is plain memory
+    struct Board {
+        receiver: UpdateReceiver,
+        stream: UpdateStream,
+        active: Vec<u8>,
+        installed: bool,
+    }
+
+    impl Board {
+        fn new(model_ver: &str, capacity: usize) -> Self {
+            Self {
+                receiver: UpdateReceiver::new(model_ver, capacity as u32),
+                stream: UpdateStream::new(),
+                active: std::vec![0xFF; capacity],
+                installed: false,
+            }
+        }
+
+        fn receive(&mut self, raw: &[u8]) -> Vec<Vec<u8>> {
+            let mut replies = Vec::new();
+            let mut frame_buf = [0u8; UPDATE_FRAME_MAX];
+            let mut tx_buf = [0u8; UPDATE_FRAME_MAX];
+            self.stream.push(raw);
+
+            while let Some(result) = self.stream.pop(&mut frame_buf) {
+                let Ok(len) = result else {
+                    continue;
+                };
+                let (frame, _) = decode(&frame_buf[..len]).unwrap();
+
+                let reply = match self.receiver.handle(&frame) {
+                    Ok(ReceiverAction::Reply(reply)) => reply,
+                    Ok(ReceiverAction::Prepare(_)) => {
+                        self.active.fill(0xFF);
+                        UpdateFrame::Ack(0)
+                    }
+                    Ok(ReceiverAction::Write { offset, data, next }) => {
+                        let offset = offset as usize;
+                        self.active[offset..offset + data.len()].copy_from_slice(data);
+                        UpdateFrame::Ack(next)
+                    }
+                    Ok(ReceiverAction::Finish { signature, len, .. }) => {
+                        match fake_sign(&self.active[..len as usize]) == signature {
+                            true => {
+                                self.installed = true;
+                                UpdateFrame::Ack(0)
+                            }
+                            false => UpdateFrame::Nack(UpdateError::BadSignature),
+                        }
+                    }
+                    Ok(ReceiverAction::Abort) => UpdateFrame::Ack(0),
+                    Err(e) => UpdateFrame::Nack(e),
+                };
+
+                replies.push(encode(&reply, &mut tx_buf).unwrap().to_vec());
+            }
+
+            replies
+        }
+    }
+
+    fn image(len: usize) -> Vec<u8> {
+        (0..len).map(|x| (x * 7 + 3) as u8).collect()
+    }
+
+    fn trailer(image_size: usize, model_ver: &str) -> ImageTrailer {
+        ImageTrailer {
+            image_size: image_size as u32,
+            model_ver: model_ver_field(model_ver),
+            firmware_ver: *b"0.4.0\0\0\0\0\0\0\0\0\0\0\0",
+            git_hash: [0xA5; GIT_HASH_LEN],
+            is_nda: false,
+            is_dirty: true,
+        }
+    }
+
+    /// Same layout with output of `fw-sign`
+    fn signed(image: &[u8], trailer: &ImageTrailer) -> Vec<u8> {
+        let mut ret = image.to_vec();
+        ret.extend(trailer.to_bytes());
+        let signature = fake_sign(&ret);
+        ret.extend(signature);
+        ret
+    }
+
+    /// Run uploader against board, `lose_ack` drops n-th answer to test retransmission
+    fn upload(
+        board: &mut Board,
+        signed: &[u8],
+        lose_ack: Option<usize>,
+    ) -> Result<(), UpdateError> {
+        let signed = SignedImage::parse(signed)?;
+        let mut uploader = Uploader::new(&signed);
+        let mut tx_buf = [0u8; UPDATE_FRAME_MAX];
+        let mut sent = 0;
+
+        while let UploaderStep::Send(frame) = uploader.step() {
+            let raw = encode(&frame, &mut tx_buf).unwrap();
+            // deliver fragmented
+            let mut replies = Vec::new();
+            for chunk in raw.chunks(7) {
+                replies.extend(board.receive(chunk));
+            }
+            sent += 1;
+            if Some(sent) == lose_ack {
+                continue;
+            }
+
+            assert_eq!(replies.len(), 1);
+            let (answer, _) = decode(&replies[0]).unwrap();
+            uploader.answer(&answer)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn roundtrip() {
+        let data = image(UPDATE_BLOCK_SIZE);
+        let frames = [
+            UpdateFrame::Hello,
+            UpdateFrame::Begin(ImageInfo {
+                size: 1234,
+                crc: 0xDEAD_BEEF,
+                model_ver: model_ver_field("MINI-0V5"),
+            }),
+            UpdateFrame::Block {
+                offset: 0x100,
+                data: &data,
+            },
+            UpdateFrame::Finish {
+                signature: [0x5A; SIGNATURE_LEN],
+            },
+            UpdateFrame::Abort,
+            UpdateFrame::Ready(BoardInfo {
+                capacity: 28 * 1024,
+                model_ver: model_ver_field("MINI-0V5"),
+            }),
+            UpdateFrame::Ack(0x80),
+            UpdateFrame::Nack(UpdateError::ModelMismatch),
+        ];
+
+        for frame in frames {
+            let mut buf = [0u8; UPDATE_FRAME_MAX];
+            let raw = encode(&frame, &mut buf).unwrap();
+            assert_eq!(decode(raw), Ok((frame, raw.len())));
+        }
+    }
+
+    #[test]
+    fn hello_in_other_protocol() {
+        let mut buf = [0u8; UPDATE_FRAME_MAX];
+        let hello = encode(&UpdateFrame::Hello, &mut buf).unwrap().to_vec();
+        let mut raw = std::vec![0x02, 0x00, 0x05, UPDATE_SOF, 0x03];
+        assert!(!contains_hello(&raw));
+
+        raw.extend(&hello);
+        assert!(contains_hello(&raw));
+    }
+
+    #[test]
+    fn crc_check_values() {
+        assert_eq!(crc16(b"123456789"), 0x29B1);
+        assert_eq!(Crc32::checksum(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn trailer_roundtrip() {
+        let trailer = trailer(1000, "MINI-0V5");
+        assert_eq!(ImageTrailer::from_bytes(&trailer.to_bytes()), Ok(trailer));
+
+        let mut raw = trailer.to_bytes();
+        raw[0] = b'X';
+        assert_eq!(ImageTrailer::from_bytes(&raw), Err(UpdateError::BadTrailer));
+
+        let data = image(1000);
+        let signed = signed(&data, &trailer);
+        assert_eq!(SignedImage::parse(&signed).unwrap().trailer, trailer);
+        assert_eq!(
+            SignedImage::parse(&signed[1..]),
+            Err(UpdateError::BadTrailer)
+        );
+    }
+
+    #[test]
+    fn loopback_update() {
+        let mut board = Board::new("MINI-0V5", 4096);
+        let data = image(1000); // last block is not full
+        let trailer = trailer(data.len(), "MINI-0V5");
+
+        assert_eq!(upload(&mut board, &signed(&data, &trailer), None), Ok(()));
+        assert!(board.installed);
+        assert_eq!(&board.active[..data.len()], &data[..]);
+        assert_eq!(
+            &board.active[data.len()..data.len() + TRAILER_LEN],
+            &trailer.to_bytes()
+        );
+    }
+
+    #[test]
+    fn loopback_lost_ack() {
+        let mut board = Board::new("MINI-0V5", 4096);
+        let data = image(1000);
+        let signed = signed(&data, &trailer(data.len(), "MINI-0V5"));
+
+        // 4th frame is second block
+        assert_eq!(upload(&mut board, &signed, Some(4)), Ok(()));
+        assert!(board.installed);
+        assert_eq!(&board.active[..data.len()], &data[..]);
+    }
+
+    #[test]
+    fn loopback_model_mismatch() {
+        let mut board = Board::new("MINI-0V5", 4096);
+        let data = image(1000);
+        let signed = signed(&data, &trailer(data.len(), "0V4"));
+
+        assert_eq!(
+            upload(&mut board, &signed, None),
+            Err(UpdateError::ModelMismatch)
+        );
+        assert!(!board.installed);
+
+        // board also rejects, when host skips the check
+        let mut receiver = UpdateReceiver::new("MINI-0V5", 4096);
+        let begin = UpdateFrame::Begin(ImageInfo {
+            size: 1000,
+            crc: 0,
+            model_ver: model_ver_field("0V4"),
+        });
+        assert_eq!(receiver.handle(&begin), Err(UpdateError::ModelMismatch));
+    }
+
+    #[test]
+    fn trailer_model_is_checked() {
+        // `Begin` claims the board model, but signed trailer is built for other hardware
+        let data = image(300);
+        let payload = &signed(&data, &trailer(data.len(), "0V4"))[..data.len() + TRAILER_LEN];
+        let mut receiver = UpdateReceiver::new("MINI-0V5", 4096);
+        let begin = UpdateFrame::Begin(ImageInfo {
+            size: payload.len() as u32,
+            crc: Crc32::checksum(payload),
+            model_ver: model_ver_field("MINI-0V5"),
+        });
+        assert!(receiver.handle(&begin).is_ok());
+
+        for (idx, chunk) in payload.chunks(UPDATE_BLOCK_SIZE).enumerate() {
+            let block = UpdateFrame::Block {
+                offset: (idx * UPDATE_BLOCK_SIZE) as u32,
+                data: chunk,
+            };
+            assert!(receiver.handle(&block).is_ok());
+        }
+
+        let finish = UpdateFrame::Finish {
+            signature: fake_sign(payload),
+        };
+        assert_eq!(receiver.handle(&finish), Err(UpdateError::ModelMismatch));
+    }
+
+    #[test]
+    fn tampered_image_is_rejected() {
+        let mut board = Board::new("MINI-0V5", 4096);
+        let data = image(1000);
+        let mut signed = signed(&data, &trailer(data.len(), "MINI-0V5"));
+        signed[500] ^= 0xFF;
+
+        assert_eq!(
+            upload(&mut board, &signed, None),
+            Err(UpdateError::BadSignature)
+        );
+        assert!(!board.installed);
+    }
+
+    #[test]
+    fn corrupted_image_is_rejected() {
+        let data = image(300);
+        let mut receiver = UpdateReceiver::new("MINI-0V5", 4096);
+        let begin = UpdateFrame::Begin(ImageInfo {
+            size: data.len() as u32,
+            crc: Crc32::checksum(&data) ^ 1,
+            model_ver: model_ver_field("MINI-0V5"),
+        });
+        assert!(receiver.handle(&begin).is_ok());
+
+        for (idx, chunk) in data.chunks(UPDATE_BLOCK_SIZE).enumerate() {
+            let block = UpdateFrame::Block {
+                offset: (idx * UPDATE_BLOCK_SIZE) as u32,
+                data: chunk,
+            };
+            assert!(receiver.handle(&block).is_ok());
+        }
+
+        let finish = UpdateFrame::Finish {
+            signature: fake_sign(&data),
+        };
+        assert_eq!(receiver.handle(&finish), Err(UpdateError::BadImageCrc));
+    }
+
+    #[test]
+    fn stream_recovers_from_noise() {
+        let mut stream = UpdateStream::new();
+        let mut buf = [0u8; UPDATE_FRAME_MAX];
+        let mut out = [0u8; UPDATE_FRAME_MAX];
+        let raw = encode(&UpdateFrame::Hello, &mut buf).unwrap();
+
+        stream.push(&[0x02, 0x00, UPDATE_SOF, 0xFF, 0xFF]);
+        stream.push(raw);
+
+        let mut frames = Vec::new();
+        while let Some(result) = stream.pop(&mut out) {
+            if let Ok(len) = result {
+                frames.push(out[..len].to_vec());
+            }
+        }
+
+        assert_eq!(frames, std::vec![raw.to_vec()]);
+    }
+
+    #[test]
+    fn boot_state_round_trip() {
+        let erased = [0xFFu8; BOOT_STATE_LEN];
+        assert_eq!(
+            BootState::from_bytes(&erased),
+            BootState {
+                image: None,
+                is_update_requested: false,
+            }
+        );
+
+        let state = BootState {
+            image: Some(InstalledImage {
+                trailer: trailer(1000, "MINI-0V5"),
+                signature: [0x5A; SIGNATURE_LEN],
+            }),
+            is_update_requested: false,
+        };
+        assert_eq!(BootState::from_bytes(&state.to_bytes()), state);
+    }
+
+    #[test]
+    fn update_request_is_programmed_on_erased_part() {
+        let state = BootState {
+            image: Some(InstalledImage {
+                trailer: trailer(1000, "MINI-0V5"),
+                signature: [0x5A; SIGNATURE_LEN],
+            }),
+            is_update_requested: false,
+        };
+        let mut raw = state.to_bytes();
+        assert!(raw[UPDATE_REQUEST_OFFSET..].iter().all(|&x| x == 0xFF));
+
+        raw[UPDATE_REQUEST_OFFSET..].copy_from_slice(&UPDATE_REQUEST_MAGIC);
+        let requested = BootState::from_bytes(&raw);
+        assert!(requested.is_update_requested);
+        assert_eq!(requested.image, state.image);
+    }
+}
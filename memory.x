@@ -4,12 +4,11 @@
  * SPDX-License-Identifier: MIT OR Apache-2.0
  */
 
-/* STM32G030C8 */
-MEMORY
-{
-  FLASH : ORIGIN = 0x08000000, LENGTH = 64K
-  RAM : ORIGIN = 0x20000000, LENGTH = 8K
-}
+/*
+ * STM32G030C8
+ * MEMORY is selected by `build.rs`, `memory/standalone.x` or `memory/fw-update.x`
+ */
+INCLUDE memory-layout.x
 
 /*
  * Mass-Production usage ELF section
@@ -0,0 +1,29 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+/*
+ * STM32G030C8, application on active partition behind bootloader, `fw_update` feature.
+ * Should be same with `bootloader/memory.x`.
+ * Bootloader receives new image directly on active partition, there's no DFU partition for swap.
+ * Swap layout needs application under 20K, `firmware size` workflow reports it.
+ */
+MEMORY
+{
+  BOOTLOADER       : ORIGIN = 0x08000000, LENGTH = 20K
+  BOOTLOADER_STATE : ORIGIN = 0x08005000, LENGTH = 2K
+  FLASH            : ORIGIN = 0x08005800, LENGTH = 42K
+  RAM              : ORIGIN = 0x20000000, LENGTH = 8K
+}
+
+__bootloader_state_start = ORIGIN(BOOTLOADER_STATE) - ORIGIN(BOOTLOADER);
+__bootloader_state_end = ORIGIN(BOOTLOADER_STATE) + LENGTH(BOOTLOADER_STATE) - ORIGIN(BOOTLOADER);
+
+/*
+ * `ImageTrailer` (64 bytes) is appended after the image by `fw-sign`, it should fit in active partition too.
+ * Image ends at load address of `.data`, see `link.x` of cortex-m-rt.
+ */
+ASSERT(__sidata + (__edata - __sdata) + 64 <= ORIGIN(FLASH) + LENGTH(FLASH),
+  "Image and trailer don't fit in active partition, see `memory/fw-update.x`");
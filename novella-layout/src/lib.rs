@@ -0,0 +1,836 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! EEPROM layout of Novella, the EEPROM layer of BillMock firmware.
+//! Every released layout is kept here, thus firmware migrates contents of older layout
+//! on boot instead of treating them as broken and healing them to zero.
+//! The crate is independent from hardware, migration is tested on host with dump images.
+//!
+//! +---------------------------- 24C16, 2048 bytes ---------------------------+
+//! | 0x000 ..= 0x7EF | sections, each section has power of two slots        |
+//! | 0x7F0 ..= 0x7FF | layout header, single page                           |
+//! +--------------------------------------------------------------------------+
+//!
+//! Layout header
+//! +-------+---------+----------------------------------------+-------------+
+//! | 0     | 1       | 2 ..= 13                               | 14 ..= 15   |
+//! +-------+---------+----------------------------------------+-------------+
+//! | 'N'   | version | type ID of each section, 0xFF padded   | CRC16 (LE)  |
+//! +-------+---------+----------------------------------------+-------------+
+//! - Image without valid header is written by firmware before layout versioning.
+
+#![no_std]
+
+#[cfg(test)] // for the dump image test code
+extern crate std;
+
+/// 24C16, 16Kbits
+pub const EEPROM_SIZE: u16 = 2048;
+pub const PAGE_SIZE: usize = 16;
+/// `embassy_time::Duration` ticks on head of every slot
+pub const UPTIME_SIZE: usize = 8;
+/// CRC16 on tail of every slot
+pub const CHECKSUM_SIZE: usize = 2;
+/// Type ID of every section should fit in the header
+pub const SECTION_MAX: usize = 12;
+/// Longest data of a section
+pub const SECTION_DATA_MAX: usize = 64;
+/// Layout header is placed on the last page, section cannot be placed on it
+pub const HEADER_ADDR: u16 = EEPROM_SIZE - PAGE_SIZE as u16;
+const HEADER_MAGIC: u8 = b'N';
+const HEADER_PAD: u8 = 0xFF;
+
+/// Type ID of section, high nibble is kind of the section and low nibble is revision of its type.
+/// Revision is increased when the type is changed, the kind is never reused for other section.
+pub mod type_id {
+    pub const P1_CARD_CNT: u8 = 0x10;
+    pub const P2_CARD_CNT: u8 = 0x20;
+    pub const P1_COIN_CNT: u8 = 0x30;
+    pub const P2_COIN_CNT: u8 = 0x40;
+    pub const FAULT_LOG: u8 = 0x50;
+    pub const HW_BOOT_CNT: u8 = 0x60;
+    pub const TERMINAL_ID: u8 = 0x70;
+    pub const CARD_PORT_BACKUP: u8 = 0x80;
+    pub const PAYOUT_CNT: u8 = 0x90;
+    /// 36 bytes, until cabinet profile
+    pub const INSTALL_CONFIG_V0: u8 = 0xA0;
+    /// 42 bytes, link role, language, safe-state exempt, inhibit policy and output drive mode
+    /// are appended
+    pub const INSTALL_CONFIG: u8 = 0xA1;
+    /// 8 bytes, player 1 and 2
+    pub const SERVICE_CNT_V0: u8 = 0xB0;
+    /// 16 bytes, player 1 to 4
+    pub const SERVICE_CNT: u8 = 0xB1;
+    /// Card and coin counters of player 3 and 4, 16 bytes
+    pub const EXTRA_PLAYER_CNT: u8 = 0xC0;
+
+    pub const fn kind(type_id: u8) -> u8 {
+        type_id >> 4
+    }
+}
+
+/// Placement of a section
+#[repr(C)]
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub struct NvSectionInfo {
+    /// Start address in bytes, not page index
+    pub sect_start_page: u16,
+    pub slot_num: u8,
+    /// Pages of single slot
+    pub slot_size: u8,
+    pub real_data_size: u8,
+    pub type_id: u8,
+}
+
+impl NvSectionInfo {
+    /// Place sections from start of EEPROM in the order, `(type_id, slot_num, data_size)`.
+    /// Slot has uptime and checksum with the data, slot size is rounded up to pages.
+    pub const fn layout<const N: usize>(sections: [(u8, u8, usize); N]) -> [Self; N] {
+        let mut table = [Self {
+            sect_start_page: 0,
+            slot_num: 0,
+            slot_size: 0,
+            real_data_size: 0,
+            type_id: 0,
+        }; N];
+        let mut start = 0;
+        let mut idx = 0;
+
+        assert!(N <= SECTION_MAX, "Too many sections for layout header");
+
+        while idx < N {
+            let (type_id, slot_num, data_size) = sections[idx];
+            let slot_size = (UPTIME_SIZE + data_size + CHECKSUM_SIZE).div_ceil(PAGE_SIZE);
+
+            // robin is 7 bits and next slot is masked by `slot_num - 1`
+            assert!(slot_num.is_power_of_two() && (slot_num <= 0x80));
+            assert!(data_size <= SECTION_DATA_MAX);
+
+            table[idx] = Self {
+                sect_start_page: start as u16,
+                slot_num,
+                slot_size: slot_size as u8,
+                real_data_size: data_size as u8,
+                type_id,
+            };
+            start += slot_num as usize * slot_size * PAGE_SIZE;
+            idx += 1;
+        }
+
+        assert!(
+            start <= HEADER_ADDR as usize,
+            "Novella sections exceed EEPROM_SIZE"
+        );
+
+        table
+    }
+
+    pub const fn kind(&self) -> u8 {
+        type_id::kind(self.type_id)
+    }
+
+    /// Address of the slot
+    pub const fn slot_addr(&self, slot_idx: u8) -> u16 {
+        self.sect_start_page + (self.slot_size as u16 * slot_idx as u16) * PAGE_SIZE as u16
+    }
+
+    const fn is_same(&self, other: &Self) -> bool {
+        (self.sect_start_page == other.sect_start_page)
+            && (self.slot_num == other.slot_num)
+            && (self.slot_size == other.slot_size)
+            && (self.real_data_size == other.real_data_size)
+            && (self.type_id == other.type_id)
+    }
+}
+
+pub const fn total_slot_num(sections: &[NvSectionInfo]) -> usize {
+    let mut sum = 0;
+    let mut idx = 0;
+
+    while idx < sections.len() {
+        sum += sections[idx].slot_num as usize;
+        idx += 1;
+    }
+
+    sum
+}
+
+const fn total_data_size(sections: &[NvSectionInfo]) -> usize {
+    let mut sum = 0;
+    let mut idx = 0;
+
+    while idx < sections.len() {
+        sum += sections[idx].real_data_size as usize;
+        idx += 1;
+    }
+
+    sum
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct NvLayout {
+    pub version: u8,
+    pub sections: &'static [NvSectionInfo],
+}
+
+impl NvLayout {
+    /// Compare with section table of firmware, for compile time assertion
+    pub const fn is_same(&self, sections: &[NvSectionInfo]) -> bool {
+        if self.sections.len() != sections.len() {
+            return false;
+        }
+
+        let mut idx = 0;
+        while idx < sections.len() {
+            if !self.sections[idx].is_same(&sections[idx]) {
+                return false;
+            }
+            idx += 1;
+        }
+
+        true
+    }
+
+    /// Section of the kind, see `type_id::kind`
+    pub fn section(&self, kind: u8) -> Option<&NvSectionInfo> {
+        self.sections.iter().find(|x| x.kind() == kind)
+    }
+
+    /// Released layout of the version
+    pub fn find(version: u8) -> Option<&'static NvLayout> {
+        RELEASED_LAYOUTS.iter().find(|x| x.version == version)
+    }
+}
+
+const SECTIONS_V0: [NvSectionInfo; 8] = NvSectionInfo::layout([
+    (type_id::P1_CARD_CNT, 16, 4),
+    (type_id::P2_CARD_CNT, 16, 4),
+    (type_id::P1_COIN_CNT, 16, 4),
+    (type_id::P2_COIN_CNT, 16, 4),
+    (type_id::FAULT_LOG, 8, 6),
+    (type_id::HW_BOOT_CNT, 8, 4),
+    (type_id::TERMINAL_ID, 8, 13),
+    (type_id::CARD_PORT_BACKUP, 8, 32),
+]);
+
+const SECTIONS_V1: [NvSectionInfo; 11] = NvSectionInfo::layout([
+    (type_id::P1_CARD_CNT, 16, 4),
+    (type_id::P2_CARD_CNT, 16, 4),
+    (type_id::P1_COIN_CNT, 16, 4),
+    (type_id::P2_COIN_CNT, 16, 4),
+    (type_id::FAULT_LOG, 8, 6),
+    (type_id::HW_BOOT_CNT, 8, 4),
+    (type_id::TERMINAL_ID, 8, 13),
+    (type_id::CARD_PORT_BACKUP, 8, 32),
+    (type_id::PAYOUT_CNT, 2, 4),
+    (type_id::INSTALL_CONFIG_V0, 1, 36),
+    (type_id::SERVICE_CNT_V0, 1, 8),
+]);
+
+const SECTIONS_V2: [NvSectionInfo; 12] = NvSectionInfo::layout([
+    (type_id::P1_CARD_CNT, 16, 4),
+    (type_id::P2_CARD_CNT, 16, 4),
+    (type_id::P1_COIN_CNT, 16, 4),
+    (type_id::P2_COIN_CNT, 16, 4),
+    (type_id::FAULT_LOG, 4, 6),
+    (type_id::HW_BOOT_CNT, 4, 4),
+    (type_id::TERMINAL_ID, 4, 13),
+    (type_id::CARD_PORT_BACKUP, 4, 32),
+    (type_id::PAYOUT_CNT, 2, 4),
+    (type_id::INSTALL_CONFIG, 2, 42),
+    (type_id::SERVICE_CNT, 2, 16),
+    (type_id::EXTRA_PLAYER_CNT, 8, 16),
+]);
+
+/// Firmware 0.4.0 and earlier, counters, fault log, boot count and card terminal backup,
+/// without header. It's the only released layout without header.
+pub const LAYOUT_V0: NvLayout = NvLayout {
+    version: 0,
+    sections: &SECTIONS_V0,
+};
+
+/// Not released. Development builds after 0.4.0 appended payout, install config and
+/// service counter without header. It's kept as reader of headerless image, see `LAYOUT_HEADERLESS`.
+pub const LAYOUT_V1: NvLayout = NvLayout {
+    version: 1,
+    sections: &SECTIONS_V1,
+};
+
+/// Install config is 42 bytes for link role, language, safe-state exempt, inhibit policy and
+/// output drive mode, install config and service counter are kept in two slots thus torn write
+/// doesn't wipe them. Service counter has four players, card and coin counters of player 3 and 4
+/// are appended in single section, layout header has room for `SECTION_MAX` sections only.
+/// Event sections are shrunk to 4 slots for them. First released layout with header,
+/// next release after 0.4.0
+pub const LAYOUT_V2: NvLayout = NvLayout {
+    version: 2,
+    sections: &SECTIONS_V2,
+};
+
+/// Every layout that firmware may find on EEPROM, version is the index.
+/// New layout should be appended here, and `MIGRATIONS` for the section whose type is changed.
+pub const RELEASED_LAYOUTS: [NvLayout; 3] = [LAYOUT_V0, LAYOUT_V1, LAYOUT_V2];
+
+/// Layout of running firmware, `novella_sections!` of firmware is checked against it.
+pub const LAYOUT_CURRENT: NvLayout = RELEASED_LAYOUTS[RELEASED_LAYOUTS.len() - 1];
+
+/// Reader of image without header. It's deliberately the superset `LAYOUT_V1`, not released
+/// `LAYOUT_V0`, thus image of development builds is migrated too. Sections were only appended
+/// before versioning, thus it reads image of `LAYOUT_V0` as it is and appended sections are
+/// just empty.
+pub const LAYOUT_HEADERLESS: NvLayout = LAYOUT_V1;
+
+/// Initial value of CRC peripheral for slot checksum, boards configure CRC with it
+pub const SLOT_CRC_INIT: u32 = 0xA097;
+const SLOT_CRC_POLY: u32 = 0x04C1_1DB7;
+
+/// Slot checksum in software, same with CRC peripheral of STM32G0 configured by boards.
+/// CRC-32 polynomial from `SLOT_CRC_INIT`, input bits are reversed and output is not,
+/// lower 16 bits are stored. `raw` is uptime followed by data of the slot.
+/// Reversal by word on `feed_words` equals reversal by byte on `feed_bytes` of same memory,
+/// thus uptime fed in words and data fed in bytes are computed in single byte stream.
+pub fn slot_checksum(raw: &[u8]) -> u16 {
+    raw.iter().fold(SLOT_CRC_INIT, |acc, x| {
+        (0..8).fold(acc ^ ((x.reverse_bits() as u32) << 24), |acc, _| {
+            match acc & 0x8000_0000 {
+                0 => acc << 1,
+                _ => (acc << 1) ^ SLOT_CRC_POLY,
+            }
+        })
+    }) as u16
+}
+
+/// CRC-16/CCITT-FALSE, header is checked without CRC peripheral of MCU
+fn crc16(raw: &[u8]) -> u16 {
+    raw.iter().fold(0xFFFF, |acc, x| {
+        (0..8).fold(acc ^ ((*x as u16) << 8), |acc, _| match acc & 0x8000 {
+            0 => acc << 1,
+            _ => (acc << 1) ^ 0x1021,
+        })
+    })
+}
+
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub struct NvLayoutHeader {
+    pub version: u8,
+    pub type_ids: [u8; SECTION_MAX],
+}
+
+impl NvLayoutHeader {
+    pub fn of(layout: &NvLayout) -> Self {
+        let mut type_ids = [HEADER_PAD; SECTION_MAX];
+        for (dst, section) in type_ids.iter_mut().zip(layout.sections) {
+            *dst = section.type_id;
+        }
+
+        Self {
+            version: layout.version,
+            type_ids,
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; PAGE_SIZE] {
+        let mut raw = [0u8; PAGE_SIZE];
+        raw[0] = HEADER_MAGIC;
+        raw[1] = self.version;
+        raw[2..2 + SECTION_MAX].copy_from_slice(&self.type_ids);
+
+        let checksum = crc16(&raw[..PAGE_SIZE - CHECKSUM_SIZE]);
+        raw[PAGE_SIZE - CHECKSUM_SIZE..].copy_from_slice(&checksum.to_le_bytes());
+        raw
+    }
+
+    /// None when magic or checksum is not matched, e.g. erased page
+    pub fn from_bytes(raw: &[u8; PAGE_SIZE]) -> Option<Self> {
+        let checksum = u16::from_le_bytes([raw[PAGE_SIZE - 2], raw[PAGE_SIZE - 1]]);
+
+        if (raw[0] != HEADER_MAGIC) || (crc16(&raw[..PAGE_SIZE - CHECKSUM_SIZE]) != checksum) {
+            return None;
+        }
+
+        let mut type_ids = [HEADER_PAD; SECTION_MAX];
+        type_ids.copy_from_slice(&raw[2..2 + SECTION_MAX]);
+
+        Some(Self {
+            version: raw[1],
+            type_ids,
+        })
+    }
+
+    /// Released layout of the header, None for unknown version or type IDs are not matched
+    pub fn layout(&self) -> Option<&'static NvLayout> {
+        NvLayout::find(self.version).filter(|layout| Self::of(layout) == *self)
+    }
+}
+
+/// Conversion of a section whose type is changed, `src` and `dst` are sized by each type
+pub struct NvMigration {
+    pub from: u8,
+    pub to: u8,
+    pub convert: fn(&[u8], &mut [u8]),
+}
+
+/// Fields are appended on the end or the reserved bytes, zero is default of new field
+fn zero_extend(src: &[u8], dst: &mut [u8]) {
+    let len = src.len().min(dst.len());
+    dst.fill(0);
+    dst[..len].copy_from_slice(&src[..len]);
+}
+
+pub const MIGRATIONS: [NvMigration; 2] = [
+    NvMigration {
+        from: type_id::INSTALL_CONFIG_V0,
+        to: type_id::INSTALL_CONFIG,
+        convert: zero_extend,
+    },
+    NvMigration {
+        from: type_id::SERVICE_CNT_V0,
+        to: type_id::SERVICE_CNT,
+        convert: zero_extend,
+    },
+];
+
+/// Returns false if there's no migration between the types
+fn convert(from: u8, to: u8, src: &[u8], dst: &mut [u8]) -> bool {
+    if from == to {
+        dst.copy_from_slice(src);
+        return true;
+    }
+
+    match MIGRATIONS.iter().find(|x| (x.from == from) && (x.to == to)) {
+        Some(migration) => {
+            (migration.convert)(src, dst);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Raw access of EEPROM for migration, firmware implements it with I2C and CRC peripheral.
+pub trait NvStorage {
+    type Error;
+
+    /// Read data of the slot, returns uptime ticks when checksum is valid.
+    /// Length of `data` is `real_data_size` of the section.
+    fn read_slot(
+        &mut self,
+        section: &NvSectionInfo,
+        slot_idx: u8,
+        data: &mut [u8],
+    ) -> Result<Option<u64>, Self::Error>;
+
+    fn write_slot(
+        &mut self,
+        section: &NvSectionInfo,
+        slot_idx: u8,
+        uptime: u64,
+        data: &[u8],
+    ) -> Result<(), Self::Error>;
+
+    fn read_header(&mut self) -> Result<[u8; PAGE_SIZE], Self::Error>;
+
+    fn write_header(&mut self, raw: &[u8; PAGE_SIZE]) -> Result<(), Self::Error>;
+}
+
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub enum NvMigrateOk {
+    /// Contents are already current layout
+    UpToDate,
+    /// Contents are moved from the version, second parameter is rewritten section count
+    Migrated(u8, u8),
+}
+
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub enum NvMigrateError<E> {
+    Storage(E),
+    /// Written by newer firmware, contents should not be touched
+    UnknownLayout(u8),
+}
+
+#[derive(Clone, Copy)]
+enum SectionPlan {
+    /// Same section on same place, nothing to do
+    Keep,
+    /// Source doesn't have valid slot, it's healed like broken slot
+    Empty,
+    /// Rewrite every slot with converted data and uptime of source
+    Rewrite(u64),
+}
+
+const MIGRATE_BUFFER_SIZE: usize = total_data_size(LAYOUT_CURRENT.sections);
+
+/// Newest valid slot of the section, later slot wins on same uptime
+fn newest_slot<S: NvStorage>(
+    storage: &mut S,
+    section: &NvSectionInfo,
+    dst: &mut [u8; SECTION_DATA_MAX],
+) -> Result<Option<u64>, S::Error> {
+    let len = section.real_data_size as usize;
+    let mut data = [0u8; SECTION_DATA_MAX];
+    let mut newest = None;
+
+    for slot_idx in 0..section.slot_num {
+        if let Some(uptime) = storage.read_slot(section, slot_idx, &mut data[..len])? {
+            if newest.iter().all(|x| *x <= uptime) {
+                newest = Some(uptime);
+                dst[..len].copy_from_slice(&data[..len]);
+            }
+        }
+    }
+
+    Ok(newest)
+}
+
+/// Move contents of the layout in the header to `LAYOUT_CURRENT`, run before reading sections.
+/// Every section is read before any write, because new section can be placed over old one.
+/// Header is written at last, thus interrupted migration runs again on next boot.
+/// Counters never moved in released layouts, they survive the interrupted migration.
+pub fn migrate<S: NvStorage>(storage: &mut S) -> Result<NvMigrateOk, NvMigrateError<S::Error>> {
+    let current = &LAYOUT_CURRENT;
+    let raw_header = storage.read_header().map_err(NvMigrateError::Storage)?;
+    let from = match NvLayoutHeader::from_bytes(&raw_header) {
+        Some(header) => header
+            .layout()
+            .ok_or(NvMigrateError::UnknownLayout(header.version))?,
+        None => &LAYOUT_HEADERLESS,
+    };
+
+    if from.version == current.version {
+        return Ok(NvMigrateOk::UpToDate);
+    }
+
+    let mut buffer = [0u8; MIGRATE_BUFFER_SIZE];
+    let mut plans = [SectionPlan::Keep; SECTION_MAX];
+    let mut offset = 0;
+
+    for (to, plan) in current.sections.iter().zip(plans.iter_mut()) {
+        let dst = &mut buffer[offset..offset + to.real_data_size as usize];
+        offset += to.real_data_size as usize;
+
+        let Some(src) = from.section(to.kind()) else {
+            *plan = SectionPlan::Empty;
+            continue;
+        };
+
+        if src == to {
+            continue;
+        }
+
+        let mut data = [0u8; SECTION_DATA_MAX];
+        *plan = match newest_slot(storage, src, &mut data).map_err(NvMigrateError::Storage)? {
+            Some(uptime) => {
+                let src_data = &data[..src.real_data_size as usize];
+                if !convert(src.type_id, to.type_id, src_data, dst) {
+                    // overwrite old type that cannot be converted
+                    dst.fill(0);
+                }
+                SectionPlan::Rewrite(uptime)
+            }
+            None => SectionPlan::Empty,
+        };
+    }
+
+    let mut rewritten = 0;
+    offset = 0;
+
+    for (to, plan) in current.sections.iter().zip(plans.iter()) {
+        let data = &buffer[offset..offset + to.real_data_size as usize];
+        offset += to.real_data_size as usize;
+
+        if let SectionPlan::Rewrite(uptime) = *plan {
+            for slot_idx in 0..to.slot_num {
+                storage
+                    .write_slot(to, slot_idx, uptime, data)
+                    .map_err(NvMigrateError::Storage)?;
+            }
+            rewritten += 1;
+        }
+    }
+
+    storage
+        .write_header(&NvLayoutHeader::of(current).to_bytes())
+        .map_err(NvMigrateError::Storage)?;
+
+    Ok(NvMigrateOk::Migrated(from.version, rewritten))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+
+    /// Dump of whole EEPROM
+    struct Image([u8; EEPROM_SIZE as usize]);
+
+    impl Image {
+        fn blank() -> Self {
+            Self([0xFF; EEPROM_SIZE as usize])
+        }
+
+        fn slot(&self, section: &NvSectionInfo, slot_idx: u8) -> core::ops::Range<usize> {
+            let start = section.slot_addr(slot_idx) as usize;
+            start..start + section.slot_size as usize * PAGE_SIZE
+        }
+
+        /// Newest data of the section, like `Novella::init` does
+        fn newest(&mut self, section: &NvSectionInfo) -> Option<(u64, Vec<u8>)> {
+            let mut data = [0u8; SECTION_DATA_MAX];
+            let uptime = newest_slot(self, section, &mut data).unwrap()?;
+            Some((uptime, data[..section.real_data_size as usize].to_vec()))
+        }
+    }
+
+    impl NvStorage for Image {
+        type Error = ();
+
+        fn read_slot(
+            &mut self,
+            section: &NvSectionInfo,
+            slot_idx: u8,
+            data: &mut [u8],
+        ) -> Result<Option<u64>, ()> {
+            let raw = &self.0[self.slot(section, slot_idx)];
+            let (body, checksum) = raw.split_at(raw.len() - CHECKSUM_SIZE);
+            let len = UPTIME_SIZE + data.len();
+
+            if slot_checksum(&body[..len]).to_le_bytes() != checksum {
+                return Ok(None);
+            }
+
+            data.copy_from_slice(&body[UPTIME_SIZE..len]);
+            Ok(Some(u64::from_le_bytes(
+                body[..UPTIME_SIZE].try_into().unwrap(),
+            )))
+        }
+
+        fn write_slot(
+            &mut self,
+            section: &NvSectionInfo,
+            slot_idx: u8,
+            uptime: u64,
+            data: &[u8],
+        ) -> Result<(), ()> {
+            let range = self.slot(section, slot_idx);
+            let raw = &mut self.0[range];
+            let len = UPTIME_SIZE + data.len();
+            let tail = raw.len() - CHECKSUM_SIZE;
+
+            raw.fill(0xFF);
+            raw[..UPTIME_SIZE].copy_from_slice(&uptime.to_le_bytes());
+            raw[UPTIME_SIZE..len].copy_from_slice(data);
+            let checksum = slot_checksum(&raw[..len]);
+            raw[tail..].copy_from_slice(&checksum.to_le_bytes());
+            Ok(())
+        }
+
+        fn read_header(&mut self) -> Result<[u8; PAGE_SIZE], ()> {
+            Ok(self.0[HEADER_ADDR as usize..].try_into().unwrap())
+        }
+
+        fn write_header(&mut self, raw: &[u8; PAGE_SIZE]) -> Result<(), ()> {
+            self.0[HEADER_ADDR as usize..].copy_from_slice(raw);
+            Ok(())
+        }
+    }
+
+    /// Distinct data of the section for the uptime
+    fn sample(section: &NvSectionInfo, uptime: u64) -> Vec<u8> {
+        (0..section.real_data_size)
+            .map(|x| section.type_id ^ x ^ uptime as u8)
+            .collect()
+    }
+
+    /// Dump of the layout as written by its firmware, few slots are written per section
+    fn dump(layout: &NvLayout) -> Image {
+        let mut image = Image::blank();
+
+        for section in layout.sections {
+            let last = section.slot_num - 1;
+            for (slot_idx, uptime) in [(0, 100), (last, 200), (1 & last, 300)] {
+                image
+                    .write_slot(section, slot_idx, uptime, &sample(section, uptime))
+                    .unwrap();
+            }
+        }
+
+        if LAYOUT_HEADERLESS.version < layout.version {
+            image
+                .write_header(&NvLayoutHeader::of(layout).to_bytes())
+                .unwrap();
+        }
+
+        image
+    }
+
+    #[test]
+    fn released_layouts() {
+        let v2: Vec<_> = LAYOUT_V2
+            .sections
+            .iter()
+            .map(|x| (x.sect_start_page, x.slot_num, x.slot_size, x.real_data_size))
+            .collect();
+        assert_eq!(
+            v2,
+            [
+                (0, 16, 1, 4),
+                (256, 16, 1, 4),
+                (512, 16, 1, 4),
+                (768, 16, 1, 4),
+                (1024, 4, 1, 6),
+                (1088, 4, 1, 4),
+                (1152, 4, 2, 13),
+                (1280, 4, 3, 32),
+                (1472, 2, 1, 4),
+                (1504, 2, 4, 42),
+                (1632, 2, 2, 16),
+                (1696, 8, 2, 16)
+            ]
+        );
+        assert_eq!(total_slot_num(LAYOUT_CURRENT.sections), 94);
+
+        for (idx, layout) in RELEASED_LAYOUTS.iter().enumerate() {
+            assert_eq!(layout.version as usize, idx);
+
+            // kind is unique in a layout
+            for section in layout.sections {
+                let same_kind = layout
+                    .sections
+                    .iter()
+                    .filter(|x| x.kind() == section.kind());
+                assert_eq!(same_kind.count(), 1);
+            }
+
+            // meters never move, interrupted migration relies on it
+            for kind in 1..=4 {
+                assert_eq!(layout.section(kind), LAYOUT_CURRENT.section(kind));
+            }
+        }
+    }
+
+    #[test]
+    fn slot_checksum_by_word() {
+        // word reversed input, what `feed_words` does on uptime
+        fn feed_word(acc: u32, word: u32) -> u32 {
+            (0..32).fold(acc ^ word.reverse_bits(), |acc, _| {
+                match acc & 0x8000_0000 {
+                    0 => acc << 1,
+                    _ => (acc << 1) ^ SLOT_CRC_POLY,
+                }
+            })
+        }
+
+        let uptime = 0x0123_4567_89AB_CDEFu64.to_le_bytes();
+        let by_word = uptime
+            .chunks(4)
+            .map(|x| u32::from_le_bytes(x.try_into().unwrap()))
+            .fold(SLOT_CRC_INIT, feed_word);
+
+        assert_eq!(slot_checksum(&uptime), by_word as u16);
+        assert_ne!(slot_checksum(&[0x00]), slot_checksum(&[0x00, 0x00]));
+    }
+
+    #[test]
+    fn header() {
+        let header = NvLayoutHeader::of(&LAYOUT_CURRENT);
+        let mut raw = header.to_bytes();
+
+        assert_eq!(NvLayoutHeader::from_bytes(&raw), Some(header));
+        assert_eq!(
+            header.layout().map(|x| x.version),
+            Some(LAYOUT_CURRENT.version)
+        );
+        assert_eq!(NvLayoutHeader::from_bytes(&[0xFF; PAGE_SIZE]), None);
+
+        raw[5] ^= 0x01;
+        assert_eq!(NvLayoutHeader::from_bytes(&raw), None);
+
+        // known version but different sections
+        let mut header = NvLayoutHeader::of(&LAYOUT_CURRENT);
+        header.type_ids[9] = type_id::INSTALL_CONFIG_V0;
+        assert!(header.layout().is_none());
+    }
+
+    #[test]
+    fn migrate_released_dumps() {
+        for layout in RELEASED_LAYOUTS.iter() {
+            let mut image = dump(layout);
+            let result = migrate(&mut image);
+
+            // image without header is read as `LAYOUT_HEADERLESS`
+            let from = layout.version.max(LAYOUT_HEADERLESS.version);
+            if layout.version == LAYOUT_CURRENT.version {
+                assert_eq!(result, Ok(NvMigrateOk::UpToDate));
+            } else {
+                assert!(matches!(result, Ok(NvMigrateOk::Migrated(v, _)) if v == from));
+            }
+
+            let header = NvLayoutHeader::from_bytes(&image.read_header().unwrap()).unwrap();
+            assert_eq!(header, NvLayoutHeader::of(&LAYOUT_CURRENT));
+
+            for to in LAYOUT_CURRENT.sections {
+                let newest = image.newest(to);
+
+                match layout.section(to.kind()) {
+                    // newest slot is kept with its uptime
+                    Some(from) if from.type_id == to.type_id => {
+                        assert_eq!(newest, Some((300, sample(from, 300))));
+                    }
+                    Some(from) => {
+                        let mut expected = sample(from, 300);
+                        expected.resize(to.real_data_size as usize, 0);
+                        assert_eq!(newest, Some((300, expected)));
+                    }
+                    // appended section is empty, `Novella::init` heals it
+                    None => assert_eq!(newest, None),
+                }
+            }
+
+            assert_eq!(migrate(&mut image), Ok(NvMigrateOk::UpToDate));
+        }
+    }
+
+    #[test]
+    fn install_config_is_extended() {
+        let mut image = dump(&LAYOUT_V1);
+        // every section after the counters is moved
+        assert_eq!(migrate(&mut image), Ok(NvMigrateOk::Migrated(1, 7)));
+
+        let section = LAYOUT_CURRENT
+            .section(type_id::kind(type_id::INSTALL_CONFIG))
+            .unwrap();
+        let mut data = [0u8; 42];
+        for slot_idx in 0..section.slot_num {
+            assert_eq!(image.read_slot(section, slot_idx, &mut data), Ok(Some(300)));
+            assert_eq!(data[36..], [0; 6]);
+        }
+    }
+
+    #[test]
+    fn blank_eeprom() {
+        let mut image = Image::blank();
+        assert_eq!(migrate(&mut image), Ok(NvMigrateOk::Migrated(1, 0)));
+
+        // only header is written, first boot is still detected
+        assert!(image.0[..HEADER_ADDR as usize].iter().all(|x| *x == 0xFF));
+        assert!(NvLayoutHeader::from_bytes(&image.read_header().unwrap()).is_some());
+    }
+
+    #[test]
+    fn unknown_layout() {
+        let mut image = dump(&LAYOUT_CURRENT);
+        let mut header = NvLayoutHeader::of(&LAYOUT_CURRENT);
+        header.version = 0x7F;
+        image.write_header(&header.to_bytes()).unwrap();
+        let before = image.0;
+
+        assert_eq!(
+            migrate(&mut image),
+            Err(NvMigrateError::UnknownLayout(0x7F))
+        );
+        assert_eq!(image.0, before);
+    }
+}
@@ -0,0 +1,133 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Health state machine for payment path.
+//! When a fault in safe-state policy is active, acceptors and card terminal are inhibited
+//! through `MutualInhibit`, and released automatically when the fault is recovered.
+
+use card_terminal_adapter::CardTerminalTxCmd;
+use embassy_time::{Duration, Instant};
+
+use crate::boards::*;
+use crate::types::install_config::InstallConfig;
+
+/// Check period, health sources are cheap but no need to check on every loop.
+const HEALTH_CHECK_PERIOD: Duration = Duration::from_millis(1000);
+/// Card terminal is silent during this time, ask terminal info to confirm link is alive.
+const TERMINAL_LINK_PROBE: Duration = Duration::from_secs(10);
+/// Card terminal doesn't respond even after probe, treat link as dead.
+const TERMINAL_LINK_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[repr(u8)]
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub enum HealthFault {
+    /// Eeprom doesn't respond, income counters and slot backup cannot be stored.
+    MissingEeprom = 0,
+    /// Card terminal had been connected but doesn't respond anymore.
+    TerminalLinkLost = 1,
+}
+
+impl HealthFault {
+    pub const fn mask(self) -> u8 {
+        1 << (self as u8)
+    }
+}
+
+/// Faults that force safe state (inhibit), faults out of policy are only logged.
+pub const DEFAULT_SAFE_STATE_POLICY: u8 =
+    HealthFault::MissingEeprom.mask() | HealthFault::TerminalLinkLost.mask();
+
+pub struct HealthMachine {
+    /// Bit mask of active `HealthFault`
+    active: u8,
+    /// Bit mask of `HealthFault` that force safe state
+    policy: u8,
+    last_check: Instant,
+    /// Last probe time for card terminal link
+    link_probe: Option<Instant>,
+}
+
+impl HealthMachine {
+    pub fn new() -> Self {
+        Self {
+            active: 0,
+            policy: DEFAULT_SAFE_STATE_POLICY,
+            last_check: Instant::now(),
+            link_probe: None,
+        }
+    }
+
+    /// Load safe-state policy, faults exempted by install config are only logged.
+    pub fn load(&mut self, config: &InstallConfig) {
+        let policy = DEFAULT_SAFE_STATE_POLICY & !config.safe_state_exempt;
+
+        if policy != self.policy {
+            defmt::info!("Health - safe state policy : {:02b}", policy);
+            self.policy = policy;
+        }
+    }
+
+    #[inline]
+    pub fn is_safe_state(&self) -> bool {
+        (self.active & self.policy) != 0
+    }
+
+    fn update(&mut self, fault: HealthFault, is_active: bool) {
+        let was_active = (self.active & fault.mask()) != 0;
+
+        if was_active != is_active {
+            match is_active {
+                true => defmt::error!("Health - fault {} detected", fault),
+                false => defmt::info!("Health - fault {} recovered", fault),
+            }
+        }
+
+        self.active = (self.active & !fault.mask()) | (fault.mask() * is_active as u8);
+    }
+
+    async fn check_terminal_link(&mut self, board: &'static Board, now: Instant) -> bool {
+        let card_reader = &board.hardware.card_reader;
+
+        // Card terminal is not installed or not yet connected, that's not a fault.
+        let Some(last_rx) = card_reader.last_rx() else {
+            return false;
+        };
+
+        // Keep probing while silent, terminal may come back after the link is lost.
+        let silent = now - last_rx;
+        let since_probe = now - self.link_probe.unwrap_or(last_rx).max(last_rx);
+        if TERMINAL_LINK_PROBE < silent && TERMINAL_LINK_PROBE < since_probe {
+            self.link_probe = Some(now);
+            card_reader
+                .send(CardTerminalTxCmd::RequestTerminalInfo)
+                .await;
+        }
+
+        TERMINAL_LINK_TIMEOUT < silent
+    }
+
+    /// Check health sources periodically, returns `Some(safe_state)` when safe state is changed.
+    pub async fn poll(&mut self, board: &'static Board) -> Option<bool> {
+        let now = Instant::now();
+        if now < (self.last_check + HEALTH_CHECK_PERIOD) {
+            return None;
+        }
+        self.last_check = now;
+
+        let prev = self.is_safe_state();
+
+        self.update(
+            HealthFault::MissingEeprom,
+            board.hardware.eeprom.is_missing(),
+        );
+
+        let link_lost = self.check_terminal_link(board, now).await;
+        self.update(HealthFault::TerminalLinkLost, link_lost);
+
+        let after = self.is_safe_state();
+        (prev != after).then_some(after)
+    }
+}
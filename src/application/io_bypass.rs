@@ -10,6 +10,7 @@ use super::{DEFAULT_BUSY_ALPHA_TIMING_MS, DEFAULT_VEND_INDICATOR_TIMING_MS};
 use crate::boards::*;
 use crate::semi_layer::buffered_wait::InputEventKind;
 use crate::types::input_port::{InputEvent, InputPortKind};
+use crate::types::player::Player;
 
 pub async fn io_bypass(board: &'static Board, event: &InputEvent, override_druation_force: bool) {
     let output = match board.correspond_output(&event.port) {
@@ -30,11 +31,11 @@ pub async fn io_bypass(board: &'static Board, event: &InputEvent, override_druat
     let led = board.correspond_indicator(&event.port);
     let busy = board.correspond_busy(&event.port);
 
-    match (event.port, event.event) {
-        (x, InputEventKind::LongPressed(0) | InputEventKind::LongPressed(1)) => {
-            warn!("{:?} too short pressed", x);
+    match (event.port.for_player(Player::Player1), event.event) {
+        (_, InputEventKind::LongPressed(0) | InputEventKind::LongPressed(1)) => {
+            warn!("{:?} too short pressed", event.port);
         }
-        (InputPortKind::Vend1P | InputPortKind::Vend2P, InputEventKind::LongPressed(x)) => {
+        (InputPortKind::Vend1P, InputEventKind::LongPressed(x)) => {
             let led_timing = if override_druation_force {
                 output.tick_tock(1).await;
 
@@ -55,7 +56,7 @@ pub async fn io_bypass(board: &'static Board, event: &InputEvent, override_druat
                 led.alt_tick_tock(1, led_timing, led_timing).await;
             }
         }
-        (InputPortKind::StartJam1P | InputPortKind::StartJam2P, _) => {
+        (InputPortKind::StartJam1P, _) => {
             // skip
         }
         (_, InputEventKind::Pressed) => {
@@ -7,7 +7,8 @@
 use card_terminal_adapter::types::IncomeArcadeRequest;
 
 use super::{DEFAULT_BUSY_ALPHA_TIMING_MS, DEFAULT_VEND_INDICATOR_TIMING_MS};
-use crate::components::eeprom;
+use crate::types::buffered_opendrain_kind::MeterKind;
+use crate::types::install_config::CabinetProfile;
 use crate::{boards::*, types::player::Player};
 
 #[derive(Debug, defmt::Format, Clone, Eq, PartialEq, Ord, PartialOrd)]
@@ -44,20 +45,51 @@ impl From<IncomeArcadeRequest> for PaymentReceive {
 }
 
 impl PaymentReceive {
-    pub async fn apply_output(self, board: &'static Board, override_druation_force: bool) -> Self {
-        let player = {
-            defmt::debug!("port 0x{:02X}", self.recv.port);
-            let temp = (self.recv.port.clamp(1, 4) - 1) & 0x1;
-
-            match temp {
-                0 => Player::Player1,
-                1 => Player::Player2,
-                x => {
-                    defmt::warn!("Something is wrong {}", x);
-                    Player::Player1
-                }
+    /// Add bonus credits in percent, for happy hour and etc.
+    pub fn with_bonus(mut self, percent: u16) -> Self {
+        if percent != 0 {
+            let origin = self.recv.pulse_count;
+            let bonus = (origin as u32 * percent as u32) / 100;
+
+            self.recv.pulse_count = (origin as u32 + bonus).min(u16::MAX as u32) as u16;
+            defmt::info!(
+                "Bonus {}% applied, {} -> {}",
+                percent,
+                origin,
+                self.recv.pulse_count
+            );
+        }
+
+        self
+    }
+
+    /// Decide player of held payment, output port follows the player.
+    pub fn assign(mut self, player: Player) -> Self {
+        self.origin = player;
+        self.recv.port = player.port_num();
+
+        self
+    }
+
+    /// Player that takes the payment, decided by port number
+    pub fn player(&self) -> Player {
+        defmt::debug!("port 0x{:02X}", self.recv.port);
+        Player::from_port_num(self.recv.port)
+    }
+
+    /// Income goes to the coin line of cabinet profile
+    pub fn for_cabinet(self, profile: CabinetProfile) -> Self {
+        match profile {
+            CabinetProfile::TwoPlayer => self,
+            x => {
+                let player = x.income_player(self.player());
+                self.assign(player)
             }
-        };
+        }
+    }
+
+    pub async fn apply_output(self, board: &'static Board, override_druation_force: bool) -> Self {
+        let player = self.player();
 
         if player != self.origin {
             defmt::warn!(
@@ -82,42 +114,22 @@ impl PaymentReceive {
                 .await;
         }
 
-        match player {
-            Player::Player1 => {
-                let count = board
-                    .hardware
-                    .eeprom
-                    .lock_read(eeprom::select::P1_CARD_CNT)
-                    .await;
-                let new_count = count + coin_cnt as u32;
-
-                board
-                    .hardware
-                    .eeprom
-                    .lock_write(eeprom::select::P1_CARD_CNT, new_count)
-                    .await;
-
-                defmt::debug!("P1_CARD_CNT, {} -> {}", count, new_count);
-            }
-            Player::Player2 => {
-                let count = board
-                    .hardware
-                    .eeprom
-                    .lock_read(eeprom::select::P2_CARD_CNT)
-                    .await;
-                let new_count = count + coin_cnt as u32;
-
-                board
-                    .hardware
-                    .eeprom
-                    .lock_write(eeprom::select::P2_CARD_CNT, new_count)
-                    .await;
-
-                defmt::debug!("P2_CARD_CNT, {} -> {}", count, new_count);
-            }
-            _ => {
-                defmt::error!("Unrecheable");
-            }
+        if player.index().is_some() {
+            let eeprom = &board.hardware.eeprom;
+            let count = eeprom.lock_read_income(MeterKind::Card, player).await;
+            let new_count = count + coin_cnt as u32;
+
+            eeprom
+                .lock_write_income(MeterKind::Card, player, new_count)
+                .await;
+
+            defmt::debug!("{} CARD_CNT, {} -> {}", player, count, new_count);
+        } else {
+            defmt::error!("Unrecheable");
+        }
+
+        if let Some(meter) = board.correspond_meter(MeterKind::Card) {
+            meter.count(coin_cnt as u16);
         }
 
         led.alt_tick_tock(
@@ -7,11 +7,65 @@
 use super::io_bypass::io_bypass;
 use super::pulse_meory_filter::PulseMemoryFilterMachine;
 use crate::boards::*;
-use crate::components::eeprom;
 use crate::semi_layer::buffered_wait::InputEventKind;
+use crate::types::buffered_opendrain_kind::MeterKind;
+use crate::types::dip_switch_config::AppMode0V3;
 use crate::types::input_port::{InputEvent, InputPortKind};
+use crate::types::install_config::*;
 use crate::types::player::Player;
 
+/// Input events after I/O routing, single input would fan-out to several outputs.
+pub struct RoutedEvents {
+    events: [Option<InputEvent>; IO_ROUTE_NUM],
+}
+
+/// Built-in routing of `STR` input on vend side, indexed by `AppMode0V3`.
+const APP_MODE_ROUTES: [RawIoRoute; 4] = [
+    // BypassStart
+    RawIoRoute::new(
+        InputPortKind::StartJam1P,
+        InputPortKind::Start1P,
+        IO_ROUTE_FLAG_ENABLE | IO_ROUTE_FLAG_MIRROR,
+    ),
+    // StartButtonDecideSerialToVend
+    RawIoRoute::new(
+        InputPortKind::StartJam1P,
+        InputPortKind::Start1P,
+        IO_ROUTE_FLAG_ENABLE | IO_ROUTE_FLAG_MIRROR,
+    ),
+    // BypassJam
+    RawIoRoute::new(
+        InputPortKind::StartJam1P,
+        InputPortKind::Jam1P,
+        IO_ROUTE_FLAG_ENABLE | IO_ROUTE_FLAG_MIRROR,
+    ),
+    // DisplayRom
+    RawIoRoute::new(
+        InputPortKind::StartJam1P,
+        InputPortKind::Jam1P,
+        IO_ROUTE_FLAG_ENABLE | IO_ROUTE_FLAG_MIRROR,
+    ),
+];
+
+impl RoutedEvents {
+    pub const fn empty() -> Self {
+        Self {
+            events: [None; IO_ROUTE_NUM],
+        }
+    }
+
+    /// Append event, event over capacity is dropped.
+    pub fn push(&mut self, event: InputEvent) {
+        if let Some(slot) = self.events.iter_mut().find(|x| x.is_none()) {
+            *slot = Some(event);
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = InputEvent> + '_ {
+        self.events.iter().flatten().copied()
+    }
+}
+
 #[allow(dead_code)]
 impl InputEvent {
     pub fn replace(&self, port: (InputPortKind, InputPortKind)) -> Self {
@@ -89,40 +143,112 @@ impl InputEvent {
 
     pub fn flip_player(&self) -> Self {
         Self {
-            port: match self.port {
-                InputPortKind::Inhibit1P => InputPortKind::Inhibit2P,
-                InputPortKind::Inhibit2P => InputPortKind::Inhibit1P,
-                InputPortKind::Start1P => InputPortKind::Start2P,
-                InputPortKind::Start2P => InputPortKind::Start1P,
-                InputPortKind::Jam1P => InputPortKind::Jam2P,
-                InputPortKind::Jam2P => InputPortKind::Jam1P,
-                InputPortKind::StartJam1P => InputPortKind::StartJam2P,
-                InputPortKind::StartJam2P => InputPortKind::StartJam1P,
-                InputPortKind::Vend2P => InputPortKind::Vend1P,
-                InputPortKind::Vend1P => InputPortKind::Vend2P,
-                InputPortKind::SvcButton => InputPortKind::SvcButton,
-                InputPortKind::Nothing => InputPortKind::Nothing,
-            },
+            port: self.port.flip_player(),
             event: self.event,
         }
     }
 
+    /// Bill / coin income of other players goes to player 1 on shared credit cabinet.
+    pub fn for_cabinet(&self, profile: CabinetProfile) -> Self {
+        match (profile, self.port.for_player(Player::Player1)) {
+            (CabinetProfile::SharedCredit, InputPortKind::Vend1P) => Self {
+                port: InputPortKind::Vend1P,
+                event: self.event,
+            },
+            _ => *self,
+        }
+    }
+
+    fn apply_route(&self, route: &RawIoRoute, output: InputPortKind) -> Self {
+        if route.has_flag(IO_ROUTE_FLAG_IGNORE) {
+            return Self {
+                port: InputPortKind::Nothing,
+                event: self.event,
+            };
+        }
+
+        // Stretch is applied by output port itself, see `Board::apply_io_routes`
+        let event = match (route.has_flag(IO_ROUTE_FLAG_INVERT), self.event) {
+            (true, InputEventKind::Pressed) => InputEventKind::Released,
+            (true, InputEventKind::Released) => InputEventKind::Pressed,
+            (_, x) => x,
+        };
+
+        Self {
+            port: output,
+            event,
+        }
+    }
+
+    /// Output port of the route when the event matches its input, including mirrored one.
+    fn route_output(&self, route: &RawIoRoute) -> Option<InputPortKind> {
+        if !route.is_enabled() {
+            return None;
+        }
+
+        let (Ok(input), Ok(output)) = (
+            InputPortKind::try_from(route.input),
+            InputPortKind::try_from(route.output),
+        ) else {
+            return None;
+        };
+
+        if self.port == input {
+            Some(output)
+        } else if route.has_flag(IO_ROUTE_FLAG_MIRROR)
+            && (input != input.flip_player())
+            && (self.port == input.flip_player())
+        {
+            Some(output.flip_player())
+        } else {
+            None
+        }
+    }
+
+    /// Route input event by configured rules, input without rule follows built-in routing.
+    /// Players are swapped before the rules when `swap_players` is set.
+    pub fn route(&self, routes: &[RawIoRoute; IO_ROUTE_NUM], swap_players: bool) -> RoutedEvents {
+        let origin = match swap_players {
+            true => self.flip_player(),
+            false => *self,
+        };
+
+        let mut ret = RoutedEvents::empty();
+
+        for route in routes.iter() {
+            if let Some(output) = origin.route_output(route) {
+                ret.push(origin.apply_route(route, output));
+            }
+        }
+
+        if ret.events.iter().all(|x| x.is_none()) {
+            ret.push(origin);
+        }
+
+        ret
+    }
+
+    /// Built-in routing by application mode, free play always bypass start.
+    pub fn route_app_mode(&self, appmode: AppMode0V3, is_free_play: bool) -> Self {
+        let appmode = match is_free_play {
+            true => AppMode0V3::BypassStart,
+            false => appmode,
+        };
+        let route = &APP_MODE_ROUTES[u8::from(appmode) as usize];
+
+        self.route_output(route)
+            .map_or(*self, |output| self.apply_route(route, output))
+    }
+
     pub fn ignore_player(&self, player: Player) -> Self {
-        match (self.port, player) {
+        match (self.port.for_player(Player::Player1), self.port.player()) {
             (
                 InputPortKind::Inhibit1P
                 | InputPortKind::Jam1P
                 | InputPortKind::Start1P
                 | InputPortKind::Vend1P,
-                Player::Player1,
-            ) => *self,
-            (
-                InputPortKind::Inhibit2P
-                | InputPortKind::Jam2P
-                | InputPortKind::Start2P
-                | InputPortKind::Vend2P,
-                Player::Player2,
-            ) => *self,
+                x,
+            ) if x == player => *self,
             _ => Self {
                 port: InputPortKind::Nothing,
                 event: self.event,
@@ -136,27 +262,33 @@ impl InputEvent {
         filter_state: &mut PulseMemoryFilterMachine,
         override_druation_force: bool,
     ) -> Self {
-        if let Some((player_index, rom_sel, time_in_10ms)) = match (self.port, self.event) {
-            (InputPortKind::Vend1P, InputEventKind::LongPressed(time_in_10ms)) => Some((
-                PLAYER_1_INDEX as u8,
-                eeprom::select::P1_COIN_CNT,
-                time_in_10ms,
-            )),
-            (InputPortKind::Vend2P, InputEventKind::LongPressed(time_in_10ms)) => Some((
-                PLAYER_2_INDEX as u8,
-                eeprom::select::P2_COIN_CNT,
-                time_in_10ms,
-            )),
+        if let Some((player, player_index, time_in_10ms)) = match (
+            self.port.for_player(Player::Player1),
+            self.port.player().index(),
+            self.event,
+        ) {
+            (InputPortKind::Vend1P, Some(idx), InputEventKind::LongPressed(time_in_10ms))
+                if idx < PLAYER_INDEX_MAX =>
+            {
+                Some((self.port.player(), idx, time_in_10ms))
+            }
             _ => None,
         } {
-            let count = board.hardware.eeprom.lock_read(rom_sel).await;
+            let eeprom = &board.hardware.eeprom;
+            let count = eeprom.lock_read_income(MeterKind::Coin, player).await;
             let new_count = count + 1;
 
-            board.hardware.eeprom.lock_write(rom_sel, new_count).await;
+            eeprom
+                .lock_write_income(MeterKind::Coin, player, new_count)
+                .await;
+
+            if let Some(meter) = board.correspond_meter(MeterKind::Coin) {
+                meter.count(1);
+            }
 
             let timing_in_ms = (time_in_10ms as u16) * 10;
 
-            filter_state.player[player_index as usize].mark(timing_in_ms);
+            filter_state.player[player_index].mark(timing_in_ms);
         }
 
         if self.port != InputPortKind::Nothing {
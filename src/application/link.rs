@@ -0,0 +1,242 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Board-to-board link, two boards behind single card terminal for 3 and 4 player cabinet.
+//! Primary board forwards payment of card terminal port 3 and 4 to secondary board,
+//! secondary board reports inhibit status and counters back to primary board.
+
+use board_link::{LinkCmd, LinkCounters, LinkPayment};
+use card_terminal_adapter::types::IncomeArcadeRequest;
+use embassy_time::{with_timeout, Duration, Instant};
+
+use super::io_card::PaymentReceive;
+use crate::boards::*;
+use crate::components::board_link::{BoardLink, LinkRecv};
+use crate::types::buffered_opendrain_kind::MeterKind;
+use crate::types::install_config::{InstallConfig, LinkRole};
+use crate::types::player::Player;
+
+/// Secondary board answers forwarded payment after its output is queued.
+const LINK_ACK_TIMEOUT: Duration = Duration::from_millis(1000);
+/// Secondary board refuses forwarded payment that waited longer than this after it's received,
+/// thus it never credits a payment that primary board already refused to card terminal.
+/// Link task of each board sends up to `WAIT_DURATION_RX` (200ms) late, both are in the margin.
+const LINK_FORWARD_FRESH: Duration = Duration::from_millis(400);
+
+pub struct LinkMachine {
+    role: LinkRole,
+    /// Inhibit bit mask of secondary board, b0 is player 3
+    remote_inhibit: u8,
+    /// Inhibit bit mask that last sent to primary board
+    sent_inhibit: Option<u8>,
+    /// Primary board asks counters of secondary board once after config is loaded
+    counters_requested: bool,
+    /// Sequence of last forwarded payment, late answer of previous one is not taken
+    seq: u8,
+}
+
+impl LinkMachine {
+    pub const fn new() -> Self {
+        Self {
+            role: LinkRole::Standalone,
+            remote_inhibit: 0,
+            sent_inhibit: None,
+            counters_requested: false,
+            seq: 0,
+        }
+    }
+
+    pub fn load(&mut self, config: &InstallConfig) {
+        let role = config.link_role();
+
+        if role != self.role {
+            defmt::info!("BoardLink - role : {}", role);
+
+            self.role = role;
+            self.remote_inhibit = 0;
+            self.sent_inhibit = None;
+            self.counters_requested = false;
+        }
+    }
+
+    /// Forward payment of card terminal port 3 and 4 to secondary board.
+    /// Returns `None` when the payment is for this board, otherwise returns secondary board acked it.
+    /// Missing answer in `LINK_ACK_TIMEOUT` is regarded as refused.
+    pub async fn forward(
+        &mut self,
+        board: &'static Board,
+        payment: &PaymentReceive,
+    ) -> Option<bool> {
+        if self.role != LinkRole::Primary {
+            return None;
+        }
+
+        let link = board.correspond_link()?;
+        let remote_player = match payment.recv.port {
+            3 => 1,
+            4 => 2,
+            _ => return None,
+        };
+
+        if (self.remote_inhibit & (1 << (remote_player - 1))) != 0 {
+            defmt::warn!("BoardLink - player {} is inhibited", remote_player + 2);
+            return Some(false);
+        }
+
+        defmt::info!(
+            "BoardLink - forward to player {}, income : {}",
+            remote_player + 2,
+            payment
+        );
+
+        self.seq = self.seq.wrapping_add(1);
+        link.send(LinkCmd::ForwardPayment(LinkPayment {
+            seq: self.seq,
+            player: remote_player,
+            pulse_count: payment.recv.pulse_count,
+            pulse_duration: payment.recv.pulse_duration,
+        }))
+        .await;
+
+        Some(self.wait_ack(board, link).await)
+    }
+
+    /// Wait answer of forwarded payment, other commands in between are handled as usual.
+    async fn wait_ack(&mut self, board: &'static Board, link: &BoardLink) -> bool {
+        let answer = with_timeout(LINK_ACK_TIMEOUT, async {
+            loop {
+                match link.recv_channel.receive().await.cmd {
+                    LinkCmd::Ack(seq) if seq == self.seq => break true,
+                    LinkCmd::Nack(seq) if seq == self.seq => break false,
+                    x => self.on_primary_cmd(board, x),
+                }
+            }
+        })
+        .await;
+
+        match answer {
+            Ok(true) => true,
+            Ok(false) => {
+                defmt::warn!("BoardLink - secondary board refused payment");
+                false
+            }
+            Err(_) => {
+                defmt::error!("BoardLink - secondary board doesn't answer payment");
+                false
+            }
+        }
+    }
+
+    fn on_primary_cmd(&mut self, board: &'static Board, cmd: LinkCmd) {
+        match cmd {
+            LinkCmd::Inhibit(bits) => {
+                defmt::info!("BoardLink - remote inhibit : {:02b}", bits);
+                self.remote_inhibit = bits;
+            }
+            LinkCmd::Counters(x) => {
+                board.hardware.card_reader.set_linked_counters(x);
+            }
+            LinkCmd::Ack(_) => {
+                // secondary board refuses stale payment, this shouldn't happen
+                defmt::error!("BoardLink - payment is credited after timeout {}", cmd);
+            }
+            LinkCmd::Nack(_) => {
+                // answer after timeout, the payment is already refused to card terminal
+                defmt::warn!("BoardLink - late answer {}", cmd);
+            }
+            x => {
+                defmt::warn!("BoardLink - unexpected command {} on primary", x);
+            }
+        }
+    }
+
+    async fn send_counters(board: &'static Board, link: &BoardLink) {
+        let eeprom = &board.hardware.eeprom;
+        let counters = LinkCounters {
+            card: eeprom.lock_read_income_pair(MeterKind::Card).await,
+            coin: eeprom.lock_read_income_pair(MeterKind::Coin).await,
+        };
+
+        link.send(LinkCmd::Counters(counters)).await;
+    }
+
+    /// Handle link commands, returns the player if forwarded payment is credited on this board.
+    pub async fn poll(
+        &mut self,
+        board: &'static Board,
+        inhibited: [bool; PLAYER_INDEX_MAX],
+        override_druation_force: bool,
+    ) -> Option<Player> {
+        let link = board.correspond_link()?;
+
+        match self.role {
+            LinkRole::Standalone => return None,
+            LinkRole::Primary if !self.counters_requested => {
+                self.counters_requested = true;
+                link.send(LinkCmd::RequestCounters).await;
+            }
+            LinkRole::Secondary => {
+                let bits = inhibited
+                    .iter()
+                    .enumerate()
+                    .fold(0u8, |acc, (idx, x)| acc | ((*x as u8) << idx));
+
+                if self.sent_inhibit != Some(bits) {
+                    self.sent_inhibit = Some(bits);
+                    link.send(LinkCmd::Inhibit(bits)).await;
+                }
+            }
+            _ => {}
+        }
+
+        let Ok(LinkRecv { cmd, received }) = link.recv_channel.try_receive() else {
+            return None;
+        };
+
+        match (self.role, cmd) {
+            (LinkRole::Secondary, LinkCmd::ForwardPayment(x)) => {
+                let player = Player::from_port_num(x.player);
+
+                if LINK_FORWARD_FRESH < (Instant::now() - received) {
+                    defmt::warn!("BoardLink - refuse stale payment of {}", player);
+                    link.send(LinkCmd::Nack(x.seq)).await;
+                    return None;
+                }
+
+                if player.index().is_some_and(|idx| inhibited[idx]) {
+                    defmt::warn!("BoardLink - refuse payment of inhibited {}", player);
+                    link.send(LinkCmd::Nack(x.seq)).await;
+                    return None;
+                }
+
+                PaymentReceive::from(IncomeArcadeRequest {
+                    port: x.player,
+                    pulse_count: x.pulse_count,
+                    pulse_duration: x.pulse_duration,
+                })
+                .assign(player)
+                .apply_output(board, override_druation_force)
+                .await;
+
+                link.send(LinkCmd::Ack(x.seq)).await;
+                Self::send_counters(board, link).await;
+
+                return Some(player);
+            }
+            (LinkRole::Secondary, LinkCmd::RequestCounters) => {
+                Self::send_counters(board, link).await;
+            }
+            (LinkRole::Primary, x) => {
+                self.on_primary_cmd(board, x);
+            }
+            (role, x) => {
+                defmt::warn!("BoardLink - unexpected command {} on {}", x, role);
+            }
+        }
+
+        None
+    }
+}
@@ -0,0 +1,356 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! # Localized operator screens
+//! `DisplayRom`, `DisplayHwInfo`, warnings and inhibit reason are composed as `TextPage`
+//! from the string table of selected `Language`, thus same firmware shows local language.
+//! `Language::Terminal` keeps built-in screens of the plug.
+//!
+//! Strings are encoded bytes for the terminal code page, the original text is on the comment.
+
+use card_terminal_adapter::text_page::{TextEncoding, TextLine, TextPage};
+use card_terminal_adapter::types::RawPlayersInhibitReason;
+use card_terminal_adapter::{CardTerminalDisplayWarning, CardTerminalTxCmd};
+
+use super::mutual_inhibit::InhibitSource;
+use crate::boards::Board;
+use crate::components::eeprom::{select, NovellaWear};
+use crate::const_str;
+use crate::types::buffered_opendrain_kind::MeterKind;
+use crate::types::install_config::Language;
+
+/// Same time with built-in screens of the terminal
+const PAGE_TIMEOUT_SECS: u16 = 10;
+/// Number of `InhibitSource`
+const INHIBIT_SOURCE_NUM: usize = 5;
+
+type Text = &'static [u8];
+
+pub struct StringTable {
+    encoding: TextEncoding,
+    /// `DisplayRom`, card and bill / coin counters of player 1 and 2
+    card: [Text; 2],
+    coin: [Text; 2],
+    /// `DisplayHwInfo`
+    version: Text,
+    boot_count: Text,
+    uptime: Text,
+    last_fault: Text,
+    /// EEPROM wear, title, slot writes since boot and projected lifetime
+    wear: Text,
+    wear_writes: Text,
+    wear_days: Text,
+    /// `DisplayWarning`, title and two lines of each warning
+    warning: Text,
+    require_arcade_specific_version: [Text; 2],
+    require_latest_terminal_version: [Text; 2],
+    warn_experimental_version: [Text; 2],
+    warn_unknown: [Text; 2],
+    warn_eeprom_factory_reset: [Text; 2],
+    warn_payout_empty: [Text; 2],
+    warn_payout_jam: [Text; 2],
+    /// `DisplayInhibitReason`, title and holder of each player in `InhibitSource` order
+    inhibit: Text,
+    not_inhibited: Text,
+    inhibit_sources: [Text; INHIBIT_SOURCE_NUM],
+}
+
+static KOREAN: StringTable = StringTable {
+    encoding: TextEncoding::EucKr,
+    card: [
+        b"1P \xC4\xAB\xB5\xE5", // "1P 카드"
+        b"2P \xC4\xAB\xB5\xE5", // "2P 카드"
+    ],
+    coin: [
+        b"1P \xC7\xF6\xB1\xDD", // "1P 현금"
+        b"2P \xC7\xF6\xB1\xDD", // "2P 현금"
+    ],
+    version: b"\xB9\xF6\xC0\xFC",                              // "버전"
+    boot_count: b"\xBA\xCE\xC6\xC3 \xC8\xBD\xBC\xF6",          // "부팅 횟수"
+    uptime: b"\xB0\xA1\xB5\xBF \xBD\xC3\xB0\xA3(\xBA\xD0)",    // "가동 시간(분)"
+    last_fault: b"\xC0\xCC\xC0\xFC \xBF\xC0\xB7\xF9",          // "이전 오류"
+    wear: b"EEPROM \xBC\xF6\xB8\xED",                          // "EEPROM 수명"
+    wear_writes: b"\xBE\xB2\xB1\xE2 \xC8\xBD\xBC\xF6",         // "쓰기 횟수"
+    wear_days: b"\xB3\xB2\xC0\xBA \xBC\xF6\xB8\xED(\xC0\xCF)", // "남은 수명(일)"
+    warning: b"\xB0\xE6\xB0\xED",                              // "경고"
+    require_arcade_specific_version: [
+        b"\xBE\xC6\xC4\xC9\xC0\xCC\xB5\xE5 \xC0\xFC\xBF\xEB", // "아케이드 전용"
+        b"\xB4\xDC\xB8\xBB\xB1\xE2\xB0\xA1 \xC7\xCA\xBF\xE4\xC7\xD4", // "단말기가 필요함"
+    ],
+    require_latest_terminal_version: [
+        b"\xB4\xDC\xB8\xBB\xB1\xE2 \xC6\xDF\xBF\xFE\xBE\xEE", // "단말기 펌웨어"
+        b"\xBE\xF7\xB5\xA5\xC0\xCC\xC6\xAE \xC7\xCA\xBF\xE4", // "업데이트 필요"
+    ],
+    warn_experimental_version: [
+        b"\xBD\xC3\xC7\xE8\xBF\xEB \xB4\xDC\xB8\xBB\xB1\xE2", // "시험용 단말기"
+        b"\xB9\xF6\xC0\xFC \xC1\xD6\xC0\xC7",                 // "버전 주의"
+    ],
+    warn_unknown: [
+        b"\xBE\xCB \xBC\xF6 \xBE\xF8\xB4\xC2",        // "알 수 없는"
+        b"\xB4\xDC\xB8\xBB\xB1\xE2 \xB9\xF6\xC0\xFC", // "단말기 버전"
+    ],
+    warn_eeprom_factory_reset: [
+        b"\xC4\xAB\xBF\xEE\xC5\xCD \xC3\xCA\xB1\xE2\xC8\xAD", // "카운터 초기화"
+        b"\xBF\xCF\xB7\xE1",                                  // "완료"
+    ],
+    warn_payout_empty: [
+        b"\xC8\xA3\xC6\xDB \xBA\xF1\xBE\xEE\xC0\xD6\xC0\xBD", // "호퍼 비어있음"
+        b"\xBA\xB8\xC3\xE6 \xC7\xCA\xBF\xE4",                 // "보충 필요"
+    ],
+    warn_payout_jam: [
+        b"\xB9\xE8\xC3\xE2\xB1\xE2 \xB0\xC9\xB8\xB2", // "배출기 걸림"
+        b"\xC1\xA1\xB0\xCB \xC7\xCA\xBF\xE4",         // "점검 필요"
+    ],
+    inhibit: b"\xC6\xC7\xB8\xC5 \xC1\xDF\xC1\xF6", // "판매 중지"
+    not_inhibited: b"\xC1\xA4\xBB\xF3",            // "정상"
+    inhibit_sources: [
+        b"\xB0\xE1\xC1\xA6 \xC0\xCC\xBB\xF3", // "결제 이상"
+        b"DIP \xBC\xB3\xC1\xA4",              // "DIP 설정"
+        b"\xB4\xDC\xB8\xBB\xB1\xE2",          // "단말기"
+        b"\xBF\xB5\xBE\xF7 \xC1\xBE\xB7\xE1", // "영업 종료"
+        b"\xB0\xD4\xC0\xD3\xB1\xE2",          // "게임기"
+    ],
+};
+
+static ENGLISH: StringTable = StringTable {
+    encoding: TextEncoding::Ascii,
+    card: [b"1P CARD", b"2P CARD"],
+    coin: [b"1P CASH", b"2P CASH"],
+    version: b"VERSION",
+    boot_count: b"BOOT COUNT",
+    uptime: b"UPTIME (MIN)",
+    last_fault: b"LAST FAULT",
+    wear: b"EEPROM WEAR",
+    wear_writes: b"WRITES",
+    wear_days: b"LIFE (DAYS)",
+    warning: b"WARNING",
+    require_arcade_specific_version: [b"ARCADE TERMINAL", b"REQUIRED"],
+    require_latest_terminal_version: [b"UPDATE TERMINAL", b"FIRMWARE"],
+    warn_experimental_version: [b"EXPERIMENTAL", b"TERMINAL VERSION"],
+    warn_unknown: [b"UNKNOWN", b"TERMINAL VERSION"],
+    warn_eeprom_factory_reset: [b"COUNTER RESET", b"DONE"],
+    warn_payout_empty: [b"HOPPER EMPTY", b"PLEASE REFILL"],
+    warn_payout_jam: [b"PAYOUT JAM", b"CHECK DISPENSER"],
+    inhibit: b"INHIBITED",
+    not_inhibited: b"OK",
+    inhibit_sources: [b"FAULT", b"DIP SW", b"TERMINAL", b"CLOSED", b"GAME I/O"],
+};
+
+static JAPANESE: StringTable = StringTable {
+    encoding: TextEncoding::ShiftJis,
+    card: [
+        b"1P \x83J\x81[\x83h", // "1P カード"
+        b"2P \x83J\x81[\x83h", // "2P カード"
+    ],
+    coin: [
+        b"1P \x8C\xBB\x8B\xE0", // "1P 現金"
+        b"2P \x8C\xBB\x8B\xE0", // "2P 現金"
+    ],
+    version: b"\x83o\x81[\x83W\x83\x87\x83\x93", // "バージョン"
+    boot_count: b"\x8BN\x93\xAE\x89\xF1\x90\x94", // "起動回数"
+    uptime: b"\x89\xD2\x93\xAD\x8E\x9E\x8A\xD4(\x95\xAA)", // "稼働時間(分)"
+    last_fault: b"\x91O\x89\xF1\x83G\x83\x89\x81[", // "前回エラー"
+    wear: b"EEPROM\x8E\xF5\x96\xBD",             // "EEPROM寿命"
+    wear_writes: b"\x8F\x91\x8D\x9E\x89\xF1\x90\x94", // "書込回数"
+    wear_days: b"\x8Ec\x82\xE8\x8E\xF5\x96\xBD(\x93\xFA)", // "残り寿命(日)"
+    warning: b"\x8Cx\x8D\x90",                   // "警告"
+    require_arcade_specific_version: [
+        b"\x83A\x81[\x83P\x81[\x83h\x90\xEA\x97p", // "アーケード専用"
+        b"\x92[\x96\x96\x82\xAA\x95K\x97v\x82\xC5\x82\xB7", // "端末が必要です"
+    ],
+    require_latest_terminal_version: [
+        b"\x92[\x96\x96\x82\xCC\x8DX\x90V\x82\xAA", // "端末の更新が"
+        b"\x95K\x97v\x82\xC5\x82\xB7",              // "必要です"
+    ],
+    warn_experimental_version: [
+        b"\x8E\x8E\x8C\xB1\x97p\x82\xCC",                // "試験用の"
+        b"\x92[\x96\x96\x83o\x81[\x83W\x83\x87\x83\x93", // "端末バージョン"
+    ],
+    warn_unknown: [
+        b"\x95s\x96\xBE\x82\xC8",                        // "不明な"
+        b"\x92[\x96\x96\x83o\x81[\x83W\x83\x87\x83\x93", // "端末バージョン"
+    ],
+    warn_eeprom_factory_reset: [
+        b"\x83J\x83E\x83\x93\x83^\x81[", // "カウンター"
+        b"\x8F\x89\x8A\xFA\x89\xBB\x82\xB5\x82\xDC\x82\xB5\x82\xBD", // "初期化しました"
+    ],
+    warn_payout_empty: [
+        b"\x83z\x83b\x83p\x81[\x8B\xF3", // "ホッパー空"
+        b"\x95\xE2\x8F[\x82\xB5\x82\xC4\x82\xAD\x82\xBE\x82\xB3\x82\xA2", // "補充してください"
+    ],
+    warn_payout_jam: [
+        b"\x95\xA5\x8Fo\x8Bl\x82\xDC\x82\xE8", // "払出詰まり"
+        b"\x93_\x8C\x9F\x82\xB5\x82\xC4\x82\xAD\x82\xBE\x82\xB3\x82\xA2", // "点検してください"
+    ],
+    inhibit: b"\x94\xCC\x94\x84\x92\xE2\x8E~", // "販売停止"
+    not_inhibited: b"\x90\xB3\x8F\xED",        // "正常"
+    inhibit_sources: [
+        b"\x88\xD9\x8F\xED",                   // "異常"
+        b"DIP\x90\xDD\x92\xE8",                // "DIP設定"
+        b"\x92[\x96\x96",                      // "端末"
+        b"\x89c\x8B\xC6\x8E\x9E\x8A\xD4\x8AO", // "営業時間外"
+        b"\x83Q\x81[\x83\x80\x8B@",            // "ゲーム機"
+    ],
+};
+
+impl StringTable {
+    /// `None` for built-in screens of the terminal
+    pub fn of(language: Language) -> Option<&'static Self> {
+        match language {
+            Language::Terminal => None,
+            Language::Korean => Some(&KOREAN),
+            Language::English => Some(&ENGLISH),
+            Language::Japanese => Some(&JAPANESE),
+        }
+    }
+
+    const fn page(&self) -> TextPage {
+        TextPage::new(PAGE_TIMEOUT_SECS).encoding(self.encoding)
+    }
+
+    pub const fn rom_page(&self, card: [u32; 2], coin: [u32; 2]) -> TextPage {
+        self.page()
+            .line(0, TextLine::number(self.card[0], card[0]))
+            .line(1, TextLine::number(self.card[1], card[1]))
+            .line(2, TextLine::number(self.coin[0], coin[0]))
+            .line(3, TextLine::number(self.coin[1], coin[1]))
+    }
+
+    /// Last fault is shown instead of uptime when previous boot was reset by fault
+    pub fn hw_info_page(
+        &self,
+        hw_boot_cnt: u32,
+        uptime_minutes: u32,
+        last_fault_code: u16,
+    ) -> TextPage {
+        self.page()
+            .line(0, TextLine::value(self.version, &const_str::VERSION_STR))
+            .line(1, TextLine::text(const_str::get_serial_number()))
+            .line(2, TextLine::number(self.boot_count, hw_boot_cnt))
+            .line(
+                3,
+                match last_fault_code {
+                    0 => TextLine::number(self.uptime, uptime_minutes),
+                    code => TextLine::hex(self.last_fault, code as u32),
+                },
+            )
+    }
+
+    /// Projected lifetime is unknown before any write
+    pub fn wear_page(&self, wear: &NovellaWear) -> TextPage {
+        let writes = wear
+            .writes
+            .iter()
+            .fold(0u32, |acc, x| acc.saturating_add(*x));
+
+        self.page()
+            .line(0, TextLine::centered(self.wear))
+            .line(2, TextLine::number(self.wear_writes, writes))
+            .line(
+                3,
+                match wear.projected_days {
+                    Some(days) => TextLine::number(self.wear_days, days),
+                    None => TextLine::value(self.wear_days, b"-"),
+                },
+            )
+    }
+
+    pub const fn warning_page(&self, warn: CardTerminalDisplayWarning) -> TextPage {
+        let [first, second] = match warn {
+            CardTerminalDisplayWarning::RequireArcadeSpecificVersion => {
+                self.require_arcade_specific_version
+            }
+            CardTerminalDisplayWarning::RequireLatestTerminalVersion => {
+                self.require_latest_terminal_version
+            }
+            CardTerminalDisplayWarning::WarnExperimentalVesion => self.warn_experimental_version,
+            CardTerminalDisplayWarning::WarnUnknown => self.warn_unknown,
+            CardTerminalDisplayWarning::WarnEepromFactoryReset => self.warn_eeprom_factory_reset,
+            CardTerminalDisplayWarning::WarnPayoutEmpty => self.warn_payout_empty,
+            CardTerminalDisplayWarning::WarnPayoutJam => self.warn_payout_jam,
+        };
+
+        self.page()
+            .line(0, TextLine::centered(self.warning))
+            .line(2, TextLine::centered(first))
+            .line(3, TextLine::centered(second))
+    }
+
+    /// Name of holding source, the first one in priority order
+    const fn holder(&self, holds: u8) -> Text {
+        match holds {
+            0 => self.not_inhibited,
+            x => {
+                self.inhibit_sources
+                    [InhibitSource::from_priority(x.trailing_zeros() as u8) as usize]
+            }
+        }
+    }
+
+    pub const fn inhibit_page(&self, reason: &RawPlayersInhibitReason) -> TextPage {
+        self.page()
+            .line(0, TextLine::centered(self.inhibit))
+            .line(2, TextLine::value(b"1P", self.holder(reason.p1)))
+            .line(3, TextLine::value(b"2P", self.holder(reason.p2)))
+    }
+}
+
+/// Send display request to card terminal.
+/// When a language is selected, the screen is composed here and sent as `DisplayTextPage`,
+/// otherwise the request goes to the plug for its built-in screen.
+pub async fn display(board: &Board, request: CardTerminalTxCmd) {
+    let card_reader = &board.hardware.card_reader;
+    let Some(table) = StringTable::of(card_reader.language()) else {
+        card_reader.send(request).await;
+        return;
+    };
+    let novella = &board.hardware.eeprom;
+
+    let page = match request {
+        CardTerminalTxCmd::DisplayRom => {
+            // player 3 and 4 of quad or linked board are counted with same parity of port
+            let linked = card_reader.linked_counters();
+            let mut card = novella.lock_read_income_pair(MeterKind::Card).await;
+            let mut coin = novella.lock_read_income_pair(MeterKind::Coin).await;
+            for (x, y) in card.iter_mut().zip(linked.card) {
+                *x = x.wrapping_add(y);
+            }
+            for (x, y) in coin.iter_mut().zip(linked.coin) {
+                *x = x.wrapping_add(y);
+            }
+
+            table.rom_page(card, coin)
+        }
+        CardTerminalTxCmd::DisplayHwInfo => {
+            let hw_boot_cnt = novella.lock_read(select::HW_BOOT_CNT).await;
+            let uptime_minutes = (novella.get_uptime().as_secs() / 60).min(u32::MAX as u64) as u32;
+            let last_fault_code = novella
+                .lock_read(select::FAULT_LOG)
+                .await
+                .previous_boot_error(hw_boot_cnt);
+
+            table.hw_info_page(hw_boot_cnt, uptime_minutes, last_fault_code)
+        }
+        CardTerminalTxCmd::DisplayWarning(warn) => table.warning_page(warn),
+        CardTerminalTxCmd::DisplayInhibitReason(reason) => table.inhibit_page(&reason),
+        request => {
+            card_reader.send(request).await;
+            return;
+        }
+    };
+
+    card_reader.display_page(page).await;
+}
+
+/// EEPROM wear is a diagnostic screen that the terminal doesn't have built-in,
+/// thus it's shown in English for `Language::Terminal`.
+pub async fn display_wear(board: &Board) {
+    let card_reader = &board.hardware.card_reader;
+    let table = StringTable::of(card_reader.language()).unwrap_or(&ENGLISH);
+    let wear = board.hardware.eeprom.lock_wear().await;
+
+    card_reader.display_page(table.wear_page(&wear)).await;
+}
@@ -4,31 +4,55 @@
  * SPDX-License-Identifier: MIT OR Apache-2.0
  */
 
+mod health;
 mod io_bypass;
 mod io_card;
 mod io_remap;
+mod link;
+mod locale;
 mod mutual_inhibit;
+#[cfg(feature = "payout")]
+mod payout;
 mod player_to_vend_led;
 mod pulse_meory_filter;
+mod schedule;
+mod service_credit;
+mod start_decide;
+mod start_led;
 
 use card_terminal_adapter::types::*;
 use card_terminal_adapter::*;
 use embassy_futures::yield_now;
-use embassy_time::{Duration, Timer};
+use embassy_time::{Duration, Instant, Timer};
 use io_card::PaymentReceive;
-#[cfg(feature = "svc_button")]
-use {crate::components::eeprom, embassy_time::Instant};
 
+use self::health::HealthMachine;
+use self::io_remap::RoutedEvents;
+use self::link::LinkMachine;
+use self::mutual_inhibit::{InhibitSource, FREE_PLAY_SUPPRESSED_SOURCES};
+#[cfg(feature = "payout")]
+use self::payout::PayoutMachine;
+use self::schedule::ScheduleMachine;
+use self::start_decide::StartDecideMachine;
+use self::start_led::StartLedMachine;
 use self::{mutual_inhibit::MutualInhibit, pulse_meory_filter::PulseMemoryFilterMachine};
 use crate::boards::*;
+#[cfg(feature = "svc_button")]
+use crate::components::eeprom;
+use crate::components::serial_device::support_payment_cancel;
 use crate::semi_layer;
 use crate::semi_layer::buffered_wait::InputEventKind;
-use crate::types::dip_switch_config::{AppMode0V3, TimingOverride};
+use crate::semi_layer::heartbeat::{HeartbeatKind, HEARTBEAT};
+use crate::types::dip_switch_config::{AppMode0V3, InhibitOverride, TimingOverride};
 use crate::types::input_port::{InputEvent, InputPortKind};
-use crate::types::player::Player;
+use crate::types::install_config::{Language, ScheduleAction};
+use crate::types::player::{Player, PLAYERS};
 
 pub const DEFAULT_VEND_INDICATOR_TIMING_MS: u16 = 200;
 pub const DEFAULT_BUSY_ALPHA_TIMING_MS: u16 = 10;
+/// SVC button long press in this time after power on enters firmware update mode
+#[cfg(feature = "svc_button")]
+const FW_UPDATE_GESTURE_WINDOW: Duration = Duration::from_secs(30);
 
 pub struct Application {
     /// Hardware and necessary shared object
@@ -51,22 +75,53 @@ impl Application {
         let mut timing = TimingOverride::default();
         let mut appmode = AppMode0V3::default();
         let mut default_serial = Player::Undefined;
-        let mut income_backup: Option<PaymentReceive> = None; // for StartButtonDecideSerialToVend
+        let mut start_decide = StartDecideMachine::new(); // for StartButtonDecideSerialToVend
+        let mut start_led = StartLedMachine::new();
         let mut mutual_inhibit = MutualInhibit::new();
+        let mut health = HealthMachine::new();
+        let mut schedule = ScheduleMachine::new();
+        let mut link = LinkMachine::new();
         let mut did_we_ask: u8 = 0;
         let mut did_we_alert_version_warning = false;
         let mut did_we_received_slot_info = false;
         let mut slot_info_asked_time = Instant::now();
         let mut filter_state = PulseMemoryFilterMachine::new();
+        #[cfg(feature = "payout")]
+        let mut payout = PayoutMachine::new();
         #[cfg(feature = "svc_button")]
         let (mut last_svc_pressed, mut is_svc_pressed): (Instant, bool) = (Instant::now(), false);
         #[cfg(feature = "svc_button")]
+        let mut is_svc_combo = false; // SVC button is used with start button
+        #[cfg(feature = "svc_button")]
         let eeprom = &hardware.eeprom;
+        let heartbeat = HEARTBEAT.register(HeartbeatKind::MainTask);
+
+        let install_config = hardware
+            .eeprom
+            .lock_read(crate::components::eeprom::select::INSTALL_CONFIG)
+            .await;
+        schedule.load(&install_config);
+        start_decide.load(&install_config);
+        link.load(&install_config);
+        health.load(&install_config);
+        mutual_inhibit.load(&install_config);
+        let mut free_play_config = install_config.is_free_play();
+        let mut io_routes = install_config.routes;
+        board.apply_io_routes(&io_routes);
+        let mut swap_players = install_config.is_swap_players();
+        board.apply_polarity(&install_config.polarity());
+        let mut cabinet = install_config.cabinet();
+        board.apply_cabinet(cabinet);
+        mutual_inhibit.set_cabinet(cabinet);
+        card_reader.set_language(install_config.language());
+        let mut is_free_play = false;
 
         // Show HW info when update firmware using SWD directly
-        card_reader.send(CardTerminalTxCmd::DisplayHwInfo).await;
+        locale::display(board, CardTerminalTxCmd::DisplayHwInfo).await;
 
         loop {
+            HEARTBEAT.beat(heartbeat);
+
             // timing flag would be used in future implementation.
             // reading dipsw will be changed to actor model
             let (inhibit_latest, timing_latest, appmode_latest) = hardware.dipsw.read();
@@ -81,13 +136,69 @@ impl Application {
                 mutual_inhibit.test_and_apply_output(board).await;
             }
 
+            // Safe state on payment path fault
+            if let Some(safe_state) = health.poll(board).await {
+                defmt::warn!("Health - safe state : {}", safe_state);
+
+                mutual_inhibit.update_health(safe_state);
+                mutual_inhibit.test_and_apply_output(board).await;
+            }
+
+            // Time-of-day schedule, closing time
+            if let Some(action) = schedule.poll(board).await {
+                let is_closed = action == ScheduleAction::Closed;
+
+                mutual_inhibit.set(InhibitSource::Schedule, Player::Undefined, is_closed);
+                mutual_inhibit.test_and_apply_output(board).await;
+            }
+
+            // Free-play mode, by install config or schedule window
+            let free_play_latest =
+                free_play_config || schedule.action() == ScheduleAction::FreePlay;
+            if free_play_latest != is_free_play {
+                defmt::info!("Free-play mode : {}", free_play_latest);
+
+                is_free_play = free_play_latest;
+                mutual_inhibit.set_suppressed(match is_free_play {
+                    true => FREE_PLAY_SUPPRESSED_SOURCES,
+                    false => 0,
+                });
+                mutual_inhibit.test_and_apply_output(board).await;
+            }
+
+            // Language setup, TIMING DIP selects language while INHIBIT and MODE DIP are all on
+            let language = Language::from(timing_latest as u8);
+            if (inhibit_latest == InhibitOverride::ForceInhibitGlobal)
+                && (appmode_latest == AppMode0V3::DisplayRom)
+                && (language != card_reader.language())
+            {
+                defmt::info!("Language changed : {}", language);
+
+                card_reader.set_language(language);
+                let mut config = hardware
+                    .eeprom
+                    .lock_read(crate::components::eeprom::select::INSTALL_CONFIG)
+                    .await;
+                config.language = language as u8;
+                hardware
+                    .eeprom
+                    .lock_write(crate::components::eeprom::select::INSTALL_CONFIG, config)
+                    .await;
+
+                // redraw in new language, otherwise it's shown on entering `DisplayRom` below
+                if appmode == AppMode0V3::DisplayRom {
+                    locale::display(board, CardTerminalTxCmd::DisplayRom).await;
+                }
+            }
+
             // Timing Override
             if timing_latest != timing {
                 let new_timing = timing_latest.get_toggle_timing();
                 defmt::info!("Timing status chagned : {}", timing_latest);
 
-                shared.arcade_players_timing[PLAYER_1_INDEX].set(new_timing);
-                shared.arcade_players_timing[PLAYER_2_INDEX].set(new_timing);
+                for player_timing in shared.arcade_players_timing.iter() {
+                    player_timing.set(new_timing);
+                }
 
                 timing = timing_latest;
             }
@@ -103,13 +214,13 @@ impl Application {
                     AppMode0V3::BypassJam => Player::Player1,
                 };
 
-                // should be reset
-                income_backup = None;
+                // held payments shouldn't be lost
+                start_decide.flush(board, timing.is_override_force()).await;
 
                 if appmode_latest == AppMode0V3::DisplayRom {
-                    card_reader.send(CardTerminalTxCmd::DisplayRom).await;
+                    locale::display(board, CardTerminalTxCmd::DisplayRom).await;
                 } else if appmode == AppMode0V3::DisplayRom {
-                    card_reader.send(CardTerminalTxCmd::DisplayHwInfo).await;
+                    locale::display(board, CardTerminalTxCmd::DisplayHwInfo).await;
                 }
 
                 appmode = appmode_latest;
@@ -167,21 +278,35 @@ impl Application {
                     }
                     CardTerminalRxCmd::AlertPaymentIncomeArcade(raw_income) => {
                         // judge current application mode and income backup
-                        let payment = PaymentReceive::from((default_serial, raw_income.into()));
-
-                        if appmode == AppMode0V3::StartButtonDecideSerialToVend {
-                            match income_backup.is_some() {
-                                true => {
-                                    defmt::warn!("StartButtonDecideSerialToVend - duplicated income received, player should press start button.");
-                                    card_reader.send_nack().await; // even send nack, it doesn't cancel payment with NDA device.
-                                }
+                        let request: IncomeArcadeRequest = raw_income.into();
+                        let payment = PaymentReceive::from((default_serial, request.clone()))
+                            .with_bonus(schedule.bonus_percent());
+
+                        // player 3 and 4 payment goes to secondary board
+                        if let Some(is_accepted) = link.forward(board, &payment).await {
+                            match is_accepted {
+                                true => card_reader.send_ack().await,
                                 false => {
-                                    defmt::info!("StartButtonDecideSerialToVend - income received, wait for start button");
-                                    income_backup = Some(payment);
-                                    card_reader.send_ack().await;
+                                    card_reader.send_nack().await;
+                                    // nack doesn't cancel payment with some terminal
+                                    if support_payment_cancel() {
+                                        card_reader
+                                            .send(CardTerminalTxCmd::RequestPaymentCancel(
+                                                request.into(),
+                                            ))
+                                            .await;
+                                    }
                                 }
                             }
+                        } else if appmode == AppMode0V3::StartButtonDecideSerialToVend {
+                            match start_decide.hold(board, payment, request).await {
+                                true => card_reader.send_ack().await,
+                                // even send nack, it doesn't cancel payment with NDA device.
+                                false => card_reader.send_nack().await,
+                            }
                         } else {
+                            let payment = payment.for_cabinet(cabinet);
+                            start_led.credit(payment.player());
                             payment
                                 // .override_player_by_duration()
                                 .apply_output(board, timing.is_override_force())
@@ -192,28 +317,23 @@ impl Application {
                     CardTerminalRxCmd::AlertPaymentIncomePrice(raw_price) => {
                         let u32_price: u32 = raw_price.into();
 
-                        let payment = PaymentReceive::from((
-                            default_serial,
-                            IncomeArcadeRequest {
-                                port: 0, // fake value,
-                                pulse_count: (u32_price / 500).max(1).max(u8::MAX as u32) as u16,
-                                pulse_duration: semi_layer::timing::ToggleTiming::default().high_ms,
-                            },
-                        ));
+                        let request = IncomeArcadeRequest {
+                            port: 0, // fake value,
+                            pulse_count: (u32_price / 500).max(1).max(u8::MAX as u32) as u16,
+                            pulse_duration: semi_layer::timing::ToggleTiming::default().high_ms,
+                        };
+                        let payment = PaymentReceive::from((default_serial, request.clone()))
+                            .with_bonus(schedule.bonus_percent());
 
                         if appmode == AppMode0V3::StartButtonDecideSerialToVend {
-                            match income_backup.is_some() {
-                                true => {
-                                    defmt::warn!("StartButtonDecideSerialToVend - duplicated income received, player should press start button.");
-                                    card_reader.send_nack().await; // even send nack, it doesn't cancel payment with NDA device.
-                                }
-                                false => {
-                                    defmt::info!("StartButtonDecideSerialToVend - income received, wait for start button");
-                                    income_backup = Some(payment);
-                                    card_reader.send_ack().await;
-                                }
+                            match start_decide.hold(board, payment, request).await {
+                                true => card_reader.send_ack().await,
+                                // even send nack, it doesn't cancel payment with NDA device.
+                                false => card_reader.send_nack().await,
                             }
                         } else {
+                            let payment = payment.for_cabinet(cabinet);
+                            start_led.credit(payment.player());
                             payment
                                 // .override_player_by_duration()
                                 .apply_output(board, timing.is_override_force())
@@ -221,9 +341,88 @@ impl Application {
                             card_reader.send_ack().await;
                         }
                     }
+                    CardTerminalRxCmd::RequestPayout(req) => {
+                        #[cfg(feature = "payout")]
+                        match payout.request(board, req).await {
+                            true => card_reader.send_ack().await,
+                            false => card_reader.send_nack().await,
+                        }
+
+                        #[cfg(not(feature = "payout"))]
+                        {
+                            defmt::warn!("Payout is not supported on this hardware, {}", req);
+                            card_reader.send_nack().await;
+                        }
+                    }
+                    CardTerminalRxCmd::ResponseDateTime(date_time) => {
+                        schedule.sync(&date_time);
+                    }
+                    CardTerminalRxCmd::RequestWriteConfig(item) => {
+                        let eeprom = &hardware.eeprom;
+                        let mut config = eeprom
+                            .lock_read(crate::components::eeprom::select::INSTALL_CONFIG)
+                            .await;
+
+                        if config.apply(item.key, item.value) {
+                            defmt::info!("Install config written : {}", item);
+
+                            schedule.load(&config);
+                            start_decide.load(&config);
+                            link.load(&config);
+                            health.load(&config);
+                            mutual_inhibit.load(&config);
+                            mutual_inhibit.update_health(health.is_safe_state());
+                            mutual_inhibit.test_and_apply_output(board).await;
+                            free_play_config = config.is_free_play();
+                            io_routes = config.routes;
+                            board.apply_io_routes(&io_routes);
+                            swap_players = config.is_swap_players();
+                            card_reader.set_language(config.language());
+                            board.apply_polarity(&config.polarity());
+                            if cabinet != config.cabinet() {
+                                cabinet = config.cabinet();
+                                defmt::info!("Cabinet profile : {}", cabinet);
+
+                                board.apply_cabinet(cabinet);
+                                mutual_inhibit.set_cabinet(cabinet);
+                                mutual_inhibit.test_and_apply_output(board).await;
+                            }
+                            eeprom
+                                .lock_write(
+                                    crate::components::eeprom::select::INSTALL_CONFIG,
+                                    config,
+                                )
+                                .await;
+                            card_reader.send_ack().await;
+                        } else {
+                            defmt::warn!("Unknown install config : {}", item);
+                            card_reader.send_nack().await;
+                        }
+                    }
+                    CardTerminalRxCmd::RequestServiceCredit(req) => {
+                        // same port rule with PaymentReceive
+                        let player = cabinet.income_player(Player::from_port_num(req.port));
+
+                        card_reader.send_ack().await;
+                        service_credit::apply_service_credit(board, player, req.count).await;
+                        start_led.credit(player);
+                    }
                     CardTerminalRxCmd::ResponseSaleSlotInfo => {
-                        // read from lock_read for do something
                         // todo! - handle different TId/and something
+                        let slot_info = hardware
+                            .eeprom
+                            .lock_read(eeprom::select::CARD_PORT_BACKUP)
+                            .await;
+
+                        // Player whose every sale slot is disabled on card terminal
+                        for player in PLAYERS {
+                            mutual_inhibit.set(
+                                InhibitSource::Terminal,
+                                player,
+                                slot_info.is_player_disabled(player as u8, PLAYER_INDEX_MAX as u8),
+                            );
+                        }
+                        mutual_inhibit.test_and_apply_output(board).await;
                     }
                     // read from lock_read for do something
                     // handle different TID/and something
@@ -250,8 +449,7 @@ impl Application {
                                     Some(CardTerminalDisplayWarning::WarnUnknown)
                                 }
                             } {
-                                card_reader
-                                    .send(CardTerminalTxCmd::DisplayWarning(alert))
+                                locale::display(board, CardTerminalTxCmd::DisplayWarning(alert))
                                     .await;
 
                                 did_we_alert_version_warning = true;
@@ -264,7 +462,7 @@ impl Application {
             }
 
             // Arcade legacy,
-            let input_event = if let Ok(raw_input_event) = async_input_event_ch.try_receive() {
+            let input_events = if let Ok(raw_input_event) = async_input_event_ch.try_receive() {
                 // let input_bits = async_input_event_ch.get_cache();
                 // defmt::info!("Input cache state changed : {:04X}", input_bits);
 
@@ -277,12 +475,19 @@ impl Application {
                         match event {
                             InputEventKind::Pressed => {
                                 is_svc_pressed = true;
+                                is_svc_combo = false;
                                 last_svc_pressed = Instant::now();
                             }
                             InputEventKind::LongPressed(t) => {
-                                if t < 2 {
+                                if t < 2 || is_svc_combo {
+                                } else if (2 < t)
+                                    && (t < 120)
+                                    && (appmode == AppMode0V3::DisplayRom)
+                                {
+                                    // ROM is already shown in this mode, show EEPROM wear instead
+                                    locale::display_wear(board).await;
                                 } else if (2 < t) && (t < 120) {
-                                    card_reader.send(CardTerminalTxCmd::DisplayRom).await;
+                                    locale::display(board, CardTerminalTxCmd::DisplayRom).await;
                                 } else {
                                     // Factory reset by SvcButton
                                     // but this clear only 1/2p credit and coin count
@@ -292,18 +497,29 @@ impl Application {
                                     {
                                         defmt::info!("Factory reset EEPROM");
 
-                                        card_reader
-                                            .send(CardTerminalTxCmd::DisplayWarning(
+                                        locale::display(
+                                            board,
+                                            CardTerminalTxCmd::DisplayWarning(
                                                 CardTerminalDisplayWarning::WarnEepromFactoryReset,
-                                            ))
-                                            .await;
+                                            ),
+                                        )
+                                        .await;
 
-                                        eeprom.lock_write_zero(eeprom::select::P1_CARD_CNT).await;
-                                        eeprom.lock_write_zero(eeprom::select::P2_CARD_CNT).await;
-                                        eeprom.lock_write_zero(eeprom::select::P1_COIN_CNT).await;
-                                        eeprom.lock_write_zero(eeprom::select::P2_COIN_CNT).await;
+                                        eeprom.lock_write_zero_income().await;
                                     } else {
-                                        card_reader.send(CardTerminalTxCmd::DisplayHwInfo).await;
+                                        // Bootloader boots this image again without uploader
+                                        if let Some(fw_update) =
+                                            board.correspond_fw_update().filter(|_| {
+                                                last_svc_pressed.as_secs()
+                                                    < FW_UPDATE_GESTURE_WINDOW.as_secs()
+                                            })
+                                        {
+                                            defmt::info!("Firmware update mode by SvcButton");
+                                            fw_update.request();
+                                        }
+
+                                        locale::display(board, CardTerminalTxCmd::DisplayHwInfo)
+                                            .await;
                                     }
                                 }
                                 is_svc_pressed = false;
@@ -318,83 +534,131 @@ impl Application {
                         //     port: InputPortKind::SvcButton,
                         //     event: InputEventKind::LongPressed(t),
                         // })
-                        None
+                        RoutedEvents::empty()
                     }
-                    Ok(y) => {
-                        let ret = y
-                            .replace_arr(match appmode {
-                                AppMode0V3::BypassStart
-                                | AppMode0V3::StartButtonDecideSerialToVend => &[
-                                    (InputPortKind::StartJam1P, InputPortKind::Start1P),
-                                    (InputPortKind::StartJam2P, InputPortKind::Start2P),
-                                ],
-                                AppMode0V3::BypassJam | AppMode0V3::DisplayRom => &[
-                                    (InputPortKind::StartJam1P, InputPortKind::Jam1P),
-                                    (InputPortKind::StartJam2P, InputPortKind::Jam2P),
-                                ],
-                            })
-                            .test_mut_inh_early_output(&mut mutual_inhibit, board)
-                            .await
-                            .apply_output(board, &mut filter_state, timing.is_override_force())
-                            .await;
+                    #[cfg(feature = "payout")]
+                    Ok(InputEvent {
+                        port: InputPortKind::PayoutSense,
+                        event,
+                    }) => {
+                        payout.sense(board, event).await;
+
+                        RoutedEvents::empty()
+                    }
+                    Ok(x) => {
+                        let mut ret = RoutedEvents::empty();
+
+                        for y in x.route(&io_routes, swap_players).iter() {
+                            let y = y.for_cabinet(cabinet).route_app_mode(appmode, is_free_play);
 
-                        Some(ret)
+                            // SVC button + start button gives service credit for the player
+                            #[cfg(feature = "svc_button")]
+                            let y = match (is_svc_pressed, y.port.for_player(Player::Player1)) {
+                                (true, InputPortKind::Start1P) => {
+                                    if matches!(y.event, InputEventKind::Pressed) {
+                                        let player = cabinet.income_player(y.port.player());
+
+                                        is_svc_combo = true;
+                                        service_credit::apply_service_credit(board, player, 1)
+                                            .await;
+                                        start_led.credit(player);
+                                    }
+
+                                    InputEvent {
+                                        port: InputPortKind::Nothing,
+                                        event: y.event,
+                                    }
+                                }
+                                _ => y,
+                            };
+
+                            ret.push(
+                                y.test_mut_inh_early_output(&mut mutual_inhibit, board)
+                                    .await
+                                    .apply_output(
+                                        board,
+                                        &mut filter_state,
+                                        timing.is_override_force(),
+                                    )
+                                    .await,
+                            );
+                        }
+
+                        ret
                     }
                     Err(e) => {
                         defmt::error!("Some wrong value incomed 0x{:02X}", e.number);
-                        None
+                        RoutedEvents::empty()
                     }
                 }
             } else {
                 filter_state.report_when_expired(board).await;
 
-                None
+                RoutedEvents::empty()
             };
 
             // StartButtonDecideSerialToVend related
-            if let Some((player, income, p_idx, ms)) = match (income_backup.clone(), input_event) {
-                (
-                    Some(income),
-                    Some(InputEvent {
-                        port: InputPortKind::Start1P,
-                        event: InputEventKind::LongPressed(ms),
-                    }),
-                ) => Some((Player::Player1, income, PLAYER_1_INDEX, ms as u16)),
-                (
-                    Some(income),
-                    Some(InputEvent {
-                        port: InputPortKind::Start2P,
+            for input_event in input_events.iter() {
+                let Some((player, p_idx, ms)) = (match input_event {
+                    InputEvent {
+                        port,
                         event: InputEventKind::LongPressed(ms),
-                    }),
-                ) => Some((Player::Player2, income, PLAYER_2_INDEX, ms as u16)),
-                _ => None,
-            } {
-                defmt::info!(
-                    "StartButtonDecideSerialToVend - condition matched, player : {}, income : {}",
-                    player,
-                    income,
-                );
-
-                PaymentReceive {
-                    origin: player,
-                    recv: income.recv,
-                }
-                .apply_output(board, timing.is_override_force())
-                .await;
+                    } if port.for_player(Player::Player1) == InputPortKind::Start1P => port
+                        .player()
+                        .index()
+                        .filter(|x| *x < PLAYER_INDEX_MAX)
+                        .map(|p_idx| (port.player(), p_idx, ms as u16)),
+                    _ => None,
+                }) else {
+                    continue;
+                };
 
-                Timer::after(Duration::from_millis(500)).await;
+                if start_decide
+                    .decide(board, player, timing.is_override_force())
+                    .await
+                {
+                    Timer::after(Duration::from_millis(500)).await;
 
-                hardware.host_sides[p_idx]
-                    .out_start
-                    .alt_forever_blink(ms, ms)
-                    .await;
+                    hardware.host_sides[p_idx]
+                        .out_start
+                        .alt_forever_blink(ms, ms)
+                        .await;
+
+                    defmt::info!("StartButtonDecideSerialToVend - exit trigger");
+                }
+            }
+            if let Some(player) = start_decide.poll(board, timing.is_override_force()).await {
+                start_led.credit(player);
+            }
 
-                // should be reset
-                income_backup = None;
+            // Start button LED
+            for event in input_events.iter() {
+                start_led.on_event(&event);
+            }
+            start_led
+                .update(
+                    board,
+                    core::array::from_fn(|idx| mutual_inhibit.is_output_inhibited(idx)),
+                    !start_decide.is_empty(),
+                    is_free_play,
+                )
+                .await;
 
-                defmt::info!("StartButtonDecideSerialToVend - exit trigger");
+            // Board-to-board link, forwarded payment from primary board
+            if let Some(player) = link
+                .poll(
+                    board,
+                    core::array::from_fn(|idx| mutual_inhibit.is_output_inhibited(idx)),
+                    timing.is_override_force(),
+                )
+                .await
+            {
+                start_led.credit(player);
             }
 
+            #[cfg(feature = "payout")]
+            payout.poll(board).await;
+
             yield_now().await;
         }
     }
@@ -5,46 +5,189 @@
  */
 
 use bit_field::BitField;
+use card_terminal_adapter::types::RawPlayersInhibitReason;
+use card_terminal_adapter::CardTerminalTxCmd;
 
-use crate::boards::{Board, PLAYER_1_INDEX, PLAYER_2_INDEX};
+use super::locale;
+use crate::boards::{Board, PLAYER_1_INDEX, PLAYER_2_INDEX, PLAYER_INDEX_MAX};
 use crate::semi_layer::buffered_wait::InputEventKind;
 use crate::types::dip_switch_config::InhibitOverride;
 use crate::types::input_port::{InputEvent, InputPortKind};
-use crate::types::player::Player;
+use crate::types::install_config::{CabinetProfile, InstallConfig};
+use crate::types::player::{Player, PLAYERS};
+
+/// Named reason to inhibit, the declaration order is the priority.
+/// When several sources hold inhibit together, the first one is reported as the holder.
+#[repr(u8)]
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub enum InhibitSource {
+    /// Payment path is unhealthy (safe state)
+    Health = 0,
+    /// Inhibit override DIP switch
+    DipSwitch = 1,
+    /// Every sale slot of the player is disabled on card terminal
+    Terminal = 2,
+    /// Operating schedule (e.g. closed hours)
+    Schedule = 3,
+    /// GAME I/O inhibit signal
+    GameIo = 4,
+}
+
+impl InhibitSource {
+    #[inline]
+    pub const fn mask(self) -> u8 {
+        1 << (self as u8)
+    }
+
+    pub const fn from_priority(idx: u8) -> Self {
+        match idx {
+            0 => Self::Health,
+            1 => Self::DipSwitch,
+            2 => Self::Terminal,
+            3 => Self::Schedule,
+            _ => Self::GameIo,
+        }
+    }
+}
+
+/// Policy to combine holding sources into inhibit output
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub enum InhibitPolicy {
+    /// Inhibit when any source holds inhibit
+    Any,
+    /// Inhibit only when every source in the mask holds inhibit,
+    /// sources out of the mask still inhibit by themselves.
+    All(u8),
+}
+
+/// Sources that always inhibit by themselves regardless of `InhibitPolicy`
+const INHIBIT_UNCOMBINED_SOURCES: u8 = InhibitSource::Health.mask();
+
+/// Sources that change frequently in normal operation, not reported on the terminal display
+const INHIBIT_QUIET_SOURCES: u8 = InhibitSource::GameIo.mask();
+
+/// Output bit mask that every player is inhibited
+const INHIBIT_OUTPUT_ALL: u8 = (1 << PLAYER_INDEX_MAX) - 1;
+
+/// Sources ignored on free-play mode, safety related sources are still effective.
+pub const FREE_PLAY_SUPPRESSED_SOURCES: u8 =
+    InhibitSource::GameIo.mask() | InhibitSource::Terminal.mask() | InhibitSource::Schedule.mask();
 
 /// Mutual Inhibit module for resolve complex inhibit input / output source.
+/// Each player has bit mask of `InhibitSource` which currently holds inhibit.
 #[derive(Clone, Copy)]
-pub struct MutualInhibit(u8);
+pub struct MutualInhibit {
+    holds: [u8; PLAYER_INDEX_MAX],
+    /// Sources that don't affect output even if those hold inhibit
+    suppressed: u8,
+    policy: InhibitPolicy,
+    /// Last applied output, bit mask by player index
+    output: u8,
+    /// Last reported sources on terminal display
+    reported: [u8; PLAYER_INDEX_MAX],
+    /// Player 1 inhibit of GAME I/O is applied to every player (shared coin line)
+    shared_gpio: bool,
+}
 
 impl MutualInhibit {
     pub const fn new() -> Self {
-        Self(0)
+        Self {
+            holds: [0; PLAYER_INDEX_MAX],
+            suppressed: 0,
+            policy: InhibitPolicy::Any,
+            output: 0,
+            reported: [0; PLAYER_INDEX_MAX],
+            shared_gpio: false,
+        }
+    }
+
+    pub fn set_policy(&mut self, policy: InhibitPolicy) {
+        if policy != self.policy {
+            defmt::info!("Inhibit - policy : {}", policy);
+            self.policy = policy;
+        }
+    }
+
+    /// Load inhibit policy of install config, safety related sources are never combined.
+    pub fn load(&mut self, config: &InstallConfig) {
+        self.set_policy(
+            match config.inhibit_all_mask & !INHIBIT_UNCOMBINED_SOURCES {
+                0 => InhibitPolicy::Any,
+                mask => InhibitPolicy::All(mask),
+            },
+        );
+    }
+
+    /// Suppress sources by bit mask of `InhibitSource`, zero for nothing.
+    pub fn set_suppressed(&mut self, mask: u8) {
+        self.suppressed = mask;
+    }
+
+    /// Hold or release inhibit by the source, `Player::Undefined` means every player.
+    pub fn set(&mut self, source: InhibitSource, player: Player, state: bool) {
+        for (idx, holds) in self.holds.iter_mut().enumerate() {
+            if player == Player::Undefined || player.index() == Some(idx) {
+                holds.set_bit(source as usize, state);
+            }
+        }
+    }
+
+    pub fn get(&self, source: InhibitSource, player: Player) -> bool {
+        match player.index() {
+            None => self.holds.iter().all(|x| x.get_bit(source as usize)),
+            Some(idx) => self.holds[idx].get_bit(source as usize),
+        }
+    }
+
+    /// Override of 1P and 2P is applied to player 3 and 4 by parity, see `Player::fold`.
+    fn update_override(&mut self, source: InhibitSource, value: InhibitOverride) {
+        for player in PLAYERS {
+            let bit = player.fold().index().unwrap_or(PLAYER_1_INDEX);
+            self.set(source, player, (value as u8).get_bit(bit));
+        }
+    }
+
+    fn get_override(&self, source: InhibitSource) -> InhibitOverride {
+        let p1 = self.holds[PLAYER_1_INDEX].get_bit(source as usize) as u8;
+        let p2 = self.holds[PLAYER_2_INDEX].get_bit(source as usize) as u8;
+
+        InhibitOverride::try_from(p1 | (p2 << 1)).unwrap() // infallable
     }
 
     #[inline]
     pub fn update_dipsw(&mut self, dipsw: InhibitOverride) {
-        self.0 = (self.0 & 0b1111_1100) | ((dipsw as u8) & 0b0000_0011);
+        self.update_override(InhibitSource::DipSwitch, dipsw);
     }
 
     #[inline]
     pub fn get_dipsw(&self) -> InhibitOverride {
-        InhibitOverride::try_from(self.0 & 0b11).unwrap() // infallable
+        self.get_override(InhibitSource::DipSwitch)
     }
 
     #[inline]
     #[allow(unused)]
     pub fn update_gpio(&mut self, gpio: InhibitOverride) {
-        self.0 = self.0 & 0b1111_0011 | (((gpio as u8) & 0b0000_0011) << 2);
+        self.update_override(InhibitSource::GameIo, gpio);
     }
 
     #[inline]
     pub fn set_gpio_player(&mut self, player: Player, state: bool) {
-        match player {
-            Player::Undefined => {
-                self.0.set_bits(2..=3, 0b11 * state as u8);
-            }
-            x => {
-                self.0.set_bit(x as usize + 1, state);
+        let player = match (self.shared_gpio, player) {
+            (true, Player::Player1) => Player::Undefined,
+            (_, x) => x,
+        };
+
+        self.set(InhibitSource::GameIo, player, state);
+    }
+
+    /// Follow GAME I/O inhibit by cabinet profile, inhibit of unused players is released.
+    pub fn set_cabinet(&mut self, profile: CabinetProfile) {
+        self.shared_gpio = profile == CabinetProfile::SharedCredit;
+
+        if profile != CabinetProfile::TwoPlayer {
+            let p1 = self.get(InhibitSource::GameIo, Player::Player1);
+            for player in PLAYERS.into_iter().skip(PLAYER_2_INDEX) {
+                self.set(InhibitSource::GameIo, player, self.shared_gpio && p1);
             }
         }
     }
@@ -52,35 +195,96 @@ impl MutualInhibit {
     #[inline]
     #[allow(unused)]
     pub fn get_gpio(&self) -> InhibitOverride {
-        InhibitOverride::try_from((self.0 >> 2) & 0b11).unwrap() // infallable
+        self.get_override(InhibitSource::GameIo)
+    }
+
+    /// Safe state by health machine forces global inhibit
+    #[inline]
+    pub fn update_health(&mut self, safe_state: bool) {
+        self.set(InhibitSource::Health, Player::Undefined, safe_state);
+    }
+
+    /// The source which holds inhibit with highest priority
+    pub fn holder(&self, player_idx: usize) -> Option<InhibitSource> {
+        let holds = self.holds[player_idx] & !self.suppressed;
+
+        (holds != 0).then(|| InhibitSource::from_priority(holds.trailing_zeros() as u8))
+    }
+
+    fn is_inhibited(&self, player_idx: usize) -> bool {
+        let holds = self.holds[player_idx] & !self.suppressed;
+
+        match self.policy {
+            InhibitPolicy::Any => holds != 0,
+            InhibitPolicy::All(mask) => {
+                ((holds & !mask) != 0) || ((mask != 0) && ((holds & mask) == mask))
+            }
+        }
+    }
+
+    /// Last applied inhibit output of the player
+    pub fn is_output_inhibited(&self, player_idx: usize) -> bool {
+        self.output.get_bit(player_idx)
     }
 
-    pub fn test_and_check(&mut self) -> Option<InhibitOverride> {
-        let cmp = ((self.0 >> 2) & 0b11) | (self.0 & 0b11);
-        let prev = (self.0 >> 4) & 0b11;
-        if cmp != prev {
-            let after = InhibitOverride::try_from(cmp).unwrap(); // infallable
-            self.0 = (self.0 & 0b1100_1111) | (cmp << 4);
+    /// Active inhibit sources of each player for terminal display and diagnostic.
+    /// Sources of player 3 and 4 are merged into player 1 and 2 by parity, see `Player::fold`.
+    pub fn reason(&self) -> RawPlayersInhibitReason {
+        let mut ret = RawPlayersInhibitReason { p1: 0, p2: 0 };
+
+        for (player, holds) in PLAYERS.into_iter().zip(self.holds) {
+            match player.fold() {
+                Player::Player2 => ret.p2 |= holds,
+                _ => ret.p1 |= holds,
+            }
+        }
+
+        ret
+    }
+
+    /// Inhibit output as bit mask by player index, `None` when it's not changed.
+    pub fn test_and_check(&mut self) -> Option<u8> {
+        let after = (0..PLAYER_INDEX_MAX).fold(0u8, |acc, idx| {
+            acc | ((self.is_inhibited(idx) as u8) << idx)
+        });
+
+        if after != self.output {
+            self.output = after;
+
+            for (idx, player) in PLAYERS.into_iter().enumerate() {
+                defmt::info!(
+                    "Inhibit - {} : {} ({})",
+                    player,
+                    after.get_bit(idx),
+                    self.holder(idx),
+                );
+            }
+
             Some(after)
         } else {
             None
         }
     }
 
+    /// Show active inhibit sources on card terminal display
+    async fn report(&mut self, board: &Board) {
+        let reason = self.reason();
+        self.reported = self.holds;
+
+        locale::display(board, CardTerminalTxCmd::DisplayInhibitReason(reason)).await;
+    }
+
     pub async fn test_and_apply_output(&mut self, board: &Board) {
         // use card_terminal_adapter::types::RawPlayersInhibit;
 
-        let inhibit_1p = &board.hardware.vend_sides[PLAYER_1_INDEX].out_inhibit;
-        let inhibit_2p = &board.hardware.vend_sides[PLAYER_2_INDEX].out_inhibit;
         let serial_credit = &board.hardware.card_reader;
 
         let result = self.test_and_check();
 
         if let Some(x) = result {
-            let (p1, p2) = ((x as u8).get_bit(0), (x as u8).get_bit(1));
-
-            inhibit_1p.set_level(p1).await;
-            inhibit_2p.set_level(p2).await;
+            for (idx, vend_side) in board.hardware.vend_sides.iter().enumerate() {
+                vend_side.out_inhibit.set_level(x.get_bit(idx)).await;
+            }
 
             // ED785 doesn't support inhibit signal well....
             // serial_credit
@@ -88,9 +292,20 @@ impl MutualInhibit {
             //     .await;
 
             serial_credit
-                .send_transaction_availability(InhibitOverride::ForceInhibitGlobal != x)
+                .send_transaction_availability(x != INHIBIT_OUTPUT_ALL)
                 .await;
         }
+
+        // Report only when unusual source is changed, GAME I/O inhibit toggles too often.
+        let changed = self
+            .holds
+            .iter()
+            .zip(self.reported.iter())
+            .any(|(x, y)| (x & !INHIBIT_QUIET_SOURCES) != (y & !INHIBIT_QUIET_SOURCES));
+
+        if changed {
+            self.report(board).await;
+        }
     }
 }
 
@@ -103,19 +318,18 @@ fn map_event_kind(evt: &InputEventKind) -> bool {
 }
 
 impl InputEvent {
+    /// Player and state of GAME I/O inhibit input
+    fn gpio_inhibit(&self) -> Option<(Player, bool)> {
+        match (self.port.for_player(Player::Player1), self.event) {
+            (_, InputEventKind::LongPressed(_)) => None,
+            (InputPortKind::Inhibit1P, x) => Some((self.port.player(), map_event_kind(&x))),
+            _ => None,
+        }
+    }
+
     #[allow(unused)]
     pub fn test_mut_inh(&self, mut_inh: &mut MutualInhibit) -> Self {
-        if let Some((player, status)) = {
-            if matches!(self.event, InputEventKind::LongPressed(_)) {
-                None
-            } else if self.port == InputPortKind::Inhibit1P {
-                Some((Player::Player1, map_event_kind(&self.event)))
-            } else if self.port == InputPortKind::Inhibit2P {
-                Some((Player::Player2, map_event_kind(&self.event)))
-            } else {
-                None
-            }
-        } {
+        if let Some((player, status)) = self.gpio_inhibit() {
             mut_inh.set_gpio_player(player, status);
         }
 
@@ -124,17 +338,7 @@ impl InputEvent {
 
     #[allow(unused)]
     pub fn test_mut_inh_then_ignore(&self, mut_inh: &mut MutualInhibit) -> Self {
-        if let Some((player, status)) = {
-            if matches!(self.event, InputEventKind::LongPressed(_)) {
-                None
-            } else if self.port == InputPortKind::Inhibit1P {
-                Some((Player::Player1, map_event_kind(&self.event)))
-            } else if self.port == InputPortKind::Inhibit2P {
-                Some((Player::Player2, map_event_kind(&self.event)))
-            } else {
-                None
-            }
-        } {
+        if let Some((player, status)) = self.gpio_inhibit() {
             mut_inh.set_gpio_player(player, status);
             Self {
                 port: InputPortKind::Nothing,
@@ -150,17 +354,7 @@ impl InputEvent {
         mut_inh: &mut MutualInhibit,
         board: &Board,
     ) -> Self {
-        if let Some((player, status)) = {
-            if matches!(self.event, InputEventKind::LongPressed(_)) {
-                None
-            } else if self.port == InputPortKind::Inhibit1P {
-                Some((Player::Player1, map_event_kind(&self.event)))
-            } else if self.port == InputPortKind::Inhibit2P {
-                Some((Player::Player2, map_event_kind(&self.event)))
-            } else {
-                None
-            }
-        } {
+        if let Some((player, status)) = self.gpio_inhibit() {
             mut_inh.set_gpio_player(player, status);
 
             mut_inh.test_and_apply_output(board).await;
@@ -0,0 +1,153 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+use card_terminal_adapter::types::{PayoutReport, PayoutRequest, PayoutResult};
+use card_terminal_adapter::{CardTerminalDisplayWarning, CardTerminalTxCmd};
+use embassy_time::{Duration, Instant};
+
+use super::locale;
+use crate::boards::*;
+use crate::components::eeprom;
+use crate::semi_layer::buffered_wait::InputEventKind;
+use crate::types::player::Player;
+
+/// No item passed the feedback sensor within this time, hopper or ticket bin is empty.
+const PAYOUT_EMPTY_TIMEOUT: Duration = Duration::from_millis(3000);
+/// Feedback sensor kept active longer than this time, item is stuck on the sensor.
+const PAYOUT_JAM_TIMEOUT: Duration = Duration::from_millis(1000);
+
+pub(crate) struct PayoutMachine {
+    /// Port of the request, reported back as it is
+    port: u8,
+    player: Player,
+    requested: u16,
+    dispensed: u16,
+    /// Last time motor started or item passed sensor, `None` when motor is stopped
+    last_item: Option<Instant>,
+    /// The time feedback sensor became active
+    sense_since: Option<Instant>,
+}
+
+impl PayoutMachine {
+    pub const fn new() -> Self {
+        Self {
+            port: 0,
+            player: Player::Undefined,
+            requested: 0,
+            dispensed: 0,
+            last_item: None,
+            sense_since: None,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.last_item.is_some()
+    }
+
+    /// Start dispensing, return false when previous payout is not finished yet.
+    pub async fn request(&mut self, board: &'static Board, req: PayoutRequest) -> bool {
+        if self.is_running() {
+            defmt::warn!("Payout - busy, requested : {}", req);
+            return false;
+        }
+
+        if req.count == 0 {
+            return true;
+        }
+
+        // same port rule with PaymentReceive
+        self.port = req.port;
+        self.player = Player::from_port_num(req.port);
+        self.requested = req.count;
+        self.dispensed = 0;
+        self.sense_since = None;
+        self.last_item = Some(Instant::now());
+
+        defmt::info!("Payout - start {}, count : {}", self.player, self.requested);
+        board.hardware.dispenser.out_motor.set_high().await;
+
+        true
+    }
+
+    /// Handle feedback sensor event, an item is counted when it leaves the sensor.
+    pub async fn sense(&mut self, board: &'static Board, event: InputEventKind) {
+        match event {
+            InputEventKind::Pressed => {
+                self.sense_since = Some(Instant::now());
+            }
+            InputEventKind::Released => {
+                self.sense_since = None;
+
+                if !self.is_running() {
+                    defmt::warn!("Payout - item detected while motor stopped");
+                    return;
+                }
+
+                self.dispensed += 1;
+                self.last_item = Some(Instant::now());
+
+                let eeprom = &board.hardware.eeprom;
+                let count = eeprom.lock_read(eeprom::select::PAYOUT_CNT).await;
+                eeprom
+                    .lock_write(eeprom::select::PAYOUT_CNT, count + 1)
+                    .await;
+
+                if self.requested <= self.dispensed {
+                    self.finish(board, PayoutResult::Done).await;
+                }
+            }
+            InputEventKind::LongPressed(_) => {}
+        }
+    }
+
+    /// Check empty and jam timeout, should be called periodically.
+    pub async fn poll(&mut self, board: &'static Board) {
+        let Some(last_item) = self.last_item else {
+            return;
+        };
+        let now = Instant::now();
+
+        if let Some(since) = self.sense_since {
+            if (since + PAYOUT_JAM_TIMEOUT) < now {
+                self.finish(board, PayoutResult::Jam).await;
+            }
+        } else if (last_item + PAYOUT_EMPTY_TIMEOUT) < now {
+            self.finish(board, PayoutResult::Empty).await;
+        }
+    }
+
+    async fn finish(&mut self, board: &'static Board, result: PayoutResult) {
+        let card_reader = &board.hardware.card_reader;
+
+        board.hardware.dispenser.out_motor.set_low().await;
+        self.last_item = None;
+
+        defmt::info!(
+            "Payout - {} finished {}, {} / {}",
+            self.player,
+            result,
+            self.dispensed,
+            self.requested
+        );
+
+        card_reader
+            .send(CardTerminalTxCmd::PushPayoutReport(PayoutReport {
+                port: self.port,
+                requested: self.requested,
+                dispensed: self.dispensed,
+                result,
+            }))
+            .await;
+
+        if let Some(warn) = match result {
+            PayoutResult::Done => None,
+            PayoutResult::Empty => Some(CardTerminalDisplayWarning::WarnPayoutEmpty),
+            PayoutResult::Jam => Some(CardTerminalDisplayWarning::WarnPayoutJam),
+        } {
+            locale::display(board, CardTerminalTxCmd::DisplayWarning(warn)).await;
+        }
+    }
+}
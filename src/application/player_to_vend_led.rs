@@ -4,26 +4,30 @@
  * SPDX-License-Identifier: MIT OR Apache-2.0
  */
 
-use crate::boards::{Board, LED_1_INDEX, LED_2_INDEX, PLAYER_1_INDEX, PLAYER_2_INDEX};
+use crate::boards::{Board, LED_1_INDEX, LED_2_INDEX, PLAYER_1_INDEX, PLAYER_INDEX_MAX};
 use crate::semi_layer::buffered_opendrain::BufferedOpenDrain;
 use crate::types::player::Player;
 
 impl Player {
-    pub const fn to_vend_busy_led(
+    /// Host side vend, busy output and indicator of the player, `Player::Undefined` is player 1.
+    /// Indicators are only two, player 3 and 4 share the indicator of player 1 and 2.
+    pub fn to_vend_busy_led(
         self,
         board: &'static Board,
     ) -> (&BufferedOpenDrain, &BufferedOpenDrain, &BufferedOpenDrain) {
-        match self {
-            Player::Player2 => (
-                &board.hardware.host_sides[PLAYER_2_INDEX].out_vend,
-                &board.hardware.host_sides[PLAYER_2_INDEX].out_busy,
-                &board.hardware.indicators[LED_2_INDEX],
-            ),
-            _ => (
-                &board.hardware.host_sides[PLAYER_1_INDEX].out_vend,
-                &board.hardware.host_sides[PLAYER_2_INDEX].out_busy,
-                &board.hardware.indicators[LED_1_INDEX],
-            ),
-        }
+        let idx = self
+            .index()
+            .filter(|x| *x < PLAYER_INDEX_MAX)
+            .unwrap_or(PLAYER_1_INDEX);
+        let led_idx = match self.fold() {
+            Player::Player2 => LED_2_INDEX,
+            _ => LED_1_INDEX,
+        };
+
+        (
+            &board.hardware.host_sides[idx].out_vend,
+            &board.hardware.host_sides[idx].out_busy,
+            &board.hardware.indicators[led_idx],
+        )
     }
 }
@@ -5,6 +5,7 @@ use crate::boards::*;
 use crate::components::eeprom;
 use crate::semi_layer::buffered_wait::InputEventKind;
 use crate::types::input_port::{InputEvent, InputPortKind};
+use crate::types::player::Player;
 
 #[derive(Clone)]
 pub(crate) struct PulseMemory {
@@ -61,9 +62,8 @@ impl PulseMemory {
 
 impl PulseMemoryFilterMachine {
     pub fn new() -> Self {
-        let new = PulseMemory::new();
         Self {
-            player: [new.clone(), new],
+            player: core::array::from_fn(|_| PulseMemory::new()),
         }
     }
 
@@ -72,13 +72,13 @@ impl PulseMemoryFilterMachine {
         // should I detect signal first?
         if let Some((index, timing_in_ms)) = match input {
             InputEvent {
-                port: InputPortKind::Vend1P,
+                port,
                 event: InputEventKind::LongPressed(time_in_10ms),
-            } => Some((PLAYER_1_INDEX, (*time_in_10ms as u16) * 10)),
-            InputEvent {
-                port: InputPortKind::Vend2P,
-                event: InputEventKind::LongPressed(time_in_10ms),
-            } => Some((PLAYER_2_INDEX, (*time_in_10ms as u16) * 10)),
+            } if port.for_player(Player::Player1) == InputPortKind::Vend1P => port
+                .player()
+                .index()
+                .filter(|x| *x < PLAYER_INDEX_MAX)
+                .map(|x| (x, (*time_in_10ms as u16) * 10)),
             _ => None,
         } {
             let mut target = &mut self.player[index];
@@ -87,14 +87,14 @@ impl PulseMemoryFilterMachine {
     }
 
     pub async fn report_when_expired(&mut self, board: &'static Board) {
-        for player_index in [PLAYER_1_INDEX, PLAYER_2_INDEX] {
+        for player_index in 0..PLAYER_INDEX_MAX {
             if self.player[player_index].is_running_and_overtime() {
                 if let Some(assume_report) = board
                     .hardware
                     .eeprom
                     .lock_read(eeprom::select::CARD_PORT_BACKUP)
                     .await
-                    .guess_raw_income_by_player(1 + player_index as u8)
+                    .guess_raw_income_by_player(1 + player_index as u8, PLAYER_INDEX_MAX as u8)
                 {
                     let actual = self.player[player_index].count.unwrap_or_default();
                     let expected = assume_report.get_pulse_count();
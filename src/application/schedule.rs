@@ -0,0 +1,165 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Time-of-day operating schedule.
+//! The board doesn't have RTC, thus wall-clock time is synced from card terminal periodically
+//! and kept with embassy uptime tick between syncs (soft RTC).
+
+use card_terminal_adapter::types::RawDateTime;
+use card_terminal_adapter::CardTerminalTxCmd;
+use embassy_time::{Duration, Instant};
+use zeroable::Zeroable;
+
+use crate::boards::*;
+use crate::types::install_config::*;
+
+/// Soft RTC drifts by crystal tolerance, resync periodically.
+const RTC_SYNC_PERIOD: Duration = Duration::from_secs(3600);
+/// Retry period when card terminal doesn't respond time yet
+const RTC_RETRY_PERIOD: Duration = Duration::from_secs(60);
+const SCHEDULE_CHECK_PERIOD: Duration = Duration::from_millis(1000);
+
+const SECS_PER_DAY: u32 = 24 * 3600;
+const SECS_PER_WEEK: u32 = 7 * SECS_PER_DAY;
+
+pub struct SoftRtc {
+    /// Seconds since Monday 00:00 at synced moment, and the moment
+    synced: Option<(u32, Instant)>,
+}
+
+impl SoftRtc {
+    pub const fn new() -> Self {
+        Self { synced: None }
+    }
+
+    pub fn sync(&mut self, date_time: &RawDateTime) -> bool {
+        if !date_time.is_valid() {
+            defmt::warn!("SoftRtc - invalid date time : {}", date_time);
+            return false;
+        }
+
+        let secs_of_week = date_time.weekday() as u32 * SECS_PER_DAY + date_time.seconds_of_day();
+        self.synced = Some((secs_of_week, Instant::now()));
+
+        defmt::info!("SoftRtc - synced : {}", date_time);
+
+        true
+    }
+
+    pub fn synced_at(&self) -> Option<Instant> {
+        self.synced.map(|(_, at)| at)
+    }
+
+    /// Current (weekday, seconds of day), weekday 0 is Monday.
+    pub fn now(&self) -> Option<(u8, u32)> {
+        self.synced.map(|(base, at)| {
+            let elapsed = ((Instant::now() - at).as_secs() % SECS_PER_WEEK as u64) as u32;
+            let secs_of_week = (base + elapsed) % SECS_PER_WEEK;
+
+            (
+                (secs_of_week / SECS_PER_DAY) as u8,
+                secs_of_week % SECS_PER_DAY,
+            )
+        })
+    }
+}
+
+pub struct ScheduleMachine {
+    rtc: SoftRtc,
+    entries: [RawScheduleEntry; SCHEDULE_ENTRY_NUM],
+    /// Active entry, first matched entry has priority
+    active: Option<RawScheduleEntry>,
+    last_check: Instant,
+    last_request: Option<Instant>,
+}
+
+impl ScheduleMachine {
+    pub fn new() -> Self {
+        Self {
+            rtc: SoftRtc::new(),
+            entries: [RawScheduleEntry::zeroed(); SCHEDULE_ENTRY_NUM],
+            active: None,
+            last_check: Instant::now(),
+            last_request: None,
+        }
+    }
+
+    pub fn load(&mut self, config: &InstallConfig) {
+        self.entries = config.schedule;
+    }
+
+    pub fn sync(&mut self, date_time: &RawDateTime) {
+        self.rtc.sync(date_time);
+    }
+
+    /// Without synced time, schedule is not applied (normal operation).
+    pub fn action(&self) -> ScheduleAction {
+        self.active
+            .map(|x| x.action())
+            .unwrap_or(ScheduleAction::Idle)
+    }
+
+    /// Bonus percent of card payment on happy hour
+    pub fn bonus_percent(&self) -> u16 {
+        match self.active {
+            Some(x) if x.action() == ScheduleAction::HappyHour => x.param() as u16 * 10,
+            _ => 0,
+        }
+    }
+
+    async fn request_sync(&mut self, board: &'static Board, now: Instant) {
+        // Card terminal is not connected yet
+        if board.hardware.card_reader.last_rx().is_none() {
+            return;
+        }
+
+        let is_stale = match self.rtc.synced_at() {
+            Some(at) => (at + RTC_SYNC_PERIOD) < now,
+            None => true,
+        };
+        let can_retry = match self.last_request {
+            Some(at) => (at + RTC_RETRY_PERIOD) < now,
+            None => true,
+        };
+
+        if is_stale && can_retry {
+            self.last_request = Some(now);
+            board
+                .hardware
+                .card_reader
+                .send(CardTerminalTxCmd::RequestDateTime)
+                .await;
+        }
+    }
+
+    /// Check schedule periodically, returns new action when active schedule is changed.
+    pub async fn poll(&mut self, board: &'static Board) -> Option<ScheduleAction> {
+        let now = Instant::now();
+        if now < (self.last_check + SCHEDULE_CHECK_PERIOD) {
+            return None;
+        }
+        self.last_check = now;
+
+        self.request_sync(board, now).await;
+
+        let prev = self.action();
+
+        self.active = self.rtc.now().and_then(|(weekday, secs)| {
+            let slot = (secs / SCHEDULE_SLOT_SECS) as u8;
+
+            self.entries
+                .iter()
+                .find(|x| x.is_active(weekday, slot))
+                .copied()
+        });
+
+        let after = self.action();
+        (prev != after).then(|| {
+            defmt::info!("Schedule - {} -> {}", prev, after);
+            after
+        })
+    }
+}
@@ -0,0 +1,47 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Service credit, credits given by operator without payment (fault refund, tournament and etc).
+//! It's counted on separated service meter, thus card and coin income count are not polluted.
+
+use super::{DEFAULT_BUSY_ALPHA_TIMING_MS, DEFAULT_VEND_INDICATOR_TIMING_MS};
+use crate::boards::*;
+use crate::components::eeprom;
+use crate::types::player::Player;
+
+/// Pulse host side vend for the player and count on service meter.
+pub async fn apply_service_credit(board: &'static Board, player: Player, count: u8) {
+    let player = match player.index() {
+        Some(idx) if idx < PLAYER_INDEX_MAX => player,
+        _ => Player::Player1,
+    };
+
+    if count == 0 {
+        return;
+    }
+
+    defmt::info!("Service credit - {}, count : {}", player, count);
+
+    let (vend, busy, led) = player.to_vend_busy_led(board);
+
+    vend.tick_tock(count).await;
+    busy.one_shot_high_shared_alpha(count, DEFAULT_BUSY_ALPHA_TIMING_MS)
+        .await;
+
+    let eeprom = &board.hardware.eeprom;
+    let mut service_cnt = eeprom.lock_read(eeprom::select::SERVICE_CNT).await;
+    service_cnt.add(player, count as u32);
+    eeprom
+        .lock_write(eeprom::select::SERVICE_CNT, service_cnt)
+        .await;
+
+    led.alt_tick_tock(
+        count,
+        DEFAULT_VEND_INDICATOR_TIMING_MS,
+        DEFAULT_VEND_INDICATOR_TIMING_MS,
+    )
+    .await;
+}
@@ -0,0 +1,219 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Held payment of start-button-decide mode (`AppMode0V3::StartButtonDecideSerialToVend`).
+//! Card payment is held until a player presses start button, then the player takes the credit.
+//! When nobody decides in time, the payment is cancelled or credited to fallback player.
+
+use card_terminal_adapter::types::IncomeArcadeRequest;
+use card_terminal_adapter::CardTerminalTxCmd;
+use embassy_time::{Duration, Instant};
+
+use super::io_card::PaymentReceive;
+use crate::boards::*;
+use crate::components::serial_device::support_payment_cancel;
+use crate::types::install_config::*;
+use crate::types::player::Player;
+
+/// Maximum number of held payments, following payment is nacked when it's full.
+const HELD_PAYMENT_QUEUE_SIZE: usize = 4;
+/// Both PCB indicators blink while payment is held, on boards without start button LED.
+#[cfg(not(feature = "start_button"))]
+const DECIDE_PROMPT_BLINK_MS: u16 = 250;
+
+struct HeldPayment {
+    payment: PaymentReceive,
+    /// As received from card terminal before bonus and player are applied,
+    /// terminal cancels the payment by it.
+    request: IncomeArcadeRequest,
+}
+
+pub struct StartDecideMachine {
+    /// Held payments, oldest one first
+    queue: [Option<HeldPayment>; HELD_PAYMENT_QUEUE_SIZE],
+    len: usize,
+    /// Decision timeout is counted for the oldest payment only
+    head_since: Instant,
+    timeout: Duration,
+    fallback: DecideFallback,
+    cabinet: CabinetProfile,
+}
+
+impl StartDecideMachine {
+    pub fn new() -> Self {
+        Self {
+            queue: Default::default(),
+            len: 0,
+            head_since: Instant::now(),
+            timeout: Duration::from_secs(DEFAULT_DECIDE_TIMEOUT_SECS as u64),
+            fallback: DecideFallback::Auto,
+            cabinet: CabinetProfile::TwoPlayer,
+        }
+    }
+
+    pub fn load(&mut self, config: &InstallConfig) {
+        self.timeout = Duration::from_secs(config.decide_timeout_secs() as u64);
+        self.fallback = config.decide_fallback();
+        self.cabinet = config.cabinet();
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Start button LEDs are blinked by `StartLedMachine` while payment is held,
+    /// PCB indicators are blinked here on boards without start button LED.
+    /// Start output to game PCB is never touched, game would start on credits already in it.
+    async fn update_prompt(&self, board: &'static Board) {
+        #[cfg(not(feature = "start_button"))]
+        for led in board.hardware.indicators.iter() {
+            match self.is_empty() {
+                true => led.set_low().await,
+                false => {
+                    led.alt_forever_blink(DECIDE_PROMPT_BLINK_MS, DECIDE_PROMPT_BLINK_MS)
+                        .await
+                }
+            }
+        }
+
+        #[cfg(feature = "start_button")]
+        let _ = board;
+    }
+
+    /// Hold payment until start button is pressed, returns false when queue is full.
+    /// `request` is the original one from card terminal, for cancel.
+    pub async fn hold(
+        &mut self,
+        board: &'static Board,
+        payment: PaymentReceive,
+        request: IncomeArcadeRequest,
+    ) -> bool {
+        if HELD_PAYMENT_QUEUE_SIZE <= self.len {
+            defmt::warn!("StartDecide - queue is full, payment : {}", payment);
+            return false;
+        }
+
+        defmt::info!(
+            "StartDecide - payment held [{}], wait for start button",
+            self.len
+        );
+
+        if self.is_empty() {
+            self.head_since = Instant::now();
+        }
+        self.queue[self.len] = Some(HeldPayment { payment, request });
+        self.len += 1;
+
+        self.update_prompt(board).await;
+
+        true
+    }
+
+    fn pop(&mut self) -> Option<HeldPayment> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let head = self.queue[0].take();
+        self.queue.rotate_left(1);
+        self.len -= 1;
+        self.head_since = Instant::now();
+
+        head
+    }
+
+    /// Credit the oldest held payment to the player, returns false if nothing is held.
+    pub async fn decide(
+        &mut self,
+        board: &'static Board,
+        player: Player,
+        override_druation_force: bool,
+    ) -> bool {
+        let Some(HeldPayment { payment, .. }) = self.pop() else {
+            return false;
+        };
+
+        defmt::info!(
+            "StartDecide - decided, player : {}, income : {}",
+            player,
+            payment
+        );
+
+        payment
+            .assign(self.cabinet.income_player(player))
+            .apply_output(board, override_druation_force)
+            .await;
+
+        self.update_prompt(board).await;
+
+        true
+    }
+
+    /// Returns the player who takes the payment, `None` when the payment is cancelled.
+    async fn fallback(
+        &self,
+        board: &'static Board,
+        held: HeldPayment,
+        override_druation_force: bool,
+    ) -> Option<Player> {
+        let HeldPayment { payment, request } = held;
+        let player = match (self.fallback, support_payment_cancel()) {
+            (DecideFallback::Auto, true) => {
+                defmt::info!("StartDecide - cancel payment : {}", request);
+                board
+                    .hardware
+                    .card_reader
+                    .send(CardTerminalTxCmd::RequestPaymentCancel(request.into()))
+                    .await;
+                return None;
+            }
+            (DecideFallback::Player2, _) => Player::Player2,
+            _ => Player::Player1,
+        };
+
+        let player = self.cabinet.income_player(player);
+        defmt::info!("StartDecide - fallback to {}, income : {}", player, payment);
+
+        payment
+            .assign(player)
+            .apply_output(board, override_druation_force)
+            .await;
+
+        Some(player)
+    }
+
+    /// Check decision timeout of the oldest held payment, returns the player if credited.
+    pub async fn poll(
+        &mut self,
+        board: &'static Board,
+        override_druation_force: bool,
+    ) -> Option<Player> {
+        if self.is_empty() || (Instant::now() < self.head_since + self.timeout) {
+            return None;
+        }
+
+        let held = self.pop()?;
+
+        defmt::warn!("StartDecide - decision timeout");
+        let ret = self.fallback(board, held, override_druation_force).await;
+        self.update_prompt(board).await;
+
+        ret
+    }
+
+    /// Release whole held payments with fallback, when application mode is changed.
+    pub async fn flush(&mut self, board: &'static Board, override_druation_force: bool) {
+        if self.is_empty() {
+            return;
+        }
+
+        while let Some(held) = self.pop() {
+            self.fallback(board, held, override_druation_force).await;
+        }
+        self.update_prompt(board).await;
+    }
+}
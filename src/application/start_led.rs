@@ -0,0 +1,113 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Start button LED indication, only for boards that have start buttons with LED.
+//! The board doesn't know credit count of GAME I/O PCB, thus credit is assumed
+//! from given payment until the player presses start button.
+
+use crate::boards::*;
+use crate::types::input_port::{InputEvent, InputPortKind};
+use crate::types::player::{Player, PLAYERS};
+
+/// Slow blink, credit is available
+const START_LED_ATTRACT_MS: u16 = 500;
+/// Fast blink, payment is held on start-button-decide mode
+const START_LED_PROMPT_MS: u16 = 150;
+
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub enum StartLedState {
+    /// Payment is inhibited for the player
+    Off,
+    /// Waiting for payment, "insert coin"
+    InsertCoin,
+    /// Credit is available, attract the player to press start button
+    Attract,
+    /// Payment is held, the player should press start button to take it
+    Prompt,
+}
+
+pub struct StartLedMachine {
+    /// Credit is given but the player doesn't press start button yet
+    has_credit: [bool; PLAYER_INDEX_MAX],
+    /// Last applied state
+    states: [Option<StartLedState>; PLAYER_INDEX_MAX],
+}
+
+impl StartLedMachine {
+    pub const fn new() -> Self {
+        Self {
+            has_credit: [false; PLAYER_INDEX_MAX],
+            states: [None; PLAYER_INDEX_MAX],
+        }
+    }
+
+    pub fn credit(&mut self, player: Player) {
+        if let Some(x) = player.index().and_then(|idx| self.has_credit.get_mut(idx)) {
+            *x = true;
+        }
+    }
+
+    /// Track credit from bill / coin vend and start button of processed input event.
+    pub fn on_event(&mut self, event: &InputEvent) {
+        let Some(has_credit) = event
+            .port
+            .player()
+            .index()
+            .and_then(|idx| self.has_credit.get_mut(idx))
+        else {
+            return;
+        };
+
+        match event.port.for_player(Player::Player1) {
+            InputPortKind::Vend1P => *has_credit = true,
+            InputPortKind::Start1P => *has_credit = false,
+            _ => {}
+        }
+    }
+
+    /// Reflect states on start button LED, LED is changed only when the state is changed.
+    pub async fn update(
+        &mut self,
+        board: &'static Board,
+        inhibited: [bool; PLAYER_INDEX_MAX],
+        is_prompt: bool,
+        is_free_play: bool,
+    ) {
+        for (idx, player) in PLAYERS.into_iter().enumerate() {
+            let Some(led) = board.correspond_start_led(player) else {
+                continue;
+            };
+
+            let state = if is_prompt {
+                StartLedState::Prompt
+            } else if is_free_play || self.has_credit[idx] {
+                StartLedState::Attract
+            } else if inhibited[idx] {
+                StartLedState::Off
+            } else {
+                StartLedState::InsertCoin
+            };
+
+            if self.states[idx] == Some(state) {
+                continue;
+            }
+            self.states[idx] = Some(state);
+
+            match state {
+                StartLedState::Off => led.set_low().await,
+                StartLedState::InsertCoin => led.set_high().await,
+                StartLedState::Attract => {
+                    led.alt_forever_blink(START_LED_ATTRACT_MS, START_LED_ATTRACT_MS)
+                        .await
+                }
+                StartLedState::Prompt => {
+                    led.alt_forever_blink(START_LED_PROMPT_MS, START_LED_PROMPT_MS)
+                        .await
+                }
+            }
+        }
+    }
+}
@@ -9,26 +9,26 @@
 //! https://github.com/pmnxis/BillMock-HW-RELEASE/blob/master/sch/BillMock-HW-0v3.pdf
 
 use embassy_stm32::exti::{Channel as HwChannel, ExtiInput};
-use embassy_stm32::gpio::{Input, Level, Output, Pin, Pull, Speed};
+#[cfg(feature = "fw_update")]
+use embassy_stm32::flash::Flash;
+use embassy_stm32::gpio::{Flex, Input, Level, Pin, Pull};
 use embassy_stm32::usart::{Config as UsartConfig, Uart};
-use embassy_stm32::{bind_interrupts, peripherals};
 use {defmt_rtt as _, panic_probe as _};
 
-use super::{Hardware, SharedResource};
+use super::{Hardware, Irqs, SharedResource};
 use super::{PLAYER_1_INDEX, PLAYER_2_INDEX};
 use crate::components;
 use crate::components::dip_switch::DipSwitch;
+#[cfg(feature = "fw_update")]
+use crate::components::fw_update::FwUpdate;
 use crate::components::host_side_bill::HostSideBill;
 use crate::components::serial_device::CardReaderDevice;
 use crate::components::vend_side_bill::VendSideBill;
-use crate::semi_layer::buffered_opendrain::BufferedOpenDrain;
+use crate::components::watchdog::Watchdog;
+use crate::semi_layer::buffered_opendrain::{output_pin, BufferedOpenDrain};
 use crate::types::buffered_opendrain_kind::BufferedOpenDrainKind;
 use crate::types::player::Player;
 
-bind_interrupts!(struct Irqs {
-    USART2 => embassy_stm32::usart::InterruptHandler<peripherals::USART2>;
-});
-
 static mut USART2_RX_BUF: [u8; components::serial_device::CARD_READER_RX_BUFFER_SIZE] =
     [0u8; components::serial_device::CARD_READER_RX_BUFFER_SIZE];
 
@@ -66,7 +66,7 @@ pub fn hardware_init_0v3(
         vend_sides: [
             VendSideBill::new(
                 Player::Player1,
-                Output::new(p.PA1.degrade(), Level::Low, Speed::Low), // REAL0_INH
+                output_pin(p.PA1.degrade(), Level::Low), // REAL0_INH
                 ExtiInput::new(
                     Input::new(p.PB2, Pull::None).degrade(), // REAL0_VND
                     p.EXTI2.degrade(),                       // EXTI2
@@ -80,7 +80,7 @@ pub fn hardware_init_0v3(
             ),
             VendSideBill::new(
                 Player::Player2,
-                Output::new(p.PA0.degrade(), Level::Low, Speed::Low), // REAL1_INH
+                output_pin(p.PA0.degrade(), Level::Low), // REAL1_INH
                 ExtiInput::new(
                     Input::new(p.PB11, Pull::None).degrade(), // REAL1_VND
                     p.EXTI11.degrade(),                       // EXTI11 (HW 0.2 was EXTI1 with PD1)
@@ -96,39 +96,33 @@ pub fn hardware_init_0v3(
         host_sides: [
             HostSideBill::new(
                 Player::Player1,
-                ExtiInput::new(
-                    Input::new(p.PD0, Pull::None).degrade(), // VIRT0_INH
-                    p.EXTI0.degrade(),                       // EXTI0
-                ),
-                Output::new(p.PD3.degrade(), Level::Low, Speed::Low), // VIRT0_BSY
-                Output::new(p.PD2.degrade(), Level::Low, Speed::Low), // VIRT0_VND
-                Output::new(p.PB9.degrade(), Level::Low, Speed::Low), // VIRT0_JAM
-                Output::new(p.PB3.degrade(), Level::Low, Speed::Low), // VIRT0_STR
+                Flex::new(p.PD0.degrade()), // VIRT0_INH, sampled with pull toggling
+                output_pin(p.PD3.degrade(), Level::Low), // VIRT0_BSY
+                output_pin(p.PD2.degrade(), Level::Low), // VIRT0_VND
+                output_pin(p.PB9.degrade(), Level::Low), // VIRT0_JAM
+                output_pin(p.PB3.degrade(), Level::Low), // VIRT0_STR
                 async_input_event_ch,
                 &shared_resource.arcade_players_timing[PLAYER_1_INDEX],
             ),
             HostSideBill::new(
                 Player::Player2,
-                ExtiInput::new(
-                    Input::new(p.PA15, Pull::None).degrade(), // VIRT1_INH
-                    p.EXTI15.degrade(),                       // EXTI15
-                ),
-                Output::new(p.PB4.degrade(), Level::Low, Speed::Low), // VIRT1_BSY
-                Output::new(p.PC13.degrade(), Level::Low, Speed::Low), // VIRT1_VND
-                Output::new(p.PB8.degrade(), Level::Low, Speed::Low), // VIRT1_JAM
-                Output::new(p.PB5.degrade(), Level::Low, Speed::Low), // VIRT1_STR
+                Flex::new(p.PA15.degrade()), // VIRT1_INH, sampled with pull toggling
+                output_pin(p.PB4.degrade(), Level::Low), // VIRT1_BSY
+                output_pin(p.PC13.degrade(), Level::Low), // VIRT1_VND
+                output_pin(p.PB8.degrade(), Level::Low), // VIRT1_JAM
+                output_pin(p.PB5.degrade(), Level::Low), // VIRT1_STR
                 async_input_event_ch,
                 &shared_resource.arcade_players_timing[PLAYER_2_INDEX],
             ),
         ],
         indicators: [
             BufferedOpenDrain::new(
-                Output::new(p.PA4.degrade(), Level::High, Speed::Low),
+                output_pin(p.PA4.degrade(), Level::High),
                 &shared_resource.indicator_timing,
                 BufferedOpenDrainKind::Indicator(1).const_str(),
             ),
             BufferedOpenDrain::new(
-                Output::new(p.PA5.degrade(), Level::High, Speed::Low),
+                output_pin(p.PA5.degrade(), Level::High),
                 &shared_resource.indicator_timing,
                 BufferedOpenDrainKind::Indicator(2).const_str(),
             ),
@@ -142,5 +136,8 @@ pub fn hardware_init_0v3(
             Input::new(p.PB12.degrade(), Pull::Up), // DIPSW5
         ),
         card_reader: CardReaderDevice::new(usart2_tx, usart2_rx),
+        watchdog: Watchdog::new(p.IWDG),
+        #[cfg(feature = "fw_update")]
+        fw_update: FwUpdate::new(Flash::new_blocking(p.FLASH)),
     }
 }
@@ -10,31 +10,31 @@
 
 use embassy_stm32::crc::{Config as CrcConfig, Crc, InputReverseConfig};
 use embassy_stm32::exti::{Channel as HwChannel, ExtiInput};
-use embassy_stm32::gpio::{Input, Level, Output, Pin, Pull, Speed};
+#[cfg(feature = "fw_update")]
+use embassy_stm32::flash::Flash;
+use embassy_stm32::gpio::{Input, Level, Pin, Pull, Speed};
 use embassy_stm32::i2c::I2c;
 use embassy_stm32::time::Hertz;
 use embassy_stm32::usart::{Config as UsartConfig, Uart};
-use embassy_stm32::{bind_interrupts, peripherals};
 use embassy_time::Duration;
+use novella_layout::SLOT_CRC_INIT;
 use {defmt_rtt as _, panic_probe as _};
 
-use super::{Hardware, SharedResource};
+use super::{Hardware, Irqs, SharedResource};
 use super::{PLAYER_1_INDEX, PLAYER_2_INDEX};
 use crate::components;
 use crate::components::dip_switch::DipSwitch;
 use crate::components::eeprom::Novella;
+#[cfg(feature = "fw_update")]
+use crate::components::fw_update::FwUpdate;
 use crate::components::host_side_bill::HostSideBill;
 use crate::components::serial_device::CardReaderDevice;
 use crate::components::vend_side_bill::VendSideBill;
-use crate::semi_layer::buffered_opendrain::BufferedOpenDrain;
+use crate::components::watchdog::Watchdog;
+use crate::semi_layer::buffered_opendrain::{output_pin, BufferedOpenDrain};
 use crate::types::buffered_opendrain_kind::BufferedOpenDrainKind;
 use crate::types::player::Player;
 
-bind_interrupts!(struct Irqs {
-    USART2 => embassy_stm32::usart::InterruptHandler<peripherals::USART2>;
-    I2C1 => embassy_stm32::i2c::EventInterruptHandler<peripherals::I2C1>, embassy_stm32::i2c::ErrorInterruptHandler<peripherals::I2C1>;
-});
-
 static mut USART2_RX_BUF: [u8; components::serial_device::CARD_READER_RX_BUFFER_SIZE] =
     [0u8; components::serial_device::CARD_READER_RX_BUFFER_SIZE];
 
@@ -89,7 +89,7 @@ pub fn hardware_init_mini_0v4(
     );
 
     // InputReverseConfig::Halfword
-    let Ok(crc_config) = CrcConfig::new(InputReverseConfig::Word, false, 0xA097) else {
+    let Ok(crc_config) = CrcConfig::new(InputReverseConfig::Word, false, SLOT_CRC_INIT) else {
         panic!("Something went horribly wrong")
     };
 
@@ -101,7 +101,7 @@ pub fn hardware_init_mini_0v4(
         vend_sides: [
             VendSideBill::new(
                 Player::Player1,
-                Output::new(p.PA1.degrade(), Level::Low, Speed::Low), // REAL0_INH
+                output_pin(p.PA1.degrade(), Level::Low), // REAL0_INH
                 ExtiInput::new(
                     Input::new(p.PB14, Pull::None).degrade(), // REAL0_VND
                     p.EXTI14.degrade(),                       // EXTI14
@@ -115,7 +115,7 @@ pub fn hardware_init_mini_0v4(
             ),
             VendSideBill::new(
                 Player::Player2,
-                Output::new(p.PA0.degrade(), Level::Low, Speed::Low), // REAL1_INH
+                output_pin(p.PA0.degrade(), Level::Low), // REAL1_INH
                 ExtiInput::new(
                     Input::new(p.PD1, Pull::None).degrade(), // REAL1_VND
                     p.EXTI1.degrade(),                       // EXTI1
@@ -135,10 +135,10 @@ pub fn hardware_init_mini_0v4(
                     Input::new(p.PD0, Pull::None).degrade(), // VIRT0_INH
                     p.EXTI0.degrade(),                       // EXTI0
                 ),
-                Output::new(p.PD3.degrade(), Level::Low, Speed::Low), // VIRT0_BSY
-                Output::new(p.PD2.degrade(), Level::Low, Speed::Low), // VIRT0_VND
-                Output::new(p.PB4.degrade(), Level::Low, Speed::Low), // VIRT0_JAM
-                Output::new(p.PB3.degrade(), Level::Low, Speed::Low), // VIRT0_STR
+                output_pin(p.PD3.degrade(), Level::Low), // VIRT0_BSY
+                output_pin(p.PD2.degrade(), Level::Low), // VIRT0_VND
+                output_pin(p.PB4.degrade(), Level::Low), // VIRT0_JAM
+                output_pin(p.PB3.degrade(), Level::Low), // VIRT0_STR
                 async_input_event_ch,
                 &shared_resource.arcade_players_timing[PLAYER_1_INDEX],
             ),
@@ -148,22 +148,22 @@ pub fn hardware_init_mini_0v4(
                     Input::new(p.PA15, Pull::None).degrade(), // VIRT1_INH
                     p.EXTI15.degrade(),                       // EXTI15
                 ),
-                Output::new(p.PC14.degrade(), Level::Low, Speed::Low), // VIRT1_BSY
-                Output::new(p.PC13.degrade(), Level::Low, Speed::Low), // VIRT1_VND
-                Output::new(p.PB5.degrade(), Level::Low, Speed::Low),  // VIRT1_JAM
-                Output::new(p.PC15.degrade(), Level::Low, Speed::Low), // VIRT1_STR
+                output_pin(p.PC14.degrade(), Level::Low), // VIRT1_BSY
+                output_pin(p.PC13.degrade(), Level::Low), // VIRT1_VND
+                output_pin(p.PB5.degrade(), Level::Low),  // VIRT1_JAM
+                output_pin(p.PC15.degrade(), Level::Low), // VIRT1_STR
                 async_input_event_ch,
                 &shared_resource.arcade_players_timing[PLAYER_2_INDEX],
             ),
         ],
         indicators: [
             BufferedOpenDrain::new(
-                Output::new(p.PA5.degrade(), Level::High, Speed::Low),
+                output_pin(p.PA5.degrade(), Level::High),
                 &shared_resource.indicator_timing,
                 BufferedOpenDrainKind::Indicator(1).const_str(),
             ),
             BufferedOpenDrain::new(
-                Output::new(p.PA4.degrade(), Level::High, Speed::Low),
+                output_pin(p.PA4.degrade(), Level::High),
                 &shared_resource.indicator_timing,
                 BufferedOpenDrainKind::Indicator(2).const_str(),
             ),
@@ -177,10 +177,15 @@ pub fn hardware_init_mini_0v4(
             Input::new(p.PB12.degrade(), Pull::Up), // DIPSW5
         ),
         card_reader: CardReaderDevice::new(usart2_tx, usart2_rx),
+        watchdog: Watchdog::new(p.IWDG),
+        #[cfg(feature = "fw_update")]
+        fw_update: FwUpdate::new(Flash::new_blocking(p.FLASH)),
         eeprom: Novella::const_new(
             i2c,
             crc,
             embassy_stm32::gpio::OutputOpenDrain::new(p.PF0, Level::Low, Speed::Low, Pull::None),
         ),
+        #[cfg(feature = "svc_button")]
+        svc_button: None, // not mounted, `hw_auto` image
     }
 }
@@ -10,36 +10,57 @@
 
 use embassy_stm32::crc::{Config as CrcConfig, Crc, InputReverseConfig};
 use embassy_stm32::exti::{Channel as HwChannel, ExtiInput};
-use embassy_stm32::gpio::{Input, Level, Output, Pin, Pull, Speed};
+#[cfg(feature = "fw_update")]
+use embassy_stm32::flash::Flash;
+use embassy_stm32::gpio::{Input, Level, Pin, Pull, Speed};
 use embassy_stm32::i2c::I2c;
 use embassy_stm32::time::Hertz;
 use embassy_stm32::usart::{Config as UsartConfig, Uart};
+#[cfg(feature = "board_link")]
 use embassy_stm32::{bind_interrupts, peripherals};
 use embassy_time::Duration;
+use novella_layout::SLOT_CRC_INIT;
 use {defmt_rtt as _, panic_probe as _};
 
-use super::{Hardware, SharedResource};
+use super::{Hardware, Irqs, SharedResource};
 use super::{PLAYER_1_INDEX, PLAYER_2_INDEX};
 use crate::components;
+#[cfg(feature = "board_link")]
+use crate::components::board_link::BoardLink;
 use crate::components::dip_switch::DipSwitch;
+#[cfg(feature = "payout")]
+use crate::components::dispenser::Dispenser;
 use crate::components::eeprom::Novella;
+#[cfg(feature = "fw_update")]
+use crate::components::fw_update::FwUpdate;
 use crate::components::host_side_bill::HostSideBill;
+#[cfg(feature = "mech_meter")]
+use crate::components::mech_meter::MechMeter;
 use crate::components::serial_device::CardReaderDevice;
+#[cfg(feature = "start_button")]
+use crate::components::start_button::StartButton;
 use crate::components::vend_side_bill::VendSideBill;
-use crate::semi_layer::buffered_opendrain::BufferedOpenDrain;
+use crate::components::watchdog::Watchdog;
+use crate::semi_layer::buffered_opendrain::{output_pin, BufferedOpenDrain};
 #[cfg(feature = "svc_button")]
 use crate::semi_layer::buffered_wait::BufferedWait;
 use crate::types::buffered_opendrain_kind::BufferedOpenDrainKind;
+#[cfg(feature = "mech_meter")]
+use crate::types::buffered_opendrain_kind::MeterKind;
 use crate::types::player::Player;
 
-bind_interrupts!(struct Irqs {
-    USART2 => embassy_stm32::usart::InterruptHandler<peripherals::USART2>;
-    I2C1 => embassy_stm32::i2c::EventInterruptHandler<peripherals::I2C1>, embassy_stm32::i2c::ErrorInterruptHandler<peripherals::I2C1>;
-});
-
 static mut USART2_RX_BUF: [u8; components::serial_device::CARD_READER_RX_BUFFER_SIZE] =
     [0u8; components::serial_device::CARD_READER_RX_BUFFER_SIZE];
 
+#[cfg(feature = "board_link")]
+bind_interrupts!(struct LinkIrqs {
+    USART1 => embassy_stm32::usart::InterruptHandler<peripherals::USART1>;
+});
+
+#[cfg(feature = "board_link")]
+static mut USART1_RX_BUF: [u8; components::board_link::BOARD_LINK_RX_BUFFER_SIZE] =
+    [0u8; components::board_link::BOARD_LINK_RX_BUFFER_SIZE];
+
 const WAIT_DURATION_PER_PAGE: Duration = Duration::from_millis(20); // heuristic value
 
 pub fn hardware_init_mini_0v5(
@@ -72,6 +93,25 @@ pub fn hardware_init_mini_0v5(
         (tx, rx.into_ring_buffered(usart2_rx_buf))
     };
 
+    // USART1 initialization for BoardLink, spare pins
+    #[cfg(feature = "board_link")]
+    let (usart1_tx, usart1_rx) = {
+        let usart1_rx_buf = unsafe { &mut *core::ptr::addr_of_mut!(USART1_RX_BUF) };
+
+        let (tx, rx) = Uart::new(
+            p.USART1,
+            p.PB7, // spare, LINK_RX
+            p.PB6, // spare, LINK_TX
+            LinkIrqs,
+            embassy_stm32::dma::NoDma,
+            p.DMA1_CH5,
+            usart2_config,
+        )
+        .unwrap()
+        .split();
+        (tx, rx.into_ring_buffered(usart1_rx_buf))
+    };
+
     let i2c_config = {
         let mut ret: embassy_stm32::i2c::Config = embassy_stm32::i2c::Config::default();
         ret.timeout = WAIT_DURATION_PER_PAGE;
@@ -90,7 +130,7 @@ pub fn hardware_init_mini_0v5(
     );
 
     // InputReverseConfig::Halfword
-    let Ok(crc_config) = CrcConfig::new(InputReverseConfig::Word, false, 0xA097) else {
+    let Ok(crc_config) = CrcConfig::new(InputReverseConfig::Word, false, SLOT_CRC_INIT) else {
         panic!("Something went horribly wrong")
     };
 
@@ -106,7 +146,7 @@ pub fn hardware_init_mini_0v5(
         vend_sides: [
             VendSideBill::new(
                 Player::Player1,
-                Output::new(p.PA1.degrade(), Level::Low, Speed::Low), // REAL0_INH
+                output_pin(p.PA1.degrade(), Level::Low), // REAL0_INH
                 ExtiInput::new(
                     Input::new(p.PB14, Pull::None).degrade(), // REAL0_VND
                     p.EXTI14.degrade(),                       // EXTI14
@@ -120,7 +160,7 @@ pub fn hardware_init_mini_0v5(
             ),
             VendSideBill::new(
                 Player::Player2,
-                Output::new(p.PA0.degrade(), Level::Low, Speed::Low), // REAL1_INH
+                output_pin(p.PA0.degrade(), Level::Low), // REAL1_INH
                 ExtiInput::new(
                     Input::new(p.PD1, Pull::None).degrade(), // REAL1_VND
                     p.EXTI1.degrade(),                       // EXTI1
@@ -140,10 +180,10 @@ pub fn hardware_init_mini_0v5(
                     Input::new(p.PD0, Pull::None).degrade(), // VIRT0_INH
                     p.EXTI0.degrade(),                       // EXTI0
                 ),
-                Output::new(p.PD3.degrade(), Level::Low, Speed::Low), // VIRT0_BSY
-                Output::new(p.PD2.degrade(), Level::Low, Speed::Low), // VIRT0_VND
-                Output::new(p.PB4.degrade(), Level::Low, Speed::Low), // VIRT0_JAM
-                Output::new(p.PB3.degrade(), Level::Low, Speed::Low), // VIRT0_STR
+                output_pin(p.PD3.degrade(), Level::Low), // VIRT0_BSY
+                output_pin(p.PD2.degrade(), Level::Low), // VIRT0_VND
+                output_pin(p.PB4.degrade(), Level::Low), // VIRT0_JAM
+                output_pin(p.PB3.degrade(), Level::Low), // VIRT0_STR
                 async_input_event_ch,
                 &shared_resource.arcade_players_timing[PLAYER_1_INDEX],
             ),
@@ -153,22 +193,22 @@ pub fn hardware_init_mini_0v5(
                     Input::new(p.PA15, Pull::None).degrade(), // VIRT1_INH
                     p.EXTI15.degrade(),                       // EXTI15
                 ),
-                Output::new(p.PC14.degrade(), Level::Low, Speed::Low), // VIRT1_BSY
-                Output::new(p.PC13.degrade(), Level::Low, Speed::Low), // VIRT1_VND
-                Output::new(p.PB5.degrade(), Level::Low, Speed::Low),  // VIRT1_JAM
-                Output::new(p.PC15.degrade(), Level::Low, Speed::Low), // VIRT1_STR
+                output_pin(p.PC14.degrade(), Level::Low), // VIRT1_BSY
+                output_pin(p.PC13.degrade(), Level::Low), // VIRT1_VND
+                output_pin(p.PB5.degrade(), Level::Low),  // VIRT1_JAM
+                output_pin(p.PC15.degrade(), Level::Low), // VIRT1_STR
                 async_input_event_ch,
                 &shared_resource.arcade_players_timing[PLAYER_2_INDEX],
             ),
         ],
         indicators: [
             BufferedOpenDrain::new(
-                Output::new(p.PA5.degrade(), Level::High, Speed::Low),
+                output_pin(p.PA5.degrade(), Level::High),
                 &shared_resource.indicator_timing,
                 BufferedOpenDrainKind::Indicator(1).const_str(),
             ),
             BufferedOpenDrain::new(
-                Output::new(p.PA4.degrade(), Level::High, Speed::Low),
+                output_pin(p.PA4.degrade(), Level::High),
                 &shared_resource.indicator_timing,
                 BufferedOpenDrainKind::Indicator(2).const_str(),
             ),
@@ -182,13 +222,16 @@ pub fn hardware_init_mini_0v5(
             Input::new(p.PB12.degrade(), Pull::Up), // DIPSW5
         ),
         card_reader: CardReaderDevice::new(usart2_tx, usart2_rx),
+        watchdog: Watchdog::new(p.IWDG),
+        #[cfg(feature = "fw_update")]
+        fw_update: FwUpdate::new(Flash::new_blocking(p.FLASH)),
         eeprom: Novella::const_new(
             i2c,
             crc,
             embassy_stm32::gpio::OutputOpenDrain::new(p.PF0, Level::Low, Speed::Low, Pull::None),
         ),
         #[cfg(feature = "svc_button")]
-        svc_button: BufferedWait::new(
+        svc_button: Some(BufferedWait::new(
             ExtiInput::new(
                 Input::new(p.PA8, Pull::Up).degrade(), // SW_SERVICE, need pull-up
                 p.EXTI8.degrade(),                     // EXTI8
@@ -196,6 +239,54 @@ pub fn hardware_init_mini_0v5(
             async_input_event_ch,
             svc_p,
             svc_str,
+        )),
+        #[cfg(feature = "mech_meter")]
+        meters: [
+            MechMeter::new(
+                MeterKind::Card,
+                output_pin(p.PB0.degrade(), Level::Low), // spare, METER_CARD
+                &shared_resource.meter_timing,
+            ),
+            MechMeter::new(
+                MeterKind::Coin,
+                output_pin(p.PB1.degrade(), Level::Low), // spare, METER_COIN
+                &shared_resource.meter_timing,
+            ),
+        ],
+        #[cfg(feature = "payout")]
+        dispenser: Dispenser::new(
+            output_pin(p.PB15.degrade(), Level::Low), // spare, PAYOUT_MOTOR
+            ExtiInput::new(
+                Input::new(p.PA10, Pull::Up).degrade(), // spare, PAYOUT_SENSE, open-collector
+                p.EXTI10.degrade(),                     // EXTI10
+            ),
+            async_input_event_ch,
+            &shared_resource.indicator_timing,
         ),
+        #[cfg(feature = "start_button")]
+        start_buttons: [
+            StartButton::new(
+                Player::Player1,
+                ExtiInput::new(
+                    Input::new(p.PA6, Pull::Up).degrade(), // spare, START0_SW, need pull-up
+                    p.EXTI6.degrade(),                     // EXTI6
+                ),
+                output_pin(p.PB10.degrade(), Level::Low), // spare, START0_LED
+                async_input_event_ch,
+                &shared_resource.indicator_timing,
+            ),
+            StartButton::new(
+                Player::Player2,
+                ExtiInput::new(
+                    Input::new(p.PA7, Pull::Up).degrade(), // spare, START1_SW, need pull-up
+                    p.EXTI7.degrade(),                     // EXTI7
+                ),
+                output_pin(p.PC7.degrade(), Level::Low), // spare, START1_LED
+                async_input_event_ch,
+                &shared_resource.indicator_timing,
+            ),
+        ],
+        #[cfg(feature = "board_link")]
+        board_link: BoardLink::new(usart1_tx, usart1_rx),
     }
 }
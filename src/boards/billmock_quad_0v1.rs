@@ -0,0 +1,251 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Hardware initialization code for BillMock Hardware Version quad 0.1
+//! Four player channels on STM32G070RB (LQFP64), single card terminal sells credits to four players.
+//! Player 1, 2 and common peripherals are on same pins with mini 0.5,
+//! player 3 and 4 are on pins that only exist on LQFP64.
+//! EXTI line of every input is unique, EXTI6, EXTI7 and EXTI13 are left.
+
+use embassy_stm32::crc::{Config as CrcConfig, Crc, InputReverseConfig};
+use embassy_stm32::exti::{Channel as HwChannel, ExtiInput};
+use embassy_stm32::gpio::{Input, Level, Pin, Pull, Speed};
+use embassy_stm32::i2c::I2c;
+use embassy_stm32::time::Hertz;
+use embassy_stm32::usart::{Config as UsartConfig, Uart};
+use embassy_time::Duration;
+use novella_layout::SLOT_CRC_INIT;
+use {defmt_rtt as _, panic_probe as _};
+
+use super::{Hardware, Irqs, SharedResource};
+use super::{PLAYER_1_INDEX, PLAYER_2_INDEX, PLAYER_3_INDEX, PLAYER_4_INDEX};
+use crate::components;
+use crate::components::dip_switch::DipSwitch;
+use crate::components::eeprom::Novella;
+use crate::components::host_side_bill::HostSideBill;
+use crate::components::serial_device::CardReaderDevice;
+use crate::components::vend_side_bill::VendSideBill;
+use crate::components::watchdog::Watchdog;
+use crate::semi_layer::buffered_opendrain::{output_pin, BufferedOpenDrain};
+use crate::semi_layer::buffered_wait::BufferedWait;
+use crate::types::buffered_opendrain_kind::BufferedOpenDrainKind;
+use crate::types::player::Player;
+
+static mut USART2_RX_BUF: [u8; components::serial_device::CARD_READER_RX_BUFFER_SIZE] =
+    [0u8; components::serial_device::CARD_READER_RX_BUFFER_SIZE];
+
+const WAIT_DURATION_PER_PAGE: Duration = Duration::from_millis(20); // heuristic value
+
+pub fn hardware_init_quad_0v1(
+    p: embassy_stm32::Peripherals,
+    shared_resource: &'static SharedResource,
+) -> Hardware {
+    // USART2 initialization for CardReaderDevice
+    let usart2_rx_buf = unsafe { &mut *core::ptr::addr_of_mut!(USART2_RX_BUF) };
+
+    let usart2_config = {
+        let mut ret: UsartConfig = UsartConfig::default();
+        ret.baudrate = 115200;
+        ret.assume_noise_free = false;
+        ret.detect_previous_overrun = true;
+        ret
+    };
+
+    let (usart2_tx, usart2_rx) = {
+        let (tx, rx) = Uart::new(
+            p.USART2,
+            p.PA3,
+            p.PA2,
+            Irqs,
+            p.DMA1_CH2,
+            p.DMA1_CH1,
+            usart2_config,
+        )
+        .unwrap()
+        .split();
+        (tx, rx.into_ring_buffered(usart2_rx_buf))
+    };
+
+    let i2c_config = {
+        let mut ret: embassy_stm32::i2c::Config = embassy_stm32::i2c::Config::default();
+        ret.timeout = WAIT_DURATION_PER_PAGE;
+        ret
+    };
+
+    let i2c = I2c::new(
+        p.I2C1,
+        p.PB8,
+        p.PB9,
+        Irqs,
+        p.DMA1_CH4,
+        p.DMA1_CH3,
+        Hertz(400_000),
+        i2c_config,
+    );
+
+    // InputReverseConfig::Halfword
+    let Ok(crc_config) = CrcConfig::new(InputReverseConfig::Word, false, SLOT_CRC_INIT) else {
+        panic!("Something went horribly wrong")
+    };
+
+    let crc = Crc::new(p.CRC, crc_config);
+
+    let async_input_event_ch = &shared_resource.async_input_event_ch.channel;
+
+    let (svc_p, svc_str) =
+        crate::types::input_port::InputPortKind::SvcButton.to_raw_and_const_str(Player::Undefined);
+
+    Hardware {
+        vend_sides: [
+            VendSideBill::new(
+                Player::Player1,
+                output_pin(p.PA1.degrade(), Level::Low), // REAL0_INH
+                ExtiInput::new(
+                    Input::new(p.PB14, Pull::None).degrade(), // REAL0_VND
+                    p.EXTI14.degrade(),                       // EXTI14
+                ),
+                ExtiInput::new(
+                    Input::new(p.PB2, Pull::None).degrade(), // REAL0_STR
+                    p.EXTI2.degrade(),                       // EXTI2
+                ),
+                async_input_event_ch,
+                &shared_resource.arcade_players_timing[PLAYER_1_INDEX],
+            ),
+            VendSideBill::new(
+                Player::Player2,
+                output_pin(p.PA0.degrade(), Level::Low), // REAL1_INH
+                ExtiInput::new(
+                    Input::new(p.PD1, Pull::None).degrade(), // REAL1_VND
+                    p.EXTI1.degrade(),                       // EXTI1
+                ),
+                ExtiInput::new(
+                    Input::new(p.PB11, Pull::None).degrade(), // REAL1_STR
+                    p.EXTI11.degrade(),                       // EXTI11
+                ),
+                async_input_event_ch,
+                &shared_resource.arcade_players_timing[PLAYER_2_INDEX],
+            ),
+            VendSideBill::new(
+                Player::Player3,
+                output_pin(p.PC0.degrade(), Level::Low), // REAL2_INH
+                ExtiInput::new(
+                    Input::new(p.PC3, Pull::None).degrade(), // REAL2_VND
+                    p.EXTI3.degrade(),                       // EXTI3
+                ),
+                ExtiInput::new(
+                    Input::new(p.PC4, Pull::None).degrade(), // REAL2_STR
+                    p.EXTI4.degrade(),                       // EXTI4
+                ),
+                async_input_event_ch,
+                &shared_resource.arcade_players_timing[PLAYER_3_INDEX],
+            ),
+            VendSideBill::new(
+                Player::Player4,
+                output_pin(p.PB0.degrade(), Level::Low), // REAL3_INH
+                ExtiInput::new(
+                    Input::new(p.PC5, Pull::None).degrade(), // REAL3_VND
+                    p.EXTI5.degrade(),                       // EXTI5
+                ),
+                ExtiInput::new(
+                    Input::new(p.PC9, Pull::None).degrade(), // REAL3_STR
+                    p.EXTI9.degrade(),                       // EXTI9
+                ),
+                async_input_event_ch,
+                &shared_resource.arcade_players_timing[PLAYER_4_INDEX],
+            ),
+        ],
+        host_sides: [
+            HostSideBill::new(
+                Player::Player1,
+                ExtiInput::new(
+                    Input::new(p.PD0, Pull::None).degrade(), // VIRT0_INH
+                    p.EXTI0.degrade(),                       // EXTI0
+                ),
+                output_pin(p.PD3.degrade(), Level::Low), // VIRT0_BSY
+                output_pin(p.PD2.degrade(), Level::Low), // VIRT0_VND
+                output_pin(p.PB4.degrade(), Level::Low), // VIRT0_JAM
+                output_pin(p.PB3.degrade(), Level::Low), // VIRT0_STR
+                async_input_event_ch,
+                &shared_resource.arcade_players_timing[PLAYER_1_INDEX],
+            ),
+            HostSideBill::new(
+                Player::Player2,
+                ExtiInput::new(
+                    Input::new(p.PA15, Pull::None).degrade(), // VIRT1_INH
+                    p.EXTI15.degrade(),                       // EXTI15
+                ),
+                output_pin(p.PC14.degrade(), Level::Low), // VIRT1_BSY
+                output_pin(p.PC13.degrade(), Level::Low), // VIRT1_VND
+                output_pin(p.PB5.degrade(), Level::Low),  // VIRT1_JAM
+                output_pin(p.PC15.degrade(), Level::Low), // VIRT1_STR
+                async_input_event_ch,
+                &shared_resource.arcade_players_timing[PLAYER_2_INDEX],
+            ),
+            HostSideBill::new(
+                Player::Player3,
+                ExtiInput::new(
+                    Input::new(p.PC12, Pull::None).degrade(), // VIRT2_INH
+                    p.EXTI12.degrade(),                       // EXTI12
+                ),
+                output_pin(p.PC1.degrade(), Level::Low), // VIRT2_BSY
+                output_pin(p.PC2.degrade(), Level::Low), // VIRT2_VND
+                output_pin(p.PC7.degrade(), Level::Low), // VIRT2_JAM
+                output_pin(p.PC8.degrade(), Level::Low), // VIRT2_STR
+                async_input_event_ch,
+                &shared_resource.arcade_players_timing[PLAYER_3_INDEX],
+            ),
+            HostSideBill::new(
+                Player::Player4,
+                ExtiInput::new(
+                    Input::new(p.PC10, Pull::None).degrade(), // VIRT3_INH
+                    p.EXTI10.degrade(),                       // EXTI10
+                ),
+                output_pin(p.PB1.degrade(), Level::Low), // VIRT3_BSY
+                output_pin(p.PB10.degrade(), Level::Low), // VIRT3_VND
+                output_pin(p.PD4.degrade(), Level::Low), // VIRT3_JAM
+                output_pin(p.PD5.degrade(), Level::Low), // VIRT3_STR
+                async_input_event_ch,
+                &shared_resource.arcade_players_timing[PLAYER_4_INDEX],
+            ),
+        ],
+        indicators: [
+            BufferedOpenDrain::new(
+                output_pin(p.PA5.degrade(), Level::High),
+                &shared_resource.indicator_timing,
+                BufferedOpenDrainKind::Indicator(1).const_str(),
+            ),
+            BufferedOpenDrain::new(
+                output_pin(p.PA4.degrade(), Level::High),
+                &shared_resource.indicator_timing,
+                BufferedOpenDrainKind::Indicator(2).const_str(),
+            ),
+        ],
+        dipsw: DipSwitch::new(
+            Input::new(p.PC6.degrade(), Pull::Up),  // DIPSW0
+            Input::new(p.PA12.degrade(), Pull::Up), // DIPSW1
+            Input::new(p.PA11.degrade(), Pull::Up), // DIPSW2
+            Input::new(p.PA9.degrade(), Pull::Up),  // DIPSW3
+            Input::new(p.PB13.degrade(), Pull::Up), // DIPSW4
+            Input::new(p.PB12.degrade(), Pull::Up), // DIPSW5
+        ),
+        card_reader: CardReaderDevice::new(usart2_tx, usart2_rx),
+        watchdog: Watchdog::new(p.IWDG),
+        eeprom: Novella::const_new(
+            i2c,
+            crc,
+            embassy_stm32::gpio::OutputOpenDrain::new(p.PF0, Level::Low, Speed::Low, Pull::None),
+        ),
+        svc_button: Some(BufferedWait::new(
+            ExtiInput::new(
+                Input::new(p.PA8, Pull::Up).degrade(), // SW_SERVICE, need pull-up
+                p.EXTI8.degrade(),                     // EXTI8
+            ),
+            async_input_event_ch,
+            svc_p,
+            svc_str,
+        )),
+    }
+}
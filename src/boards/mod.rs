@@ -4,60 +4,139 @@
  * SPDX-License-Identifier: MIT OR Apache-2.0
  */
 
+use billmock_plug_card::KiccEd785Plug;
+use card_terminal_adapter::text_page::{TextLine, TextPage};
+use card_terminal_adapter::CardTerminalTxGen;
 use defmt::*;
 use embassy_executor::Spawner;
+use embassy_stm32::dma::NoDma;
+use embassy_stm32::usart::{Config as UsartConfig, UartTx};
 use embassy_stm32::Config as Stm32Config;
+use embassy_stm32::{bind_interrupts, peripherals};
+use embassy_time::Duration;
+use static_assertions::const_assert;
 use static_cell::make_static;
 
 #[cfg(feature = "hw_0v2")]
 use self::billmock_0v2::hardware_init_0v2;
 #[cfg(feature = "hw_0v3")]
 use self::billmock_0v3::hardware_init_0v3;
-#[cfg(feature = "hw_0v4")]
+#[cfg(any(feature = "hw_0v4", feature = "hw_auto"))]
 use self::billmock_0v4::hardware_init_0v4;
-#[cfg(feature = "hw_mini_0v4")]
+#[cfg(any(feature = "hw_mini_0v4", feature = "hw_auto"))]
 use self::billmock_mini_0v4::hardware_init_mini_0v4;
-#[cfg(feature = "hw_mini_0v5")]
+#[cfg(any(feature = "hw_mini_0v5", feature = "hw_auto"))]
 use self::billmock_mini_0v5::hardware_init_mini_0v5;
+#[cfg(feature = "hw_quad_0v1")]
+use self::billmock_quad_0v1::hardware_init_quad_0v1;
+#[cfg(feature = "board_link")]
+use crate::components::board_link::board_link_spawn;
+use crate::components::board_link::BoardLink;
 use crate::components::dip_switch::DipSwitch;
+#[cfg(feature = "payout")]
+use crate::components::dispenser::{dispenser_spawn, Dispenser};
 use crate::components::eeprom::novella_spawn;
 use crate::components::eeprom::Novella;
+use crate::components::fw_update::FwUpdate;
 use crate::components::host_side_bill::HostSideBill;
+use crate::components::mech_meter::MechMeter;
+#[cfg(feature = "mech_meter")]
+use crate::components::mech_meter::{mech_meter_spawn, METER_DEFAULT_TIMING};
 use crate::components::serial_device::{self, card_reader_device_spawn, CardReaderDevice};
+#[cfg(feature = "start_button")]
+use crate::components::start_button::{start_button_spawn, StartButton};
 use crate::components::vend_side_bill::VendSideBill;
+use crate::components::watchdog::{watchdog_spawn, Watchdog};
 use crate::semi_layer::buffered_opendrain::{buffered_opendrain_spawn, BufferedOpenDrain};
 #[cfg(feature = "svc_button")]
 use crate::semi_layer::buffered_wait::{buffered_wait_spawn, BufferedWait};
 use crate::semi_layer::buffered_wait_receiver::BufferedWaitReceiver;
 use crate::semi_layer::timing::{SharedToggleTiming, ToggleTiming};
+use crate::types::buffered_opendrain_kind::MeterKind;
+use crate::types::hw_revision::HwRevision;
 use crate::types::input_port::InputPortKind;
-
-pub const PLAYER_INDEX_MAX: usize = 2;
+use crate::types::install_config::{
+    CabinetProfile, RawIoRoute, IO_ROUTE_FLAG_MIRROR, IO_ROUTE_NUM,
+};
+use crate::types::player::{Player, PLAYERS};
+use crate::types::port_polarity::PortPolarity;
+
+#[cfg(all(feature = "mech_meter", not(feature = "hw_mini_0v5")))]
+compile_error!("`mech_meter` feature requires spare pins, only `hw_mini_0v5` has them.");
+
+#[cfg(all(feature = "payout", not(feature = "hw_mini_0v5")))]
+compile_error!("`payout` feature requires spare pins, only `hw_mini_0v5` has them.");
+
+#[cfg(all(feature = "start_button", not(feature = "hw_mini_0v5")))]
+compile_error!("`start_button` feature requires spare pins, only `hw_mini_0v5` has them.");
+
+#[cfg(all(
+    feature = "hotfix_hwbug_host_inhibit_floating",
+    not(any(feature = "hw_0v2", feature = "hw_0v3"))
+))]
+compile_error!("`hotfix_hwbug_host_inhibit_floating` feature is only for `hw_0v2` and `hw_0v3`.");
+
+#[cfg(all(feature = "board_link", not(feature = "hw_mini_0v5")))]
+compile_error!("`board_link` feature requires spare USART1 pins, only `hw_mini_0v5` has them.");
+
+#[cfg(all(feature = "fw_update", feature = "hw_quad_0v1"))]
+compile_error!(
+    "`fw_update` bootloader is built for STM32G030C8, `hw_quad_0v1` is not supported yet."
+);
+
+// Hardware shape features should follow the revision
+const_assert!(!HwRevision::TARGET.has_eeprom() || cfg!(feature = "eeprom"));
+const_assert!(!HwRevision::TARGET.has_svc_button() || cfg!(feature = "svc_button"));
+const_assert!(
+    !HwRevision::TARGET.has_inhibit_floating_bug()
+        || cfg!(feature = "hotfix_hwbug_host_inhibit_floating")
+);
+
+// Interrupts are common on every revision, board modules shouldn't bind them again.
+bind_interrupts!(pub(crate) struct Irqs {
+    USART2 => embassy_stm32::usart::InterruptHandler<peripherals::USART2>;
+    I2C1 => embassy_stm32::i2c::EventInterruptHandler<peripherals::I2C1>, embassy_stm32::i2c::ErrorInterruptHandler<peripherals::I2C1>;
+});
+
+pub const PLAYER_INDEX_MAX: usize = PLAYERS.len();
 pub const PLAYER_1_INDEX: usize = 0;
 pub const PLAYER_2_INDEX: usize = 1;
+#[cfg(feature = "hw_quad_0v1")]
+pub const PLAYER_3_INDEX: usize = 2;
+#[cfg(feature = "hw_quad_0v1")]
+pub const PLAYER_4_INDEX: usize = 3;
 
 pub const LED_INDEX_MAX: usize = 2;
 pub const LED_1_INDEX: usize = 0;
 pub const LED_2_INDEX: usize = 1;
 
+#[cfg(feature = "mech_meter")]
+pub const METER_INDEX_MAX: usize = 2;
+#[cfg(feature = "mech_meter")]
+pub const METER_CARD_INDEX: usize = 0;
+#[cfg(feature = "mech_meter")]
+pub const METER_COIN_INDEX: usize = 1;
+
 pub mod const_str;
 
 #[cfg(feature = "hw_0v2")]
 mod billmock_0v2;
 #[cfg(feature = "hw_0v3")]
 mod billmock_0v3;
-#[cfg(feature = "hw_0v4")]
+#[cfg(any(feature = "hw_0v4", feature = "hw_auto"))]
 mod billmock_0v4;
-#[cfg(feature = "hw_mini_0v4")]
+#[cfg(any(feature = "hw_mini_0v4", feature = "hw_auto"))]
 mod billmock_mini_0v4;
-#[cfg(feature = "hw_mini_0v5")]
+#[cfg(any(feature = "hw_mini_0v5", feature = "hw_auto"))]
 mod billmock_mini_0v5;
+#[cfg(feature = "hw_quad_0v1")]
+mod billmock_quad_0v1;
 
 pub struct Hardware {
-    /// Bill paper and coin acceptor input device for 1 and 2 player sides
+    /// Bill paper and coin acceptor input device for each player side
     pub vend_sides: [VendSideBill; PLAYER_INDEX_MAX],
 
-    /// GAME I/O PCB for 1 and 2 player sides
+    /// GAME I/O PCB for each player side
     pub host_sides: [HostSideBill; PLAYER_INDEX_MAX],
 
     /// Two indicators with green light
@@ -72,9 +151,32 @@ pub struct Hardware {
     /// Eeprom manager, powered by Novella
     pub eeprom: Novella,
 
+    /// Independent watchdog, supervises heartbeat of tasks
+    pub watchdog: Watchdog,
+
     #[cfg(feature = "svc_button")]
-    /// SVC button (tactile switch) for engineer or foreman
-    pub svc_button: BufferedWait,
+    /// SVC button (tactile switch) for engineer or foreman, `None` on revision without it
+    pub svc_button: Option<BufferedWait>,
+
+    #[cfg(feature = "mech_meter")]
+    /// Mechanical meters (counter coil) for card and coin income
+    pub meters: [MechMeter; METER_INDEX_MAX],
+
+    #[cfg(feature = "payout")]
+    /// Hopper or ticket dispenser for redemption payout
+    pub dispenser: Dispenser,
+
+    #[cfg(feature = "start_button")]
+    /// Start buttons with LED for 1 and 2 player sides
+    pub start_buttons: [StartButton; PLAYER_INDEX_MAX],
+
+    #[cfg(feature = "board_link")]
+    /// Link to the other BillMock board, for 3 and 4 player cabinet
+    pub board_link: BoardLink,
+
+    #[cfg(feature = "fw_update")]
+    /// Serial firmware update on card reader UART, session is served by bootloader
+    pub fw_update: FwUpdate,
 }
 
 impl Hardware {
@@ -116,58 +218,26 @@ impl Hardware {
     /// > `Hardware::hardware_init(..)`
     /// > 4 `hardware.start_tasks(..)`
     fn hardware_init(
+        revision: HwRevision,
         peripherals: embassy_stm32::Peripherals,
         shared_resource: &'static SharedResource,
     ) -> Hardware {
-        #[cfg(all(
-            feature = "hw_0v2",
-            not(feature = "hw_0v3"),
-            not(feature = "hw_0v4"),
-            not(feature = "hw_mini_0v4"),
-            not(feature = "hw_mini_0v5")
-        ))]
-        let ret = hardware_init_0v2(peripherals, shared_resource);
-
-        #[cfg(all(
-            all(
-                feature = "hw_0v3",
-                not(feature = "hw_0v2"),
-                not(feature = "hw_0v4"),
-                not(feature = "hw_mini_0v4"),
-                not(feature = "hw_mini_0v5")
-            ),
-            feature = "billmock_default"
-        ))]
-        let ret = hardware_init_0v3(peripherals, shared_resource);
-
-        #[cfg(all(
-            feature = "hw_0v4",
-            not(feature = "hw_0v2"),
-            not(feature = "hw_0v3"),
-            not(feature = "hw_mini_0v4"),
-            not(feature = "hw_mini_0v5")
-        ))]
-        let ret = hardware_init_0v4(peripherals, shared_resource);
-
-        #[cfg(all(
-            feature = "hw_mini_0v4",
-            not(feature = "hw_0v2"),
-            not(feature = "hw_0v3"),
-            not(feature = "hw_0v4"),
-            not(feature = "hw_mini_0v5")
-        ))]
-        let ret = hardware_init_mini_0v4(peripherals, shared_resource);
-
-        #[cfg(all(
-            feature = "hw_mini_0v5",
-            not(feature = "hw_0v2"),
-            not(feature = "hw_0v3"),
-            not(feature = "hw_0v4"),
-            not(feature = "hw_mini_0v4")
-        ))]
-        let ret = hardware_init_mini_0v5(peripherals, shared_resource);
-
-        ret
+        match revision {
+            #[cfg(feature = "hw_0v2")]
+            HwRevision::V0v2 => hardware_init_0v2(peripherals, shared_resource),
+            #[cfg(feature = "hw_0v3")]
+            HwRevision::V0v3 => hardware_init_0v3(peripherals, shared_resource),
+            #[cfg(any(feature = "hw_0v4", feature = "hw_auto"))]
+            HwRevision::V0v4 => hardware_init_0v4(peripherals, shared_resource),
+            #[cfg(any(feature = "hw_mini_0v4", feature = "hw_auto"))]
+            HwRevision::Mini0v4 => hardware_init_mini_0v4(peripherals, shared_resource),
+            #[cfg(any(feature = "hw_mini_0v5", feature = "hw_auto"))]
+            HwRevision::Mini0v5 => hardware_init_mini_0v5(peripherals, shared_resource),
+            #[cfg(feature = "hw_quad_0v1")]
+            HwRevision::Quad0v1 => hardware_init_quad_0v1(peripherals, shared_resource),
+            #[allow(unreachable_patterns)]
+            x => defmt::panic!("{} layout is not built in this firmware", x),
+        }
     }
 
     /// Initialize MCU peripherals and nearby components
@@ -177,12 +247,14 @@ impl Hardware {
     /// > `hardware.start_tasks(..)`
     fn start_tasks(&'static self, spawner: &Spawner) {
         // Vend legacy device initialization
-        self.vend_sides[PLAYER_1_INDEX].start_tasks(spawner);
-        self.vend_sides[PLAYER_2_INDEX].start_tasks(spawner);
+        for vend_side in &self.vend_sides {
+            vend_side.start_tasks(spawner);
+        }
 
-        // Game IO PCB side player 1 and 2 mocked module initialization
-        self.host_sides[PLAYER_1_INDEX].start_tasks(spawner);
-        self.host_sides[PLAYER_2_INDEX].start_tasks(spawner);
+        // Game IO PCB side of each player mocked module initialization
+        for host_side in &self.host_sides {
+            host_side.start_tasks(spawner);
+        }
 
         // LED indicators inside of PCB initialization. for debug / indication.
         unwrap!(spawner.spawn(buffered_opendrain_spawn(&self.indicators[LED_1_INDEX])));
@@ -190,16 +262,51 @@ impl Hardware {
 
         #[cfg(feature = "svc_button")]
         // SVC button (tact button) insde of PCB. for engineer and foreman
-        unwrap!(spawner.spawn(buffered_wait_spawn(&self.svc_button)));
+        if let Some(svc_button) = &self.svc_button {
+            unwrap!(spawner.spawn(buffered_wait_spawn(svc_button)));
+        }
+
+        #[cfg(feature = "mech_meter")]
+        {
+            // Mechanical meters on spare pins, for card and coin income
+            unwrap!(spawner.spawn(mech_meter_spawn(&self.meters[METER_CARD_INDEX])));
+            unwrap!(spawner.spawn(mech_meter_spawn(&self.meters[METER_COIN_INDEX])));
+        }
+
+        #[cfg(feature = "payout")]
+        // Hopper or ticket dispenser motor and feedback sensor
+        unwrap!(spawner.spawn(dispenser_spawn(&self.dispenser)));
+
+        #[cfg(feature = "start_button")]
+        {
+            // Start buttons with LED on spare pins
+            unwrap!(spawner.spawn(start_button_spawn(&self.start_buttons[PLAYER_1_INDEX])));
+            unwrap!(spawner.spawn(start_button_spawn(&self.start_buttons[PLAYER_2_INDEX])));
+        }
+
+        #[cfg(feature = "board_link")]
+        // Board-to-board link on spare USART1
+        unwrap!(spawner.spawn(board_link_spawn(&self.board_link)));
 
         unwrap!(spawner.spawn(novella_spawn(&self.eeprom)));
 
         // nothing to do for dipsw for now
         // DIP switch module initialization
 
-        // USART CardReaderDevice module initialization
-        unwrap!(spawner.spawn(card_reader_device_spawn(&self.card_reader, &self.eeprom)));
+        // USART CardReaderDevice module initialization, it also serves firmware update
+        #[cfg(feature = "fw_update")]
+        let fw_update = Some(&self.fw_update);
+        #[cfg(not(feature = "fw_update"))]
+        let fw_update = None;
+        unwrap!(spawner.spawn(card_reader_device_spawn(
+            &self.card_reader,
+            &self.eeprom,
+            fw_update
+        )));
         serial_device::alert_module_status();
+
+        // Independent watchdog supervisor, should be spawned after supervised tasks
+        unwrap!(spawner.spawn(watchdog_spawn(&self.watchdog, &self.eeprom)));
     }
 }
 
@@ -212,6 +319,10 @@ pub struct SharedResource {
 
     /// LED and start button LED related timing that shared or const-ish.
     pub indicator_timing: SharedToggleTiming,
+
+    #[cfg(feature = "mech_meter")]
+    /// Mechanical meter coil timing, minimum on/off time of counter coil.
+    pub meter_timing: SharedToggleTiming,
 }
 
 impl SharedResource {
@@ -223,12 +334,46 @@ impl SharedResource {
     fn init() -> Self {
         Self {
             async_input_event_ch: BufferedWaitReceiver::new(),
-            arcade_players_timing: [SharedToggleTiming::default(), SharedToggleTiming::default()],
+            arcade_players_timing: core::array::from_fn(|_| SharedToggleTiming::default()),
             indicator_timing: SharedToggleTiming::new_custom(ToggleTiming {
                 high_ms: 500,
                 low_ms: 500,
             }),
+            #[cfg(feature = "mech_meter")]
+            meter_timing: SharedToggleTiming::new_custom(METER_DEFAULT_TIMING),
+        }
+    }
+}
+
+/// `hw_auto` image on board without valid revision on OTP, see `HwRevision::detect`.
+/// Pins are not configured since each layout uses them differently, thus outputs stay
+/// on reset state (Hi-Z) and acceptors are not driven. USART2 of card reader is common
+/// on every layout, the error is shown on card terminal until the board is reset.
+fn halt_without_revision(p: embassy_stm32::Peripherals) -> ! {
+    const HALT_PAGE: TextPage = TextPage::new(0)
+        .line(0, TextLine::centered(b"HW REVISION ERROR"))
+        .line(2, TextLine::centered(b"PROGRAM REVISION"))
+        .line(3, TextLine::centered(b"ON OTP BY MPTOOL"));
+
+    let usart2_config = {
+        let mut ret: UsartConfig = UsartConfig::default();
+        ret.baudrate = 115200;
+        ret
+    };
+    let mut tx = UartTx::new(p.USART2, p.PA2, NoDma, usart2_config).ok();
+    let plug = KiccEd785Plug {};
+    let mut tx_buf = [0u8; serial_device::CARD_READER_TX_BUFFER_SIZE];
+
+    loop {
+        defmt::error!("HW revision is not programmed on OTP, hw_auto image is halted");
+
+        if let Some(tx) = tx.as_mut() {
+            tx.blocking_write(plug.display_text_page(&mut tx_buf, &HALT_PAGE))
+                .ok();
         }
+
+        // terminal may be booted later than the board
+        embassy_time::block_for(Duration::from_secs(5));
     }
 }
 
@@ -239,6 +384,8 @@ pub struct BoardCorrespondOutputMatchError<Enum: Sized> {
 pub struct Board {
     pub hardware: Hardware,
     pub shared_resource: &'static SharedResource,
+    /// Detected on boot, see `HwRevision::detect`
+    revision: HwRevision,
 }
 
 impl Board {
@@ -251,55 +398,167 @@ impl Board {
         #[rustfmt::skip]
         defmt::println!("Firmware Ver : {} {=[u8]:a}", const_str::PROJECT_NAME, const_str::VERSION_STR);
         defmt::println!("SerialNumber : {=[u8]:a}", *const_str::get_serial_number());
+        let revision = HwRevision::detect();
+        #[rustfmt::skip]
+        defmt::println!("HW Revision  : {} ({})", revision.map_or("UNKNOWN", HwRevision::model_ver), HwRevision::IMAGE_MODEL_VER);
         defmt::println!("Git Hash     : {}", const_str::COMMIT_HASH);
         #[rustfmt::skip]
         defmt::println!("Git Datetime : {} | {=[u8]:a}", const_str::GIT_COMMIT_DATETIME, const_str::COMMIT_SHORT);
         defmt::println!("{}", const_str::PRINT_BAR);
 
+        let Some(revision) = revision else {
+            halt_without_revision(p);
+        };
+
         let shared_resource = make_static!(SharedResource::init());
-        let hardware: Hardware = Hardware::hardware_init(p, shared_resource);
+        let hardware: Hardware = Hardware::hardware_init(revision, p, shared_resource);
 
         Self {
             hardware,
             shared_resource,
+            revision,
         }
     }
 
+    #[allow(dead_code)]
+    pub const fn revision(&self) -> HwRevision {
+        self.revision
+    }
+
     pub fn start_tasks(&'static self, spawner: &Spawner) -> &Self {
         self.hardware.start_tasks(spawner);
         self
     }
 
+    /// Apply signal polarity on every player ports
+    pub fn apply_polarity(&self, polarity: &PortPolarity) {
+        for (idx, player) in PLAYERS.into_iter().enumerate() {
+            self.hardware.vend_sides[idx].set_polarity(player, polarity);
+            self.hardware.host_sides[idx].set_polarity(player, polarity);
+
+            #[cfg(feature = "start_button")]
+            self.hardware.start_buttons[idx].set_polarity(player, polarity);
+        }
+    }
+
+    /// Disable inputs of player 2 and later sides that not used by cabinet profile
+    pub fn apply_cabinet(&self, profile: CabinetProfile) {
+        let is_used = profile != CabinetProfile::SinglePlayer;
+
+        for idx in PLAYER_2_INDEX..PLAYER_INDEX_MAX {
+            self.hardware.vend_sides[idx].set_enabled(is_used);
+            // Shared credit cabinet has single coin line, other inhibits of GAME I/O float
+            self.hardware.host_sides[idx].set_inhibit_enabled(profile == CabinetProfile::TwoPlayer);
+
+            #[cfg(feature = "start_button")]
+            self.hardware.start_buttons[idx].set_enabled(is_used);
+        }
+    }
+
+    /// Apply minimum high duration of stretched I/O routes on output ports
+    pub fn apply_io_routes(&'static self, routes: &[RawIoRoute; IO_ROUTE_NUM]) {
+        let ports =
+            (0..=(InputPortKind::Inhibit4P as u8)).filter_map(|x| InputPortKind::try_from(x).ok());
+        for port in ports {
+            if let Ok(output) = self.correspond_output(&port) {
+                output.set_min_high_ms(0);
+            }
+        }
+
+        for route in routes.iter().filter(|x| x.stretch_ms() != 0) {
+            let Ok(port) = InputPortKind::try_from(route.output) else {
+                continue;
+            };
+
+            let mirrored = route
+                .has_flag(IO_ROUTE_FLAG_MIRROR)
+                .then(|| port.flip_player());
+            for port in core::iter::once(port).chain(mirrored) {
+                if let Ok(output) = self.correspond_output(&port) {
+                    output.set_min_high_ms(route.stretch_ms());
+                }
+            }
+        }
+    }
+
     pub fn correspond_output(
         &'static self,
         port: &InputPortKind,
     ) -> Result<&BufferedOpenDrain, BoardCorrespondOutputMatchError<InputPortKind>> {
-        match port {
-            InputPortKind::Vend1P => Ok(&self.hardware.host_sides[PLAYER_1_INDEX].out_vend),
-            InputPortKind::Vend2P => Ok(&self.hardware.host_sides[PLAYER_2_INDEX].out_vend),
-            InputPortKind::Jam1P => Ok(&self.hardware.host_sides[PLAYER_1_INDEX].out_jam),
-            InputPortKind::Jam2P => Ok(&self.hardware.host_sides[PLAYER_2_INDEX].out_jam),
-            InputPortKind::Start1P => Ok(&self.hardware.host_sides[PLAYER_1_INDEX].out_start),
-            InputPortKind::Start2P => Ok(&self.hardware.host_sides[PLAYER_2_INDEX].out_start),
-            InputPortKind::Inhibit1P => Ok(&self.hardware.vend_sides[PLAYER_1_INDEX].out_inhibit),
-            InputPortKind::Inhibit2P => Ok(&self.hardware.vend_sides[PLAYER_2_INDEX].out_inhibit),
-            x => Err(BoardCorrespondOutputMatchError { origin: *x }),
+        let err = BoardCorrespondOutputMatchError { origin: *port };
+        let Some(idx) = port.player().index().filter(|x| *x < PLAYER_INDEX_MAX) else {
+            return Err(err);
+        };
+        let host_side = &self.hardware.host_sides[idx];
+
+        match port.for_player(Player::Player1) {
+            InputPortKind::Vend1P => Ok(&host_side.out_vend),
+            InputPortKind::Jam1P => Ok(&host_side.out_jam),
+            InputPortKind::Start1P => Ok(&host_side.out_start),
+            InputPortKind::Inhibit1P => Ok(&self.hardware.vend_sides[idx].out_inhibit),
+            _ => Err(err),
         }
     }
 
+    /// Indicators follow vend of player 1 and 2, player 3 and 4 share them by parity
     pub fn correspond_indicator(&'static self, port: &InputPortKind) -> Option<&BufferedOpenDrain> {
         match port {
-            InputPortKind::Vend1P => Some(&self.hardware.indicators[LED_1_INDEX]),
-            InputPortKind::Vend2P => Some(&self.hardware.indicators[LED_2_INDEX]),
+            InputPortKind::Vend1P | InputPortKind::Vend3P => {
+                Some(&self.hardware.indicators[LED_1_INDEX])
+            }
+            InputPortKind::Vend2P | InputPortKind::Vend4P => {
+                Some(&self.hardware.indicators[LED_2_INDEX])
+            }
             _ => None, // this is optional action, thus return with None , not Err
         }
     }
 
+    #[allow(unused_variables)]
+    pub fn correspond_meter(&'static self, kind: MeterKind) -> Option<&MechMeter> {
+        #[cfg(feature = "mech_meter")]
+        return Some(match kind {
+            MeterKind::Card => &self.hardware.meters[METER_CARD_INDEX],
+            MeterKind::Coin => &self.hardware.meters[METER_COIN_INDEX],
+        });
+
+        #[cfg(not(feature = "mech_meter"))]
+        None // board doesn't have meter, this is optional action
+    }
+
+    #[allow(unused_variables)]
+    pub fn correspond_start_led(&'static self, player: Player) -> Option<&BufferedOpenDrain> {
+        #[cfg(feature = "start_button")]
+        return player
+            .index()
+            .and_then(|idx| self.hardware.start_buttons.get(idx))
+            .map(|x| &x.out_led);
+
+        #[cfg(not(feature = "start_button"))]
+        None // board doesn't have start button LED, this is optional action
+    }
+
+    pub fn correspond_link(&'static self) -> Option<&BoardLink> {
+        #[cfg(feature = "board_link")]
+        return Some(&self.hardware.board_link);
+
+        #[cfg(not(feature = "board_link"))]
+        None // board doesn't have board-to-board link, this is optional action
+    }
+
+    pub fn correspond_fw_update(&'static self) -> Option<&FwUpdate> {
+        #[cfg(feature = "fw_update")]
+        return Some(&self.hardware.fw_update);
+
+        #[cfg(not(feature = "fw_update"))]
+        None // firmware is not built for bootloader, this is optional action
+    }
+
     pub fn correspond_busy(&'static self, port: &InputPortKind) -> Option<&BufferedOpenDrain> {
-        match port {
-            InputPortKind::Vend1P => Some(&self.hardware.host_sides[PLAYER_1_INDEX].out_busy),
-            InputPortKind::Vend2P => Some(&self.hardware.host_sides[PLAYER_2_INDEX].out_busy),
-            _ => None, // this is optional action, thus return with None , not Err
+        if port.for_player(Player::Player1) != InputPortKind::Vend1P {
+            return None; // this is optional action, thus return with None , not Err
         }
+
+        let idx = port.player().index()?;
+        self.hardware.host_sides.get(idx).map(|x| &x.out_busy)
     }
 }
@@ -0,0 +1,129 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Board-to-board link over spare USART, chaining two BillMock boards behind single card terminal.
+//! Frames are defined in `board-link` crate, this component only moves them between USART and channels.
+
+#![cfg_attr(not(feature = "board_link"), allow(dead_code))]
+
+use core::cell::UnsafeCell;
+
+use board_link::*;
+use embassy_stm32::dma::NoDma;
+use embassy_stm32::peripherals::{DMA1_CH5, USART1};
+use embassy_stm32::usart::{RingBufferedUartRx, UartTx};
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_sync::channel::Channel;
+use embassy_time::{with_timeout, Duration, Instant};
+
+use crate::semi_layer::heartbeat::{HeartbeatKind, HEARTBEAT};
+
+const BOARD_LINK_COMMAND_CHANNEL_SIZE_RX: usize = 4;
+const BOARD_LINK_COMMAND_CHANNEL_SIZE_TX: usize = 4;
+const WAIT_DURATION_RX: Duration = Duration::from_millis(200); // heuristic value
+
+pub const BOARD_LINK_RX_BUFFER_SIZE: usize = 64;
+
+/// Received command with its arrival time, stale forwarded payment is refused by it
+#[derive(Debug, defmt::Format, Clone, Copy)]
+pub struct LinkRecv {
+    pub cmd: LinkCmd,
+    pub received: Instant,
+}
+
+pub type BoardLinkResponseChannel =
+    Channel<ThreadModeRawMutex, LinkRecv, BOARD_LINK_COMMAND_CHANNEL_SIZE_RX>;
+
+pub type BoardLinkRequestChannel =
+    Channel<ThreadModeRawMutex, LinkCmd, BOARD_LINK_COMMAND_CHANNEL_SIZE_TX>;
+
+pub struct BoardLink {
+    // USART is complex to use generic
+    tx: UnsafeCell<UartTx<'static, USART1, NoDma>>,
+    rx: UnsafeCell<RingBufferedUartRx<'static, USART1, DMA1_CH5>>,
+    pub recv_channel: BoardLinkResponseChannel,
+    pub req_channel: BoardLinkRequestChannel,
+}
+
+impl BoardLink {
+    pub const fn new(
+        tx: UartTx<'static, USART1, NoDma>,
+        ringbuffer_rx: RingBufferedUartRx<'static, USART1, DMA1_CH5>,
+    ) -> Self {
+        Self {
+            tx: UnsafeCell::new(tx),
+            rx: UnsafeCell::new(ringbuffer_rx),
+            recv_channel: Channel::new(),
+            req_channel: Channel::new(),
+        }
+    }
+
+    async fn run(&self) {
+        let rx = unsafe { &mut *self.rx.get() };
+        let tx = unsafe { &mut *self.tx.get() };
+        let mut rx_buf = [0u8; BOARD_LINK_RX_BUFFER_SIZE];
+        let mut tx_buf = [0u8; LINK_FRAME_MAX];
+        let mut stream = LinkStream::new();
+        let heartbeat = HEARTBEAT.register(HeartbeatKind::BoardLink);
+
+        loop {
+            HEARTBEAT.beat(heartbeat);
+
+            // Other board has deep enough buffer, send whole pending commands
+            while let Ok(tx_cmd) = self.req_channel.try_receive() {
+                defmt::debug!("BoardLink Tx : {}", tx_cmd);
+
+                match encode(&tx_cmd, &mut tx_buf) {
+                    Ok(send_source) => {
+                        if let Err(e) = tx.blocking_write(send_source) {
+                            defmt::error!("BoardLink USART TX error : {:?}", e);
+                        }
+                    }
+                    Err(e) => defmt::error!("BoardLink encode error : {}", e),
+                }
+            }
+
+            match with_timeout(WAIT_DURATION_RX, rx.read(&mut rx_buf)).await {
+                Ok(Ok(rx_len)) => {
+                    stream.push(&rx_buf[..rx_len]);
+
+                    while let Some(result) = stream.pop() {
+                        match result {
+                            Ok(rx_cmd) => {
+                                defmt::debug!("BoardLink Rx : {}", rx_cmd);
+                                self.recv_channel
+                                    .send(LinkRecv {
+                                        cmd: rx_cmd,
+                                        received: Instant::now(),
+                                    })
+                                    .await;
+                            }
+                            Err(e) => defmt::error!("BoardLink parse error : {}", e),
+                        }
+                    }
+                }
+                Err(_timeout_e) => {
+                    // incompleted frame is not continued after idle
+                    stream.clear();
+                }
+                Ok(Err(e)) => {
+                    stream.clear();
+                    defmt::error!("BoardLink USART error : {:?}", e);
+                }
+            }
+        }
+    }
+
+    #[inline]
+    pub async fn send(&self, request: LinkCmd) {
+        self.req_channel.send(request).await;
+    }
+}
+
+#[embassy_executor::task(pool_size = 1)]
+pub async fn board_link_spawn(instance: &'static BoardLink) {
+    instance.run().await;
+}
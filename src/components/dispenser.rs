@@ -0,0 +1,54 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Hopper / ticket dispenser, motor output and item feedback sensor.
+//! Feedback sensor events are delivered through common input event channel,
+//! counting and empty/jam detection are handled in application side.
+
+#![cfg_attr(not(feature = "payout"), allow(dead_code))]
+
+use embassy_futures::join::join;
+use embassy_stm32::exti::ExtiInput;
+use embassy_stm32::gpio::AnyPin;
+
+use crate::semi_layer::buffered_opendrain::{BufferedOpenDrain, BufferedOpenDrainPin};
+use crate::semi_layer::buffered_wait::{BufferedWait, InputEventChannel, RawInputPortKind};
+use crate::semi_layer::timing::SharedToggleTiming;
+use crate::types::buffered_opendrain_kind::BufferedOpenDrainKind;
+use crate::types::input_port::InputPortKind;
+use crate::types::player::Player;
+
+pub struct Dispenser {
+    pub out_motor: BufferedOpenDrain,
+    in_sense: BufferedWait,
+}
+
+impl Dispenser {
+    pub const fn new(
+        out_motor: BufferedOpenDrainPin,
+        in_sense: ExtiInput<'static, AnyPin>,
+        mpsc_ch: &'static InputEventChannel,
+        shared_timing: &'static SharedToggleTiming,
+    ) -> Self {
+        let motor_str: &'static str = BufferedOpenDrainKind::PayoutMotor.const_str();
+        let (sense_p, sense_str): (RawInputPortKind, &'static str) =
+            InputPortKind::PayoutSense.to_raw_and_const_str(Player::Undefined);
+
+        Self {
+            out_motor: BufferedOpenDrain::new(out_motor, shared_timing, motor_str),
+            in_sense: BufferedWait::new(in_sense, mpsc_ch, sense_p, sense_str),
+        }
+    }
+
+    async fn run(&self) {
+        join(self.out_motor.run(), self.in_sense.run()).await;
+    }
+}
+
+#[embassy_executor::task(pool_size = 1)]
+pub async fn dispenser_spawn(instance: &'static Dispenser) {
+    instance.run().await
+}
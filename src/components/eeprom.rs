@@ -4,7 +4,6 @@
  * SPDX-License-Identifier: MIT OR Apache-2.0
  */
 
-use core::borrow::BorrowMut;
 use core::cell::UnsafeCell;
 use core::marker::PhantomData;
 
@@ -17,45 +16,43 @@ use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
 use embassy_sync::mutex::Mutex;
 use embassy_sync::mutex::MutexGuard;
 use embassy_time::{Duration, Instant, Timer};
+use novella_layout::{
+    type_id, NvMigrateError, NvMigrateOk, NvSectionInfo, NvStorage, HEADER_ADDR, LAYOUT_CURRENT,
+};
 use zeroable::Zeroable;
 
+use crate::semi_layer::heartbeat::{HeartbeatKind, HEARTBEAT};
+use crate::types::buffered_opendrain_kind::MeterKind;
+use crate::types::extra_player_count::ExtraPlayerCount;
 use crate::types::fault_log::FaultLog;
+use crate::types::install_config::InstallConfig;
+use crate::types::player::{Player, PLAYERS};
+use crate::types::service_count::ServiceCount;
 
 // Memory Map - Assume 2KB (16KBits) EEPROM.
 // +---------------------------- Memory Map - Assume 2KB (16KBits) EEPROM ---------------------------+
 // |                                                                                                 |
-// |  Section 0 (0x00-0xFF bytes)   p1_card_cnt    Section =7 (0x600-0x77F bytes) card_reader...     |
-// |  +-----------------------------------------+  +-----------------------------------------+       |
-// |  | Slot 0  | uptime    | p1_card_cnt | CRC |  | Page 0  | uptime    | lsb               | page0 |
-// |  | Slot 1  | uptime    | p1_card_cnt | CRC |  |    card_reader_port_backup (32 bytes)   | page1 |
-// |  | ...     | ...       | ...         | ... |  |                       msb         | CRC | page2 |
-// |  | Slot 15 | uptime    | p1_card_cnt | CRC |  +--...------------------------------------+       |
-// |  +-----------------------------------------+  | Page 0  | uptime    | lsb               | page0 |
-// |                                               |    card_reader_port_backup (32 bytes)   | page1 |
-// |  Section 1 (0x100-0x1FF bytes) p2_card_cnt    |                               msb | CRC | page2 |
-// |  +-----------------------------------------+  +-----------------------------------------+       |
-// |  | Slot 0  | uptime    | p2_card_cnt | CRC | <- This section's slot size is single page         |
-// |  | Slot 1  | uptime    | p2_card_cnt | CRC |                                                    |
-// |  | ...     | ...       | ...         | ... |  Section 0..=3 (normal sections, 16 slot-ish data) |
-// |  | Slot 15 | last_time | p2_card_cnt | CRC |  Section  0 : p1_card_cnt          u32    4 bytes  |
-// |  +-----------------------------------------+  Section  1 : p1_card_cnt          u32    4 bytes  |
-// |   . . . . .                                   Section  2 : p1_coin_cnt          u32    4 bytes  |
-// |                                               Section  3 : p2_coin_cnt          u32    4 bytes  |
-// |  Section 5 (0x480-0x4FF bytes) hw_boot_cnt                                                      |
-// |  +-----------------------------------------+  Section 4..=5 (small sections, 8 slot-ish data)   |
-// |  | Slot 0  | uptime    | hw_boot_cnt | CRC |  Section  4 : hw_boot_cnt          u32    4 bytes  |
-// |  | ...     | ...       | ...         | ... |  Section  5 : fault_log         Struct    6 bytes  |
-// |  | Slot 7  | uptime    | hw_boot_cnt | CRC |                                                    |
-// |  +-----------------------------------------+                             2/3 page for slot      |
-// |                                               Section 6..=7 (big sections, 8 slot-ish data      |
-// |  Section 6 (0x500-0x5FF bytes) raw_terminal   Section  5 : raw_terminal      Struct   13 bytes  |
-// |  +-----------------------------------------+                           2 pages for single slot  |
-// |  | Slot 0  | uptime    | lsb  raw_terminal |                                                    |
-// |  |         raw_terminal          msb | CRC |  Section  6 : card_reader_port_backup    32 bytes  |
-// |  +--...------------------------------------+                           3 pages for single slot  |
-// |  | Slot 7  | uptime    | lsb  raw_terminal | page0                                              |
-// |  |         raw_terminal          msb | CRC | page1                                              |
+// |  Section 0 (0x000-0x0FF bytes) p1_card_cnt      Address     Field                   SxPg Data   |
 // |  +-----------------------------------------+                                                    |
+// |  | Slot 0  | uptime    | p1_card_cnt | CRC |  0 0x000-0x0FF p1_card_cnt             16x1   4    |
+// |  | Slot 1  | uptime    | p1_card_cnt | CRC |  1 0x100-0x1FF p2_card_cnt             16x1   4    |
+// |  | ...     | ...       | ...         | ... |  2 0x200-0x2FF p1_coin_cnt             16x1   4    |
+// |  | Slot 15 | uptime    | p1_card_cnt | CRC |  3 0x300-0x3FF p2_coin_cnt             16x1   4    |
+// |  +-----------------------------------------+  4 0x400-0x43F fault_log                4x1   6    |
+// |  slot of counter is single page               5 0x440-0x47F hw_boot_cnt              4x1   4    |
+// |                                               6 0x480-0x4FF raw_terminal             4x2  13    |
+// |  Section 7 card_reader_port_backup            7 0x500-0x5BF card_reader_port_backup  4x3  32    |
+// |  slot of backup is three pages                8 0x5C0-0x5DF payout_cnt               2x1   4    |
+// |  +-----------------------------------------+  9 0x5E0-0x65F install_config           2x4  42    |
+// |  | Slot 0  | uptime    | lsb               | 10 0x660-0x69F service_cnt              2x2  16    |
+// |  |    card_reader_port_backup (32 bytes)   | 11 0x6A0-0x79F extra_player_cnt         8x2  16    |
+// |  |                               msb | CRC |                                                    |
+// |  +--...------------------------------------+    0x7F0-0x7FF layout header of novella-layout     |
+// |  | Slot 3  | uptime    | lsb               |                                                    |
+// |  |    card_reader_port_backup (32 bytes)   |                                                    |
+// |  |                               msb | CRC |                                                    |
+// |  +-----------------------------------------+                                                    |
+// |                                                                                                 |
 // +-------------------------------------------------------------------------------------------------+
 //
 //   Write cycle endurance of each page is 1,200,000 ~ 4,000,0000
@@ -86,16 +83,189 @@ use crate::types::fault_log::FaultLog;
 //   |                                    Actual Data                                    |   CRC16   |
 //   +-----+-----+-----+-----+-----+-----+-----+-----+-----+-----+-----+-----+-----+-----+-----+-----+
 
-#[derive(Zeroable)]
-pub struct MemStorage {
-    pub p1_card_cnt: u32,
-    pub p2_card_cnt: u32,
-    pub p1_coin_cnt: u32,
-    pub p2_coin_cnt: u32,
-    pub hw_boot_cnt: u32,
-    pub fault_log: FaultLog,
-    pub raw_terminal: RawTerminalId,
-    pub card_reader_port_backup: CardReaderPortBackup,
+/// Declare every section of Novella, the order of declaration is the order on EEPROM.
+/// It generates `MemStorage`, `NvMemSectionKind`, typed selectors of `select` and raw access
+/// of each section. Slot size and address are calculated from size of the type and `slot_num`,
+/// the layout is checked against `EEPROM_SIZE` in compile time.
+/// `policy` decides when changes of the section are written, see `NvWritePolicy`.
+///
+/// New section should be appended at the end, reordering or resizing existing section
+/// moves address of following sections. Any change of the layout should be released as new
+/// layout of `novella-layout` with its migration, the table is checked against `LAYOUT_CURRENT`.
+macro_rules! novella_sections {
+    ($(
+        $(#[$meta:meta])*
+        $kind:ident {
+            field: $field:ident,
+            ty: $ty:ty,
+            type_id: $type_id:expr,
+            slot_num: $slot_num:literal,
+            policy: $policy:expr,
+            selector: $selector:ident $(,)?
+        },
+    )+) => {
+        #[derive(Zeroable)]
+        pub struct MemStorage {
+            $(pub $field: $ty,)+
+        }
+
+        #[repr(u8)]
+        #[derive(Clone, Copy)]
+        #[allow(unused)]
+        pub enum NvMemSectionKind {
+            $($(#[$meta])* $kind,)+
+        }
+
+        const SECTION_NUM: usize = [$(NvMemSectionKind::$kind),+].len();
+
+        const SECTION_TABLE: [NvSectionInfo; SECTION_NUM] =
+            NvSectionInfo::layout([$(($type_id, $slot_num, core::mem::size_of::<$ty>())),+]);
+
+        const _: () = assert!(
+            LAYOUT_CURRENT.is_same(&SECTION_TABLE),
+            "Novella sections are not same with LAYOUT_CURRENT of novella-layout"
+        );
+
+        const WRITE_POLICY_TABLE: [NvWritePolicy; SECTION_NUM] = [$($policy),+];
+
+        const _: () = assert!(
+            NOVELLA_LIFETIME_WORST_SECS >= NOVELLA_LIFETIME_MIN_SECS,
+            "Write policy of a section wears EEPROM out before NOVELLA_LIFETIME_MIN_SECS"
+        );
+
+        mod selector {
+            use super::*;
+
+            $(
+                pub const $selector: NovellaSelector<$ty> =
+                    NovellaSelector::new(NvMemSectionKind::$kind);
+            )+
+        }
+
+        impl NovellaModuleControlBlock {
+            unsafe fn get_data_raw_slice(&mut self, kind: NvMemSectionKind) -> &mut [u8] {
+                match kind {
+                    $(
+                        NvMemSectionKind::$kind => core::slice::from_raw_parts_mut(
+                            (&mut self.data.$field as *mut $ty) as *mut u8,
+                            core::mem::size_of::<$ty>(),
+                        ),
+                    )+
+                }
+            }
+        }
+    };
+}
+
+novella_sections! {
+    /// Card income counter of player 1, u32
+    P1CardCnt {
+        field: p1_card_cnt,
+        ty: u32,
+        type_id: type_id::P1_CARD_CNT,
+        slot_num: 16,
+        policy: NvWritePolicy::COUNTER,
+        selector: P1_CARD_CNT,
+    },
+    /// Card income counter of player 2, u32
+    P2CardCnt {
+        field: p2_card_cnt,
+        ty: u32,
+        type_id: type_id::P2_CARD_CNT,
+        slot_num: 16,
+        policy: NvWritePolicy::COUNTER,
+        selector: P2_CARD_CNT,
+    },
+    /// Bill / coin income counter of player 1, u32
+    P1CoinCnt {
+        field: p1_coin_cnt,
+        ty: u32,
+        type_id: type_id::P1_COIN_CNT,
+        slot_num: 16,
+        policy: NvWritePolicy::COUNTER,
+        selector: P1_COIN_CNT,
+    },
+    /// Bill / coin income counter of player 2, u32
+    P2CoinCnt {
+        field: p2_coin_cnt,
+        ty: u32,
+        type_id: type_id::P2_COIN_CNT,
+        slot_num: 16,
+        policy: NvWritePolicy::COUNTER,
+        selector: P2_COIN_CNT,
+    },
+    /// Last fault and boot count of the fault, 6 bytes
+    FaultLog {
+        field: fault_log,
+        ty: FaultLog,
+        type_id: type_id::FAULT_LOG,
+        slot_num: 4,
+        policy: NvWritePolicy::EVENT,
+        selector: FAULT_LOG,
+    },
+    /// Boot count of the hardware, u32
+    HwBootCount {
+        field: hw_boot_cnt,
+        ty: u32,
+        type_id: type_id::HW_BOOT_CNT,
+        slot_num: 4,
+        policy: NvWritePolicy::EVENT,
+        selector: HW_BOOT_CNT,
+    },
+    /// TID of card terminal, 13 bytes
+    TerminalId {
+        field: raw_terminal,
+        ty: RawTerminalId,
+        type_id: type_id::TERMINAL_ID,
+        slot_num: 4,
+        policy: NvWritePolicy::EVENT,
+        selector: TERMINAL_ID,
+    },
+    /// Sale slot backup of card terminal, 32 bytes (4+4)*4
+    CardPortBackup {
+        field: card_reader_port_backup,
+        ty: CardReaderPortBackup,
+        type_id: type_id::CARD_PORT_BACKUP,
+        slot_num: 4,
+        policy: NvWritePolicy::EVENT,
+        selector: CARD_PORT_BACKUP,
+    },
+    /// Dispensed tickets / tokens counter, u32
+    PayoutCnt {
+        field: payout_cnt,
+        ty: u32,
+        type_id: type_id::PAYOUT_CNT,
+        slot_num: 2,
+        policy: NvWritePolicy::PAYOUT,
+        selector: PAYOUT_CNT,
+    },
+    /// Install config written by card terminal, 42 bytes
+    InstallConfig {
+        field: install_config,
+        ty: InstallConfig,
+        type_id: type_id::INSTALL_CONFIG,
+        slot_num: 2,
+        policy: NvWritePolicy::OPERATOR,
+        selector: INSTALL_CONFIG,
+    },
+    /// Service credit counter, 16 bytes (4*4)
+    ServiceCnt {
+        field: service_cnt,
+        ty: ServiceCount,
+        type_id: type_id::SERVICE_CNT,
+        slot_num: 2,
+        policy: NvWritePolicy::OPERATOR,
+        selector: SERVICE_CNT,
+    },
+    /// Card and coin income counters of player 3 and 4, 16 bytes (4*4)
+    ExtraPlayerCnt {
+        field: extra_player_cnt,
+        ty: ExtraPlayerCount,
+        type_id: type_id::EXTRA_PLAYER_CNT,
+        slot_num: 8,
+        policy: NvWritePolicy::EXTRA_COUNTER,
+        selector: EXTRA_PLAYER_CNT,
+    },
 }
 
 /// Tiny control block for manage single section, it include what page is longest and is dirty state
@@ -115,20 +285,6 @@ pub type EepromAddress = u8;
 pub type DevSelAddress = u8;
 type Checksum = u16;
 
-#[repr(u8)]
-#[derive(Clone, Copy)]
-#[allow(unused)]
-pub enum NvMemSectionKind {
-    P1CardCnt = 0,      // 1*16, u32
-    P2CardCnt = 1,      // 1*16, u32
-    P1CoinCnt = 2,      // 1*16, u32
-    P2CoinCnt = 3,      // 1*16, u32
-    FaultLog = 4,       // 1*16, Not determined 6 bytes
-    HwBootCount = 5,    // 1*08, u32
-    TerminalId = 6,     // 2*08, 13 bytes
-    CardPortBackup = 7, // 3*08, 32 bytes (4+4)*4
-}
-
 impl From<u8> for NvMemSectionKind {
     // instead of FromPrimitive for reduce dependancy
     fn from(value: u8) -> Self {
@@ -156,55 +312,49 @@ impl NvMemSectionKind {
         }
     }
 
-    // this should be generated by macro
     const fn get_last() -> Self {
-        Self::CardPortBackup
+        unsafe { core::mem::transmute::<u8, NvMemSectionKind>((SECTION_NUM - 1) as u8) }
     }
 }
 
+/// Typed handle of a section, only `novella_sections!` makes it with the declared type
 #[derive(Clone, Copy)]
 pub struct NovellaSelector<T> {
-    pub section: NvMemSectionKind,
-    pub marker: PhantomData<T>, // 0-byte guarantees
+    section: NvMemSectionKind,
+    marker: PhantomData<T>, // 0-byte guarantees
+}
+
+impl<T> NovellaSelector<T> {
+    const fn new(section: NvMemSectionKind) -> Self {
+        Self {
+            section,
+            marker: PhantomData,
+        }
+    }
 }
 
 #[allow(unused)]
 pub mod select {
+    pub use super::selector::*;
     use super::*;
-    use crate::types::fault_log::FaultLog;
 
-    pub const P1_CARD_CNT: NovellaSelector<u32> = NovellaSelector {
-        section: NvMemSectionKind::P1CardCnt,
-        marker: core::marker::PhantomData,
-    };
-    pub const P2_CARD_CNT: NovellaSelector<u32> = NovellaSelector {
-        section: NvMemSectionKind::P2CardCnt,
-        marker: core::marker::PhantomData,
-    };
-    pub const P1_COIN_CNT: NovellaSelector<u32> = NovellaSelector {
-        section: NvMemSectionKind::P1CoinCnt,
-        marker: core::marker::PhantomData,
-    };
-    pub const P2_COIN_CNT: NovellaSelector<u32> = NovellaSelector {
-        section: NvMemSectionKind::P2CoinCnt,
-        marker: core::marker::PhantomData,
-    };
-    pub const FAULT_LOG: NovellaSelector<FaultLog> = NovellaSelector {
-        section: NvMemSectionKind::FaultLog,
-        marker: core::marker::PhantomData,
-    };
-    pub const HW_BOOT_CNT: NovellaSelector<u32> = NovellaSelector {
-        section: NvMemSectionKind::HwBootCount,
-        marker: core::marker::PhantomData,
-    };
-    pub const TERMINAL_ID: NovellaSelector<RawTerminalId> = NovellaSelector {
-        section: NvMemSectionKind::TerminalId,
-        marker: core::marker::PhantomData,
-    };
-    pub const CARD_PORT_BACKUP: NovellaSelector<CardReaderPortBackup> = NovellaSelector {
-        section: NvMemSectionKind::CardPortBackup,
-        marker: core::marker::PhantomData,
-    };
+    /// Card income counter of player 1 or 2, other players are treated as player 1.
+    /// Use `Novella::lock_read_income` for every player.
+    pub const fn card_cnt(player: Player) -> NovellaSelector<u32> {
+        match player {
+            Player::Player2 => P2_CARD_CNT,
+            _ => P1_CARD_CNT,
+        }
+    }
+
+    /// Bill / coin income counter of player 1 or 2, other players are treated as player 1.
+    /// Use `Novella::lock_read_income` for every player.
+    pub const fn coin_cnt(player: Player) -> NovellaSelector<u32> {
+        match player {
+            Player::Player2 => P2_COIN_CNT,
+            _ => P1_COIN_CNT,
+        }
+    }
 }
 
 #[allow(async_fn_in_trait)]
@@ -224,10 +374,6 @@ pub trait NovellaRw {
     async fn lock_write_zero(&self, mutex: &Mutex<ThreadModeRawMutex, NovellaModuleControlBlock>);
 }
 
-fn should_not_happen() -> ! {
-    panic!("should not be happens")
-}
-
 fn delay_write_time_blocking() {
     let wait_for = Instant::now() + Duration::from_millis(5);
 
@@ -237,25 +383,16 @@ fn delay_write_time_blocking() {
     }
 }
 
-// this should be generated by macro
-impl NovellaRw for NovellaSelector<u32> {
-    type InnerType = u32;
+// Type of selector is same with the section, it's guaranteed by `novella_sections!`
+impl<T: Zeroable> NovellaRw for NovellaSelector<T> {
+    type InnerType = T;
     async fn lock_read(
         &self,
         mutex: &Mutex<ThreadModeRawMutex, NovellaModuleControlBlock>,
     ) -> Self::InnerType {
-        let cb = mutex.lock().await;
-
-        match self.section {
-            NvMemSectionKind::P1CardCnt => cb.data.p1_card_cnt,
-            NvMemSectionKind::P2CardCnt => cb.data.p2_card_cnt,
-            NvMemSectionKind::P1CoinCnt => cb.data.p1_coin_cnt,
-            NvMemSectionKind::P2CoinCnt => cb.data.p2_coin_cnt,
-            NvMemSectionKind::HwBootCount => cb.data.hw_boot_cnt,
-            _ => {
-                should_not_happen();
-            }
-        }
+        let mut cb = mutex.lock().await;
+
+        unsafe { (cb.get_data_raw_slice(self.section).as_ptr() as *const T).read_unaligned() }
     }
 
     async fn lock_write(
@@ -265,265 +402,238 @@ impl NovellaRw for NovellaSelector<u32> {
     ) {
         let mut cb = mutex.lock().await;
 
-        match self.section {
-            NvMemSectionKind::P1CardCnt => {
-                cb.data.p1_card_cnt = src;
-            }
-            NvMemSectionKind::P2CardCnt => {
-                cb.data.p2_card_cnt = src;
-            }
-            NvMemSectionKind::P1CoinCnt => {
-                cb.data.p1_coin_cnt = src;
-            }
-            NvMemSectionKind::P2CoinCnt => {
-                cb.data.p2_coin_cnt = src;
-            }
-            NvMemSectionKind::HwBootCount => {
-                cb.data.hw_boot_cnt = src;
-            }
-            _ => {
-                should_not_happen();
-            }
-        };
+        unsafe {
+            (cb.get_data_raw_slice(self.section).as_mut_ptr() as *mut T).write_unaligned(src);
+        }
 
         cb.control_mut(self.section).set_dirty();
+        cb.writes[self.section as usize].on_change(boot_secs());
     }
 
     async fn lock_write_zero(&self, mutex: &Mutex<ThreadModeRawMutex, NovellaModuleControlBlock>) {
-        let mut cb = mutex.lock().await;
-
-        *(match self.section {
-            NvMemSectionKind::P1CardCnt => &mut cb.data.p1_card_cnt,
-            NvMemSectionKind::P2CardCnt => &mut cb.data.p2_card_cnt,
-            NvMemSectionKind::P1CoinCnt => &mut cb.data.p1_coin_cnt,
-            NvMemSectionKind::P2CoinCnt => &mut cb.data.p2_coin_cnt,
-            NvMemSectionKind::HwBootCount => &mut cb.data.hw_boot_cnt,
-            _ => {
-                should_not_happen();
-            }
-        }) = Self::InnerType::zeroed();
-
-        cb.control_mut(self.section).set_dirty();
+        self.lock_write(mutex, Self::InnerType::zeroed()).await;
     }
 }
 
-impl NovellaRw for NovellaSelector<FaultLog> {
-    type InnerType = FaultLog;
-    async fn lock_read(
-        &self,
-        mutex: &Mutex<ThreadModeRawMutex, NovellaModuleControlBlock>,
-    ) -> Self::InnerType {
-        let cb = mutex.lock().await;
-
-        match self.section {
-            NvMemSectionKind::FaultLog => cb.data.fault_log.clone(),
-            _ => {
-                should_not_happen();
-            }
-        }
+impl NovellaSectionControlBlock {
+    fn set_dirty(&mut self) {
+        self.inner |= 1 << 7;
     }
 
-    async fn lock_write(
-        &self,
-        mutex: &Mutex<ThreadModeRawMutex, NovellaModuleControlBlock>,
-        src: Self::InnerType,
-    ) {
-        let mut cb = mutex.lock().await;
-
-        match self.section {
-            NvMemSectionKind::FaultLog => {
-                cb.data.fault_log = src;
-            }
-            _ => {
-                should_not_happen();
-            }
-        }
+    fn clr_dirty(&mut self) {
+        self.inner &= !(1 << 7);
+    }
 
-        cb.control_mut(self.section).set_dirty();
+    fn is_dirty(&self) -> bool {
+        (self.inner & (1 << 7)) != 0
     }
 
-    async fn lock_write_zero(&self, mutex: &Mutex<ThreadModeRawMutex, NovellaModuleControlBlock>) {
-        let mut cb = mutex.lock().await;
+    fn set_robin(&mut self, robin: RawNvRobin) {
+        self.inner = (self.inner & (1 << 7)) | (robin & !(1 << 7))
+    }
 
-        *(match self.section {
-            NvMemSectionKind::FaultLog => &mut cb.data.fault_log,
-            _ => {
-                should_not_happen();
-            }
-        }) = Self::InnerType::zeroed();
+    fn force_robin(&mut self, section_info: &NvSectionInfo) -> RawNvRobin {
+        let ret = (section_info.slot_num - 1) & (self.inner + 1);
+        self.inner = ret | (1 << 7);
+        self.set_dirty();
+        ret
+    }
 
-        cb.control_mut(self.section).set_dirty();
+    fn test_and_robin(&mut self, section_info: &NvSectionInfo) -> Option<RawNvRobin> {
+        if !self.is_dirty() {
+            None
+        } else {
+            Some(self.force_robin(section_info))
+        }
     }
 }
 
-impl NovellaRw for NovellaSelector<RawTerminalId> {
-    type InnerType = RawTerminalId;
-    async fn lock_read(
-        &self,
-        mutex: &Mutex<ThreadModeRawMutex, NovellaModuleControlBlock>,
-    ) -> Self::InnerType {
-        let cb = mutex.lock().await;
+/// Write cycles of each page, 24C16 guarantees 1M cycles
+const EEPROM_ENDURANCE: u64 = 1_000_000;
+/// Period of `run` loop
+const RUN_PERIOD_SECS: u64 = 2;
+/// `run` rewrites a section in turn when nothing is written for this, thus uptime keeps going
+const STARVING_REWRITE_SECS: u64 = 60;
+/// Period of wear report on `run`
+const WEAR_REPORT_SECS: u64 = 3600;
+/// Shortest EEPROM lifetime when every section is changed continuously,
+/// 5 years of 24 hours a day is 10 years of 12 hours a day.
+const NOVELLA_LIFETIME_MIN_SECS: u64 = 5 * 365 * 24 * 3600;
+
+/// Write policy of a section.
+/// Changes are coalesced in `MemStorage`, dirty section is written by `run` when it's idle,
+/// its oldest change is delayed too long or too many changes are pending.
+/// But the section is written once per `min_interval` on average, thus worst case write rate
+/// and lifetime of EEPROM are known in compile time. `burst` writes are allowed back to back,
+/// they're once and don't change the rate. Starving rewrite of `run` doesn't delay the next write,
+/// the lifetime counts it separately.
+/// Changes after last write are lost on power down, it's the trade-off with lifetime.
+#[derive(Clone, Copy)]
+pub struct NvWritePolicy {
+    /// Seconds, the section is written once within this on average
+    pub min_interval: u16,
+    /// Seconds, flush when there's no change for this
+    pub idle: u16,
+    /// Seconds, flush when oldest pending change is this old
+    pub max_delay: u16,
+    /// Flush when this many changes are coalesced
+    pub max_pending: u16,
+    /// Writes allowed ahead of `min_interval`, refilled one per `min_interval`
+    pub burst: u16,
+}
 
-        match self.section {
-            NvMemSectionKind::TerminalId => cb.data.raw_terminal.clone(),
-            _ => {
-                should_not_happen();
-            }
-        }
-    }
+impl NvWritePolicy {
+    /// Meters changed on every coin pulse and card payment, 16 slots
+    pub const COUNTER: Self = Self {
+        min_interval: 12,
+        idle: 2,
+        max_delay: 30,
+        max_pending: 16,
+        burst: 1,
+    };
 
-    async fn lock_write(
-        &self,
-        mutex: &Mutex<ThreadModeRawMutex, NovellaModuleControlBlock>,
-        src: Self::InnerType,
-    ) {
-        let mut cb = mutex.lock().await;
+    /// Meters of player 3 and 4 in single section, 8 slots.
+    /// Fewer slots than `COUNTER`, thus it's written less often and loses more on power down.
+    pub const EXTRA_COUNTER: Self = Self {
+        min_interval: 24,
+        idle: 2,
+        max_delay: 30,
+        max_pending: 16,
+        burst: 1,
+    };
 
-        match self.section {
-            NvMemSectionKind::TerminalId => {
-                cb.data.raw_terminal = src;
-            }
-            _ => {
-                should_not_happen();
-            }
-        };
+    /// Changed by rare events, fault, boot and card terminal, 4 slots
+    pub const EVENT: Self = Self {
+        min_interval: 60,
+        idle: 0,
+        max_delay: 0,
+        max_pending: 1,
+        burst: 1,
+    };
 
-        cb.control_mut(self.section).set_dirty();
-    }
+    /// Changed on every dispensed ticket or token, 2 slots
+    pub const PAYOUT: Self = Self {
+        min_interval: 120,
+        idle: 2,
+        max_delay: 120,
+        max_pending: 16,
+        burst: 1,
+    };
 
-    async fn lock_write_zero(&self, mutex: &Mutex<ThreadModeRawMutex, NovellaModuleControlBlock>) {
-        let mut cb = mutex.lock().await;
+    /// Changed by operator or service credit, written immediately not to lose them on power down.
+    /// Operator changes several keys or gives few credits in a row, it's in `burst`.
+    pub const OPERATOR: Self = Self {
+        min_interval: 120,
+        idle: 0,
+        max_delay: 0,
+        max_pending: 1,
+        burst: 16,
+    };
 
-        *(match self.section {
-            NvMemSectionKind::TerminalId => &mut cb.data.raw_terminal,
-            _ => {
-                should_not_happen();
-            }
-        }) = Self::InnerType::zeroed();
+    /// Lifetime of the section in seconds when it's changed continuously.
+    /// Writes are spread over slots, and starving rewrite of `run` writes each section in turn.
+    pub const fn worst_lifetime_secs(&self, section: &NvSectionInfo) -> u64 {
+        let interval = self.min_interval as u64;
+        let starving = STARVING_REWRITE_SECS * SECTION_NUM as u64;
 
-        cb.control_mut(self.section).set_dirty();
+        // writes per second is `1 / interval + 1 / starving`
+        section.slot_num as u64 * EEPROM_ENDURANCE * interval * starving / (interval + starving)
     }
 }
 
-impl NovellaRw for NovellaSelector<CardReaderPortBackup> {
-    type InnerType = CardReaderPortBackup;
-    async fn lock_read(
-        &self,
-        mutex: &Mutex<ThreadModeRawMutex, NovellaModuleControlBlock>,
-    ) -> Self::InnerType {
-        let cb = mutex.lock().await;
+/// Shortest `NvWritePolicy::worst_lifetime_secs` of sections
+const NOVELLA_LIFETIME_WORST_SECS: u64 = {
+    let mut ret = u64::MAX;
+    let mut idx = 0;
 
-        match self.section {
-            NvMemSectionKind::CardPortBackup => cb.data.card_reader_port_backup.clone(),
-            _ => {
-                should_not_happen();
-            }
+    while idx < SECTION_NUM {
+        let lifetime = WRITE_POLICY_TABLE[idx].worst_lifetime_secs(&SECTION_TABLE[idx]);
+        if lifetime < ret {
+            ret = lifetime;
         }
+        idx += 1;
     }
 
-    async fn lock_write(
-        &self,
-        mutex: &Mutex<ThreadModeRawMutex, NovellaModuleControlBlock>,
-        src: Self::InnerType,
-    ) {
-        let mut cb = mutex.lock().await;
-
-        match self.section {
-            NvMemSectionKind::CardPortBackup => {
-                cb.data.card_reader_port_backup = src;
-            }
-            _ => {
-                should_not_happen();
-            }
-        };
-
-        cb.control_mut(self.section).set_dirty();
-    }
-
-    async fn lock_write_zero(&self, mutex: &Mutex<ThreadModeRawMutex, NovellaModuleControlBlock>) {
-        let mut cb = mutex.lock().await;
+    ret
+};
 
-        *(match self.section {
-            NvMemSectionKind::CardPortBackup => &mut cb.data.card_reader_port_backup,
-            _ => {
-                should_not_happen();
-            }
-        }) = Self::InnerType::zeroed();
+/// Seconds since boot, for `NovellaSectionWriteState`
+fn boot_secs() -> u32 {
+    Instant::now().as_secs() as u32
+}
 
-        cb.control_mut(self.section).set_dirty();
-    }
+/// Pending changes and writes of a section for `NvWritePolicy`, times are `boot_secs`
+#[derive(Zeroable, Clone, Copy)]
+pub struct NovellaSectionWriteState {
+    /// Changes coalesced since last write
+    pending: u16,
+    first_change: u32,
+    last_change: u32,
+    /// Next write by `min_interval`, `burst` allows writes ahead of it
+    next_write: u32,
+    /// Slot writes since boot
+    writes: u32,
 }
 
-impl NovellaSectionControlBlock {
-    fn set_dirty(&mut self) {
-        self.inner |= 1 << 7;
+impl NovellaSectionWriteState {
+    fn on_change(&mut self, now: u32) {
+        if self.pending == 0 {
+            self.first_change = now;
+        }
+        self.pending = self.pending.saturating_add(1);
+        self.last_change = now;
     }
 
-    fn clr_dirty(&mut self) {
-        self.inner &= !(1 << 7);
+    /// Failed write is counted too, it wears the page as well
+    fn on_write(&mut self) {
+        self.pending = 0;
+        self.writes = self.writes.saturating_add(1);
     }
 
-    fn is_dirty(&self) -> bool {
-        (self.inner & (1 << 7)) != 0
+    /// Write by `should_flush`, it takes `min_interval` from the budget
+    fn on_flush(&mut self, policy: &NvWritePolicy, now: u32) {
+        self.next_write = self.next_write.max(now) + policy.min_interval as u32;
+        self.on_write();
     }
 
-    fn set_robin(&mut self, robin: RawNvRobin) {
-        self.inner = (self.inner & (1 << 7)) | (robin & !(1 << 7))
-    }
+    /// Dirty section should be written now
+    fn should_flush(&self, policy: &NvWritePolicy, now: u32) -> bool {
+        let since = |secs: u32| now.saturating_sub(secs);
 
-    fn force_robin(&mut self, section_info: &NvSectionInfo) -> RawNvRobin {
-        let ret = (section_info.slot_num - 1) & (self.inner + 1);
-        self.inner = ret | (1 << 7);
-        self.set_dirty();
-        ret
-    }
+        let ahead = policy.burst.saturating_sub(1) as u32 * policy.min_interval as u32;
+        let allowed = self.next_write <= now + ahead;
+        let budget = (policy.idle as u32 <= since(self.last_change))
+            || (policy.max_delay as u32 <= since(self.first_change))
+            || (policy.max_pending <= self.pending);
 
-    fn test_and_robin(&mut self, section_info: &NvSectionInfo) -> Option<RawNvRobin> {
-        if !self.is_dirty() {
-            None
-        } else {
-            Some(self.force_robin(section_info))
-        }
+        allowed && budget
     }
 }
 
-// this should be store as const type
-#[repr(C)]
-struct NvSectionInfo {
-    sect_start_page: u16, // sometimes it's u16, real_offset / 128 (r>>7)
-    slot_num: u8,
-    slot_size: u8,
-    real_data_size: u8,
+/// Slot writes of each section since boot, and EEPROM lifetime projected by them
+#[derive(defmt::Format)]
+pub struct NovellaWear {
+    pub writes: [u32; SECTION_NUM],
+    /// Days until the busiest section wears out in current write rate, None before any write
+    pub projected_days: Option<u32>,
 }
 
-#[rustfmt::skip]
- const SECTION_TABLE: [NvSectionInfo; 8] = [
-     NvSectionInfo{sect_start_page :    0, slot_num : 16, slot_size : 1, real_data_size :  4 },
-     NvSectionInfo{sect_start_page :  256, slot_num : 16, slot_size : 1, real_data_size :  4 },
-     NvSectionInfo{sect_start_page :  512, slot_num : 16, slot_size : 1, real_data_size :  4 },
-     NvSectionInfo{sect_start_page :  768, slot_num : 16, slot_size : 1, real_data_size :  4 },
-     NvSectionInfo{sect_start_page : 1024, slot_num :  8, slot_size : 1, real_data_size :  6 },
-     NvSectionInfo{sect_start_page : 1152, slot_num :  8, slot_size : 1, real_data_size :  4 },
-     NvSectionInfo{sect_start_page : 1280, slot_num :  8, slot_size : 2, real_data_size : 13 },
-     NvSectionInfo{sect_start_page : 1536, slot_num :  8, slot_size : 3, real_data_size : 32 },
- ];
-
-const PAGE_SIZE: usize = 16;
+const PAGE_SIZE: usize = novella_layout::PAGE_SIZE;
 const PAGE_SHIFT: usize = 4;
-const SECTION_NUM: usize = 8;
 const ROM_7B_ADDRESS: u8 = 0b1010000; // Embassy require 7bits address as parameter.
                                       // const ROM_ADDRESS_FIELD_SIZE: usize = core::mem::size_of::<u8>();
 const CHECKSUM_SIZE: usize = core::mem::size_of::<Checksum>();
 const UPTIME_SIZE: usize = core::mem::size_of::<Duration>();
-const TOTAL_SLOT_NUM: usize = 96; // should be calculated in compile time
+const TOTAL_SLOT_NUM: usize = novella_layout::total_slot_num(&SECTION_TABLE);
 const TOTAL_SLOT_ARR_LEN: usize =
-    (TOTAL_SLOT_NUM + core::mem::size_of::<u8>() - 1) / (core::mem::size_of::<u8>() * 8);
-const EEPROM_SIZE: RawRomAddress = 2048;
+    (TOTAL_SLOT_NUM + core::mem::size_of::<u8>() * 8 - 1) / (core::mem::size_of::<u8>() * 8);
+const EEPROM_SIZE: RawRomAddress = novella_layout::EEPROM_SIZE;
 const EEPROM_PAGE_MAX: RawRomAddress = (EEPROM_SIZE >> PAGE_SHIFT) as RawRomAddress;
 
 pub struct NovellaModuleControlBlock {
     data: MemStorage,
     controls: [NovellaSectionControlBlock; SECTION_NUM],
+    writes: [NovellaSectionWriteState; SECTION_NUM],
 }
 
 unsafe impl Zeroable for NovellaModuleControlBlock {
@@ -531,6 +641,7 @@ unsafe impl Zeroable for NovellaModuleControlBlock {
         Self {
             data: MemStorage::zeroed(),
             controls: [NovellaSectionControlBlock::zeroed(); SECTION_NUM],
+            writes: [NovellaSectionWriteState::zeroed(); SECTION_NUM],
         }
     }
 }
@@ -541,43 +652,6 @@ impl NovellaModuleControlBlock {
         unsafe { const_zero::const_zero!(NovellaModuleControlBlock) }
     }
 
-    unsafe fn get_data_raw_slice(&mut self, kind: NvMemSectionKind) -> &mut [u8] {
-        match kind {
-            NvMemSectionKind::P1CardCnt => core::slice::from_raw_parts_mut(
-                (self.data.p1_card_cnt.borrow_mut() as *mut _) as *mut u8,
-                core::mem::size_of_val(&self.data.p1_card_cnt),
-            ),
-            NvMemSectionKind::P2CardCnt => core::slice::from_raw_parts_mut(
-                (self.data.p2_card_cnt.borrow_mut() as *mut _) as *mut u8,
-                core::mem::size_of_val(&self.data.p2_card_cnt),
-            ),
-            NvMemSectionKind::P1CoinCnt => core::slice::from_raw_parts_mut(
-                (self.data.p1_coin_cnt.borrow_mut() as *mut _) as *mut u8,
-                core::mem::size_of_val(&self.data.p1_coin_cnt),
-            ),
-            NvMemSectionKind::P2CoinCnt => core::slice::from_raw_parts_mut(
-                (self.data.p2_coin_cnt.borrow_mut() as *mut _) as *mut u8,
-                core::mem::size_of_val(&self.data.p2_coin_cnt),
-            ),
-            NvMemSectionKind::FaultLog => core::slice::from_raw_parts_mut(
-                (self.data.fault_log.borrow_mut() as *mut _) as *mut u8,
-                core::mem::size_of_val(&self.data.fault_log),
-            ),
-            NvMemSectionKind::HwBootCount => core::slice::from_raw_parts_mut(
-                (self.data.hw_boot_cnt.borrow_mut() as *mut _) as *mut u8,
-                core::mem::size_of_val(&self.data.hw_boot_cnt),
-            ),
-            NvMemSectionKind::TerminalId => core::slice::from_raw_parts_mut(
-                (self.data.raw_terminal.borrow_mut() as *mut _) as *mut u8,
-                core::mem::size_of_val(&self.data.raw_terminal),
-            ),
-            NvMemSectionKind::CardPortBackup => core::slice::from_raw_parts_mut(
-                (self.data.card_reader_port_backup.borrow_mut() as *mut _) as *mut u8,
-                core::mem::size_of_val(&self.data.card_reader_port_backup),
-            ),
-        }
-    }
-
     fn control_mut(&mut self, kind: NvMemSectionKind) -> &mut NovellaSectionControlBlock {
         // It's fine with get_unchecked_mut, instead of `get_mut(...) -> Option<I>`.
         // KindT enum is directly limited on element number of internnaly array.
@@ -599,6 +673,8 @@ pub enum NovellaInitOk {
 pub enum NovellaInitError {
     FirstBoot,
     MissingEeprom,
+    /// Layout version is written by newer firmware, EEPROM is not written until next boot
+    UnknownLayout(u8),
     // FaultChecksum,
 }
 
@@ -609,10 +685,14 @@ pub enum NovellaReadError {
     Unknown,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, defmt::Format)]
 pub enum NovellaWriteError {
     Wearout,
     MissingEeprom,
+    /// Memory storage is locked by other task
+    Busy,
+    /// Layout is unknown, contents of newer firmware are kept
+    ReadOnly,
     Unknown,
 }
 
@@ -622,6 +702,10 @@ pub struct Novella {
     crc: UnsafeCell<Crc<'static>>, // crc will be mutexed for reuse HwConfig
     buffer: UnsafeCell<[u8; PAGE_SIZE + core::mem::size_of::<EepromAddress>()]>,
     uptime: UnsafeCell<Duration>,
+    /// Eeprom didn't respond on last access
+    missing: UnsafeCell<bool>,
+    /// Layout is unknown on init, nothing is written
+    read_only: UnsafeCell<bool>,
     mem_storage: Mutex<ThreadModeRawMutex, NovellaModuleControlBlock>,
 }
 
@@ -640,9 +724,27 @@ impl Novella {
             buffer: UnsafeCell::new([0u8; PAGE_SIZE + core::mem::size_of::<EepromAddress>()]),
             mem_storage: Mutex::new(NovellaModuleControlBlock::const_default()),
             uptime: UnsafeCell::new(Duration::from_ticks(0)),
+            missing: UnsafeCell::new(false),
+            read_only: UnsafeCell::new(false),
+        }
+    }
+
+    /// Eeprom didn't respond on init or last write, it's recovered on next successful write
+    pub fn is_missing(&self) -> bool {
+        unsafe { *self.missing.get() }
+    }
+
+    fn set_missing(&self, missing: bool) {
+        unsafe {
+            *self.missing.get() = missing;
         }
     }
 
+    /// EEPROM is written by newer firmware, see `NovellaInitError::UnknownLayout`
+    pub fn is_read_only(&self) -> bool {
+        unsafe { *self.read_only.get() }
+    }
+
     pub async fn lock_read<R>(&self, slot: R) -> R::InnerType
     where
         R: NovellaRw,
@@ -664,6 +766,54 @@ impl Novella {
         slot.lock_write_zero(&self.mem_storage).await
     }
 
+    /// Income counter of the player, player 3 and 4 are in `EXTRA_PLAYER_CNT`.
+    /// `Player::Undefined` is treated as player 1.
+    pub async fn lock_read_income(&self, kind: MeterKind, player: Player) -> u32 {
+        match (kind, player) {
+            (_, Player::Player3 | Player::Player4) => {
+                let extra = self.lock_read(select::EXTRA_PLAYER_CNT).await;
+                extra.get(kind, player)
+            }
+            (MeterKind::Card, x) => self.lock_read(select::card_cnt(x)).await,
+            (MeterKind::Coin, x) => self.lock_read(select::coin_cnt(x)).await,
+        }
+    }
+
+    /// Write income counter of the player, see `lock_read_income`
+    pub async fn lock_write_income(&self, kind: MeterKind, player: Player, count: u32) {
+        match (kind, player) {
+            (_, Player::Player3 | Player::Player4) => {
+                let mut extra = self.lock_read(select::EXTRA_PLAYER_CNT).await;
+                extra.set(kind, player, count);
+                self.lock_write(select::EXTRA_PLAYER_CNT, extra).await;
+            }
+            (MeterKind::Card, x) => self.lock_write(select::card_cnt(x), count).await,
+            (MeterKind::Coin, x) => self.lock_write(select::coin_cnt(x), count).await,
+        }
+    }
+
+    /// Income counters of every player of the board summed into player 1 and 2 by parity,
+    /// for card terminal screen that shows two players. See `Player::fold`.
+    pub async fn lock_read_income_pair(&self, kind: MeterKind) -> [u32; 2] {
+        let mut ret = [0u32; 2];
+
+        for player in PLAYERS {
+            let idx = player.fold().index().unwrap_or(0);
+            ret[idx] = ret[idx].wrapping_add(self.lock_read_income(kind, player).await);
+        }
+
+        ret
+    }
+
+    /// Clear income counters of every player
+    pub async fn lock_write_zero_income(&self) {
+        self.lock_write_zero(select::P1_CARD_CNT).await;
+        self.lock_write_zero(select::P2_CARD_CNT).await;
+        self.lock_write_zero(select::P1_COIN_CNT).await;
+        self.lock_write_zero(select::P2_COIN_CNT).await;
+        self.lock_write_zero(select::EXTRA_PLAYER_CNT).await;
+    }
+
     #[inline]
     fn consider_initial_uptime(page_idx: u8) -> bool {
         page_idx == 0
@@ -674,11 +824,8 @@ impl Novella {
         slot_size == (page_idx + 1)
     }
 
-    fn get_raw_addr(sect_idx: usize, slot_idx: u8, page_idx: u8) -> RawRomAddress {
-        SECTION_TABLE[sect_idx].sect_start_page
-            + (SECTION_TABLE[sect_idx].slot_size as RawRomAddress * slot_idx as RawRomAddress
-                + page_idx as RawRomAddress)
-                * PAGE_SIZE as RawRomAddress
+    fn get_raw_addr(section: &NvSectionInfo, slot_idx: u8, page_idx: u8) -> RawRomAddress {
+        section.slot_addr(slot_idx) + page_idx as RawRomAddress * PAGE_SIZE as RawRomAddress
     }
 
     fn set_write_protect(&self) {
@@ -697,6 +844,18 @@ impl Novella {
         cb: &mut MutexGuard<'_, ThreadModeRawMutex, NovellaModuleControlBlock>,
         kind: NvMemSectionKind,
         slot_idx: u8,
+    ) -> Result<Duration, NovellaReadError> {
+        let slot_mem = unsafe { cb.get_data_raw_slice(kind) };
+
+        self.raw_slot_read_at(&SECTION_TABLE[kind as usize], slot_idx, slot_mem)
+    }
+
+    /// Read a slot of the section on `slot_mem`, the section can be one of older layout.
+    fn raw_slot_read_at(
+        &self,
+        section: &NvSectionInfo,
+        slot_idx: u8,
+        slot_mem: &mut [u8],
     ) -> Result<Duration, NovellaReadError> {
         let bus = unsafe { &mut *self.bus.get() };
         let crc = unsafe { &mut *self.crc.get() };
@@ -709,23 +868,20 @@ impl Novella {
 
         crc.reset();
 
-        let sect_idx: usize = kind as u8 as usize;
-        let slot_size = SECTION_TABLE[sect_idx].slot_size;
+        let slot_size = section.slot_size;
 
-        assert!(SECTION_TABLE[sect_idx].slot_num > slot_idx); // should not happens
+        assert!(section.slot_num > slot_idx); // should not happens
 
         // let mut cb = self.mem_storage.lock().await;
         // <- MUTEX SECTION START FROM HERE ->
 
-        let slot_mem = unsafe { cb.get_data_raw_slice(kind) };
-
-        let mut real_data_left = SECTION_TABLE[sect_idx].real_data_size as usize;
+        let mut real_data_left = section.real_data_size as usize;
         let mut slot_uptime = Duration::from_ticks(0);
         let mut checksum_expected: u16 = 0;
 
         for page_idx in 0..slot_size {
             // #[cfg(i2c_addr_bits_include_msb)]
-            let raw_addr = Self::get_raw_addr(sect_idx, slot_idx, page_idx);
+            let raw_addr = Self::get_raw_addr(section, slot_idx, page_idx);
 
             let data_address_slice = (raw_addr as EepromAddress).to_be_bytes();
             let i2c_address = ROM_7B_ADDRESS | ((raw_addr >> 8) as DevSelAddress & 0x7);
@@ -762,7 +918,7 @@ impl Novella {
 
             let size_can_read = max_real_data_in_page.min(real_data_left);
 
-            let slot_mem_start = SECTION_TABLE[sect_idx].real_data_size as usize - real_data_left;
+            let slot_mem_start = section.real_data_size as usize - real_data_left;
             let dst = &mut slot_mem[slot_mem_start..slot_mem_start + size_can_read];
             let src = &rx_buffer[start_read..start_read + size_can_read];
 
@@ -821,7 +977,7 @@ impl Novella {
             // Set eeprom data address
             // #[cfg(i2c_addr_bits_include_msb)]
 
-            let raw_addr = Self::get_raw_addr(sect_idx, slot_idx, page_idx);
+            let raw_addr = Self::get_raw_addr(&SECTION_TABLE[sect_idx], slot_idx, page_idx);
 
             addr_buffer.copy_from_slice(&((raw_addr & 0xFF) as u8).to_be_bytes());
 
@@ -910,7 +1066,7 @@ impl Novella {
             // Set eeprom data address
             // #[cfg(i2c_addr_bits_include_msb)]
 
-            let raw_addr = Self::get_raw_addr(sect_idx, slot_idx, page_idx);
+            let raw_addr = Self::get_raw_addr(&SECTION_TABLE[sect_idx], slot_idx, page_idx);
 
             addr_buffer.copy_from_slice(&((raw_addr & 0xFF) as u8).to_be_bytes());
 
@@ -983,6 +1139,19 @@ impl Novella {
         kind: NvMemSectionKind,
         slot_idx: u8,
         uptime: Duration,
+    ) -> Result<(), NovellaWriteError> {
+        let slot_mem = unsafe { cb.get_data_raw_slice(kind) };
+
+        self.raw_slot_write_at(&SECTION_TABLE[kind as usize], slot_idx, uptime, slot_mem)
+    }
+
+    /// Write `slot_mem` on a slot of the section, the section can be one of older layout.
+    fn raw_slot_write_at(
+        &self,
+        section: &NvSectionInfo,
+        slot_idx: u8,
+        uptime: Duration,
+        slot_mem: &[u8],
     ) -> Result<(), NovellaWriteError> {
         let bus = unsafe { &mut *self.bus.get() };
         let crc = unsafe { &mut *self.crc.get() };
@@ -991,16 +1160,14 @@ impl Novella {
         let mut data_buffer =
             unsafe { &mut (&mut *self.buffer.get())[core::mem::size_of::<EepromAddress>()..] };
 
-        let sect_idx: usize = kind as u8 as usize;
-        let slot_size = SECTION_TABLE[sect_idx].slot_size;
+        let slot_size = section.slot_size;
 
-        assert!(SECTION_TABLE[sect_idx].slot_num > slot_idx); // should not happens
+        assert!(section.slot_num > slot_idx); // should not happens
 
         // let mut cb = self.mem_storage.lock().await;
         // <- MUTEX SECTION START FROM HERE ->
         // JUST COPIED
-        let mut real_data_left = SECTION_TABLE[sect_idx].real_data_size as usize;
-        let slot_mem = unsafe { cb.get_data_raw_slice(kind) };
+        let mut real_data_left = section.real_data_size as usize;
 
         let mut checksum_expected: Checksum = 0;
         crc.reset();
@@ -1012,7 +1179,7 @@ impl Novella {
             // Set eeprom data address
             // #[cfg(i2c_addr_bits_include_msb)]
 
-            let raw_addr = Self::get_raw_addr(sect_idx, slot_idx, page_idx);
+            let raw_addr = Self::get_raw_addr(section, slot_idx, page_idx);
 
             addr_buffer.copy_from_slice(&((raw_addr & 0xFF) as u8).to_be_bytes());
 
@@ -1046,7 +1213,7 @@ impl Novella {
 
             let size_can_write = max_real_data_in_page.min(real_data_left);
 
-            let slot_mem_start = SECTION_TABLE[sect_idx].real_data_size as usize - real_data_left;
+            let slot_mem_start = section.real_data_size as usize - real_data_left;
             let dst = &mut data_buffer[start_write..start_write + size_can_write];
             let src = &slot_mem[slot_mem_start..slot_mem_start + size_can_write];
 
@@ -1095,14 +1262,14 @@ impl Novella {
         // change buffer address for align issue
         let mut addr_buffer = unsafe { &mut (&mut *self.buffer.get())[PAGE_SIZE..] };
         let mut data_buffer = unsafe { &mut (&mut *self.buffer.get())[..PAGE_SIZE] };
-        let mut real_data_left = SECTION_TABLE[sect_idx].real_data_size as usize;
+        let mut real_data_left = section.real_data_size as usize;
         crc.reset();
 
         for page_idx in 0..slot_size {
             // Set eeprom data address
             // #[cfg(i2c_addr_bits_include_msb)]
 
-            let raw_addr = Self::get_raw_addr(sect_idx, slot_idx, page_idx);
+            let raw_addr = Self::get_raw_addr(section, slot_idx, page_idx);
 
             addr_buffer.copy_from_slice(&((raw_addr & 0xFF) as u8).to_be_bytes());
 
@@ -1148,7 +1315,7 @@ impl Novella {
 
             let size_can_read = max_real_data_in_page.min(real_data_left);
 
-            let slot_mem_start = SECTION_TABLE[sect_idx].real_data_size as usize - real_data_left;
+            let slot_mem_start = section.real_data_size as usize - real_data_left;
             let src = &data_buffer[start_read..start_read + size_can_read];
 
             checksum_double_expected = crc.feed_bytes(src) as Checksum;
@@ -1170,6 +1337,53 @@ impl Novella {
         // <- MUTEX SECTION END HERE ->
     }
 
+    fn raw_header_read(&self) -> Result<[u8; PAGE_SIZE], NovellaReadError> {
+        let bus = unsafe { &mut *self.bus.get() };
+        let mut raw = [0u8; PAGE_SIZE];
+        let i2c_address = ROM_7B_ADDRESS | ((HEADER_ADDR >> 8) as DevSelAddress & 0x7);
+
+        bus.blocking_write_read(
+            i2c_address,
+            &(HEADER_ADDR as EepromAddress).to_be_bytes(),
+            &mut raw,
+        )
+        .map_err(|e| match e {
+            embassy_stm32::i2c::Error::Timeout | embassy_stm32::i2c::Error::Nack => {
+                NovellaReadError::MissingEeprom
+            }
+            _ => NovellaReadError::Unknown,
+        })?;
+
+        Ok(raw)
+    }
+
+    fn raw_header_write(&self, raw: &[u8; PAGE_SIZE]) -> Result<(), NovellaWriteError> {
+        let bus = unsafe { &mut *self.bus.get() };
+        let buffer = unsafe { &mut *self.buffer.get() };
+        let i2c_address = ROM_7B_ADDRESS | ((HEADER_ADDR >> 8) as DevSelAddress & 0x7);
+
+        buffer[..core::mem::size_of::<EepromAddress>()]
+            .copy_from_slice(&(HEADER_ADDR as EepromAddress).to_be_bytes());
+        buffer[core::mem::size_of::<EepromAddress>()..].copy_from_slice(raw);
+
+        self.clr_write_protect();
+        let result = bus.blocking_write(i2c_address, buffer);
+        self.set_write_protect();
+        delay_write_time_blocking();
+
+        if let Err(e) = result {
+            defmt::error!("blocking_write_timeout : {:?}", e);
+        }
+
+        // Read for check
+        match self.raw_header_read() {
+            Ok(given) if given == *raw => Ok(()),
+            Ok(_) => Err(NovellaWriteError::Wearout),
+            Err(NovellaReadError::MissingEeprom) => Err(NovellaWriteError::MissingEeprom),
+            Err(_) => Err(NovellaWriteError::Unknown),
+        }
+    }
+
     /// when success return marked last time
     /// Success to detect eeprom but it's filled in 0xFF or 0xFF are initial factory value, return NovellaInitError::FirstBoot
     /// initialization is not using async/await for safety
@@ -1184,6 +1398,29 @@ impl Novella {
             slot_size == page_idx - 1
         }
 
+        // Contents of older layout are moved before reading, otherwise they're healed to zero
+        match novella_layout::migrate(&mut NovellaMigration(self)) {
+            Ok(NvMigrateOk::UpToDate) => {}
+            Ok(NvMigrateOk::Migrated(version, rewritten)) => {
+                defmt::warn!(
+                    "Novella layout is migrated from version {}, rewritten sections : {}",
+                    version,
+                    rewritten
+                );
+            }
+            Err(NvMigrateError::UnknownLayout(version)) => {
+                defmt::error!("Unknown Novella layout version {}, keep it as is", version);
+                unsafe {
+                    *self.read_only.get() = true;
+                }
+                return Err(NovellaInitError::UnknownLayout(version));
+            }
+            Err(NvMigrateError::Storage(e)) => {
+                self.set_missing(true);
+                return Err(e);
+            }
+        }
+
         let bus = unsafe { &mut *self.bus.get() };
         let crc = unsafe { &mut *self.crc.get() };
         let buffer: &mut [u8] = unsafe { &mut *self.buffer.get() };
@@ -1224,6 +1461,7 @@ impl Novella {
                         broken_detected += 1;
                     }
                     Err(NovellaReadError::MissingEeprom) => {
+                        self.set_missing(true);
                         return Err(NovellaInitError::MissingEeprom);
                     }
                 }
@@ -1279,6 +1517,7 @@ impl Novella {
                     match self.raw_slot_write(&mut cb, kind, slot_idx, renew_uptime) {
                         Ok(_) => {}
                         Err(NovellaWriteError::MissingEeprom) => {
+                            self.set_missing(true);
                             return Err(NovellaInitError::MissingEeprom);
                         }
                         Err(e) => {
@@ -1340,6 +1579,59 @@ impl Novella {
         self.set_write_protect();
     }
 
+    /// Record fault log immediately without waiting for the `run` loop.
+    /// This is for the moment right before reset, thus doesn't use async/await.
+    pub fn try_blocking_record_fault(&self, error_code: u16) -> Result<(), NovellaWriteError> {
+        if self.is_read_only() {
+            return Err(NovellaWriteError::ReadOnly);
+        }
+
+        let kind = NvMemSectionKind::FaultLog;
+        let sect_idx = kind as usize;
+        let mut cb = self
+            .mem_storage
+            .try_lock()
+            .map_err(|_| NovellaWriteError::Busy)?;
+
+        cb.data.fault_log = FaultLog {
+            current_boot_cnt: cb.data.hw_boot_cnt,
+            error_code,
+        };
+
+        let next_slot = cb.controls[sect_idx].force_robin(&SECTION_TABLE[sect_idx]);
+        let result = self.raw_slot_write(&mut cb, kind, next_slot, self.get_uptime());
+        cb.writes[sect_idx].on_write();
+
+        if result.is_ok() {
+            cb.controls[sect_idx].clr_dirty();
+        }
+
+        result
+    }
+
+    /// Slot writes of each section since boot and lifetime projected by them
+    pub async fn lock_wear(&self) -> NovellaWear {
+        let cb = self.mem_storage.lock().await;
+        let elapsed = Instant::now().as_secs();
+        let mut writes = [0u32; SECTION_NUM];
+        let mut projected_secs: Option<u64> = None;
+
+        for (sect_idx, state) in cb.writes.iter().enumerate() {
+            writes[sect_idx] = state.writes;
+
+            if state.writes != 0 {
+                let slot_num = SECTION_TABLE[sect_idx].slot_num as u64;
+                let lifetime = slot_num * EEPROM_ENDURANCE * elapsed / state.writes as u64;
+                projected_secs = Some(projected_secs.map_or(lifetime, |x| x.min(lifetime)));
+            }
+        }
+
+        NovellaWear {
+            writes,
+            projected_days: projected_secs.map(|x| (x / (24 * 3600)).min(u32::MAX as u64) as u32),
+        }
+    }
+
     /// Get uptime of this board
     pub fn get_uptime(&self) -> Duration {
         Duration::from_ticks(unsafe { *self.uptime.get() }.as_ticks() + Instant::now().as_ticks())
@@ -1363,7 +1655,9 @@ impl Novella {
                     // try next time and slot
                     defmt::error!("Wearout, T_T try next slot later");
                 }
-                Err(NovellaWriteError::Unknown) => {
+                Err(NovellaWriteError::Unknown)
+                | Err(NovellaWriteError::Busy)
+                | Err(NovellaWriteError::ReadOnly) => {
                     // try next time and slot
                     defmt::error!("Unknown, ?_? try next slot later");
                 }
@@ -1372,14 +1666,38 @@ impl Novella {
 
         let mut starving_kind = NvMemSectionKind::get_last();
         let mut every_2sec = 0u8;
+        let mut last_report = Instant::now();
+        let heartbeat = HEARTBEAT.register(HeartbeatKind::Novella);
+
+        defmt::info!(
+            "Novella worst case lifetime : {} days",
+            NOVELLA_LIFETIME_WORST_SECS / (24 * 3600)
+        );
 
         loop {
+            HEARTBEAT.beat(heartbeat);
+
+            if self.is_read_only() {
+                Timer::after(Duration::from_secs(RUN_PERIOD_SECS)).await;
+                continue;
+            }
+
+            if Duration::from_secs(WEAR_REPORT_SECS) <= (Instant::now() - last_report) {
+                last_report = Instant::now();
+                defmt::info!("Novella wear : {}", self.lock_wear().await);
+            }
+
             #[allow(clippy::needless_range_loop)]
             for sect_idx in 0..SECTION_TABLE.len() {
                 // for (sect_idx, section) in SECTION_TABLE.iter().enumerate() {
                 let kind = NvMemSectionKind::from(sect_idx as u8);
                 let mut cb = self.mem_storage.lock().await;
 
+                // changes are coalesced until the policy allows
+                if !cb.writes[sect_idx].should_flush(&WRITE_POLICY_TABLE[sect_idx], boot_secs()) {
+                    continue;
+                }
+
                 let dirty_or_next_slot =
                     cb.controls[sect_idx].test_and_robin(&SECTION_TABLE[sect_idx]);
                 // let dirty_or_next_slot = cb.controls[sect_idx].test_and_robin(section);
@@ -1397,12 +1715,14 @@ impl Novella {
                     let result = self
                         .raw_slot_write_nonblocking(&mut cb, kind, next_slot, new_uptime)
                         .await;
+                    cb.writes[sect_idx].on_flush(&WRITE_POLICY_TABLE[sect_idx], boot_secs());
+                    self.set_missing(result == Err(NovellaWriteError::MissingEeprom));
                     after_write(result, sect_idx, &mut cb);
                     every_2sec = 0;
                 }
             }
 
-            if every_2sec >= 30 {
+            if every_2sec as u64 >= STARVING_REWRITE_SECS / RUN_PERIOD_SECS {
                 starving_kind = starving_kind.next().unwrap_or(NvMemSectionKind::from(0));
                 let mut cb = self.mem_storage.lock().await;
 
@@ -1420,6 +1740,8 @@ impl Novella {
                 let result = self
                     .raw_slot_write_nonblocking(&mut cb, starving_kind, force_next_slot, new_uptime)
                     .await;
+                cb.writes[starving_kind as usize].on_write();
+                self.set_missing(result == Err(NovellaWriteError::MissingEeprom));
                 after_write(result, starving_kind as usize, &mut cb);
 
                 every_2sec = 0;
@@ -1427,7 +1749,58 @@ impl Novella {
                 every_2sec += 1;
             }
 
-            Timer::after(Duration::from_secs(2)).await;
+            Timer::after(Duration::from_secs(RUN_PERIOD_SECS)).await;
+        }
+    }
+}
+
+/// Raw access of Novella for `novella_layout::migrate` on init
+struct NovellaMigration<'a>(&'a Novella);
+
+impl NvStorage for NovellaMigration<'_> {
+    type Error = NovellaInitError;
+
+    fn read_slot(
+        &mut self,
+        section: &NvSectionInfo,
+        slot_idx: u8,
+        data: &mut [u8],
+    ) -> Result<Option<u64>, Self::Error> {
+        match self.0.raw_slot_read_at(section, slot_idx, data) {
+            Ok(uptime) => Ok(Some(uptime.as_ticks())),
+            Err(NovellaReadError::MissingEeprom) => Err(NovellaInitError::MissingEeprom),
+            Err(_) => Ok(None),
+        }
+    }
+
+    fn write_slot(
+        &mut self,
+        section: &NvSectionInfo,
+        slot_idx: u8,
+        uptime: u64,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        match self
+            .0
+            .raw_slot_write_at(section, slot_idx, Duration::from_ticks(uptime), data)
+        {
+            Err(NovellaWriteError::MissingEeprom) => Err(NovellaInitError::MissingEeprom),
+            // broken slot is healed on init
+            _ => Ok(()),
+        }
+    }
+
+    fn read_header(&mut self) -> Result<[u8; PAGE_SIZE], Self::Error> {
+        self.0
+            .raw_header_read()
+            .map_err(|_| NovellaInitError::MissingEeprom)
+    }
+
+    fn write_header(&mut self, raw: &[u8; PAGE_SIZE]) -> Result<(), Self::Error> {
+        match self.0.raw_header_write(raw) {
+            Err(NovellaWriteError::MissingEeprom) => Err(NovellaInitError::MissingEeprom),
+            // migrated again on next boot
+            _ => Ok(()),
         }
     }
 }
@@ -0,0 +1,69 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Serial firmware update through card reader UART, frames are defined in `fw-update` crate.
+//! Update session is served by bootloader, application only programs update request
+//! on boot state page and resets. Bootloader receives new image directly on active partition,
+//! thus the application doesn't carry signature verification and DFU partition.
+
+#![cfg_attr(not(feature = "fw_update"), allow(dead_code))]
+
+use core::cell::RefCell;
+
+use embassy_stm32::flash::{Blocking, Flash};
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embedded_storage::nor_flash::NorFlash;
+use fw_update::{UPDATE_REQUEST_MAGIC, UPDATE_REQUEST_OFFSET};
+
+// Defined in `memory/fw-update.x`, offsets from start of flash
+#[cfg(feature = "fw_update")]
+extern "C" {
+    static __bootloader_state_start: u32;
+}
+
+type FwFlash = Flash<'static, Blocking>;
+
+fn state_offset() -> u32 {
+    #[cfg(feature = "fw_update")]
+    return unsafe { &__bootloader_state_start as *const u32 as u32 };
+
+    #[cfg(not(feature = "fw_update"))]
+    0 // standalone layout doesn't have bootloader partitions
+}
+
+pub struct FwUpdate {
+    flash: Mutex<ThreadModeRawMutex, RefCell<FwFlash>>,
+}
+
+impl FwUpdate {
+    pub const fn new(flash: FwFlash) -> Self {
+        Self {
+            flash: Mutex::new(RefCell::new(flash)),
+        }
+    }
+
+    /// Enter update mode of bootloader, by `Hello` frame on card reader UART or SVC button gesture.
+    /// MCU is reset on success, uploader sends `Hello` again and bootloader answers it.
+    /// Bootloader boots this image again when uploader doesn't start a session.
+    pub fn request(&self) {
+        // Bootloader clears the request before booting application, thus it's still erased here
+        let result = self.flash.lock(|flash| {
+            flash.borrow_mut().write(
+                state_offset() + UPDATE_REQUEST_OFFSET as u32,
+                &UPDATE_REQUEST_MAGIC,
+            )
+        });
+
+        match result {
+            Ok(()) => {
+                defmt::warn!("Firmware update - reset to bootloader");
+                cortex_m::peripheral::SCB::sys_reset();
+            }
+            Err(e) => defmt::error!("Firmware update - failed to request update mode : {:?}", e),
+        }
+    }
+}
@@ -6,21 +6,40 @@
 
 use defmt::unwrap;
 use embassy_executor::Spawner;
+#[cfg(not(feature = "hotfix_hwbug_host_inhibit_floating"))]
 use embassy_stm32::exti::ExtiInput;
-use embassy_stm32::gpio::{AnyPin, Output};
+use embassy_stm32::gpio::AnyPin;
+#[cfg(feature = "hotfix_hwbug_host_inhibit_floating")]
+use embassy_stm32::gpio::Flex;
 
-use crate::semi_layer::buffered_opendrain::{buffered_opendrain_spawn, BufferedOpenDrain};
+use crate::semi_layer::buffered_opendrain::{
+    buffered_opendrain_spawn, BufferedOpenDrain, BufferedOpenDrainPin,
+};
 #[cfg(not(feature = "hotfix_hwbug_host_inhibit_floating"))]
-use crate::semi_layer::buffered_wait::buffered_wait_spawn;
-use crate::semi_layer::buffered_wait::{BufferedWait, InputEventChannel, RawInputPortKind};
+use crate::semi_layer::buffered_wait::{buffered_wait_spawn, BufferedWait};
+use crate::semi_layer::buffered_wait::{InputEventChannel, RawInputPortKind};
+#[cfg(feature = "hotfix_hwbug_host_inhibit_floating")]
+use crate::semi_layer::sampled_wait::{sampled_wait_spawn, SampledWait};
 use crate::semi_layer::timing::SharedToggleTiming;
 use crate::types::buffered_opendrain_kind::BufferedOpenDrainKind;
 use crate::types::input_port::InputPortKind;
 use crate::types::player::Player;
+use crate::types::port_polarity::PortPolarity;
+
+/// Inhibit input from GAME I/O PCB, EXTI is used when the line doesn't float
+#[cfg(not(feature = "hotfix_hwbug_host_inhibit_floating"))]
+pub type HostInhibitInput = ExtiInput<'static, AnyPin>;
+#[cfg(not(feature = "hotfix_hwbug_host_inhibit_floating"))]
+type HostInhibitWait = BufferedWait;
+
+/// Inhibit input from GAME I/O PCB, sampled with pull toggling for floating line (#19)
+#[cfg(feature = "hotfix_hwbug_host_inhibit_floating")]
+pub type HostInhibitInput = Flex<'static, AnyPin>;
+#[cfg(feature = "hotfix_hwbug_host_inhibit_floating")]
+type HostInhibitWait = SampledWait;
 
 pub struct HostSideBill {
-    #[cfg_attr(feature = "hotfix_hwbug_host_inhibit_floating", allow(unused))]
-    in_inhibit: BufferedWait,
+    in_inhibit: HostInhibitWait,
     pub out_busy: BufferedOpenDrain,
     pub out_vend: BufferedOpenDrain,
     pub out_jam: BufferedOpenDrain,
@@ -31,11 +50,11 @@ impl HostSideBill {
     #[allow(clippy::all)]
     pub const fn new(
         player: Player,
-        in_inhibit: ExtiInput<'static, AnyPin>,
-        out_busy: Output<'static, AnyPin>,
-        out_vend: Output<'static, AnyPin>,
-        out_jam: Output<'static, AnyPin>,
-        out_start: Output<'static, AnyPin>,
+        in_inhibit: HostInhibitInput,
+        out_busy: BufferedOpenDrainPin,
+        out_vend: BufferedOpenDrainPin,
+        out_jam: BufferedOpenDrainPin,
+        out_start: BufferedOpenDrainPin,
         mpsc_ch: &'static InputEventChannel,
         shared_timing: &'static SharedToggleTiming,
     ) -> Self {
@@ -47,7 +66,7 @@ impl HostSideBill {
         let start_str: &'static str = BufferedOpenDrainKind::HostSideOutStart(player).const_str();
 
         Self {
-            in_inhibit: BufferedWait::new(in_inhibit, mpsc_ch, inh_p, inh_str),
+            in_inhibit: HostInhibitWait::new(in_inhibit, mpsc_ch, inh_p, inh_str),
             out_busy: BufferedOpenDrain::new(out_busy, shared_timing, busy_str),
             out_vend: BufferedOpenDrain::new(out_vend, shared_timing, vend_str),
             out_jam: BufferedOpenDrain::new(out_jam, shared_timing, jam_str),
@@ -63,6 +82,36 @@ impl HostSideBill {
         self.out_start.set_level(start).await;
     }
 
+    pub fn set_polarity(&self, player: Player, polarity: &PortPolarity) {
+        let outputs = [
+            (
+                &self.out_busy,
+                BufferedOpenDrainKind::HostSideOutBusy(player),
+            ),
+            (
+                &self.out_vend,
+                BufferedOpenDrainKind::HostSideOutVend(player),
+            ),
+            (&self.out_jam, BufferedOpenDrainKind::HostSideOutJam(player)),
+            (
+                &self.out_start,
+                BufferedOpenDrainKind::HostSideOutStart(player),
+            ),
+        ];
+
+        for (out, kind) in outputs {
+            out.set_polarity(kind, polarity);
+        }
+
+        self.in_inhibit
+            .set_active_high(polarity.is_input_active_high(InputPortKind::Inhibit1P, player));
+    }
+
+    /// Disable inhibit input from GAME I/O, for floating port of unused player side.
+    pub fn set_inhibit_enabled(&self, enabled: bool) {
+        self.in_inhibit.set_enabled(enabled);
+    }
+
     pub fn start_tasks(&'static self, spawner: &Spawner) {
         unwrap!(spawner.spawn(buffered_opendrain_spawn(&self.out_busy)));
         unwrap!(spawner.spawn(buffered_opendrain_spawn(&self.out_vend)));
@@ -71,5 +120,8 @@ impl HostSideBill {
 
         #[cfg(not(feature = "hotfix_hwbug_host_inhibit_floating"))]
         unwrap!(spawner.spawn(buffered_wait_spawn(&self.in_inhibit)));
+
+        #[cfg(feature = "hotfix_hwbug_host_inhibit_floating")]
+        unwrap!(spawner.spawn(sampled_wait_spawn(&self.in_inhibit)));
     }
 }
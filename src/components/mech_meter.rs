@@ -0,0 +1,104 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Electromechanical coin meter (counter coil) driver.
+//! A counter coil needs minimum on/off time per tick, thus income is queued as pending ticks
+//! and drained sequentially, so burst of credits never lose any meter tick.
+
+#![cfg_attr(not(feature = "mech_meter"), allow(dead_code))]
+
+use core::cell::Cell;
+
+use embassy_futures::join::join;
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Timer};
+
+use crate::semi_layer::buffered_opendrain::{BufferedOpenDrain, BufferedOpenDrainPin};
+use crate::semi_layer::timing::{SharedToggleTiming, ToggleTiming};
+use crate::types::buffered_opendrain_kind::{BufferedOpenDrainKind, MeterKind};
+
+/// Conservative coil timing, most of counter coil requires 50 ms on/off at least.
+pub const METER_DEFAULT_TIMING: ToggleTiming = ToggleTiming {
+    high_ms: 100,
+    low_ms: 100,
+};
+
+pub struct MechMeter {
+    out_coil: BufferedOpenDrain,
+    /// Ticks that requested but not yet delivered to the coil
+    pending: Mutex<ThreadModeRawMutex, Cell<u32>>,
+    wake: Signal<ThreadModeRawMutex, ()>,
+}
+
+impl MechMeter {
+    pub const fn new(
+        kind: MeterKind,
+        out_coil: BufferedOpenDrainPin,
+        shared_timing: &'static SharedToggleTiming,
+    ) -> Self {
+        let coil_str: &'static str = BufferedOpenDrainKind::MechMeter(kind).const_str();
+
+        Self {
+            out_coil: BufferedOpenDrain::new(out_coil, shared_timing, coil_str),
+            pending: Mutex::new(Cell::new(0)),
+            wake: Signal::new(),
+        }
+    }
+
+    /// Queue meter ticks, doesn't wait for being reflected.
+    pub fn count(&self, ticks: u16) {
+        if ticks == 0 {
+            return;
+        }
+
+        self.pending
+            .lock(|x| x.set(x.get().saturating_add(ticks as u32)));
+        self.wake.signal(());
+    }
+
+    #[allow(dead_code)]
+    pub fn pending(&self) -> u32 {
+        self.pending.lock(|x| x.get())
+    }
+
+    async fn drain(&self) -> ! {
+        loop {
+            self.wake.wait().await;
+
+            loop {
+                // tick tock request only carries u8 count
+                let ticks = self.pending.lock(|x| {
+                    let ticks = x.get().min(u8::MAX as u32);
+                    x.set(x.get() - ticks);
+                    ticks as u8
+                });
+
+                if ticks == 0 {
+                    break;
+                }
+
+                let timing = self.out_coil.get_shared_timing();
+                self.out_coil.tick_tock(ticks).await;
+
+                // wait the coil finishing the whole sequence, left ticks stay in queue meanwhile
+                let elapse = (timing.high_ms as u64 + timing.low_ms as u64) * ticks as u64;
+                Timer::after(Duration::from_millis(elapse)).await;
+            }
+        }
+    }
+
+    async fn run(&self) {
+        join(self.out_coil.run(), self.drain()).await;
+    }
+}
+
+// Card and coin meter
+#[embassy_executor::task(pool_size = 2)]
+pub async fn mech_meter_spawn(instance: &'static MechMeter) {
+    instance.run().await
+}
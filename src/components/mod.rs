@@ -4,10 +4,15 @@
  * SPDX-License-Identifier: MIT OR Apache-2.0
  */
 
+pub(crate) mod board_link;
 pub(crate) mod dip_switch;
+pub(crate) mod dispenser;
+pub(crate) mod fw_update;
 pub(crate) mod host_side_bill;
+pub(crate) mod mech_meter;
 pub(crate) mod start_button;
 pub(crate) mod vend_side_bill;
+pub(crate) mod watchdog;
 
 pub(crate) mod serial_device;
 
@@ -4,20 +4,27 @@
  * SPDX-License-Identifier: MIT OR Apache-2.0
  */
 
-use core::cell::UnsafeCell;
+use core::cell::{Cell, UnsafeCell};
 
 use billmock_plug_card::*;
+use board_link::LinkCounters;
+use card_terminal_adapter::text_page::TextPage;
 use card_terminal_adapter::types::*;
 use card_terminal_adapter::*;
 use embassy_stm32::peripherals::USART2;
 use embassy_stm32::peripherals::{DMA1_CH1, DMA1_CH2};
 use embassy_stm32::usart::{RingBufferedUartRx, UartTx};
 use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
 use embassy_sync::channel::Channel;
 use embassy_time::{with_timeout, Duration, Instant};
 
 use crate::components::eeprom::{self, *};
+use crate::components::fw_update::FwUpdate;
 use crate::const_str;
+use crate::semi_layer::heartbeat::{HeartbeatKind, HEARTBEAT};
+use crate::types::buffered_opendrain_kind::MeterKind;
+use crate::types::install_config::Language;
 
 const CARD_READER_COMMAND_CHANNEL_SIZE_RX: usize = 8;
 const CARD_READER_COMMAND_CHANNEL_SIZE_TX: usize = 16;
@@ -39,6 +46,14 @@ pub struct CardReaderDevice {
     rx: UnsafeCell<RingBufferedUartRx<'static, USART2, DMA1_CH1>>, // USART is complex to use generic
     pub recv_channel: CardReaderResponseChannel,
     pub req_channel: CardReaderRequestChannel,
+    /// Last time valid packet received from card terminal, for link health
+    last_rx: Mutex<ThreadModeRawMutex, Cell<Option<Instant>>>,
+    /// Counters of secondary board on board-to-board link, added on `DisplayRom`
+    linked_counters: Mutex<ThreadModeRawMutex, Cell<LinkCounters>>,
+    /// Latest page of `DisplayTextPage`, it's too big for request channel
+    text_page: Mutex<ThreadModeRawMutex, Cell<TextPage>>,
+    /// Language of screens that application composes, from install config
+    language: Mutex<ThreadModeRawMutex, Cell<Language>>,
 }
 
 type StackedRingbufferRxIndex = usize;
@@ -66,10 +81,17 @@ impl CardReaderDevice {
             rx: UnsafeCell::new(ringbuffer_rx),
             recv_channel: Channel::new(),
             req_channel: Channel::new(),
+            last_rx: Mutex::new(Cell::new(None)),
+            linked_counters: Mutex::new(Cell::new(LinkCounters {
+                card: [0; 2],
+                coin: [0; 2],
+            })),
+            text_page: Mutex::new(Cell::new(TextPage::new(0))),
+            language: Mutex::new(Cell::new(Language::Terminal)),
         }
     }
 
-    pub async fn run(&self, novella: &'static Novella) {
+    pub async fn run(&self, novella: &'static Novella, fw_update: Option<&'static FwUpdate>) {
         let plug = KiccEd785Plug {};
         let rx = unsafe { &mut *self.rx.get() };
         let tx = unsafe { &mut *self.tx.get() };
@@ -77,8 +99,11 @@ impl CardReaderDevice {
         let mut tx_buf = [0u8; CARD_READER_TX_BUFFER_SIZE];
         let mut stacked: StackedRingbufferRxIndex = 0;
         let mut last_tx = Instant::now();
+        let heartbeat = HEARTBEAT.register(HeartbeatKind::CardReader);
 
         loop {
+            HEARTBEAT.beat(heartbeat);
+
             // TX not hang on IO wait
             if stacked == 0 {
                 let now = Instant::now();
@@ -134,11 +159,19 @@ impl CardReaderDevice {
                         CardTerminalTxCmd::RequestTerminalInfo => {
                             plug.request_terminal_info(&mut tx_buf)
                         }
+                        CardTerminalTxCmd::RequestDateTime => plug.request_date_time(&mut tx_buf),
+                        CardTerminalTxCmd::RequestPaymentCancel(x) => {
+                            plug.request_payment_cancel(&mut tx_buf, x)
+                        }
                         CardTerminalTxCmd::DisplayRom => {
-                            let p1_card = novella.lock_read(eeprom::select::P1_CARD_CNT).await;
-                            let p2_card = novella.lock_read(eeprom::select::P2_CARD_CNT).await;
-                            let p1_coin = novella.lock_read(eeprom::select::P1_COIN_CNT).await;
-                            let p2_coin = novella.lock_read(eeprom::select::P2_COIN_CNT).await;
+                            // player 3 and 4 of quad or linked board are counted with same parity of port
+                            let linked = self.linked_counters.lock(|x| x.get());
+                            let card = novella.lock_read_income_pair(MeterKind::Card).await;
+                            let coin = novella.lock_read_income_pair(MeterKind::Coin).await;
+                            let p1_card = card[0].wrapping_add(linked.card[0]);
+                            let p2_card = card[1].wrapping_add(linked.card[1]);
+                            let p1_coin = coin[0].wrapping_add(linked.coin[0]);
+                            let p2_coin = coin[1].wrapping_add(linked.coin[1]);
                             let tid = get_tid_alt(novella).await;
 
                             plug.display_rom(
@@ -156,19 +189,37 @@ impl CardReaderDevice {
                             let tid = get_tid_alt(novella).await;
                             let uptime_minutes =
                                 (novella.get_uptime().as_secs() / 60).min(u32::MAX as u64) as u32;
+                            let last_fault_code = novella
+                                .lock_read(eeprom::select::FAULT_LOG)
+                                .await
+                                .previous_boot_error(hw_boot_cnt);
 
                             plug.display_hw_info(
                                 &mut tx_buf,
-                                &const_str::VERSION_STR,
-                                const_str::get_serial_number(),
-                                &tid,
-                                hw_boot_cnt,
-                                uptime_minutes,
+                                &HwInfo {
+                                    model_version: &const_str::VERSION_STR,
+                                    serial_number: const_str::get_serial_number(),
+                                    terminal_id: &tid,
+                                    hw_boot_cnt,
+                                    uptime_minutes,
+                                    last_fault_code,
+                                },
                             )
                         }
                         CardTerminalTxCmd::DisplayWarning(x) => {
                             plug.display_warning(&mut tx_buf, x)
                         }
+                        CardTerminalTxCmd::PushPayoutReport(x) => {
+                            plug.push_payout_report(&mut tx_buf, &x)
+                        }
+                        CardTerminalTxCmd::DisplayInhibitReason(x) => {
+                            plug.display_inhibit_reason(&mut tx_buf, &x)
+                        }
+                        CardTerminalTxCmd::DisplayTextPage => {
+                            let page = self.text_page.lock(|x| x.get());
+
+                            plug.display_text_page(&mut tx_buf, &page)
+                        }
                     };
 
                     defmt::debug!("Tx Gen Buf : {:#X}", &send_source);
@@ -191,6 +242,8 @@ impl CardReaderDevice {
 
                     match plug.pre_parse_common(rx_source) {
                         Ok(rx_cmd) => {
+                            self.last_rx.lock(|x| x.set(Some(Instant::now())));
+
                             let final_rx_cmd = match rx_cmd {
                                 CardTerminalRxCmd::ResponseSaleSlotInfo => {
                                     let result = plug.post_parse_response_sale_slot_info(rx_source);
@@ -280,6 +333,15 @@ impl CardReaderDevice {
                             }
                         }
                         Err(e) => {
+                            if let Some(fw_update) =
+                                fw_update.filter(|_| fw_update::contains_hello(rx_source))
+                            {
+                                // uploader sends `Hello` again, it's answered by bootloader
+                                stacked = 0;
+                                fw_update.request();
+                                continue;
+                            }
+
                             defmt::error!("CardTerminal Parse Error : {}", e);
                             match e {
                                 CardTerminalError::BadLength | CardTerminalError::InvalidFrame => {
@@ -323,6 +385,37 @@ impl CardReaderDevice {
             .await;
     }
 
+    /// Last time valid packet received from card terminal, `None` if never seen.
+    pub fn last_rx(&self) -> Option<Instant> {
+        self.last_rx.lock(|x| x.get())
+    }
+
+    /// Counters of secondary board, shown together on `DisplayRom`
+    pub fn set_linked_counters(&self, counters: LinkCounters) {
+        self.linked_counters.lock(|x| x.set(counters));
+    }
+
+    pub fn linked_counters(&self) -> LinkCounters {
+        self.linked_counters.lock(|x| x.get())
+    }
+
+    pub fn language(&self) -> Language {
+        self.language.lock(|x| x.get())
+    }
+
+    pub fn set_language(&self, language: Language) {
+        self.language.lock(|x| x.set(language));
+    }
+
+    /// Show text page on card terminal, e.g. config menu, fault list and period meter.
+    /// Only the latest page is kept, when pages are requested faster than TX interval.
+    pub async fn display_page(&self, page: TextPage) {
+        self.text_page.lock(|x| x.set(page));
+        self.req_channel
+            .send(CardTerminalTxCmd::DisplayTextPage)
+            .await;
+    }
+
     pub async fn send_transaction_availability(&self, is_avail: bool) {
         self.req_channel
             .send(CardTerminalTxCmd::SetTransactionAvailability(is_avail))
@@ -337,8 +430,13 @@ impl CardReaderDevice {
 pub async fn card_reader_device_spawn(
     instance: &'static CardReaderDevice,
     novella: &'static Novella,
+    fw_update: Option<&'static FwUpdate>,
 ) {
-    instance.run(novella).await;
+    instance.run(novella, fw_update).await;
+}
+
+pub fn support_payment_cancel() -> bool {
+    KiccEd785Plug::support_payment_cancel()
 }
 
 pub fn alert_module_status() {
@@ -4,31 +4,34 @@
  * SPDX-License-Identifier: MIT OR Apache-2.0
  */
 
-use defmt::unwrap;
-use embassy_executor::Spawner;
+//! Start button with LED on vend side.
+//! Hardware version 0.2 had it on board, hardware mini 0.5 can have it on spare pins.
+//! Switch input is treated same as start signal of `VendSideBill`.
+
+#![cfg_attr(not(feature = "start_button"), allow(dead_code))]
+
+use embassy_futures::join::join;
 use embassy_stm32::exti::ExtiInput;
-use embassy_stm32::gpio::{AnyPin, Output};
+use embassy_stm32::gpio::AnyPin;
 
-use crate::semi_layer::buffered_opendrain::{buffered_opendrain_spawn, BufferedOpenDrain};
-use crate::semi_layer::buffered_wait::buffered_wait_spawn;
+use crate::semi_layer::buffered_opendrain::{BufferedOpenDrain, BufferedOpenDrainPin};
 use crate::semi_layer::buffered_wait::{BufferedWait, InputEventChannel, RawInputPortKind};
 use crate::semi_layer::timing::SharedToggleTiming;
 use crate::types::buffered_opendrain_kind::BufferedOpenDrainKind;
 use crate::types::input_port::InputPortKind;
 use crate::types::player::Player;
-/// deprecated from hardware version 0.3
-#[allow(dead_code)]
+use crate::types::port_polarity::PortPolarity;
+
 pub struct StartButton {
     in_switch: BufferedWait,
-    out_led: BufferedOpenDrain,
+    pub out_led: BufferedOpenDrain,
 }
 
-#[allow(dead_code)]
 impl StartButton {
     pub const fn new(
         player: Player,
         in_switch: ExtiInput<'static, AnyPin>,
-        out_led: Output<'static, AnyPin>,
+        out_led: BufferedOpenDrainPin,
         mpsc_ch: &'static InputEventChannel,
         shared_timing: &'static SharedToggleTiming,
     ) -> Self {
@@ -42,8 +45,24 @@ impl StartButton {
         }
     }
 
-    pub fn start_tasks(&'static self, spawner: &Spawner) {
-        unwrap!(spawner.spawn(buffered_wait_spawn(&self.in_switch)));
-        unwrap!(spawner.spawn(buffered_opendrain_spawn(&self.out_led)));
+    pub fn set_polarity(&self, player: Player, polarity: &PortPolarity) {
+        self.out_led
+            .set_polarity(BufferedOpenDrainKind::VendSideStartLed(player), polarity);
+        self.in_switch
+            .set_active_high(polarity.is_input_active_high(InputPortKind::StartJam1P, player));
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.in_switch.set_enabled(enabled);
     }
+
+    async fn run(&self) {
+        join(self.out_led.run(), self.in_switch.run()).await;
+    }
+}
+
+// Start button of player 1 and 2
+#[embassy_executor::task(pool_size = 2)]
+pub async fn start_button_spawn(instance: &'static StartButton) {
+    instance.run().await
 }
@@ -7,15 +7,18 @@
 use defmt::unwrap;
 use embassy_executor::Spawner;
 use embassy_stm32::exti::ExtiInput;
-use embassy_stm32::gpio::{AnyPin, Output};
+use embassy_stm32::gpio::AnyPin;
 
-use crate::semi_layer::buffered_opendrain::{buffered_opendrain_spawn, BufferedOpenDrain};
+use crate::semi_layer::buffered_opendrain::{
+    buffered_opendrain_spawn, BufferedOpenDrain, BufferedOpenDrainPin,
+};
 use crate::semi_layer::buffered_wait::buffered_wait_spawn;
 use crate::semi_layer::buffered_wait::{BufferedWait, InputEventChannel, RawInputPortKind};
 use crate::semi_layer::timing::SharedToggleTiming;
 use crate::types::buffered_opendrain_kind::BufferedOpenDrainKind;
 use crate::types::input_port::InputPortKind;
 use crate::types::player::Player;
+use crate::types::port_polarity::PortPolarity;
 
 pub struct VendSideBill {
     pub out_inhibit: BufferedOpenDrain,
@@ -26,7 +29,7 @@ pub struct VendSideBill {
 impl VendSideBill {
     pub const fn new(
         player: Player,
-        out_inhibit: Output<'static, AnyPin>,
+        out_inhibit: BufferedOpenDrainPin,
         in_vend: ExtiInput<'static, AnyPin>,
         in_start_jam: ExtiInput<'static, AnyPin>,
         mpsc_ch: &'static InputEventChannel,
@@ -45,6 +48,21 @@ impl VendSideBill {
         }
     }
 
+    pub fn set_polarity(&self, player: Player, polarity: &PortPolarity) {
+        self.out_inhibit
+            .set_polarity(BufferedOpenDrainKind::VendSideInhibit(player), polarity);
+        self.in_vend
+            .set_active_high(polarity.is_input_active_high(InputPortKind::Vend1P, player));
+        self.in_start_jam
+            .set_active_high(polarity.is_input_active_high(InputPortKind::StartJam1P, player));
+    }
+
+    /// Disable inputs of unused player side, inhibit output is kept.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.in_vend.set_enabled(enabled);
+        self.in_start_jam.set_enabled(enabled);
+    }
+
     pub fn start_tasks(&'static self, spawner: &Spawner) {
         unwrap!(spawner.spawn(buffered_opendrain_spawn(&self.out_inhibit)));
         unwrap!(spawner.spawn(buffered_wait_spawn(&self.in_vend)));
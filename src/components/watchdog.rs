@@ -0,0 +1,81 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Independent watchdog supervisor.
+//! Pets IWDG only while every registered task keeps beating on heartbeat registry.
+//! When a task is silent, the task is recorded on `FaultLog` then IWDG resets the MCU.
+
+use core::cell::UnsafeCell;
+
+use embassy_stm32::peripherals::IWDG;
+use embassy_stm32::wdg::IndependentWatchdog;
+use embassy_time::{Duration, Timer};
+
+use crate::components::eeprom::Novella;
+use crate::semi_layer::heartbeat::{HeartbeatMissing, HEARTBEAT};
+use crate::types::fault_log::FaultCode;
+
+/// IWDG reset timeout, should be enough longer than `WATCHDOG_PET_PERIOD`
+const WATCHDOG_TIMEOUT_US: u32 = 2_000_000;
+const WATCHDOG_PET_PERIOD: Duration = Duration::from_millis(500);
+/// Supervision window, Novella task sleep 2 secs per loop and would take few hundred msecs more.
+const WATCHDOG_WINDOW_PET_CNT: u8 = 10;
+
+pub struct Watchdog {
+    iwdg: UnsafeCell<IndependentWatchdog<'static, IWDG>>,
+}
+
+impl Watchdog {
+    pub fn new(iwdg: IWDG) -> Self {
+        Self {
+            iwdg: UnsafeCell::new(IndependentWatchdog::new(iwdg, WATCHDOG_TIMEOUT_US)),
+        }
+    }
+
+    fn reset_by_missing(&self, missing: HeartbeatMissing, novella: &Novella) -> ! {
+        defmt::error!(
+            "Watchdog - heartbeat missing {} [{}], reset",
+            missing.kind,
+            missing.slot
+        );
+
+        // IWDG doesn't get pet anymore, thus remained time is used for recording fault.
+        if let Err(e) = novella.try_blocking_record_fault(FaultCode::watchdog(missing)) {
+            defmt::error!("Watchdog - failed to record fault : {:?}", e);
+        }
+
+        loop {
+            cortex_m::asm::nop();
+        }
+    }
+
+    async fn run(&self, novella: &'static Novella) {
+        let iwdg = unsafe { &mut *self.iwdg.get() };
+        let mut pet_cnt = 0u8;
+
+        iwdg.unleash();
+
+        loop {
+            Timer::after(WATCHDOG_PET_PERIOD).await;
+
+            pet_cnt += 1;
+            if WATCHDOG_WINDOW_PET_CNT <= pet_cnt {
+                pet_cnt = 0;
+
+                if let Some(missing) = HEARTBEAT.take_missing() {
+                    self.reset_by_missing(missing, novella);
+                }
+            }
+
+            iwdg.pet();
+        }
+    }
+}
+
+#[embassy_executor::task(pool_size = 1)]
+pub async fn watchdog_spawn(instance: &'static Watchdog, novella: &'static Novella) {
+    instance.run(novella).await
+}
@@ -47,6 +47,9 @@ async fn initial_eeprom(eeprom: &crate::components::eeprom::Novella) {
         Err(crate::components::eeprom::NovellaInitError::MissingEeprom) => {
             defmt::error!("MissingEeprom");
         }
+        Err(crate::components::eeprom::NovellaInitError::UnknownLayout(x)) => {
+            defmt::error!("Eeprom is written by newer firmware, layout : {}", x);
+        }
         Ok(crate::components::eeprom::NovellaInitOk::Success(_)) => {
             defmt::info!("Eeprom is good status");
         }
@@ -59,6 +62,14 @@ async fn initial_eeprom(eeprom: &crate::components::eeprom::Novella) {
     let uptime_secs = uptime.as_secs();
 
     defmt::info!("Boot Count : {} -> {}", boot_cnt, boot_cnt_after,);
+
+    let last_fault_code = eeprom
+        .lock_read(select::FAULT_LOG)
+        .await
+        .previous_boot_error(boot_cnt_after);
+    if last_fault_code != 0 {
+        defmt::error!("Previous boot is reset by fault : {:#06X}", last_fault_code);
+    }
     defmt::info!(
         "Total Uptime : {} ticks  <->  {} days  {} hrs  {} mins  {} secs",
         uptime,
@@ -5,16 +5,31 @@
  */
 
 use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicBool, AtomicU16, Ordering};
 
 use bit_field::BitField;
-use embassy_stm32::gpio::{AnyPin, Level, Output};
+use embassy_stm32::gpio::{AnyPin, Flex, Level, Pull, Speed};
 use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
 use embassy_sync::channel::Channel;
 use embassy_time::{with_timeout, Duration, Instant, Timer};
 
+use super::heartbeat::{HeartbeatKind, HEARTBEAT, HEARTBEAT_PERIOD};
 use super::timing::{SharedToggleTiming, ToggleTiming};
+use crate::types::buffered_opendrain_kind::BufferedOpenDrainKind;
+use crate::types::port_polarity::PortPolarity;
 
 pub const HOST_SIDE_INTERFACE_CH_SIZE: usize = 4;
+
+/// Output pin of `BufferedOpenDrain`, drive mode is changed on runtime by `PortPolarity`.
+pub type BufferedOpenDrainPin = Flex<'static, AnyPin>;
+
+/// Push-pull output pin with initial level, same as `Output::new` of embassy.
+pub fn output_pin(pin: AnyPin, initial_output: Level) -> BufferedOpenDrainPin {
+    let mut pin = Flex::new(pin);
+    pin.set_level(initial_output);
+    pin.set_as_output(Speed::Low);
+    pin
+}
 pub type OpenDrainRequestChannel =
     Channel<ThreadModeRawMutex, RawBufferedOpenDrainRequest, HOST_SIDE_INTERFACE_CH_SIZE>;
 
@@ -417,9 +432,15 @@ impl MicroHsm {
 }
 
 pub struct BufferedOpenDrain {
-    io: UnsafeCell<Output<'static, AnyPin>>,
+    io: UnsafeCell<BufferedOpenDrainPin>,
     shared_timing: &'static SharedToggleTiming,
     channel_hsm: OpenDrainRequestChannel,
+    /// Output pin level is inverted from requested state, for active low devices.
+    inverted: AtomicBool,
+    /// Output pin only sinks current and floats on high, for pulled-up inputs of other board.
+    open_drain: AtomicBool,
+    /// Minimum high duration of output in msec, 0 is disabled.
+    min_high_ms: AtomicU16,
 
     /// only use for debug print
     #[cfg(debug_assertions)]
@@ -430,7 +451,7 @@ pub struct BufferedOpenDrain {
 impl BufferedOpenDrain {
     fn reflect_on_io(&self, hsm: &MicroHsm) {
         let io = unsafe { &mut *self.io.get() };
-        let state: Level = hsm.expect_output_pin_state().into();
+        let state: Level = (hsm.expect_output_pin_state() ^ self.is_inverted()).into();
 
         #[cfg(debug_assertions)]
         defmt::println!("OUT[{}] : {}", self.debug_name, state);
@@ -439,7 +460,7 @@ impl BufferedOpenDrain {
     }
 
     pub const fn new(
-        out_pin: Output<'static, AnyPin>,
+        out_pin: BufferedOpenDrainPin,
         shared_timing: &'static SharedToggleTiming,
         debug_name: &'static str,
     ) -> Self {
@@ -447,30 +468,124 @@ impl BufferedOpenDrain {
             io: UnsafeCell::new(out_pin),
             shared_timing,
             channel_hsm: Channel::new(),
+            inverted: AtomicBool::new(false),
+            open_drain: AtomicBool::new(false),
+            min_high_ms: AtomicU16::new(0),
             #[cfg(debug_assertions)]
             debug_name,
         }
     }
 
-    async fn run(&self) {
+    #[inline]
+    fn is_inverted(&self) -> bool {
+        self.inverted.load(Ordering::Relaxed)
+    }
+
+    /// Change output polarity, current pin level is flipped immediately.
+    pub fn set_inverted(&self, inverted: bool) {
+        // thumbv6m doesn't have atomic swap, but this is only called from thread mode.
+        if self.is_inverted() != inverted {
+            self.inverted.store(inverted, Ordering::Relaxed);
+
+            let io = unsafe { &mut *self.io.get() };
+            io.toggle();
+        }
+    }
+
+    /// Change output drive mode between open-drain and push-pull, pin level is kept.
+    pub fn set_open_drain(&self, open_drain: bool) {
+        if self.open_drain.load(Ordering::Relaxed) != open_drain {
+            self.open_drain.store(open_drain, Ordering::Relaxed);
+
+            let io = unsafe { &mut *self.io.get() };
+            match open_drain {
+                true => io.set_as_input_output(Speed::Low, Pull::None),
+                false => io.set_as_output(Speed::Low),
+            }
+        }
+    }
+
+    /// Apply output polarity and drive mode of the port.
+    pub fn set_polarity(&self, kind: BufferedOpenDrainKind, polarity: &PortPolarity) {
+        self.set_inverted(polarity.is_output_inverted(kind));
+        self.set_open_drain(polarity.is_output_open_drain(kind));
+    }
+
+    /// Set minimum high duration of output, shorter pulses are stretched. 0 disables it.
+    pub fn set_min_high_ms(&self, min_high_ms: u16) {
+        self.min_high_ms.store(min_high_ms, Ordering::Relaxed);
+    }
+
+    /// Stretch request not to break minimum high duration.
+    /// `high_for` is how long the output has been high, `None` when it is low.
+    fn stretch(
+        &self,
+        request: BufferedOpenDrainRequest,
+        high_for: Option<Duration>,
+    ) -> BufferedOpenDrainRequest {
+        let min_ms = self.min_high_ms.load(Ordering::Relaxed);
+        if min_ms == 0 {
+            return request;
+        }
+
+        match (request, high_for) {
+            (BufferedOpenDrainRequest::SetLow, Some(high_for))
+                if high_for.as_millis() < min_ms as u64 =>
+            {
+                BufferedOpenDrainRequest::OneShotHigh(min_ms as u32 - high_for.as_millis() as u32)
+            }
+            (BufferedOpenDrainRequest::TickTock(x), _) => {
+                let timing = self.shared_timing.get();
+                BufferedOpenDrainRequest::AltTickTock(AltTickTockRequest {
+                    toggle_count: x,
+                    timing: ToggleTiming {
+                        high_ms: timing.high_ms.max(min_ms),
+                        low_ms: timing.low_ms,
+                    },
+                })
+            }
+            (BufferedOpenDrainRequest::AltTickTock(x), _) => {
+                BufferedOpenDrainRequest::AltTickTock(AltTickTockRequest {
+                    toggle_count: x.toggle_count,
+                    timing: ToggleTiming {
+                        high_ms: x.timing.high_ms.max(min_ms),
+                        low_ms: x.timing.low_ms,
+                    },
+                })
+            }
+            (x, _) => x,
+        }
+    }
+
+    pub(crate) async fn run(&self) {
         let mut hsm = MicroHsm::default();
         self.reflect_on_io(&hsm);
 
         let mut last = Instant::now();
+        let mut high_since: Option<Instant> = None;
+        let heartbeat = HEARTBEAT.register(HeartbeatKind::OpenDrain);
 
         loop {
+            HEARTBEAT.beat(heartbeat);
+
             let request = match (hsm.next_sched_time(), hsm.is_busy()) {
                 (Some(wait_ms), false) => {
                     with_timeout(
-                        Duration::from_millis(wait_ms.into()),
+                        Duration::from_millis(wait_ms.into()).min(HEARTBEAT_PERIOD),
                         self.channel_hsm.receive(),
                     )
                     .await
                 }
-                (None, false) => Ok(self.channel_hsm.receive().await),
+                (None, false) => {
+                    // idle state, wake up periodically for heartbeat
+                    match with_timeout(HEARTBEAT_PERIOD, self.channel_hsm.receive()).await {
+                        Ok(x) => Ok(x),
+                        Err(_) => continue,
+                    }
+                }
                 // Not allowed in busy, that means not-interruptable
                 (Some(wait_ms), true) => {
-                    Timer::after(Duration::from_millis(wait_ms.into())).await;
+                    Timer::after(Duration::from_millis(wait_ms.into()).min(HEARTBEAT_PERIOD)).await;
                     Err(embassy_time::TimeoutError)
                 }
                 (None, true) => {
@@ -499,7 +614,8 @@ impl BufferedOpenDrain {
                 )
             }) {
                 Some(y) => {
-                    hsm = (y, self.shared_timing).into();
+                    let high_for = high_since.map(|x| Instant::now() - x);
+                    hsm = (self.stretch(y, high_for), self.shared_timing).into();
                 }
                 None => {
                     let elapsed = (Instant::now() - last).as_millis().min(u32::MAX.into()) as u32;
@@ -509,6 +625,12 @@ impl BufferedOpenDrain {
 
             self.reflect_on_io(&hsm);
             last = Instant::now();
+
+            high_since = match (hsm.expect_output_pin_state(), high_since) {
+                (true, None) => Some(last),
+                (true, x) => x,
+                (false, _) => None,
+            };
         }
     }
 
@@ -620,7 +742,15 @@ impl BufferedOpenDrain {
 // in HW v0.2 pool usage would be 13, but latest BSP only allow 12 output.
 // in HW v0.3 pool usage would be 12. PCB has 12 N-MOS open-drain.
 // single task pool consume 120 bytes
+#[cfg(not(feature = "hw_quad_0v1"))]
 #[embassy_executor::task(pool_size = 12)]
 pub async fn buffered_opendrain_spawn(instance: &'static BufferedOpenDrain) {
     instance.run().await
 }
+
+// in HW quad 0.1 pool usage would be 22. (4 players and 2 indicators)
+#[cfg(feature = "hw_quad_0v1")]
+#[embassy_executor::task(pool_size = 22)]
+pub async fn buffered_opendrain_spawn(instance: &'static BufferedOpenDrain) {
+    instance.run().await
+}
@@ -5,12 +5,15 @@
  */
 
 use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicBool, Ordering};
 
 use embassy_stm32::exti::ExtiInput;
 use embassy_stm32::gpio::AnyPin;
 use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
 use embassy_sync::channel::Channel;
-use embassy_time::Instant;
+use embassy_time::{with_timeout, Instant};
+
+use super::heartbeat::{HeartbeatKind, HeartbeatSlot, HEARTBEAT, HEARTBEAT_PERIOD};
 
 pub const MPSC_WAIT_INPUT_EVENT_CH_SIZE: usize = 32;
 
@@ -73,10 +76,14 @@ impl From<InputEventKind> for RawInputEventKind {
 }
 
 /// Internal PullUp + 4050 + OpenDrain outside (NMOS or ULN2803)
+/// Active low by default, polarity can be changed for active high devices.
 pub struct BufferedWait {
     wait: UnsafeCell<ExtiInput<'static, AnyPin>>,
     channel: &'static InputEventChannel,
     port: RawInputPortKind,
+    active_high: AtomicBool,
+    /// Disabled input keeps tracking the level, but doesn't send any event
+    enabled: AtomicBool,
     #[cfg(debug_assertions)]
     debug_name: &'static str,
 }
@@ -93,13 +100,34 @@ impl BufferedWait {
             wait: UnsafeCell::new(wait),
             channel,
             port,
+            active_high: AtomicBool::new(false),
+            enabled: AtomicBool::new(true),
 
             #[cfg(debug_assertions)]
             debug_name,
         }
     }
 
+    /// Change input polarity, it's applied from next edge.
+    pub fn set_active_high(&self, active_high: bool) {
+        self.active_high.store(active_high, Ordering::Relaxed);
+    }
+
+    #[inline]
+    fn is_active_high(&self) -> bool {
+        self.active_high.load(Ordering::Relaxed)
+    }
+
+    /// Disable unused input, e.g. floating port of unused player side.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
     async fn send(&self, event: InputEventKind) {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return;
+        }
+
         self.channel
             .send(RawInputEvent {
                 port: self.port,
@@ -108,31 +136,50 @@ impl BufferedWait {
             .await;
     }
 
-    pub async fn run(&self) -> ! {
-        // wait high for fit ot initial state.
+    /// Wait for given level, but wake up periodically for heartbeat
+    async fn wait_for_level(&self, high: bool, heartbeat: Option<HeartbeatSlot>) {
         let wait = unsafe { &mut *self.wait.get() };
-        wait.wait_for_high().await;
+
+        loop {
+            HEARTBEAT.beat(heartbeat);
+
+            let result = match high {
+                true => with_timeout(HEARTBEAT_PERIOD, wait.wait_for_high()).await,
+                false => with_timeout(HEARTBEAT_PERIOD, wait.wait_for_low()).await,
+            };
+
+            if result.is_ok() {
+                return;
+            }
+        }
+    }
+
+    pub async fn run(&self) -> ! {
+        let heartbeat = HEARTBEAT.register(HeartbeatKind::BufferedWait);
+
+        // wait idle level for fit ot initial state.
+        self.wait_for_level(!self.is_active_high(), heartbeat).await;
 
         #[cfg(debug_assertions)]
-        defmt::println!("IN [{}  ] : High", self.debug_name);
+        defmt::println!("IN [{}  ] : Idle", self.debug_name);
 
         loop {
-            // detect low signal (active low)
-            wait.wait_for_low().await;
+            // detect active signal (active low by default)
+            self.wait_for_level(self.is_active_high(), heartbeat).await;
             let entered_time = Instant::now();
 
             #[cfg(debug_assertions)]
-            defmt::println!("IN [{}  ] : Low", self.debug_name);
+            defmt::println!("IN [{}  ] : Active", self.debug_name);
 
             self.send(InputEventKind::Pressed).await;
 
-            // detect high signal (active high)
-            wait.wait_for_high().await;
+            // detect idle signal (high by default)
+            self.wait_for_level(!self.is_active_high(), heartbeat).await;
             let hold_time = Instant::now() - entered_time;
 
             #[cfg(debug_assertions)]
             defmt::println!(
-                "IN [{}  ] : High, duration : {=u64:us}",
+                "IN [{}  ] : Idle, duration : {=u64:us}",
                 self.debug_name,
                 hold_time.as_micros()
             );
@@ -160,8 +207,15 @@ pub async fn buffered_wait_spawn(instance: &'static BufferedWait) {
 
 // in HW v0.5 pool usage would be 7. (+ SVC_Button)
 // single task pool consume 88 bytes
-#[cfg(feature = "svc_button")]
+#[cfg(all(feature = "svc_button", not(feature = "hw_quad_0v1")))]
 #[embassy_executor::task(pool_size = 7)]
 pub async fn buffered_wait_spawn(instance: &'static BufferedWait) {
     instance.run().await
 }
+
+// in HW quad 0.1 pool usage would be 13. (4 players + SVC_Button)
+#[cfg(feature = "hw_quad_0v1")]
+#[embassy_executor::task(pool_size = 13)]
+pub async fn buffered_wait_spawn(instance: &'static BufferedWait) {
+    instance.run().await
+}
@@ -0,0 +1,110 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Heartbeat registry for task supervision.
+//! Each supervised task instance registers a slot on start and beats periodically,
+//! watchdog supervisor collects the beats per window and finds the silent one.
+
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::raw::ThreadModeRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_time::Duration;
+
+/// Supervised task should beat at least once in this period, even if it's idle.
+pub const HEARTBEAT_PERIOD: Duration = Duration::from_millis(1000);
+
+const HEARTBEAT_SLOT_MAX: usize = u32::BITS as usize;
+
+pub type HeartbeatSlot = u8;
+
+#[repr(u8)]
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub enum HeartbeatKind {
+    Unknown = 0,
+    MainTask = 1,
+    CardReader = 2,
+    Novella = 3,
+    OpenDrain = 4,
+    BufferedWait = 5,
+    BoardLink = 6,
+}
+
+struct HeartbeatInner {
+    registered: u32,
+    alive: u32,
+    kinds: [HeartbeatKind; HEARTBEAT_SLOT_MAX],
+}
+
+pub struct HeartbeatRegistry {
+    inner: Mutex<ThreadModeRawMutex, RefCell<HeartbeatInner>>,
+}
+
+/// Silent task in the supervision window
+#[derive(Debug, defmt::Format, Clone, Copy)]
+pub struct HeartbeatMissing {
+    pub kind: HeartbeatKind,
+    pub slot: HeartbeatSlot,
+}
+
+pub static HEARTBEAT: HeartbeatRegistry = HeartbeatRegistry::new();
+
+impl HeartbeatRegistry {
+    const fn new() -> Self {
+        Self {
+            inner: Mutex::new(RefCell::new(HeartbeatInner {
+                registered: 0,
+                alive: 0,
+                kinds: [HeartbeatKind::Unknown; HEARTBEAT_SLOT_MAX],
+            })),
+        }
+    }
+
+    /// Register task instance, returns `None` when registry is full (unsupervised).
+    pub fn register(&self, kind: HeartbeatKind) -> Option<HeartbeatSlot> {
+        let ret = self.inner.lock(|x| {
+            let mut x = x.borrow_mut();
+            let slot = x.registered.trailing_ones() as usize;
+
+            (slot < HEARTBEAT_SLOT_MAX).then(|| {
+                x.registered |= 1 << slot;
+                x.alive |= 1 << slot;
+                x.kinds[slot] = kind;
+                slot as HeartbeatSlot
+            })
+        });
+
+        if ret.is_none() {
+            defmt::warn!("Heartbeat registry is full, {} is unsupervised", kind);
+        }
+
+        ret
+    }
+
+    #[inline]
+    pub fn beat(&self, slot: Option<HeartbeatSlot>) {
+        if let Some(slot) = slot {
+            self.inner.lock(|x| x.borrow_mut().alive |= 1 << slot);
+        }
+    }
+
+    /// Close current supervision window, returns first silent task if exist.
+    pub fn take_missing(&self) -> Option<HeartbeatMissing> {
+        self.inner.lock(|x| {
+            let mut x = x.borrow_mut();
+            let missing = x.registered & !x.alive;
+            x.alive = 0;
+
+            (missing != 0).then(|| {
+                let slot = missing.trailing_zeros() as usize;
+                HeartbeatMissing {
+                    kind: x.kinds[slot],
+                    slot: slot as HeartbeatSlot,
+                }
+            })
+        })
+    }
+}
@@ -9,5 +9,7 @@
 pub(crate) mod buffered_opendrain;
 pub(crate) mod buffered_wait;
 pub(crate) mod buffered_wait_receiver;
+pub(crate) mod heartbeat;
+pub(crate) mod sampled_wait;
 
 pub mod timing;
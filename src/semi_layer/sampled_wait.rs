@@ -0,0 +1,198 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Sampled input for the line that may float, alternative of `BufferedWait`.
+//! Hardware 0.2 / 0.3 don't have pull resistor on host side inhibit (#19),
+//! thus the line floats when GAME I/O PCB is not connected and EXTI cannot be used.
+//! The line is probed with internal pull-up and pull-down alternately,
+//! driven line keeps its level on both pulls but floating line follows the pull.
+//! Active state is decided by majority of the recent samples.
+
+#![cfg_attr(not(feature = "hotfix_hwbug_host_inhibit_floating"), allow(dead_code))]
+
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use embassy_stm32::gpio::{AnyPin, Flex, Pull};
+use embassy_time::{Duration, Instant, Timer};
+
+use super::buffered_wait::{InputEventChannel, InputEventKind, RawInputEvent, RawInputPortKind};
+use super::heartbeat::{HeartbeatKind, HEARTBEAT};
+
+/// Period of single sample, both pulls are probed in a sample
+const SAMPLE_PERIOD: Duration = Duration::from_millis(10);
+/// Settling time after pull is changed, internal pull is about 40k ohm
+const PULL_SETTLE_TIME: Duration = Duration::from_micros(100);
+/// Number of recent samples for majority voting, kept as bit history
+const SAMPLE_WINDOW: u32 = 8;
+/// Becomes active when active samples are equal or more than this in the window
+const ACTIVE_THRESHOLD: u32 = 6;
+/// Becomes idle when active samples are equal or less than this in the window
+const IDLE_THRESHOLD: u32 = 2;
+/// The line is treated as floating when floating samples are more than this in the window
+const FLOATING_THRESHOLD: u32 = SAMPLE_WINDOW / 2;
+const TINY_LONG_PRESS_MAX: u8 = (0x1 << 7) - 1;
+
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+enum Sample {
+    /// Driven to active level
+    Active,
+    /// Driven to idle level
+    Idle,
+    /// Follows internal pull, nothing drives the line
+    Floating,
+}
+
+pub struct SampledWait {
+    pin: UnsafeCell<Flex<'static, AnyPin>>,
+    channel: &'static InputEventChannel,
+    port: RawInputPortKind,
+    active_high: AtomicBool,
+    /// Disabled input keeps sampling, but doesn't send any event
+    enabled: AtomicBool,
+    #[cfg(debug_assertions)]
+    debug_name: &'static str,
+}
+
+#[allow(unused)]
+impl SampledWait {
+    pub const fn new(
+        pin: Flex<'static, AnyPin>,
+        channel: &'static InputEventChannel,
+        port: RawInputPortKind,
+        debug_name: &'static str,
+    ) -> SampledWait {
+        Self {
+            pin: UnsafeCell::new(pin),
+            channel,
+            port,
+            active_high: AtomicBool::new(false),
+            enabled: AtomicBool::new(true),
+
+            #[cfg(debug_assertions)]
+            debug_name,
+        }
+    }
+
+    /// Change input polarity, it's applied from next sample.
+    pub fn set_active_high(&self, active_high: bool) {
+        self.active_high.store(active_high, Ordering::Relaxed);
+    }
+
+    /// Disable unused input, e.g. floating port of unused player side.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    async fn send(&self, event: InputEventKind) {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return;
+        }
+
+        self.channel
+            .send(RawInputEvent {
+                port: self.port,
+                event: event.into(),
+            })
+            .await;
+    }
+
+    async fn sample(&self) -> Sample {
+        let pin = unsafe { &mut *self.pin.get() };
+
+        pin.set_as_input(Pull::Up);
+        Timer::after(PULL_SETTLE_TIME).await;
+        let on_pull_up = pin.is_high();
+
+        pin.set_as_input(Pull::Down);
+        Timer::after(PULL_SETTLE_TIME).await;
+        let on_pull_down = pin.is_high();
+
+        match (on_pull_up, on_pull_down) {
+            (true, false) => Sample::Floating,
+            (level, _) if level == self.active_high.load(Ordering::Relaxed) => Sample::Active,
+            _ => Sample::Idle,
+        }
+    }
+
+    pub async fn run(&self) -> ! {
+        let heartbeat = HEARTBEAT.register(HeartbeatKind::BufferedWait);
+        let window_mask: u32 = (1 << SAMPLE_WINDOW) - 1;
+        let mut active_history: u32 = 0;
+        let mut floating_history: u32 = 0;
+        let mut is_active = false;
+        let mut is_floating = false;
+        let mut entered_time = Instant::now();
+
+        #[cfg(debug_assertions)]
+        defmt::println!("IN [{}  ] : Sampled, Idle", self.debug_name);
+
+        loop {
+            HEARTBEAT.beat(heartbeat);
+
+            let sample = self.sample().await;
+            active_history =
+                ((active_history << 1) | (sample == Sample::Active) as u32) & window_mask;
+            floating_history =
+                ((floating_history << 1) | (sample == Sample::Floating) as u32) & window_mask;
+
+            // plausibility, floating line is not connected and never be active
+            let floating_latest = FLOATING_THRESHOLD < floating_history.count_ones();
+            if floating_latest != is_floating {
+                is_floating = floating_latest;
+
+                match is_floating {
+                    true => defmt::warn!("Sampled input {} is floating", self.port),
+                    false => defmt::info!("Sampled input {} is driven", self.port),
+                }
+            }
+
+            let active_count = active_history.count_ones();
+            let active_latest = match is_active {
+                false => !is_floating && (ACTIVE_THRESHOLD <= active_count),
+                true => !is_floating && (IDLE_THRESHOLD < active_count),
+            };
+
+            if active_latest != is_active {
+                is_active = active_latest;
+
+                if is_active {
+                    entered_time = Instant::now();
+
+                    #[cfg(debug_assertions)]
+                    defmt::println!("IN [{}  ] : Active", self.debug_name);
+
+                    self.send(InputEventKind::Pressed).await;
+                } else {
+                    let hold_time = Instant::now() - entered_time;
+
+                    #[cfg(debug_assertions)]
+                    defmt::println!(
+                        "IN [{}  ] : Idle, duration : {=u64:us}",
+                        self.debug_name,
+                        hold_time.as_micros()
+                    );
+
+                    match (hold_time.as_millis().min(TINY_LONG_PRESS_MAX as u64 * 10) / 10) as u8 {
+                        0 => { /* too short time pressed */ }
+                        x => {
+                            self.send(InputEventKind::LongPressed(x)).await;
+                        }
+                    }
+                    self.send(InputEventKind::Released).await;
+                }
+            }
+
+            Timer::after(SAMPLE_PERIOD).await;
+        }
+    }
+}
+
+// in HW v0.2 and v0.3 pool usage would be 2, host side inhibit of each player.
+#[embassy_executor::task(pool_size = 2)]
+pub async fn sampled_wait_spawn(instance: &'static SampledWait) {
+    instance.run().await
+}
@@ -7,8 +7,13 @@
 use crate::types::const_convert::ConstInto;
 use crate::types::player::Player;
 
+const OUTPUT_NAME_NUM: usize = 30;
+/// Outputs of player 3 and 4 are appended after common outputs, same order with player 1 and 2
+const OUTPUT_P3_IDX: u8 = 17;
+const OUTPUT_UNKNOWN_IDX: u8 = 29;
+
 #[cfg(debug_assertions)]
-const OUTPUT_NAME_STRS: [&str; 15] = [
+const OUTPUT_NAME_STRS: [&str; OUTPUT_NAME_NUM] = [
     "HostOut_P1-    Busy", // 0
     "HostOut_P2-    Busy",
     "HostOut_P1-    Vend", // 2
@@ -23,13 +28,28 @@ const OUTPUT_NAME_STRS: [&str; 15] = [
     "VendOut_P2-StartLED", // would be not use
     "     LED1-Indicator", // 12
     "     LED2-Indicator",
-    "Unknown",
+    "  MechMeter-   Card", // 14
+    "  MechMeter-   Coin",
+    "   Payout-    Motor", // 16
+    "HostOut_P3-    Busy", // 17, player 3 and 4 of quad board
+    "HostOut_P4-    Busy",
+    "HostOut_P3-    Vend", // 19
+    "HostOut_P4-    Vend",
+    "HostOut_P3-     Jam", // 21
+    "HostOut_P4-     Jam",
+    "HostOut_P3-   Start", // 23
+    "HostOut_P4-   Start",
+    "VendOut_P3- Inhibit", // 25
+    "VendOut_P4- Inhibit",
+    "VendOut_P3-StartLED", // 27, would be not use
+    "VendOut_P4-StartLED", // would be not use
+    "Unknown",             // 29
 ];
 
 #[cfg(not(debug_assertions))]
 #[rustfmt::skip]
 /// reduced output names
-const OUTPUT_NAME_STRS: [&str; 15] = [
+const OUTPUT_NAME_STRS: [&str; OUTPUT_NAME_NUM] = [
     "P1H-oBSY", // 0
     "P2H-oBSY",
     "P1H-oVND", // 2
@@ -44,9 +64,34 @@ const OUTPUT_NAME_STRS: [&str; 15] = [
     "P2V-oSLD", // would be not use
     "LED1",     // 12
     "LED2",
-    "Unknown",
+    "MTR-CARD", // 14
+    "MTR-COIN",
+    "PAY-MOTR", // 16
+    "P3H-oBSY", // 17, player 3 and 4 of quad board
+    "P4H-oBSY",
+    "P3H-oVND", // 19
+    "P4H-oVND",
+    "P3H-oJAM", // 21
+    "P4H-oJAM",
+    "P3H-oSTR", // 23
+    "P4H-oSTR",
+    "P3V-oINH", // 25
+    "P4V-oINH",
+    "P3V-oSLD", // 27, would be not use
+    "P4V-oSLD", // would be not use
+    "Unknown",  // 29
 ];
 
+/// Which income source the mechanical meter (counter coil) accumulates
+#[repr(u8)]
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub enum MeterKind {
+    /// Counts credits that came from card terminal (serial payment)
+    Card = 1,
+    /// Counts credits that came from bill validator or coin acceptor
+    Coin = 2,
+}
+
 #[allow(unused)]
 pub enum BufferedOpenDrainKind {
     HostSideOutBusy(Player),
@@ -54,8 +99,10 @@ pub enum BufferedOpenDrainKind {
     HostSideOutJam(Player),
     HostSideOutStart(Player),
     VendSideInhibit(Player),
-    VendSideStartLed(Player), // deprecated in 0.3
+    VendSideStartLed(Player), // deprecated in 0.3, spare pins on mini 0.5
     Indicator(u8),
+    MechMeter(MeterKind),
+    PayoutMotor,
     Unknown,
 }
 
@@ -67,6 +114,16 @@ impl ConstInto<&'static str> for &BufferedOpenDrainKind {
 
 impl BufferedOpenDrainKind {
     pub const fn get_str_idx(&self) -> usize {
+        self.get_idx(false)
+    }
+
+    /// Bit index of `PortPolarity`, outputs of player 3 and 4 follow player 1 and 2.
+    /// See `Player::fold`.
+    pub const fn get_polarity_idx(&self) -> usize {
+        self.get_idx(true)
+    }
+
+    const fn get_idx(&self, fold: bool) -> usize {
         /*
             // following code consume 444 bytes additionaly
             match self {
@@ -84,7 +141,13 @@ impl BufferedOpenDrainKind {
                 Self::VendSideStartLed(Player::Player2) => 11,
                 Self::Indicator(0) => 12,
                 Self::Indicator(1) => 13,
-                _ => 14,
+                Self::MechMeter(MeterKind::Card) => 14,
+                Self::MechMeter(MeterKind::Coin) => 15,
+                Self::PayoutMotor => 16,
+                Self::HostSideOutBusy(Player::Player3) => 17,
+                ...
+                Self::VendSideStartLed(Player::Player4) => 28,
+                _ => 29,
             }
         */
 
@@ -96,18 +159,24 @@ impl BufferedOpenDrainKind {
             Self::VendSideInhibit(p) => (8, *p as u8),
             Self::VendSideStartLed(p) => (10, *p as u8),
             Self::Indicator(p) => (12, *p),
-            _ => (14, 0),
+            Self::MechMeter(k) => (14, *k as u8),
+            Self::PayoutMotor => (16, 0),
+            _ => (OUTPUT_UNKNOWN_IDX, 1),
         };
 
         // cannot use alpha.max(beta) in const fn
-        let b = if b == 0 {
-            1
-        } else if 2 < b {
-            2
+        let b = if b == 0 { 1 } else { b };
+        let is_quad_player = (a < 12) && (2 < b);
+
+        if is_quad_player && !fold {
+            let b = if 4 < b { 4 } else { b };
+            (OUTPUT_P3_IDX + a + b - 3) as usize
+        } else if is_quad_player {
+            (a + ((b - 1) & 0x1)) as usize
         } else {
-            b
-        };
-        (a + b - 1) as usize
+            let b = if 2 < b { 2 } else { b };
+            (a + b - 1) as usize
+        }
     }
 
     pub const fn const_str(&self) -> &'static str {
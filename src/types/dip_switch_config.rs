@@ -7,7 +7,7 @@
 use bit_field::BitField;
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 
-use crate::boards::{PLAYER_1_INDEX, PLAYER_2_INDEX, PLAYER_INDEX_MAX};
+use crate::boards::{PLAYER_1_INDEX, PLAYER_2_INDEX};
 use crate::semi_layer::timing::ToggleTiming;
 
 // Dip Switch spec
@@ -89,7 +89,7 @@ impl InhibitOverride {
     pub fn check_new_released(&self, previous: &Self) -> (bool, bool) {
         let self_u8 = *self as u8;
         let previous_u8 = *previous as u8;
-        let masked = (self_u8 ^ previous_u8) & ((!self_u8) & ((1 << PLAYER_INDEX_MAX) - 1));
+        let masked = (self_u8 ^ previous_u8) & ((!self_u8) & 0b11);
 
         (
             masked.get_bit(PLAYER_1_INDEX),
@@ -141,7 +141,7 @@ impl defmt::Format for InhibitOverride {
 ///
 /// - Timing SW `01`, `10`, `11` ignores the pulse duration of all signal sources and
 ///   fixes it to one of 50 mS, 100 mS, and 200 mS and outputs it.
-#[derive(TryFromPrimitive, IntoPrimitive, PartialEq, PartialOrd)]
+#[derive(TryFromPrimitive, IntoPrimitive, PartialEq, PartialOrd, Clone, Copy)]
 #[repr(u8)]
 #[allow(dead_code)]
 pub enum TimingOverride {
@@ -0,0 +1,53 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+use static_assertions::*;
+use zeroable::Zeroable;
+
+use crate::types::buffered_opendrain_kind::MeterKind;
+use crate::types::player::Player;
+
+/// Card and coin income counters of player 3 and 4, index 0 is player 3.
+/// Player 1 and 2 have own sections, but layout header has no room for four more sections.
+#[repr(C)]
+#[derive(Zeroable, Clone)]
+pub struct ExtraPlayerCount {
+    pub card: [u32; 2],
+    pub coin: [u32; 2],
+}
+assert_eq_size!(ExtraPlayerCount, [u8; 16]);
+
+impl ExtraPlayerCount {
+    const fn index(player: Player) -> Option<usize> {
+        match player {
+            Player::Player3 => Some(0),
+            Player::Player4 => Some(1),
+            _ => None,
+        }
+    }
+
+    /// Counter of player 3 or 4, zero for other players
+    pub fn get(&self, kind: MeterKind, player: Player) -> u32 {
+        let counters = match kind {
+            MeterKind::Card => &self.card,
+            MeterKind::Coin => &self.coin,
+        };
+
+        Self::index(player).map_or(0, |idx| counters[idx])
+    }
+
+    /// Set counter of player 3 or 4, other players are ignored
+    pub fn set(&mut self, kind: MeterKind, player: Player, count: u32) {
+        let counters = match kind {
+            MeterKind::Card => &mut self.card,
+            MeterKind::Coin => &mut self.coin,
+        };
+
+        if let Some(idx) = Self::index(player) {
+            counters[idx] = count;
+        }
+    }
+}
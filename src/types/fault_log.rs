@@ -7,6 +7,8 @@
 use static_assertions::*;
 use zeroable::Zeroable;
 
+use crate::semi_layer::heartbeat::HeartbeatMissing;
+
 #[repr(C, packed(2))]
 #[derive(Clone)]
 pub struct FaultLog {
@@ -15,6 +17,36 @@ pub struct FaultLog {
 }
 assert_eq_size!(FaultLog, [u8; 6]);
 
+impl FaultLog {
+    /// Returns error code only if the fault is happened on previous boot, otherwise `FaultCode::NONE`
+    pub fn previous_boot_error(&self, hw_boot_cnt: u32) -> u16 {
+        let (current_boot_cnt, error_code) = (self.current_boot_cnt, self.error_code);
+
+        match current_boot_cnt.wrapping_add(1) == hw_boot_cnt {
+            true => error_code,
+            false => FaultCode::NONE,
+        }
+    }
+}
+
+/// Helper for `FaultLog::error_code`
+/// +-----------+-----------+-----------+
+/// | b15..b12  | b11..b8   | b7..b0    |
+/// +-----------+-----------+-----------+
+/// | category  | kind      | detail    |
+/// +-----------+-----------+-----------+
+pub struct FaultCode;
+
+impl FaultCode {
+    pub const NONE: u16 = 0;
+    pub const CATEGORY_WATCHDOG: u16 = 0x1;
+
+    /// category : watchdog, kind : `HeartbeatKind`, detail : heartbeat slot
+    pub const fn watchdog(missing: HeartbeatMissing) -> u16 {
+        (Self::CATEGORY_WATCHDOG << 12) | ((missing.kind as u16) << 8) | (missing.slot as u16)
+    }
+}
+
 unsafe impl Zeroable for FaultLog {
     fn zeroed() -> Self {
         Self {
@@ -0,0 +1,125 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Hardware revision of BillMock board.
+//! Board layout is selected through this type instead of scattered `hw_*` feature checks.
+//! `hw_auto` image carries several layouts and selects one on boot by the revision on OTP.
+
+use num_enum::TryFromPrimitive;
+
+/// Revision record on last double word of OTP, written by billmock-mptool.
+/// `[b'H', b'W', revision, !revision]`, rest of the double word is left blank.
+const OTP_HW_REVISION_ADDR: usize = 0x1FFF_73F8;
+const OTP_HW_REVISION_MAGIC: [u8; 2] = *b"HW";
+
+#[allow(dead_code)]
+#[repr(u8)]
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq, TryFromPrimitive)]
+pub enum HwRevision {
+    /// BillMock 0.2, deprecated
+    V0v2 = 0x02,
+    /// BillMock 0.3, deprecated
+    V0v3 = 0x03,
+    /// BillMock 0.4 (Square)
+    V0v4 = 0x04,
+    /// BillMock Mini 0.4 (Rectangular)
+    Mini0v4 = 0x14,
+    /// BillMock Mini 0.5 (Rectangular)
+    Mini0v5 = 0x15,
+    /// BillMock Quad 0.1, four player channels on STM32G070RB
+    Quad0v1 = 0x21,
+}
+
+impl HwRevision {
+    /// Revision that firmware is built for, selected by `hw_*` feature
+    #[cfg(feature = "hw_0v2")]
+    pub const TARGET: Self = Self::V0v2;
+    #[cfg(feature = "hw_0v3")]
+    pub const TARGET: Self = Self::V0v3;
+    #[cfg(feature = "hw_0v4")]
+    pub const TARGET: Self = Self::V0v4;
+    #[cfg(feature = "hw_mini_0v4")]
+    pub const TARGET: Self = Self::Mini0v4;
+    #[cfg(feature = "hw_mini_0v5")]
+    pub const TARGET: Self = Self::Mini0v5;
+    #[cfg(feature = "hw_quad_0v1")]
+    pub const TARGET: Self = Self::Quad0v1;
+    /// Only for build time checks of hardware shape features, `hw_auto` image doesn't boot
+    /// a board as this revision without the record on OTP. See `detect`.
+    #[cfg(feature = "hw_auto")]
+    pub const TARGET: Self = Self::Mini0v5;
+
+    /// `model_ver` of `.mp_fingerprint` of the image
+    #[cfg(not(feature = "hw_auto"))]
+    pub const IMAGE_MODEL_VER: &'static str = Self::TARGET.model_ver();
+    #[cfg(feature = "hw_auto")]
+    pub const IMAGE_MODEL_VER: &'static str = "AUTO";
+
+    /// Same naming with `model_ver` of `.mp_fingerprint`
+    pub const fn model_ver(self) -> &'static str {
+        match self {
+            Self::V0v2 => "0V2",
+            Self::V0v3 => "0V3",
+            Self::V0v4 => "0V4",
+            Self::Mini0v4 => "MINI-0V4",
+            Self::Mini0v5 => "MINI-0V5",
+            Self::Quad0v1 => "QUAD-0V1",
+        }
+    }
+
+    /// I2C EEPROM is mounted, `eeprom` feature
+    pub const fn has_eeprom(self) -> bool {
+        !matches!(self, Self::V0v2 | Self::V0v3)
+    }
+
+    /// SVC button (tactile switch) is mounted, `svc_button` feature
+    pub const fn has_svc_button(self) -> bool {
+        matches!(self, Self::Mini0v5 | Self::Quad0v1)
+    }
+
+    /// Host side inhibit input floats (#19), `hotfix_hwbug_host_inhibit_floating` feature
+    pub const fn has_inhibit_floating_bug(self) -> bool {
+        matches!(self, Self::V0v2 | Self::V0v3)
+    }
+
+    /// Layout of the revision is in `hw_auto` image, STM32G030C8 boards with EEPROM
+    /// and without floating host inhibit. Spare pins of mini 0.5 are not used on it.
+    pub const fn is_in_auto_image(self) -> bool {
+        matches!(self, Self::V0v4 | Self::Mini0v4 | Self::Mini0v5)
+    }
+
+    /// Revision written on OTP, `None` when it's blank or broken.
+    pub fn from_otp() -> Option<Self> {
+        // SAFETY: OTP area is always readable on STM32G0, blank one reads as 0xFF.
+        let raw = unsafe { core::ptr::read_volatile(OTP_HW_REVISION_ADDR as *const [u8; 4]) };
+
+        if (raw[0..2] != OTP_HW_REVISION_MAGIC) || (raw[2] != !raw[3]) {
+            return None;
+        }
+
+        Self::try_from(raw[2]).ok()
+    }
+
+    /// Revision of the board on boot, image of single revision only warns when OTP says other one.
+    /// `hw_auto` image follows the revision on OTP and doesn't guess without it,
+    /// layouts of the image use same pins differently. `None` on it when OTP doesn't have
+    /// one of its layouts, the revision should be programmed first.
+    pub fn detect() -> Option<Self> {
+        match Self::from_otp() {
+            Some(x) if x == Self::TARGET => Some(x),
+            Some(x) if cfg!(feature = "hw_auto") && x.is_in_auto_image() => Some(x),
+            x if cfg!(feature = "hw_auto") => {
+                defmt::error!("HW revision on OTP is {}, it's not in hw_auto image", x);
+                None
+            }
+            None => Some(Self::TARGET),
+            Some(x) => {
+                defmt::warn!("HW revision on OTP is {}, {} is used", x, Self::TARGET);
+                Some(Self::TARGET)
+            }
+        }
+    }
+}
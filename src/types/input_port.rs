@@ -25,12 +25,25 @@ pub enum InputPortKind {
     Inhibit1P = 8,
     Inhibit2P = 9,
     SvcButton = 10,
+    // Hopper / ticket dispenser feedback sensor
+    PayoutSense = 11,
     // Ignored signal by filter function
-    Nothing = 11,
+    Nothing = 12,
+    // Player 3 and 4 ports of quad board, appended to keep raw value of the others
+    Start3P = 13,
+    Start4P = 14,
+    Vend3P = 15,
+    Vend4P = 16,
+    Jam3P = 17,
+    Jam4P = 18,
+    StartJam3P = 19,
+    StartJam4P = 20,
+    Inhibit3P = 21,
+    Inhibit4P = 22,
 }
 
 #[cfg(debug_assertions)]
-const INPUT_PORT_KIND_STRS: [&str; 12] = [
+const INPUT_PORT_KIND_STRS: [&str; 23] = [
     "VendIn_1P-Start  ",
     "VendIn_2P-Start  ",
     "VendIn_1P-Vend   ",
@@ -42,12 +55,23 @@ const INPUT_PORT_KIND_STRS: [&str; 12] = [
     "HostIn_1P-Inhibit",
     "HostIn_2P-Inhibit",
     "SVC_Button       ",
+    "PayoutSense      ",
     "Nothing",
+    "VendIn_3P-Start  ",
+    "VendIn_4P-Start  ",
+    "VendIn_3P-Vend   ",
+    "VendIn_4P-Vend   ",
+    "VendIn_3P-Jam    ",
+    "VendIn_4P-Jam    ",
+    "VendIn_3P-STR/JAM",
+    "VendIn_4P-STR/JAM",
+    "HostIn_3P-Inhibit",
+    "HostIn_4P-Inhibit",
 ];
 
 #[cfg(not(debug_assertions))]
 #[rustfmt::skip]
-const INPUT_PORT_KIND_STRS: [&str; 12] = [
+const INPUT_PORT_KIND_STRS: [&str; 23] = [
     "P1V-iSTR",
     "P2V-iSTR",
     "P1V-iVND",
@@ -59,7 +83,18 @@ const INPUT_PORT_KIND_STRS: [&str; 12] = [
     "P1H-iINH",
     "P2H-iINH",
     "iSVC_BT ",
+    "iPAYOUT ",
     "iNothing",
+    "P3V-iSTR",
+    "P4V-iSTR",
+    "P3V-iVND",
+    "P4V-iVND",
+    "P3V-iJAM",
+    "P4V-iJAM",
+    "P3V-iS/J",
+    "P4V-iS/J",
+    "P3H-iINH",
+    "P4H-iINH",
 ];
 
 // assert_eq!(InputPortKind::count(), INPUT_PORT_KIND_STRS.len());
@@ -94,25 +129,80 @@ impl const ConstInto<RawInputPortKind> for InputPortKind {
 }
 
 impl InputPortKind {
-    pub const fn to_raw_and_const_str(self, player: Player) -> (RawInputPortKind, &'static str) {
+    /// Raw value of player 1 port of same kind, common port is kept as is
+    const fn player1_raw(self) -> RawInputPortKind {
         let idx = self as u8;
 
-        // PartialEq doesn't support const boundary
-        // if Player::Undefined == player {
-        if (Player::Undefined as u8 == player as u8) || (idx == Self::SvcButton as u8) {
-            let ret: RawInputPortKind = self as u8;
-            (ret, INPUT_PORT_KIND_STRS[ret as usize])
-        } else if Self::Nothing as u8 != idx {
-            let ret = (idx + player as u8 - 1) as RawInputPortKind;
-            (ret, INPUT_PORT_KIND_STRS[ret as usize])
+        if idx <= Self::Inhibit2P as u8 {
+            idx & !0x1
+        } else if idx >= Self::Start3P as u8 {
+            (idx - Self::Start3P as u8) & !0x1
         } else {
-            (self as RawInputPortKind, self.const_str())
+            idx
         }
     }
 
+    /// Raw value and name of same kind of port for the player.
+    /// Common port and `Player::Undefined` keep the port as is.
+    pub const fn to_raw_and_const_str(self, player: Player) -> (RawInputPortKind, &'static str) {
+        let idx = self.player1_raw();
+
+        // PartialEq doesn't support const boundary
+        let ret: RawInputPortKind =
+            if (idx > Self::Inhibit2P as u8) || (Player::Undefined as u8 == player as u8) {
+                self as u8
+            } else if (player as u8) <= (Player::Player2 as u8) {
+                idx + player as u8 - 1
+            } else {
+                Self::Start3P as u8 + idx + player as u8 - 3
+            };
+
+        (ret, INPUT_PORT_KIND_STRS[ret as usize])
+    }
+
     pub const fn const_str(self) -> &'static str {
         INPUT_PORT_KIND_STRS[self as usize]
     }
+
+    /// Player of the port, common port is `Player::Undefined`
+    pub const fn player(self) -> Player {
+        let idx = self as u8;
+
+        if idx <= Self::Inhibit2P as u8 {
+            match idx & 0x1 {
+                0 => Player::Player1,
+                _ => Player::Player2,
+            }
+        } else if idx >= Self::Start3P as u8 {
+            match (idx - Self::Start3P as u8) & 0x1 {
+                0 => Player::Player3,
+                _ => Player::Player4,
+            }
+        } else {
+            Player::Undefined
+        }
+    }
+
+    /// Same kind of port for the player, common port is kept as is.
+    pub fn for_player(self, player: Player) -> Self {
+        let (raw, _) = self.to_raw_and_const_str(player);
+
+        Self::try_from(raw).unwrap_or(self)
+    }
+
+    /// Same kind of port for the other player of the pair (1P and 2P, 3P and 4P),
+    /// common port is kept as is.
+    pub fn flip_player(self) -> Self {
+        let player = match self.player() {
+            Player::Player1 => Player::Player2,
+            Player::Player2 => Player::Player1,
+            Player::Player3 => Player::Player4,
+            Player::Player4 => Player::Player3,
+            Player::Undefined => Player::Undefined,
+        };
+
+        self.for_player(player)
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -0,0 +1,479 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Install config, site specific settings that configured by installer or operator.
+//! Stored on single section of Novella, zero-filled config means factory default.
+
+use static_assertions::*;
+use zeroable::Zeroable;
+
+use crate::types::input_port::InputPortKind;
+use crate::types::player::Player;
+use crate::types::port_polarity::PortPolarity;
+
+pub const SCHEDULE_ENTRY_NUM: usize = 4;
+pub const IO_ROUTE_NUM: usize = 4;
+
+/// Schedule time is handled in 10 minutes unit, 0 ..= 143 in a day.
+pub const SCHEDULE_SLOT_SECS: u32 = 600;
+pub const SCHEDULE_SLOT_PER_DAY: u8 = 144;
+
+#[repr(u8)]
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub enum ScheduleAction {
+    /// Entry is not used
+    Idle = 0,
+    /// Bonus credits on card payment, param is bonus in 10 percent unit
+    HappyHour = 1,
+    /// Closing time, inhibit whole payment
+    Closed = 2,
+    /// Free-play window
+    FreePlay = 3,
+}
+
+/// Single schedule entry
+/// +----------+----------+----------+----------------------+
+/// | byte 0   | byte 1   | byte 2   | byte 3               |
+/// +----------+----------+----------+----------------------+
+/// | weekdays | start    | end      | action b7..6         |
+/// | b0 Mon.. | 10 min   | 10 min   | param  b5..0         |
+/// +----------+----------+----------+----------------------+
+/// - `end` is exclusive, entry passes midnight when `end` is less than `start`.
+#[repr(C)]
+#[derive(Debug, Zeroable, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub struct RawScheduleEntry {
+    pub weekdays: u8,
+    pub start: u8,
+    pub end: u8,
+    pub action_param: u8,
+}
+assert_eq_size!(RawScheduleEntry, [u8; 4]);
+
+impl RawScheduleEntry {
+    pub fn action(&self) -> ScheduleAction {
+        match self.action_param >> 6 {
+            1 => ScheduleAction::HappyHour,
+            2 => ScheduleAction::Closed,
+            3 => ScheduleAction::FreePlay,
+            _ => ScheduleAction::Idle,
+        }
+    }
+
+    pub fn param(&self) -> u8 {
+        self.action_param & 0x3F
+    }
+
+    /// `weekday` 0 is Monday, `slot` is 10 minutes unit of the day.
+    pub fn is_active(&self, weekday: u8, slot: u8) -> bool {
+        if self.action() == ScheduleAction::Idle {
+            return false;
+        }
+
+        let yesterday = (weekday + 6) % 7;
+        let is_day = |day: u8| (self.weekdays & (1 << day)) != 0;
+
+        if self.start <= self.end {
+            is_day(weekday) && (self.start <= slot) && (slot < self.end)
+        } else {
+            // passes midnight, the entry belongs to the day it started
+            (is_day(weekday) && (self.start <= slot)) || (is_day(yesterday) && (slot < self.end))
+        }
+    }
+}
+
+impl From<u32> for RawScheduleEntry {
+    fn from(value: u32) -> Self {
+        let [weekdays, start, end, action_param] = value.to_be_bytes();
+
+        Self {
+            weekdays,
+            start: start.min(SCHEDULE_SLOT_PER_DAY),
+            end: end.min(SCHEDULE_SLOT_PER_DAY),
+            action_param,
+        }
+    }
+}
+
+/// `RawIoRoute::flags` b0, drop the input event
+pub const IO_ROUTE_FLAG_IGNORE: u8 = 1 << 0;
+/// `RawIoRoute::flags` b1, invert pressed / released level
+pub const IO_ROUTE_FLAG_INVERT: u8 = 1 << 1;
+/// `RawIoRoute::flags` b2, keep output high at least `RawIoRoute::stretch_ms`
+pub const IO_ROUTE_FLAG_STRETCH: u8 = 1 << 2;
+/// `RawIoRoute::flags` b3, the route is applied for the other player symmetrically
+pub const IO_ROUTE_FLAG_MIRROR: u8 = 1 << 3;
+/// `RawIoRoute::flags` b4..b6, minimum high duration of stretch in (n + 1) * 50 ms
+pub const IO_ROUTE_FLAG_STRETCH_WIDTH_SHIFT: u8 = 4;
+/// `RawIoRoute::flags` b7, zero-filled route is not used
+pub const IO_ROUTE_FLAG_ENABLE: u8 = 1 << 7;
+
+/// Step of minimum high duration of `IO_ROUTE_FLAG_STRETCH`
+pub const IO_ROUTE_STRETCH_STEP_MS: u16 = 50;
+
+/// Single I/O routing rule, routes an input to an output with transformations.
+/// Input without any rule follows built-in routing, several rules of same input fan-out.
+/// +----------+----------+----------------------+
+/// | byte 0   | byte 1   | byte 2               |
+/// +----------+----------+----------------------+
+/// | input    | output   | flags                |
+/// +----------+----------+----------------------+
+/// - `input` and `output` are raw value of `InputPortKind`.
+/// - `flags` is bit flags of `IO_ROUTE_FLAG_*`.
+#[repr(C)]
+#[derive(Debug, Zeroable, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub struct RawIoRoute {
+    pub input: u8,
+    pub output: u8,
+    pub flags: u8,
+}
+assert_eq_size!(RawIoRoute, [u8; 3]);
+
+impl RawIoRoute {
+    pub const fn new(input: InputPortKind, output: InputPortKind, flags: u8) -> Self {
+        Self {
+            input: input as u8,
+            output: output as u8,
+            flags,
+        }
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        (self.flags & IO_ROUTE_FLAG_ENABLE) != 0
+    }
+
+    #[inline]
+    pub fn has_flag(&self, flag: u8) -> bool {
+        (self.flags & flag) != 0
+    }
+
+    /// Minimum high duration of output port, 0 when the route doesn't stretch.
+    pub fn stretch_ms(&self) -> u16 {
+        match self.is_enabled() && self.has_flag(IO_ROUTE_FLAG_STRETCH) {
+            true => {
+                let width = (self.flags >> IO_ROUTE_FLAG_STRETCH_WIDTH_SHIFT) & 0b111;
+                (width as u16 + 1) * IO_ROUTE_STRETCH_STEP_MS
+            }
+            false => 0,
+        }
+    }
+}
+
+impl TryFrom<u32> for RawIoRoute {
+    type Error = u32;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        let [input, output, flags, _] = value.to_be_bytes();
+
+        // both ports should be known port
+        match (
+            InputPortKind::try_from(input),
+            InputPortKind::try_from(output),
+        ) {
+            (Ok(_), Ok(_)) => Ok(Self {
+                input,
+                output,
+                flags,
+            }),
+            _ => Err(value),
+        }
+    }
+}
+
+/// Cabinet profile, how many players and coin lines the cabinet has
+#[repr(u8)]
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub enum CabinetProfile {
+    /// Each player has own coin line (default), every player of the board is used
+    TwoPlayer = 0,
+    /// Single player, sides of player 2 and later are not used
+    SinglePlayer = 1,
+    /// Players sharing single coin line of player 1
+    SharedCredit = 2,
+}
+
+impl CabinetProfile {
+    /// Player whose coin line takes income of the player
+    pub fn income_player(self, player: Player) -> Player {
+        match self {
+            Self::TwoPlayer => player,
+            Self::SinglePlayer | Self::SharedCredit => Player::Player1,
+        }
+    }
+}
+
+/// Role on board-to-board link, chaining two boards behind single card terminal
+#[repr(u8)]
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub enum LinkRole {
+    /// Board-to-board link is not used (default)
+    Standalone = 0,
+    /// Owns card terminal, forwards payment of player 3 and 4 to secondary board
+    Primary = 1,
+    /// Takes player 3 and 4 from primary board, card terminal is not connected
+    Secondary = 2,
+}
+
+/// Language of operator screens on card terminal LCD
+#[repr(u8)]
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub enum Language {
+    /// Built-in screens of card terminal plug (default)
+    Terminal = 0,
+    Korean = 1,
+    English = 2,
+    Japanese = 3,
+}
+
+impl From<u8> for Language {
+    fn from(value: u8) -> Self {
+        match value {
+            1 => Self::Korean,
+            2 => Self::English,
+            3 => Self::Japanese,
+            _ => Self::Terminal,
+        }
+    }
+}
+
+/// `InstallConfig::flags` b0, free-play mode
+pub const INSTALL_FLAG_FREE_PLAY: u8 = 1 << 0;
+/// `InstallConfig::flags` b1, swap player 1 and 2 of whole inputs
+pub const INSTALL_FLAG_SWAP_PLAYERS: u8 = 1 << 1;
+
+/// Default decision timeout of start-button-decide mode, when config is zero
+pub const DEFAULT_DECIDE_TIMEOUT_SECS: u8 = 30;
+
+/// Action on held payment when nobody presses start button in time
+#[repr(u8)]
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub enum DecideFallback {
+    /// Cancel payment if card terminal supports, otherwise credit to player 1
+    Auto = 0,
+    Player1 = 1,
+    Player2 = 2,
+}
+
+#[repr(C)]
+#[derive(Zeroable, Clone)]
+pub struct InstallConfig {
+    pub schedule: [RawScheduleEntry; SCHEDULE_ENTRY_NUM],
+    /// Bit flags, `INSTALL_FLAG_*`
+    pub flags: u8,
+    /// Start-button-decide mode timeout in seconds, zero is default
+    pub decide_timeout_secs: u8,
+    /// Start-button-decide mode fallback, `DecideFallback`
+    pub decide_fallback: u8,
+    /// I/O routing rules
+    pub routes: [RawIoRoute; IO_ROUTE_NUM],
+    /// `PortPolarity::input_active_high` in little endian
+    pub input_polarity: [u8; 2],
+    /// `PortPolarity::output_inverted` in little endian
+    pub output_polarity: [u8; 2],
+    /// `CabinetProfile`
+    pub cabinet: u8,
+    /// `LinkRole`
+    pub link_role: u8,
+    /// `Language`
+    pub language: u8,
+    /// Bit mask of health faults that don't force safe state, zero is default policy
+    pub safe_state_exempt: u8,
+    /// Bit mask of inhibit sources that inhibit only when all of them hold, zero is any source
+    pub inhibit_all_mask: u8,
+    /// `PortPolarity::output_open_drain` in little endian
+    pub output_open_drain: [u8; 2],
+}
+assert_eq_size!(InstallConfig, [u8; 42]);
+
+/// Key of `RawConfigItem` that written by card terminal
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq)]
+pub enum InstallConfigKey {
+    /// 0x10 ..= 0x13, schedule entry 0 ..= 3
+    Schedule(u8),
+    /// 0x20, free-play mode, zero is disabled
+    FreePlay,
+    /// 0x21, start-button-decide timeout in seconds, zero is default
+    DecideTimeout,
+    /// 0x22, start-button-decide fallback, `DecideFallback`
+    DecideFallback,
+    /// 0x23, swap player 1 and 2, zero is disabled
+    SwapPlayers,
+    /// 0x24, input polarity bit mask, set is active high
+    InputPolarity,
+    /// 0x25, output polarity bit mask, set is inverted
+    OutputPolarity,
+    /// 0x26, cabinet profile, `CabinetProfile`
+    Cabinet,
+    /// 0x27, board-to-board link role, `LinkRole`
+    LinkRole,
+    /// 0x28, language of terminal screens, `Language`
+    Language,
+    /// 0x29, health faults that don't force safe state, bit mask
+    SafeStateExempt,
+    /// 0x2A, inhibit sources combined by AND policy, bit mask
+    InhibitAllMask,
+    /// 0x2B, output drive mode bit mask, set is open-drain
+    OutputOpenDrain,
+    /// 0x30 ..= 0x33, I/O routing rule 0 ..= 3
+    IoRoute(u8),
+}
+
+impl TryFrom<u8> for InstallConfigKey {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x10..=0x13 => Ok(Self::Schedule(value - 0x10)),
+            0x20 => Ok(Self::FreePlay),
+            0x21 => Ok(Self::DecideTimeout),
+            0x22 => Ok(Self::DecideFallback),
+            0x23 => Ok(Self::SwapPlayers),
+            0x24 => Ok(Self::InputPolarity),
+            0x25 => Ok(Self::OutputPolarity),
+            0x26 => Ok(Self::Cabinet),
+            0x27 => Ok(Self::LinkRole),
+            0x28 => Ok(Self::Language),
+            0x29 => Ok(Self::SafeStateExempt),
+            0x2A => Ok(Self::InhibitAllMask),
+            0x2B => Ok(Self::OutputOpenDrain),
+            0x30..=0x33 => Ok(Self::IoRoute(value - 0x30)),
+            x => Err(x),
+        }
+    }
+}
+
+impl InstallConfig {
+    pub fn is_free_play(&self) -> bool {
+        (self.flags & INSTALL_FLAG_FREE_PLAY) != 0
+    }
+
+    pub fn is_swap_players(&self) -> bool {
+        (self.flags & INSTALL_FLAG_SWAP_PLAYERS) != 0
+    }
+
+    pub fn polarity(&self) -> PortPolarity {
+        PortPolarity {
+            input_active_high: u16::from_le_bytes(self.input_polarity),
+            output_inverted: u16::from_le_bytes(self.output_polarity),
+            output_open_drain: u16::from_le_bytes(self.output_open_drain),
+        }
+    }
+
+    pub fn cabinet(&self) -> CabinetProfile {
+        match self.cabinet {
+            1 => CabinetProfile::SinglePlayer,
+            2 => CabinetProfile::SharedCredit,
+            _ => CabinetProfile::TwoPlayer,
+        }
+    }
+
+    pub fn link_role(&self) -> LinkRole {
+        match self.link_role {
+            1 => LinkRole::Primary,
+            2 => LinkRole::Secondary,
+            _ => LinkRole::Standalone,
+        }
+    }
+
+    pub fn language(&self) -> Language {
+        Language::from(self.language)
+    }
+
+    pub fn decide_timeout_secs(&self) -> u8 {
+        match self.decide_timeout_secs {
+            0 => DEFAULT_DECIDE_TIMEOUT_SECS,
+            x => x,
+        }
+    }
+
+    pub fn decide_fallback(&self) -> DecideFallback {
+        match self.decide_fallback {
+            1 => DecideFallback::Player1,
+            2 => DecideFallback::Player2,
+            _ => DecideFallback::Auto,
+        }
+    }
+
+    /// Apply config item, returns false if the key is unknown or the value is invalid
+    pub fn apply(&mut self, key: u8, value: u32) -> bool {
+        match InstallConfigKey::try_from(key) {
+            Ok(InstallConfigKey::Schedule(idx)) => {
+                self.schedule[idx as usize] = RawScheduleEntry::from(value);
+                true
+            }
+            Ok(InstallConfigKey::FreePlay) => {
+                self.flags = match value {
+                    0 => self.flags & !INSTALL_FLAG_FREE_PLAY,
+                    _ => self.flags | INSTALL_FLAG_FREE_PLAY,
+                };
+                true
+            }
+            Ok(InstallConfigKey::DecideTimeout) => {
+                self.decide_timeout_secs = value.min(u8::MAX as u32) as u8;
+                true
+            }
+            Ok(InstallConfigKey::DecideFallback) if value <= DecideFallback::Player2 as u32 => {
+                self.decide_fallback = value as u8;
+                true
+            }
+            Ok(InstallConfigKey::SwapPlayers) => {
+                self.flags = match value {
+                    0 => self.flags & !INSTALL_FLAG_SWAP_PLAYERS,
+                    _ => self.flags | INSTALL_FLAG_SWAP_PLAYERS,
+                };
+                true
+            }
+            Ok(InstallConfigKey::InputPolarity) => {
+                self.input_polarity = (value as u16).to_le_bytes();
+                true
+            }
+            Ok(InstallConfigKey::OutputPolarity) => {
+                self.output_polarity = (value as u16).to_le_bytes();
+                true
+            }
+            Ok(InstallConfigKey::Cabinet) if value <= CabinetProfile::SharedCredit as u32 => {
+                self.cabinet = value as u8;
+                true
+            }
+            Ok(InstallConfigKey::LinkRole) if value <= LinkRole::Secondary as u32 => {
+                self.link_role = value as u8;
+                true
+            }
+            Ok(InstallConfigKey::Language) if value <= Language::Japanese as u32 => {
+                self.language = value as u8;
+                true
+            }
+            Ok(InstallConfigKey::SafeStateExempt) if value <= u8::MAX as u32 => {
+                self.safe_state_exempt = value as u8;
+                true
+            }
+            Ok(InstallConfigKey::InhibitAllMask) if value <= u8::MAX as u32 => {
+                self.inhibit_all_mask = value as u8;
+                true
+            }
+            Ok(InstallConfigKey::OutputOpenDrain) => {
+                self.output_open_drain = (value as u16).to_le_bytes();
+                true
+            }
+            Ok(InstallConfigKey::IoRoute(idx)) => match RawIoRoute::try_from(value) {
+                Ok(route) => {
+                    self.routes[idx as usize] = route;
+                    true
+                }
+                Err(_) => false,
+            },
+            Ok(
+                InstallConfigKey::DecideFallback
+                | InstallConfigKey::Cabinet
+                | InstallConfigKey::LinkRole
+                | InstallConfigKey::Language
+                | InstallConfigKey::SafeStateExempt
+                | InstallConfigKey::InhibitAllMask,
+            )
+            | Err(_) => false,
+        }
+    }
+}
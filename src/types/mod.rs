@@ -15,3 +15,13 @@ pub mod player;
 pub mod buffered_opendrain_kind;
 
 pub mod fault_log;
+
+pub mod hw_revision;
+
+pub mod install_config;
+
+pub mod port_polarity;
+
+pub mod service_count;
+
+pub mod extra_player_count;
@@ -26,12 +26,68 @@ pub enum Player {
     Undefined = 0,
     Player1 = 1,
     Player2 = 2,
+    Player3 = 3,
+    Player4 = 4,
 }
 
+/// Players of a board in index order, `Player::Undefined` is excluded.
+#[cfg(not(feature = "hw_quad_0v1"))]
+pub const PLAYERS: [Player; 2] = [Player::Player1, Player::Player2];
+/// Players of a board in index order, `Player::Undefined` is excluded.
+/// Quad board has four player channels on single board.
+#[cfg(feature = "hw_quad_0v1")]
+pub const PLAYERS: [Player; 4] = [
+    Player::Player1,
+    Player::Player2,
+    Player::Player3,
+    Player::Player4,
+];
+
 impl Player {
     pub const fn default() -> Self {
         Self::Undefined
     }
+
+    /// Index of player arrays (e.g. `Hardware::vend_sides`), `None` for `Player::Undefined`
+    pub const fn index(self) -> Option<usize> {
+        match self {
+            Self::Undefined => None,
+            x => Some(x as usize - 1),
+        }
+    }
+
+    /// Player of index of player arrays, out of range is `Player::Undefined`
+    pub const fn from_index(idx: usize) -> Self {
+        if idx < PLAYERS.len() {
+            PLAYERS[idx]
+        } else {
+            Self::Undefined
+        }
+    }
+
+    /// Player of card terminal port number, ports are assigned to players in turn.
+    /// Port 3 and 4 are player 1 and 2 on two player board.
+    pub const fn from_port_num(port: u8) -> Self {
+        Self::from_index((port.clamp(1, 4) - 1) as usize % PLAYERS.len())
+    }
+
+    /// Card terminal port number of the player, `Player::Undefined` is port 1
+    pub const fn port_num(self) -> u8 {
+        match self.index() {
+            Some(idx) => idx as u8 + 1,
+            None => 1,
+        }
+    }
+
+    /// Player 1 or 2 of same parity. Player 3 and 4 follow port polarity of player 1 and 2,
+    /// and they're summed into player 1 and 2 on card terminal screen that shows two players.
+    pub const fn fold(self) -> Self {
+        match self {
+            Self::Player3 => Self::Player1,
+            Self::Player4 => Self::Player2,
+            x => x,
+        }
+    }
 }
 
 impl ConstInto<usize> for Player {
@@ -0,0 +1,47 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Signal polarity of each player port, for active high game PCB and acceptors.
+//! Zero-filled polarity means default, inputs are active low and outputs are not inverted push-pull.
+
+use bit_field::BitField;
+
+use crate::types::buffered_opendrain_kind::BufferedOpenDrainKind;
+use crate::types::input_port::InputPortKind;
+use crate::types::player::Player;
+
+#[derive(Debug, defmt::Format, Clone, Copy, Eq, PartialEq, Default)]
+pub struct PortPolarity {
+    /// Bit index is raw `InputPortKind`, set is active high.
+    /// Ports of player 3 and 4 follow player 1 and 2.
+    pub input_active_high: u16,
+    /// Bit index is `BufferedOpenDrainKind::get_polarity_idx`, set is inverted output level.
+    /// It's idle level of the output too, inverted output idles on high level.
+    pub output_inverted: u16,
+    /// Bit index is `BufferedOpenDrainKind::get_polarity_idx`, set is open-drain, otherwise push-pull
+    pub output_open_drain: u16,
+}
+
+impl PortPolarity {
+    /// `kind` should be player 1 port, it's shifted by player.
+    pub fn is_input_active_high(&self, kind: InputPortKind, player: Player) -> bool {
+        let (raw, _) = kind.to_raw_and_const_str(player.fold());
+
+        (raw as usize) < u16::BITS as usize && self.input_active_high.get_bit(raw as usize)
+    }
+
+    pub fn is_output_inverted(&self, kind: BufferedOpenDrainKind) -> bool {
+        let idx = kind.get_polarity_idx();
+
+        idx < u16::BITS as usize && self.output_inverted.get_bit(idx)
+    }
+
+    pub fn is_output_open_drain(&self, kind: BufferedOpenDrainKind) -> bool {
+        let idx = kind.get_polarity_idx();
+
+        idx < u16::BITS as usize && self.output_open_drain.get_bit(idx)
+    }
+}
@@ -0,0 +1,27 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+use static_assertions::*;
+use zeroable::Zeroable;
+
+use crate::types::player::Player;
+
+/// Service credits (given without payment) of each player, index is `Player::index`.
+/// Counted separately, thus never mixed with card or coin income count.
+#[repr(C)]
+#[derive(Zeroable, Clone)]
+pub struct ServiceCount {
+    pub players: [u32; 4],
+}
+assert_eq_size!(ServiceCount, [u8; 16]);
+
+impl ServiceCount {
+    /// `Player::Undefined` is counted on player 1
+    pub fn add(&mut self, player: Player, count: u32) {
+        let x = &mut self.players[player.index().unwrap_or(0)];
+        *x = x.saturating_add(count);
+    }
+}
@@ -0,0 +1,84 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Decode and check fingerprint of built firmware before flashing on production line.
+//! fw-fingerprint <FIRMWARE.elf | IMAGE.bmfw | DUMP.bin> [--hw <HW_FEATURE | MODEL_VER>]
+//!   [--allow-dirty] [--nda | --no-nda]
+//! Exit code is failure when any mismatch is found, thus it can gate flashing script.
+
+use std::process::ExitCode;
+
+use fw_uploader::fingerprint::{read_fingerprint, Expectation};
+
+const USAGE: &str = "usage: fw-fingerprint <FIRMWARE.elf | IMAGE.bmfw | DUMP.bin> \
+[--hw <HW_FEATURE | MODEL_VER>] [--allow-dirty] [--nda | --no-nda]";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some(path) = args.first() else {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    };
+
+    let mut expect = Expectation::default();
+    let mut options = args[1..].iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--hw" => match options.next() {
+                Some(hw) => expect.hw = Some(hw.to_owned()),
+                None => {
+                    eprintln!("{}", USAGE);
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--allow-dirty" => expect.allow_dirty = true,
+            "--nda" => expect.is_nda = Some(true),
+            "--no-nda" => expect.is_nda = Some(false),
+            _ => {
+                eprintln!("{}", USAGE);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let raw = match std::fs::read(path) {
+        Ok(raw) => raw,
+        Err(e) => {
+            eprintln!("Failed to read {} : {}", path, e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let (source, fingerprint) = match read_fingerprint(&raw) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("{} : {}", path, e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    println!("{} ({})", path, source);
+    println!("{}", fingerprint);
+
+    let mismatches = expect.check(&fingerprint);
+    if expect.hw.is_none() {
+        println!("warning: hardware is not checked, give --hw");
+    }
+    for mismatch in &mismatches {
+        eprintln!("MISMATCH : {}", mismatch);
+    }
+
+    match mismatches.is_empty() {
+        true => {
+            println!("OK");
+            ExitCode::SUCCESS
+        }
+        false => {
+            eprintln!("REJECTED, do not flash this image");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,102 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Signing step of firmware release, after `cargo build --release --features fw_update`.
+//! fw-sign keygen <SECRET_KEY> <PUBLIC_KEY>
+//! fw-sign sign <SECRET_KEY> <FIRMWARE.elf> <IMAGE.bmfw>
+//! fw-sign verify <PUBLIC_KEY> <IMAGE.bmfw>
+//! Public key is built in the bootloader by `BILLMOCK_FW_PUBLIC_KEY` of `bootloader/build.rs`.
+
+use std::process::ExitCode;
+
+use fw_update::SignedImage;
+use fw_uploader::*;
+
+const USAGE: &str = "usage:
+  fw-sign keygen <SECRET_KEY> <PUBLIC_KEY>
+  fw-sign sign <SECRET_KEY> <FIRMWARE.elf> <IMAGE.bmfw>
+  fw-sign verify <PUBLIC_KEY> <IMAGE.bmfw>";
+
+fn keygen(secret_path: &str, public_path: &str) -> Result<(), String> {
+    let key = generate_key().map_err(|e| format!("Failed to generate key : {}", e))?;
+
+    std::fs::write(secret_path, hex::encode(key.to_bytes()))
+        .map_err(|e| format!("Failed to write {} : {}", secret_path, e))?;
+    std::fs::write(public_path, hex::encode(key.verifying_key().to_bytes()))
+        .map_err(|e| format!("Failed to write {} : {}", public_path, e))?;
+
+    println!("Secret key {} , keep it out of repository", secret_path);
+    println!(
+        "Public key {} , give it to BILLMOCK_FW_PUBLIC_KEY",
+        public_path
+    );
+    Ok(())
+}
+
+fn sign(secret_path: &str, elf_path: &str, output: &str) -> Result<(), String> {
+    let key = read_secret_key(secret_path)
+        .map_err(|e| format!("Failed to read {} : {}", secret_path, e))?;
+    let elf =
+        std::fs::read(elf_path).map_err(|e| format!("Failed to read {} : {}", elf_path, e))?;
+
+    let image =
+        image_from_elf(&elf).ok_or(format!("{} doesn't have loadable segment", elf_path))?;
+    let trailer = trailer_from_elf(&elf, image.len())
+        .ok_or(format!("{} doesn't have valid .mp_fingerprint", elf_path))?;
+    if trailer.is_dirty {
+        eprintln!("warning: {} is built from dirty working tree", elf_path);
+    }
+
+    let signed = sign_image(&key, &image, &trailer);
+    std::fs::write(output, &signed).map_err(|e| format!("Failed to write {} : {}", output, e))?;
+
+    println!(
+        "Signed {} ({} bytes, {}, nda : {}) to {}",
+        elf_path,
+        image.len(),
+        String::from_utf8_lossy(&trailer.model_ver).trim_end_matches('\0'),
+        trailer.is_nda,
+        output
+    );
+    Ok(())
+}
+
+fn verify(public_path: &str, image_path: &str) -> Result<(), String> {
+    let key = read_public_key(public_path)
+        .map_err(|e| format!("Failed to read {} : {}", public_path, e))?;
+    let raw =
+        std::fs::read(image_path).map_err(|e| format!("Failed to read {} : {}", image_path, e))?;
+    let signed = SignedImage::parse(&raw)
+        .map_err(|e| format!("{} is not signed image : {:?}", image_path, e))?;
+
+    match verify_image(&key, signed.payload, &signed.signature) {
+        true => {
+            println!("{} is signed by {}", image_path, public_path);
+            Ok(())
+        }
+        false => Err(format!("{} is not signed by {}", image_path, public_path)),
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    let result = match args.as_slice() {
+        ["keygen", secret, public] => keygen(secret, public),
+        ["sign", secret, elf, output] => sign(secret, elf, output),
+        ["verify", public, image] => verify(public, image),
+        _ => Err(USAGE.to_owned()),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,79 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! PTY based board simulator, to try `fw-uploader` without real board.
+//! fw-sim <MODEL_VER> <PUBLIC_KEY> <OUTPUT.bin>
+//! Slave side of PTY is printed, give it to `fw-uploader` as port.
+
+use std::fs::File;
+use std::io::Read;
+use std::process::ExitCode;
+
+use fw_uploader::{open_pty, read_public_key, Simulator};
+
+/// Same size with active partition of `memory/fw-update.x`
+const ACTIVE_CAPACITY: usize = 42 * 1024;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (Some(model_ver), Some(key_path), Some(output)) = (args.first(), args.get(1), args.get(2))
+    else {
+        eprintln!("usage: fw-sim <MODEL_VER> <PUBLIC_KEY> <OUTPUT.bin>");
+        return ExitCode::FAILURE;
+    };
+
+    let key = match read_public_key(key_path) {
+        Ok(key) => key,
+        Err(e) => {
+            eprintln!("Failed to read {} : {}", key_path, e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let (mut master, slave, slave_path) = match open_pty() {
+        Ok(pty) => pty,
+        Err(e) => {
+            eprintln!("Failed to open PTY : {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    println!("Simulated board {} on {}", model_ver, slave_path);
+
+    let mut simulator = Simulator::new(model_ver, ACTIVE_CAPACITY, key);
+    let result = simulator.serve(&mut master);
+
+    // closing master hangs up slave, wait uploader reads last answer and closes the port
+    drop(slave);
+    while matches!(master.read(&mut [0u8; 16]), Ok(1..)) {}
+
+    match result {
+        Ok(true) => match File::create(output)
+            .and_then(|mut f| std::io::Write::write_all(&mut f, simulator.image()))
+        {
+            Ok(()) => {
+                println!(
+                    "Received {} bytes, saved to {}",
+                    simulator.image().len(),
+                    output
+                );
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                eprintln!("Failed to save {} : {}", output, e);
+                ExitCode::FAILURE
+            }
+        },
+        Ok(false) => {
+            println!("Aborted by uploader");
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("PTY error : {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
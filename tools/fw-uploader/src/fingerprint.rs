@@ -0,0 +1,233 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Read back `.mp_fingerprint` of built firmware, before it's flashed on production line.
+//! ELF has the TOML in `.mp_fingerprint` section, but the section is not loaded on flash.
+//! Thus raw flash dump and signed image are decoded from `ImageTrailer` of `fw_update` build.
+
+use std::fmt;
+
+use fw_update::{ImageTrailer, SignedImage, TRAILER_LEN, TRAILER_MAGIC};
+use object::{Object, ObjectSection};
+use serde::Deserialize;
+
+/// `model_name` of every BillMock firmware, const value of `build.rs`
+pub const MODEL_NAME: &str = "BillMock-HW";
+/// Application image starts on flash page boundary, behind bootloader or at flash start
+const IMAGE_ALIGN: usize = 2048;
+
+/// Same with `FirmwareFingerprint` of `mp-fingerprint-type` (billmock-mptool),
+/// kept here to decode without the NDA build environment.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct FirmwareFingerprint {
+    pub model_name: String,
+    pub model_ver: String,
+    pub firmware_ver: String,
+    /// Full git hash, `-dirty` is appended when working tree was modified
+    pub firmware_git_hash: String,
+    pub is_nda: bool,
+}
+
+/// Same with `MpFingerprint` of `mp-fingerprint-type`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+struct MpFingerprint {
+    firmware_fingerprint: FirmwareFingerprint,
+}
+
+fn field_str(raw: &[u8]) -> String {
+    String::from_utf8_lossy(raw)
+        .trim_end_matches('\0')
+        .to_owned()
+}
+
+impl FirmwareFingerprint {
+    pub fn is_dirty(&self) -> bool {
+        self.firmware_git_hash.ends_with("-dirty")
+    }
+
+    pub fn from_toml(text: &str) -> Option<Self> {
+        toml::from_str::<MpFingerprint>(text.trim_end_matches('\0'))
+            .ok()
+            .map(|x| x.firmware_fingerprint)
+    }
+
+    pub fn from_elf(elf: &[u8]) -> Option<Self> {
+        let file = object::File::parse(elf).ok()?;
+        let section = file.section_by_name(".mp_fingerprint")?;
+        Self::from_toml(std::str::from_utf8(section.data().ok()?).ok()?)
+    }
+
+    /// Trailer doesn't have `model_name`, it's always BillMock
+    pub fn from_trailer(trailer: &ImageTrailer) -> Self {
+        Self {
+            model_name: MODEL_NAME.to_owned(),
+            model_ver: field_str(&trailer.model_ver),
+            firmware_ver: field_str(&trailer.firmware_ver),
+            firmware_git_hash: format!(
+                "{}{}",
+                hex::encode(trailer.git_hash),
+                if trailer.is_dirty { "-dirty" } else { "" }
+            ),
+            is_nda: trailer.is_nda,
+        }
+    }
+}
+
+impl fmt::Display for FirmwareFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "model_name        : {}", self.model_name)?;
+        writeln!(f, "model_ver         : {}", self.model_ver)?;
+        writeln!(f, "firmware_ver      : {}", self.firmware_ver)?;
+        writeln!(f, "firmware_git_hash : {}", self.firmware_git_hash)?;
+        write!(f, "is_nda            : {}", self.is_nda)
+    }
+}
+
+/// Where the fingerprint is found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FingerprintSource {
+    /// `.mp_fingerprint` section of ELF
+    Elf,
+    /// Trailer of `fw-sign` output
+    SignedImage,
+    /// Trailer found in raw flash dump, offset of the image in the dump
+    FlashDump { offset: usize },
+}
+
+impl fmt::Display for FingerprintSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Elf => write!(f, "ELF .mp_fingerprint"),
+            Self::SignedImage => write!(f, "signed image trailer"),
+            Self::FlashDump { offset } => write!(f, "flash dump trailer, image at {:#07x}", offset),
+        }
+    }
+}
+
+/// Trailer right after the image, the image starts on page boundary of the dump
+fn find_trailer(raw: &[u8]) -> Option<(usize, ImageTrailer)> {
+    (0..raw.len().saturating_sub(TRAILER_LEN - 1))
+        .filter(|&pos| raw[pos..pos + TRAILER_MAGIC.len()] == TRAILER_MAGIC)
+        .find_map(|pos| {
+            let trailer = ImageTrailer::from_bytes(raw[pos..pos + TRAILER_LEN].try_into().ok()?);
+            let offset = pos.checked_sub(trailer.ok()?.image_size as usize)?;
+            (offset % IMAGE_ALIGN == 0).then_some((offset, trailer.ok()?))
+        })
+}
+
+/// Decode fingerprint from ELF, signed image or raw flash dump
+pub fn read_fingerprint(raw: &[u8]) -> Result<(FingerprintSource, FirmwareFingerprint), String> {
+    // trailer is checked first, image itself could start with ELF magic
+    if let Ok(signed) = SignedImage::parse(raw) {
+        return Ok((
+            FingerprintSource::SignedImage,
+            FirmwareFingerprint::from_trailer(&signed.trailer),
+        ));
+    }
+
+    if raw.starts_with(b"\x7fELF") {
+        return FirmwareFingerprint::from_elf(raw)
+            .map(|x| (FingerprintSource::Elf, x))
+            .ok_or("ELF doesn't have valid .mp_fingerprint section".to_owned());
+    }
+
+    find_trailer(raw)
+        .map(|(offset, trailer)| {
+            (
+                FingerprintSource::FlashDump { offset },
+                FirmwareFingerprint::from_trailer(&trailer),
+            )
+        })
+        .ok_or(
+            "fingerprint is not found, raw dump has it only with `fw_update` build (use ELF)"
+                .to_owned(),
+        )
+}
+
+/// `model_ver` of `hw_*` feature, same conversion with `build.rs`.
+/// `model_ver` itself is given back, e.g. "hw_mini_0v5" and "MINI-0V5" are same.
+pub fn model_ver_of_feature(feature: &str) -> String {
+    let feature = feature.to_ascii_lowercase();
+    feature
+        .strip_prefix("hw_")
+        .unwrap_or(&feature)
+        .replace('_', "-")
+        .to_ascii_uppercase()
+}
+
+/// What production line expects for the board to be flashed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Expectation {
+    /// `hw_*` feature or `model_ver` of the board
+    pub hw: Option<String>,
+    /// Dirty build is rejected unless it's allowed
+    pub allow_dirty: bool,
+    pub is_nda: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    /// Not a BillMock firmware
+    ModelName(String),
+    /// Built for other hardware
+    ModelVer {
+        expected: String,
+        found: String,
+    },
+    /// Built from modified working tree
+    DirtyBuild,
+    Nda {
+        expected: bool,
+        found: bool,
+    },
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ModelName(found) => write!(f, "model_name is {}, not {}", found, MODEL_NAME),
+            Self::ModelVer { expected, found } => {
+                write!(f, "built for {}, but board is {}", found, expected)
+            }
+            Self::DirtyBuild => write!(f, "built from dirty working tree"),
+            Self::Nda { expected, found } => {
+                write!(f, "is_nda is {}, but {} is expected", found, expected)
+            }
+        }
+    }
+}
+
+impl Expectation {
+    pub fn check(&self, fingerprint: &FirmwareFingerprint) -> Vec<Mismatch> {
+        let mut ret = Vec::new();
+
+        if fingerprint.model_name != MODEL_NAME {
+            ret.push(Mismatch::ModelName(fingerprint.model_name.clone()));
+        }
+        if let Some(hw) = &self.hw {
+            let expected = model_ver_of_feature(hw);
+            if expected != fingerprint.model_ver {
+                ret.push(Mismatch::ModelVer {
+                    expected,
+                    found: fingerprint.model_ver.clone(),
+                });
+            }
+        }
+        if !self.allow_dirty && fingerprint.is_dirty() {
+            ret.push(Mismatch::DirtyBuild);
+        }
+        if let Some(expected) = self.is_nda {
+            if expected != fingerprint.is_nda {
+                ret.push(Mismatch::Nda {
+                    expected,
+                    found: fingerprint.is_nda,
+                });
+            }
+        }
+
+        ret
+    }
+}
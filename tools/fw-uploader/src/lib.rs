@@ -0,0 +1,366 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Host side of serial firmware update, shared by `fw-sign`, `fw-uploader`, `fw-sim`
+//! and `fw-fingerprint`.
+//! Transport is any `Read + Write` port that returns `TimedOut` on read timeout,
+//! thus real serial port and PTY simulator are driven by same code.
+//! Keys are 32 bytes hex text files, secret key should be kept out of the repository.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::fd::OwnedFd;
+use std::time::{Duration, Instant};
+
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use fw_update::*;
+use nix::sys::termios::{cfmakeraw, tcgetattr, tcsetattr, SetArg};
+use object::elf::PT_LOAD;
+use object::read::elf::{ElfFile32, ProgramHeader};
+use object::Endianness;
+use sha2::{Digest, Sha512};
+
+pub mod fingerprint;
+
+use fingerprint::FirmwareFingerprint;
+
+/// Waiting time for an answer, active partition is erased on `Begin` thus it takes longer
+const ANSWER_TIMEOUT: Duration = Duration::from_millis(500);
+const ANSWER_TIMEOUT_BEGIN: Duration = Duration::from_secs(3);
+/// Normal firmware resets to bootloader on first `Hello`, thus few retries are expected
+const RETRY_MAX: usize = 10;
+
+#[derive(Debug)]
+pub enum UploadError {
+    Io(io::Error),
+    /// Board rejected the frame or answered unexpected frame
+    Rejected(UpdateError),
+    /// Board didn't answer after retries
+    NoAnswer,
+}
+
+impl fmt::Display for UploadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "serial port error : {}", e),
+            Self::Rejected(e) => write!(f, "rejected by board : {:?}", e),
+            Self::NoAnswer => write!(f, "board doesn't answer"),
+        }
+    }
+}
+
+impl std::error::Error for UploadError {}
+
+/// Failure of `upload_or_restore`, `restore` is the result of uploading previous image again
+#[derive(Debug)]
+pub struct RestoreError {
+    pub upload: UploadError,
+    pub restore: Result<(), UploadError>,
+}
+
+impl fmt::Display for RestoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.restore {
+            Ok(()) => write!(f, "{}, previous image is restored", self.upload),
+            Err(e) => write!(f, "{}, previous image isn't restored : {}", self.upload, e),
+        }
+    }
+}
+
+impl std::error::Error for RestoreError {}
+
+impl From<io::Error> for UploadError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+fn fixed_field<const N: usize>(value: &str) -> [u8; N] {
+    let mut ret = [0u8; N];
+    let len = value.len().min(N);
+    ret[..len].copy_from_slice(&value.as_bytes()[..len]);
+    ret
+}
+
+/// Build `ImageTrailer` from `.mp_fingerprint` section of firmware ELF.
+pub fn trailer_from_elf(elf: &[u8], image_size: usize) -> Option<ImageTrailer> {
+    let fingerprint = FirmwareFingerprint::from_elf(elf)?;
+
+    // `firmware_git_hash` is 40 hex digits with optional `-dirty`
+    let mut git_hash = [0u8; GIT_HASH_LEN];
+    hex::decode_to_slice(
+        fingerprint.firmware_git_hash.get(..GIT_HASH_LEN * 2)?,
+        &mut git_hash,
+    )
+    .ok()?;
+
+    Some(ImageTrailer {
+        image_size: image_size as u32,
+        model_ver: fixed_field(&fingerprint.model_ver),
+        firmware_ver: fixed_field(&fingerprint.firmware_ver),
+        git_hash,
+        is_nda: fingerprint.is_nda,
+        is_dirty: fingerprint.is_dirty(),
+    })
+}
+
+/// Flash image of firmware ELF, same with `objcopy -O binary`.
+/// Loadable segments are placed by physical address, gaps are filled with zero.
+pub fn image_from_elf(elf: &[u8]) -> Option<Vec<u8>> {
+    let file = ElfFile32::<Endianness>::parse(elf).ok()?;
+    let endian = file.endian();
+
+    let mut segments = file
+        .elf_program_headers()
+        .iter()
+        .filter(|ph| (ph.p_type(endian) == PT_LOAD) && (0 < ph.p_filesz(endian)))
+        .map(|ph| Some((ph.p_paddr(endian), ph.data(endian, elf).ok()?)))
+        .collect::<Option<Vec<_>>>()?;
+    segments.sort_by_key(|(addr, _)| *addr);
+
+    let base = segments.first()?.0;
+    let mut image = Vec::new();
+    for (addr, data) in segments {
+        let offset = (addr - base) as usize;
+        image.resize(image.len().max(offset + data.len()), 0);
+        image[offset..offset + data.len()].copy_from_slice(data);
+    }
+
+    Some(image)
+}
+
+/// Signature over SHA-512 of image and trailer, bootloader verifies same digest.
+/// Returns signed image, image and trailer followed by signature.
+pub fn sign_image(key: &SigningKey, image: &[u8], trailer: &ImageTrailer) -> Vec<u8> {
+    let mut signed = image.to_vec();
+    signed.extend(trailer.to_bytes());
+
+    let signature = key.sign(&Sha512::digest(&signed));
+    signed.extend(signature.to_bytes());
+    signed
+}
+
+/// Check signature of image and trailer, what bootloader does before booting new image
+pub fn verify_image(key: &VerifyingKey, payload: &[u8], signature: &[u8; SIGNATURE_LEN]) -> bool {
+    key.verify(&Sha512::digest(payload), &Signature::from_bytes(signature))
+        .is_ok()
+}
+
+/// New secret key from OS random source
+pub fn generate_key() -> io::Result<SigningKey> {
+    let mut seed = [0u8; 32];
+    File::open("/dev/urandom")?.read_exact(&mut seed)?;
+    Ok(SigningKey::from_bytes(&seed))
+}
+
+fn read_key(path: &str) -> io::Result<[u8; 32]> {
+    let text = std::fs::read_to_string(path)?;
+    let mut key = [0u8; 32];
+    hex::decode_to_slice(text.trim(), &mut key)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(key)
+}
+
+pub fn read_secret_key(path: &str) -> io::Result<SigningKey> {
+    read_key(path).map(|key| SigningKey::from_bytes(&key))
+}
+
+pub fn read_public_key(path: &str) -> io::Result<VerifyingKey> {
+    VerifyingKey::from_bytes(&read_key(path)?)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn read_frame<P: Read>(
+    port: &mut P,
+    stream: &mut UpdateStream,
+    out: &mut [u8; UPDATE_FRAME_MAX],
+    timeout: Duration,
+) -> Result<Option<usize>, UploadError> {
+    let deadline = Instant::now() + timeout;
+    let mut rx_buf = [0u8; UPDATE_FRAME_MAX];
+
+    while Instant::now() < deadline {
+        while let Some(result) = stream.pop(out) {
+            if let Ok(len) = result {
+                return Ok(Some(len));
+            }
+        }
+
+        match port.read(&mut rx_buf) {
+            Ok(len) => stream.push(&rx_buf[..len]),
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => {}
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(None)
+}
+
+/// Send whole signed image to the board, `progress` is called with sent and total bytes.
+pub fn upload<P: Read + Write>(
+    port: &mut P,
+    signed: &SignedImage,
+    mut progress: impl FnMut(u32, u32),
+) -> Result<(), UploadError> {
+    let mut uploader = Uploader::new(signed);
+    let mut stream = UpdateStream::new();
+    let mut tx_buf = [0u8; UPDATE_FRAME_MAX];
+    let mut rx_frame = [0u8; UPDATE_FRAME_MAX];
+
+    while let UploaderStep::Send(frame) = uploader.step() {
+        let timeout = match frame {
+            UpdateFrame::Begin(_) => ANSWER_TIMEOUT_BEGIN,
+            _ => ANSWER_TIMEOUT,
+        };
+        let raw = encode(&frame, &mut tx_buf).map_err(UploadError::Rejected)?;
+
+        let mut answered = false;
+        for _ in 0..RETRY_MAX {
+            port.write_all(raw)?;
+            port.flush()?;
+
+            if let Some(len) = read_frame(port, &mut stream, &mut rx_frame, timeout)? {
+                let (answer, _) = decode(&rx_frame[..len]).map_err(UploadError::Rejected)?;
+                uploader.answer(&answer).map_err(UploadError::Rejected)?;
+                answered = true;
+                break;
+            }
+        }
+
+        if !answered {
+            return Err(UploadError::NoAnswer);
+        }
+
+        let (sent, total) = uploader.progress();
+        progress(sent, total);
+    }
+
+    Ok(())
+}
+
+/// Send new image, previous one is sent again when the session fails.
+/// Board doesn't have DFU partition, thus failed image can't fall back without uploader.
+/// `progress` is called with `true` while previous image is sent.
+pub fn upload_or_restore<P: Read + Write>(
+    port: &mut P,
+    signed: &SignedImage,
+    previous: &SignedImage,
+    mut progress: impl FnMut(bool, u32, u32),
+) -> Result<(), RestoreError> {
+    match upload(port, signed, |sent, total| progress(false, sent, total)) {
+        Ok(()) => Ok(()),
+        Err(upload_error) => Err(RestoreError {
+            upload: upload_error,
+            restore: upload(port, previous, |sent, total| progress(true, sent, total)),
+        }),
+    }
+}
+
+/// Board simulation for host test, active partition is plain memory.
+/// Serves until the image is finished or aborted.
+pub struct Simulator {
+    receiver: UpdateReceiver,
+    /// Public key built in the firmware
+    key: VerifyingKey,
+    pub active: Vec<u8>,
+    pub image_size: usize,
+}
+
+impl Simulator {
+    pub fn new(model_ver: &str, capacity: usize, key: VerifyingKey) -> Self {
+        Self {
+            receiver: UpdateReceiver::new(model_ver, capacity as u32),
+            key,
+            active: vec![0xFF; capacity],
+            image_size: 0,
+        }
+    }
+
+    /// Returns true when new image is finished, false when aborted
+    pub fn serve<P: Read + Write>(&mut self, port: &mut P) -> io::Result<bool> {
+        let mut stream = UpdateStream::new();
+        let mut rx_buf = [0u8; UPDATE_FRAME_MAX];
+        let mut rx_frame = [0u8; UPDATE_FRAME_MAX];
+        let mut tx_buf = [0u8; UPDATE_FRAME_MAX];
+
+        loop {
+            let len = match port.read(&mut rx_buf) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(len) => len,
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => continue,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            stream.push(&rx_buf[..len]);
+
+            while let Some(result) = stream.pop(&mut rx_frame) {
+                let Ok(len) = result else {
+                    continue;
+                };
+                let Ok((frame, _)) = decode(&rx_frame[..len]) else {
+                    continue;
+                };
+
+                let (reply, done) = match self.receiver.handle(&frame) {
+                    Ok(ReceiverAction::Reply(reply)) => (reply, None),
+                    Ok(ReceiverAction::Prepare(info)) => {
+                        self.active.fill(0xFF);
+                        self.image_size = info.size as usize - TRAILER_LEN;
+                        (UpdateFrame::Ack(0), None)
+                    }
+                    Ok(ReceiverAction::Write { offset, data, next }) => {
+                        let offset = offset as usize;
+                        self.active[offset..offset + data.len()].copy_from_slice(data);
+                        (UpdateFrame::Ack(next), None)
+                    }
+                    Ok(ReceiverAction::Finish { signature, len, .. }) => {
+                        match verify_image(&self.key, &self.active[..len as usize], &signature) {
+                            true => (UpdateFrame::Ack(0), Some(true)),
+                            false => (UpdateFrame::Nack(UpdateError::BadSignature), None),
+                        }
+                    }
+                    Ok(ReceiverAction::Abort) => (UpdateFrame::Ack(0), Some(false)),
+                    Err(e) => (UpdateFrame::Nack(e), None),
+                };
+
+                let raw = encode(&reply, &mut tx_buf)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", e)))?;
+                port.write_all(raw)?;
+                port.flush()?;
+
+                if let Some(finished) = done {
+                    return Ok(finished);
+                }
+            }
+        }
+    }
+
+    /// Received image without trailer, valid after `serve` returns true
+    pub fn image(&self) -> &[u8] {
+        &self.active[..self.image_size]
+    }
+}
+
+/// Open raw mode PTY for simulator, returns master, slave and path of slave.
+/// Slave should be kept open while simulator is running.
+pub fn open_pty() -> nix::Result<(File, OwnedFd, String)> {
+    let pty = nix::pty::openpty(None, None)?;
+
+    let mut termios = tcgetattr(&pty.slave)?;
+    cfmakeraw(&mut termios);
+    tcsetattr(&pty.slave, SetArg::TCSANOW, &termios)?;
+
+    let path = nix::unistd::ttyname(&pty.slave)?;
+
+    Ok((
+        File::from(pty.master),
+        pty.slave,
+        path.to_string_lossy().into_owned(),
+    ))
+}
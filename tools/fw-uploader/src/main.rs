@@ -0,0 +1,132 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Serial firmware uploader, the board should be connected instead of card terminal.
+//! fw-uploader <PORT> <IMAGE.bmfw> [--baud <BAUD>] [--fallback <PREVIOUS.bmfw>]
+//! Image is signed by `fw-sign`, `model_ver` is taken from its trailer.
+//! Previous image of `--fallback` is uploaded again when new one fails.
+
+use std::io::Write;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use fw_update::SignedImage;
+use fw_uploader::{upload, upload_or_restore};
+
+const USAGE: &str =
+    "usage: fw-uploader <PORT> <IMAGE.bmfw> [--baud <BAUD>] [--fallback <PREVIOUS.bmfw>]";
+/// Same baudrate with card terminal
+const DEFAULT_BAUDRATE: u32 = 115200;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (Some(port_name), Some(image_path)) = (args.first(), args.get(1)) else {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    };
+
+    let mut baudrate = DEFAULT_BAUDRATE;
+    let mut fallback_path = None;
+    let mut options = args[2..].iter();
+    while let Some(option) = options.next() {
+        match (option.as_str(), options.next()) {
+            ("--baud", Some(baud)) => match baud.parse() {
+                Ok(baud) => baudrate = baud,
+                Err(_) => {
+                    eprintln!("Invalid baudrate {}", baud);
+                    return ExitCode::FAILURE;
+                }
+            },
+            ("--fallback", Some(path)) => fallback_path = Some(path),
+            _ => {
+                eprintln!("{}", USAGE);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let Some(raw) = read_file(image_path) else {
+        return ExitCode::FAILURE;
+    };
+    let Some(signed) = parse_signed(image_path, &raw) else {
+        return ExitCode::FAILURE;
+    };
+    let model_ver = String::from_utf8_lossy(&signed.trailer.model_ver);
+
+    let fallback_raw = match fallback_path {
+        Some(path) => match read_file(path) {
+            Some(raw) => Some(raw),
+            None => return ExitCode::FAILURE,
+        },
+        None => None,
+    };
+    let fallback = match (fallback_path, &fallback_raw) {
+        (Some(path), Some(raw)) => match parse_signed(path, raw) {
+            Some(signed) => Some(signed),
+            None => return ExitCode::FAILURE,
+        },
+        _ => None,
+    };
+
+    let mut port = match serialport::new(port_name, baudrate)
+        .timeout(Duration::from_millis(50))
+        .open()
+    {
+        Ok(port) => port,
+        Err(e) => {
+            eprintln!("Failed to open {} : {}", port_name, e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    println!(
+        "Upload {} ({} bytes, {}) to {}",
+        image_path,
+        signed.trailer.image_size,
+        model_ver.trim_end_matches('\0'),
+        port_name
+    );
+
+    let result = match &fallback {
+        Some(previous) => {
+            upload_or_restore(&mut port, &signed, previous, |is_restore, sent, total| {
+                let label = if is_restore { "restore" } else { "upload" };
+                print!("\r{:7} {:6} / {:6} bytes", label, sent, total);
+                let _ = std::io::stdout().flush();
+            })
+            .map_err(|e| e.to_string())
+        }
+        None => upload(&mut port, &signed, |sent, total| {
+            print!("\r{:6} / {:6} bytes", sent, total);
+            let _ = std::io::stdout().flush();
+        })
+        .map_err(|e| e.to_string()),
+    };
+    println!();
+
+    match result {
+        Ok(()) => {
+            println!("Done, the board boots new image after reset");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Update failed, {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn read_file(path: &str) -> Option<Vec<u8>> {
+    std::fs::read(path)
+        .map_err(|e| eprintln!("Failed to read {} : {}", path, e))
+        .ok()
+}
+
+fn parse_signed<'a>(path: &str, raw: &'a [u8]) -> Option<SignedImage<'a>> {
+    SignedImage::parse(raw)
+        .map_err(|e| eprintln!("{} is not signed image, use fw-sign : {:?}", path, e))
+        .ok()
+}
@@ -0,0 +1,123 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Fingerprint decoding from each kind of image and production line checks.
+
+use ed25519_dalek::SigningKey;
+use fw_update::{model_ver_field, ImageTrailer};
+use fw_uploader::fingerprint::*;
+use fw_uploader::{sign_image, trailer_from_elf};
+
+/// Same form with `MpFingerprint::to_hex_string` of `build.rs`, before hex encoding
+const MP_INFO_TOML: &str = r#"[firmware_fingerprint]
+model_name = "BillMock-HW"
+model_ver = "MINI-0V5"
+firmware_ver = "0.4.0"
+firmware_git_hash = "0123456789abcdef0123456789abcdef01234567-dirty"
+is_nda = false
+"#;
+
+fn trailer(image_size: usize) -> ImageTrailer {
+    ImageTrailer {
+        image_size: image_size as u32,
+        model_ver: model_ver_field("MINI-0V5"),
+        firmware_ver: model_ver_field("0.4.0"),
+        git_hash: [0xAB; 20],
+        is_nda: true,
+        is_dirty: false,
+    }
+}
+
+#[test]
+fn decode_toml() {
+    let fingerprint = FirmwareFingerprint::from_toml(MP_INFO_TOML).unwrap();
+
+    assert_eq!(fingerprint.model_ver, "MINI-0V5");
+    assert_eq!(fingerprint.firmware_ver, "0.4.0");
+    assert!(fingerprint.is_dirty());
+    assert!(!fingerprint.is_nda);
+    assert!(FirmwareFingerprint::from_toml("model_ver = 3").is_none());
+}
+
+#[test]
+fn feature_to_model_ver() {
+    assert_eq!(model_ver_of_feature("hw_mini_0v5"), "MINI-0V5");
+    assert_eq!(model_ver_of_feature("HW_0V4"), "0V4");
+    assert_eq!(model_ver_of_feature("MINI-0V4"), "MINI-0V4");
+}
+
+#[test]
+fn reject_wrong_board_and_dirty_build() {
+    let fingerprint = FirmwareFingerprint::from_toml(MP_INFO_TOML).unwrap();
+    let mut expect = Expectation {
+        hw: Some("hw_0v4".to_owned()),
+        allow_dirty: false,
+        is_nda: Some(true),
+    };
+
+    assert_eq!(
+        expect.check(&fingerprint),
+        vec![
+            Mismatch::ModelVer {
+                expected: "0V4".to_owned(),
+                found: "MINI-0V5".to_owned()
+            },
+            Mismatch::DirtyBuild,
+            Mismatch::Nda {
+                expected: true,
+                found: false
+            },
+        ]
+    );
+
+    expect.hw = Some("hw_mini_0v5".to_owned());
+    expect.allow_dirty = true;
+    expect.is_nda = None;
+    assert_eq!(expect.check(&fingerprint), vec![]);
+}
+
+#[test]
+fn signed_image_and_flash_dump() {
+    let image: Vec<u8> = (0..3000).map(|x| (x * 3) as u8).collect();
+    let signed = sign_image(
+        &SigningKey::from_bytes(&[1; 32]),
+        &image,
+        &trailer(image.len()),
+    );
+
+    let (source, fingerprint) = read_fingerprint(&signed).unwrap();
+    assert_eq!(source, FingerprintSource::SignedImage);
+    assert_eq!(fingerprint.model_ver, "MINI-0V5");
+    assert_eq!(fingerprint.firmware_git_hash, "ab".repeat(20));
+    assert!(fingerprint.is_nda);
+
+    // whole flash, application behind 8K bootloader and 2K state
+    let mut dump = vec![0xFF; 64 * 1024];
+    dump[0x2800..0x2800 + signed.len() - 64].copy_from_slice(&signed[..signed.len() - 64]);
+    let (source, swapped) = read_fingerprint(&dump).unwrap();
+    assert_eq!(source, FingerprintSource::FlashDump { offset: 0x2800 });
+    assert_eq!(swapped, fingerprint);
+
+    // standalone build doesn't carry the trailer
+    dump[0x2800 + image.len()] = 0;
+    assert!(read_fingerprint(&dump).is_err());
+}
+
+#[test]
+fn trailer_keeps_fingerprint() {
+    let fingerprint = FirmwareFingerprint::from_toml(MP_INFO_TOML).unwrap();
+    let trailer = ImageTrailer {
+        git_hash:
+            *b"\x01\x23\x45\x67\x89\xab\xcd\xef\x01\x23\x45\x67\x89\xab\xcd\xef\x01\x23\x45\x67",
+        is_dirty: true,
+        is_nda: false,
+        ..trailer(100)
+    };
+
+    assert_eq!(FirmwareFingerprint::from_trailer(&trailer), fingerprint);
+    // not an ELF, `fw-sign` needs `.mp_fingerprint`
+    assert!(trailer_from_elf(MP_INFO_TOML.as_bytes(), 100).is_none());
+}
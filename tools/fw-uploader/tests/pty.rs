@@ -0,0 +1,154 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Uploader against PTY based simulator, same path with real serial port.
+
+use std::time::Duration;
+
+use ed25519_dalek::SigningKey;
+use fw_update::{model_ver_field, ImageTrailer, SignedImage, UpdateError, GIT_HASH_LEN};
+use fw_uploader::{
+    open_pty, sign_image, upload, upload_or_restore, verify_image, Simulator, UploadError,
+};
+use serialport::SerialPort;
+
+/// Key built in simulated firmware
+const BOARD_KEY: [u8; 32] = [0x42; 32];
+
+fn image(len: usize) -> Vec<u8> {
+    (0..len).map(|x| (x * 13 + 5) as u8).collect()
+}
+
+fn signed(key: &[u8; 32], image: &[u8], model_ver: &str) -> Vec<u8> {
+    let trailer = ImageTrailer {
+        image_size: image.len() as u32,
+        model_ver: model_ver_field(model_ver),
+        firmware_ver: model_ver_field("0.4.0"),
+        git_hash: [0x11; GIT_HASH_LEN],
+        is_nda: false,
+        is_dirty: false,
+    };
+    sign_image(&SigningKey::from_bytes(key), image, &trailer)
+}
+
+fn run(board_model: &'static str, signed: &[u8]) -> (Result<(), UploadError>, Option<Vec<u8>>) {
+    let signed = SignedImage::parse(signed).unwrap();
+    session(board_model, |port| {
+        let result = upload(port, &signed, |_, _| {});
+        let is_finished = result.is_ok();
+        (result, is_finished)
+    })
+}
+
+/// Serve simulator on PTY, `host` drives uploader on slave port and tells
+/// whether the board should have finished an image.
+fn session<R>(
+    board_model: &'static str,
+    host: impl FnOnce(&mut Box<dyn SerialPort>) -> (R, bool),
+) -> (R, Option<Vec<u8>>) {
+    let (mut master, _slave, slave_path) = open_pty().unwrap();
+    let key = SigningKey::from_bytes(&BOARD_KEY).verifying_key();
+
+    let board = std::thread::spawn(move || {
+        let mut simulator = Simulator::new(board_model, 28 * 1024, key);
+        let received = match simulator.serve(&mut master) {
+            Ok(true) => Some(simulator.image().to_vec()),
+            _ => None,
+        };
+        // closing master hangs up slave, last answer should be read before that
+        (received, master)
+    });
+
+    let mut port = serialport::new(&slave_path, 115200)
+        .timeout(Duration::from_millis(50))
+        .open()
+        .unwrap();
+    let (result, is_finished) = host(&mut port);
+    drop(port);
+    drop(_slave);
+
+    let received = match is_finished {
+        true => board.join().unwrap().0,
+        // simulator keeps waiting on failed session
+        false => None,
+    };
+
+    (result, received)
+}
+
+#[test]
+fn sign_and_verify() {
+    let data = image(5000);
+    let mut signed = signed(&BOARD_KEY, &data, "MINI-0V5");
+    let key = SigningKey::from_bytes(&BOARD_KEY).verifying_key();
+
+    let image = SignedImage::parse(&signed).unwrap();
+    assert_eq!(&image.payload[..data.len()], &data[..]);
+    assert!(verify_image(&key, image.payload, &image.signature));
+
+    signed[100] ^= 0x01;
+    let image = SignedImage::parse(&signed).unwrap();
+    assert!(!verify_image(&key, image.payload, &image.signature));
+}
+
+#[test]
+fn upload_through_pty() {
+    let data = image(5000);
+    let (result, received) = run("MINI-0V5", &signed(&BOARD_KEY, &data, "MINI-0V5"));
+
+    assert!(result.is_ok(), "{:?}", result);
+    assert_eq!(received, Some(data));
+}
+
+#[test]
+fn reject_other_key() {
+    let data = image(5000);
+    let (result, received) = run("MINI-0V5", &signed(&[0x24; 32], &data, "MINI-0V5"));
+
+    assert!(matches!(
+        result,
+        Err(UploadError::Rejected(UpdateError::BadSignature))
+    ));
+    assert_eq!(received, None);
+}
+
+#[test]
+fn reject_other_hardware() {
+    let data = image(5000);
+    let (result, received) = run("MINI-0V5", &signed(&BOARD_KEY, &data, "0V4"));
+
+    assert!(matches!(
+        result,
+        Err(UploadError::Rejected(UpdateError::ModelMismatch))
+    ));
+    assert_eq!(received, None);
+}
+
+#[test]
+fn restore_previous_image() {
+    let data = image(5000);
+    let previous = image(4000);
+    let new = signed(&[0x24; 32], &data, "MINI-0V5");
+    let old = signed(&BOARD_KEY, &previous, "MINI-0V5");
+    let (new, old) = (
+        SignedImage::parse(&new).unwrap(),
+        SignedImage::parse(&old).unwrap(),
+    );
+
+    let (result, received) = session("MINI-0V5", |port| {
+        let result = upload_or_restore(port, &new, &old, |_, _, _| {});
+        let is_restored = matches!(&result, Err(e) if e.restore.is_ok());
+        (result, is_restored)
+    });
+
+    let error = result.unwrap_err();
+    assert!(matches!(
+        error.upload,
+        UploadError::Rejected(UpdateError::BadSignature)
+    ));
+    assert!(error.restore.is_ok(), "{:?}", error.restore);
+    assert_eq!(received, Some(previous));
+}
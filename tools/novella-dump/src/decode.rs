@@ -0,0 +1,154 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Section data as fields of `MemStorage` of firmware.
+//! Firmware types are `repr(C)` on little endian MCU, fields are decoded by their offsets.
+//! `CardReaderPortBackup` is not `repr(C)`, thus it's shown in raw bytes per port.
+
+use std::fmt::Write;
+
+use novella_layout::type_id;
+
+/// `MemStorage` field name of each section kind
+const SECTION_NAMES: [(u8, &str); 12] = [
+    (type_id::kind(type_id::P1_CARD_CNT), "p1_card_cnt"),
+    (type_id::kind(type_id::P2_CARD_CNT), "p2_card_cnt"),
+    (type_id::kind(type_id::P1_COIN_CNT), "p1_coin_cnt"),
+    (type_id::kind(type_id::P2_COIN_CNT), "p2_coin_cnt"),
+    (type_id::kind(type_id::FAULT_LOG), "fault_log"),
+    (type_id::kind(type_id::HW_BOOT_CNT), "hw_boot_cnt"),
+    (type_id::kind(type_id::TERMINAL_ID), "raw_terminal"),
+    (
+        type_id::kind(type_id::CARD_PORT_BACKUP),
+        "card_reader_port_backup",
+    ),
+    (type_id::kind(type_id::PAYOUT_CNT), "payout_cnt"),
+    (type_id::kind(type_id::INSTALL_CONFIG), "install_config"),
+    (type_id::kind(type_id::SERVICE_CNT), "service_cnt"),
+    (type_id::kind(type_id::EXTRA_PLAYER_CNT), "extra_player_cnt"),
+];
+
+/// Name of the section kind, see `type_id::kind`
+pub fn section_name(kind: u8) -> &'static str {
+    SECTION_NAMES
+        .iter()
+        .find(|(x, _)| *x == kind)
+        .map_or("unknown", |(_, name)| name)
+}
+
+/// Section kind of the name
+pub fn section_kind(name: &str) -> Option<u8> {
+    SECTION_NAMES
+        .iter()
+        .find(|(_, x)| *x == name)
+        .map(|(kind, _)| *kind)
+}
+
+/// Section whose data is single `u32`
+fn is_u32(type_id: u8) -> bool {
+    matches!(
+        type_id,
+        type_id::P1_CARD_CNT
+            | type_id::P2_CARD_CNT
+            | type_id::P1_COIN_CNT
+            | type_id::P2_COIN_CNT
+            | type_id::HW_BOOT_CNT
+            | type_id::PAYOUT_CNT
+    )
+}
+
+fn u16_at(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn text(raw: &[u8]) -> String {
+    let mut ret = String::from("\"");
+    for x in raw {
+        let _ = write!(ret, "{}", x.escape_ascii());
+    }
+    ret.push('"');
+    ret
+}
+
+/// `(field, value)` of section data, field is empty for single value section.
+/// `data` should be sized by the section of `type_id`.
+pub fn fields(type_id: u8, data: &[u8]) -> Vec<(String, String)> {
+    let mut ret = Vec::new();
+    let mut push = |field: &str, value: String| ret.push((field.to_owned(), value));
+
+    match type_id {
+        x if is_u32(x) => push("", u32_at(data, 0).to_string()),
+        type_id::FAULT_LOG => {
+            push("current_boot_cnt", u32_at(data, 0).to_string());
+            push("error_code", format!("0x{:04X}", u16_at(data, 4)));
+        }
+        type_id::TERMINAL_ID => {
+            push("normal", text(&data[0..10]));
+            push("extend", text(&data[10..13]));
+        }
+        type_id::CARD_PORT_BACKUP => {
+            for (idx, port) in data.chunks(8).enumerate() {
+                push(&format!("raw_card_port_backup[{}]", idx), hex::encode(port));
+            }
+        }
+        type_id::INSTALL_CONFIG_V0 | type_id::INSTALL_CONFIG => {
+            for (idx, entry) in data[0..16].chunks(4).enumerate() {
+                push(&format!("schedule[{}]", idx), hex::encode(entry));
+            }
+            push("flags", format!("0x{:02X}", data[16]));
+            push("decide_timeout_secs", data[17].to_string());
+            push("decide_fallback", data[18].to_string());
+            for (idx, route) in data[19..31].chunks(3).enumerate() {
+                push(&format!("routes[{}]", idx), hex::encode(route));
+            }
+            push("input_polarity", format!("0x{:04X}", u16_at(data, 31)));
+            push("output_polarity", format!("0x{:04X}", u16_at(data, 33)));
+            push("cabinet", data[35].to_string());
+            // appended on `INSTALL_CONFIG`
+            if let Some(tail) = data.get(36..42) {
+                push("link_role", tail[0].to_string());
+                push("language", tail[1].to_string());
+                push("safe_state_exempt", format!("0x{:02X}", tail[2]));
+                push("inhibit_all_mask", format!("0x{:02X}", tail[3]));
+                push("output_open_drain", format!("0x{:04X}", u16_at(tail, 4)));
+            }
+        }
+        type_id::SERVICE_CNT_V0 | type_id::SERVICE_CNT => {
+            for (idx, offset) in (0..data.len()).step_by(4).enumerate() {
+                push(
+                    &format!("players[{}]", idx),
+                    u32_at(data, offset).to_string(),
+                );
+            }
+        }
+        type_id::EXTRA_PLAYER_CNT => {
+            push("card[0]", u32_at(data, 0).to_string());
+            push("card[1]", u32_at(data, 4).to_string());
+            push("coin[0]", u32_at(data, 8).to_string());
+            push("coin[1]", u32_at(data, 12).to_string());
+        }
+        _ => push("raw", hex::encode(data)),
+    }
+
+    ret
+}
+
+/// Section data from text, decimal or `0x` hex for `u32` section, otherwise hex of whole data
+pub fn parse_value(type_id: u8, value: &str) -> Option<Vec<u8>> {
+    if is_u32(type_id) {
+        let number = match value.strip_prefix("0x") {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => value.parse().ok()?,
+        };
+        return Some(number.to_le_bytes().to_vec());
+    }
+
+    hex::decode(value.trim_start_matches("0x")).ok()
+}
@@ -0,0 +1,329 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Host side of Novella EEPROM, 24C16 dump read by programmer from a board in the field.
+//! Layout, header and slot checksum come from `novella-layout`, same with firmware,
+//! thus the dump is read and written as `Novella` of firmware does.
+//! Repaired or edited image can be written back to the EEPROM by programmer.
+
+use std::convert::Infallible;
+use std::fmt;
+
+use novella_layout::*;
+
+pub mod decode;
+
+/// Whole 24C16
+pub const DUMP_SIZE: usize = EEPROM_SIZE as usize;
+/// Tick rate of `embassy-time` in firmware, `tick-hz-32_768`
+pub const TICK_HZ: u64 = 32_768;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum DumpError {
+    /// Dump is not 24C16, given size in bytes
+    Size(usize),
+    /// Header is written by newer firmware
+    UnknownLayout(u8),
+    /// Layout of the dump doesn't have the section kind
+    NoSection(u8),
+    /// Data size is not matched with the section, (expected, given)
+    DataSize(usize, usize),
+}
+
+impl fmt::Display for DumpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Size(size) => write!(f, "dump should be {} bytes, not {}", DUMP_SIZE, size),
+            Self::UnknownLayout(version) => write!(f, "unknown layout version {}", version),
+            Self::NoSection(kind) => write!(f, "layout doesn't have section kind {}", kind),
+            Self::DataSize(expected, given) => {
+                write!(f, "section is {} bytes, given {} bytes", expected, given)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DumpError {}
+
+/// Layout header of the dump
+#[derive(Debug, Clone, Copy)]
+pub enum DumpHeader {
+    /// Released layout
+    Known(&'static NvLayout),
+    /// Written before layout versioning, read as `LAYOUT_HEADERLESS`
+    Headerless,
+    /// Valid header of newer firmware
+    Unknown(NvLayoutHeader),
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SlotState {
+    Valid,
+    /// Never written, every byte is 0xFF
+    Erased,
+    /// Broken slot, firmware heals it on boot
+    BadChecksum,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SlotDump {
+    pub index: u8,
+    pub addr: u16,
+    /// Ticks of `embassy_time::Duration`, meaningful on valid slot
+    pub uptime: u64,
+    pub data: Vec<u8>,
+    pub state: SlotState,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SectionDump {
+    pub info: NvSectionInfo,
+    pub slots: Vec<SlotDump>,
+    /// Valid slot of longest uptime, later slot wins on same uptime like `Novella::init`
+    pub newest: Option<u8>,
+}
+
+impl SectionDump {
+    pub fn newest_slot(&self) -> Option<&SlotDump> {
+        self.newest.map(|x| &self.slots[x as usize])
+    }
+
+    /// Data loaded on `MemStorage`, zero when there's no valid slot
+    pub fn data(&self) -> Vec<u8> {
+        match self.newest_slot() {
+            Some(slot) => slot.data.clone(),
+            None => vec![0u8; self.info.real_data_size as usize],
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        decode::section_name(self.info.kind())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct RepairReport {
+    pub migrated: NvMigrateOk,
+    /// Rewritten slots those were erased or broken
+    pub healed: usize,
+}
+
+pub struct Dump {
+    raw: [u8; DUMP_SIZE],
+}
+
+impl Dump {
+    /// Erased EEPROM
+    pub fn blank() -> Self {
+        Self {
+            raw: [0xFF; DUMP_SIZE],
+        }
+    }
+
+    pub fn from_bytes(raw: &[u8]) -> Result<Self, DumpError> {
+        Ok(Self {
+            raw: raw.try_into().map_err(|_| DumpError::Size(raw.len()))?,
+        })
+    }
+
+    pub fn as_bytes(&self) -> &[u8; DUMP_SIZE] {
+        &self.raw
+    }
+
+    fn header_raw(&self) -> &[u8; PAGE_SIZE] {
+        self.raw[HEADER_ADDR as usize..].try_into().unwrap()
+    }
+
+    pub fn header(&self) -> DumpHeader {
+        match NvLayoutHeader::from_bytes(self.header_raw()) {
+            Some(header) => match header.layout() {
+                Some(layout) => DumpHeader::Known(layout),
+                None => DumpHeader::Unknown(header),
+            },
+            None => DumpHeader::Headerless,
+        }
+    }
+
+    /// Layout that firmware reads the dump with
+    pub fn layout(&self) -> Result<&'static NvLayout, DumpError> {
+        match self.header() {
+            DumpHeader::Known(layout) => Ok(layout),
+            DumpHeader::Headerless => Ok(&LAYOUT_HEADERLESS),
+            DumpHeader::Unknown(header) => Err(DumpError::UnknownLayout(header.version)),
+        }
+    }
+
+    fn slot_raw(&self, section: &NvSectionInfo, slot_idx: u8) -> &[u8] {
+        let start = section.slot_addr(slot_idx) as usize;
+        &self.raw[start..start + section.slot_size as usize * PAGE_SIZE]
+    }
+
+    pub fn slot(&self, section: &NvSectionInfo, slot_idx: u8) -> SlotDump {
+        let raw = self.slot_raw(section, slot_idx);
+        let len = UPTIME_SIZE + section.real_data_size as usize;
+        let checksum = &raw[raw.len() - CHECKSUM_SIZE..];
+
+        let state = if raw.iter().all(|x| *x == 0xFF) {
+            SlotState::Erased
+        } else if slot_checksum(&raw[..len]).to_le_bytes() == checksum {
+            SlotState::Valid
+        } else {
+            SlotState::BadChecksum
+        };
+
+        SlotDump {
+            index: slot_idx,
+            addr: section.slot_addr(slot_idx),
+            uptime: u64::from_le_bytes(raw[..UPTIME_SIZE].try_into().unwrap()),
+            data: raw[UPTIME_SIZE..len].to_vec(),
+            state,
+        }
+    }
+
+    pub fn section(&self, section: &NvSectionInfo) -> SectionDump {
+        let slots: Vec<_> = (0..section.slot_num)
+            .map(|x| self.slot(section, x))
+            .collect();
+        let newest = slots
+            .iter()
+            .filter(|x| x.state == SlotState::Valid)
+            .max_by_key(|x| (x.uptime, x.index))
+            .map(|x| x.index);
+
+        SectionDump {
+            info: *section,
+            slots,
+            newest,
+        }
+    }
+
+    pub fn sections(&self) -> Result<Vec<SectionDump>, DumpError> {
+        Ok(self
+            .layout()?
+            .sections
+            .iter()
+            .map(|x| self.section(x))
+            .collect())
+    }
+
+    /// Longest uptime of valid slots, firmware continues its uptime from here
+    pub fn uptime(&self) -> Result<u64, DumpError> {
+        Ok(self
+            .sections()?
+            .iter()
+            .filter_map(|x| x.newest_slot().map(|slot| slot.uptime))
+            .max()
+            .unwrap_or(0))
+    }
+
+    /// Migrate to `LAYOUT_CURRENT` and heal erased or broken slots with data of their section,
+    /// same with `Novella::init` on boot.
+    pub fn repair(&mut self) -> Result<RepairReport, DumpError> {
+        let migrated = migrate(self).map_err(|e| match e {
+            NvMigrateError::UnknownLayout(version) => DumpError::UnknownLayout(version),
+            NvMigrateError::Storage(e) => match e {},
+        })?;
+        let uptime = self.uptime()?;
+        let mut healed = 0;
+
+        for section in self.sections()? {
+            let data = section.data();
+            for slot in section.slots.iter() {
+                if slot.state != SlotState::Valid {
+                    self.put_slot(&section.info, slot.index, uptime, &data);
+                    healed += 1;
+                }
+            }
+        }
+
+        Ok(RepairReport { migrated, healed })
+    }
+
+    /// Write data of the section on next slot of the newest, as firmware writes new value.
+    /// Returns written slot index.
+    pub fn set(&mut self, kind: u8, data: &[u8]) -> Result<u8, DumpError> {
+        let info = *self
+            .layout()?
+            .section(kind)
+            .ok_or(DumpError::NoSection(kind))?;
+
+        if data.len() != info.real_data_size as usize {
+            return Err(DumpError::DataSize(
+                info.real_data_size as usize,
+                data.len(),
+            ));
+        }
+
+        let slot_idx = match self.section(&info).newest {
+            Some(newest) => (newest + 1) & (info.slot_num - 1),
+            None => 0,
+        };
+        let uptime = self.uptime()? + 1;
+        self.put_slot(&info, slot_idx, uptime, data);
+
+        Ok(slot_idx)
+    }
+
+    fn put_slot(&mut self, section: &NvSectionInfo, slot_idx: u8, uptime: u64, data: &[u8]) {
+        let start = section.slot_addr(slot_idx) as usize;
+        let raw = &mut self.raw[start..start + section.slot_size as usize * PAGE_SIZE];
+        let len = UPTIME_SIZE + data.len();
+        let tail = raw.len() - CHECKSUM_SIZE;
+
+        raw.fill(0xFF);
+        raw[..UPTIME_SIZE].copy_from_slice(&uptime.to_le_bytes());
+        raw[UPTIME_SIZE..len].copy_from_slice(data);
+        let checksum = slot_checksum(&raw[..len]);
+        raw[tail..].copy_from_slice(&checksum.to_le_bytes());
+    }
+}
+
+impl NvStorage for Dump {
+    type Error = Infallible;
+
+    fn read_slot(
+        &mut self,
+        section: &NvSectionInfo,
+        slot_idx: u8,
+        data: &mut [u8],
+    ) -> Result<Option<u64>, Infallible> {
+        let slot = self.slot(section, slot_idx);
+
+        Ok(match slot.state {
+            SlotState::Valid => {
+                data.copy_from_slice(&slot.data);
+                Some(slot.uptime)
+            }
+            _ => None,
+        })
+    }
+
+    fn write_slot(
+        &mut self,
+        section: &NvSectionInfo,
+        slot_idx: u8,
+        uptime: u64,
+        data: &[u8],
+    ) -> Result<(), Infallible> {
+        self.put_slot(section, slot_idx, uptime, data);
+        Ok(())
+    }
+
+    fn read_header(&mut self) -> Result<[u8; PAGE_SIZE], Infallible> {
+        Ok(*self.header_raw())
+    }
+
+    fn write_header(&mut self, raw: &[u8; PAGE_SIZE]) -> Result<(), Infallible> {
+        self.raw[HEADER_ADDR as usize..].copy_from_slice(raw);
+        Ok(())
+    }
+}
+
+/// Uptime ticks in `h:mm:ss`
+pub fn format_uptime(ticks: u64) -> String {
+    let secs = ticks / TICK_HZ;
+    format!("{}:{:02}:{:02}", secs / 3600, (secs / 60) % 60, secs % 60)
+}
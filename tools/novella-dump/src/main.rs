@@ -0,0 +1,156 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Decode, repair and edit Novella EEPROM dump read by programmer.
+//! novella-dump show <DUMP.bin> [--slots]
+//! novella-dump repair <DUMP.bin> <OUT.bin>
+//! novella-dump set <DUMP.bin> <OUT.bin> <SECTION> <VALUE>
+//! Section is field name of `MemStorage`, value is decimal for counters, hex for others.
+
+use std::process::ExitCode;
+
+use novella_dump::decode::{fields, parse_value, section_kind};
+use novella_dump::{format_uptime, Dump, DumpHeader, SectionDump, SlotState};
+
+const USAGE: &str = "usage: novella-dump show <DUMP.bin> [--slots]
+       novella-dump repair <DUMP.bin> <OUT.bin>
+       novella-dump set <DUMP.bin> <OUT.bin> <SECTION> <VALUE>";
+
+fn read_dump(path: &str) -> Result<Dump, String> {
+    let raw = std::fs::read(path).map_err(|e| format!("Failed to read {} : {}", path, e))?;
+    Dump::from_bytes(&raw).map_err(|e| format!("{} : {}", path, e))
+}
+
+fn write_dump(path: &str, dump: &Dump) -> Result<(), String> {
+    std::fs::write(path, dump.as_bytes()).map_err(|e| format!("Failed to write {} : {}", path, e))
+}
+
+fn print_section(section: &SectionDump, with_slots: bool) {
+    let info = &section.info;
+    let newest = match section.newest_slot() {
+        Some(slot) => format!("newest #{} at {}", slot.index, format_uptime(slot.uptime)),
+        None => "no valid slot".to_owned(),
+    };
+    println!(
+        "[{}] 0x{:03X}, type 0x{:02X}, {} slots, {}",
+        section.name(),
+        info.sect_start_page,
+        info.type_id,
+        info.slot_num,
+        newest
+    );
+
+    for (field, value) in fields(info.type_id, &section.data()) {
+        match field.is_empty() {
+            true => println!("  {} : {}", section.name(), value),
+            false => println!("  {}.{} : {}", section.name(), field, value),
+        }
+    }
+
+    if !with_slots {
+        return;
+    }
+
+    for slot in &section.slots {
+        let mark = match section.newest == Some(slot.index) {
+            true => '*',
+            false => ' ',
+        };
+        let state = match slot.state {
+            SlotState::Valid => "valid",
+            SlotState::Erased => "erased",
+            SlotState::BadChecksum => "BROKEN",
+        };
+        println!(
+            "  {}#{:<3} 0x{:03X} {:<6} {:>12} {}",
+            mark,
+            slot.index,
+            slot.addr,
+            state,
+            slot.uptime,
+            hex::encode(&slot.data)
+        );
+    }
+}
+
+fn show(path: &str, with_slots: bool) -> Result<(), String> {
+    let dump = read_dump(path)?;
+
+    match dump.header() {
+        DumpHeader::Known(layout) => println!("{} : layout version {}", path, layout.version),
+        DumpHeader::Headerless => println!("{} : layout without header, read as version 1", path),
+        DumpHeader::Unknown(header) => {
+            return Err(format!(
+                "{} : layout version {} is written by newer firmware, type IDs {}",
+                path,
+                header.version,
+                hex::encode(header.type_ids)
+            ));
+        }
+    }
+
+    let sections = dump.sections().map_err(|e| e.to_string())?;
+    let uptime = dump.uptime().map_err(|e| e.to_string())?;
+    let broken = sections
+        .iter()
+        .flat_map(|x| x.slots.iter())
+        .filter(|x| x.state == SlotState::BadChecksum)
+        .count();
+
+    println!("uptime : {} ({} ticks)", format_uptime(uptime), uptime);
+    println!("broken slots : {}", broken);
+
+    for section in &sections {
+        print_section(section, with_slots);
+    }
+
+    Ok(())
+}
+
+fn repair(path: &str, out: &str) -> Result<(), String> {
+    let mut dump = read_dump(path)?;
+    let report = dump.repair().map_err(|e| format!("{} : {}", path, e))?;
+
+    println!("{:?}, healed slots : {}", report.migrated, report.healed);
+    write_dump(out, &dump)
+}
+
+fn set(path: &str, out: &str, section: &str, value: &str) -> Result<(), String> {
+    let mut dump = read_dump(path)?;
+    let kind = section_kind(section).ok_or(format!("Unknown section {}", section))?;
+    let layout = dump.layout().map_err(|e| e.to_string())?;
+    let info = layout.section(kind).ok_or(format!(
+        "Layout version {} doesn't have {}",
+        layout.version, section
+    ))?;
+    let data = parse_value(info.type_id, value)
+        .ok_or(format!("Invalid value for {} : {}", section, value))?;
+
+    let slot_idx = dump.set(kind, &data).map_err(|e| e.to_string())?;
+    println!("{} is written on slot #{}", section, slot_idx);
+    write_dump(out, &dump)
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(|x| x.as_str()).collect();
+
+    let result = match args.as_slice() {
+        ["show", path] => show(path, false),
+        ["show", path, "--slots"] => show(path, true),
+        ["repair", path, out] => repair(path, out),
+        ["set", path, out, section, value] => set(path, out, section, value),
+        _ => Err(USAGE.to_owned()),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
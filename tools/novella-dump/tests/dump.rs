@@ -0,0 +1,184 @@
+/*
+ * SPDX-FileCopyrightText: © 2023 Jinwoo Park (pmnxis@gmail.com)
+ *
+ * SPDX-License-Identifier: MIT OR Apache-2.0
+ */
+
+//! Decoding, repairing and editing of dump images.
+
+use novella_dump::decode::*;
+use novella_dump::*;
+use novella_layout::{type_id, NvLayoutHeader, NvMigrateOk, HEADER_ADDR, LAYOUT_CURRENT};
+
+fn kind(name: &str) -> u8 {
+    section_kind(name).unwrap()
+}
+
+fn section(dump: &Dump, name: &str) -> SectionDump {
+    dump.sections()
+        .unwrap()
+        .into_iter()
+        .find(|x| x.name() == name)
+        .unwrap()
+}
+
+/// Repaired blank EEPROM, same with the EEPROM after first boot
+fn first_boot() -> Dump {
+    let mut dump = Dump::blank();
+    let report = dump.repair().unwrap();
+
+    assert_eq!(report.migrated, NvMigrateOk::Migrated(1, 0));
+    assert_eq!(
+        report.healed,
+        novella_layout::total_slot_num(LAYOUT_CURRENT.sections)
+    );
+    dump
+}
+
+#[test]
+fn blank_dump() {
+    let dump = Dump::blank();
+
+    assert!(matches!(dump.header(), DumpHeader::Headerless));
+    for section in dump.sections().unwrap() {
+        assert_eq!(section.newest, None);
+        assert!(section.slots.iter().all(|x| x.state == SlotState::Erased));
+        assert!(section.data().iter().all(|x| *x == 0));
+    }
+
+    let dump = first_boot();
+    assert!(matches!(dump.header(), DumpHeader::Known(x) if x.version == LAYOUT_CURRENT.version));
+    for section in dump.sections().unwrap() {
+        assert!(section.slots.iter().all(|x| x.state == SlotState::Valid));
+    }
+}
+
+#[test]
+fn set_goes_round_robin() {
+    let mut dump = first_boot();
+    let p1_card_cnt = kind("p1_card_cnt");
+
+    // every slot has same uptime after healing, the last one is newest
+    assert_eq!(section(&dump, "p1_card_cnt").newest, Some(15));
+
+    for (value, slot_idx) in [(1234u32, 0), (1235, 1), (1236, 2)] {
+        assert_eq!(dump.set(p1_card_cnt, &value.to_le_bytes()), Ok(slot_idx));
+
+        let section = section(&dump, "p1_card_cnt");
+        assert_eq!(section.newest, Some(slot_idx));
+        assert_eq!(
+            fields(section.info.type_id, &section.data()),
+            [(String::new(), value.to_string())]
+        );
+    }
+    assert_eq!(dump.uptime(), Ok(3));
+
+    assert_eq!(
+        dump.set(p1_card_cnt, &[0; 3]),
+        Err(DumpError::DataSize(4, 3))
+    );
+}
+
+#[test]
+fn broken_slot_is_healed() {
+    let mut dump = first_boot();
+    dump.set(kind("p2_coin_cnt"), &77u32.to_le_bytes()).unwrap();
+
+    let section_before = section(&dump, "p2_coin_cnt");
+    let addr = section_before.slots[3].addr as usize;
+    let mut raw = *dump.as_bytes();
+    raw[addr + 9] ^= 0x10;
+    let mut dump = Dump::from_bytes(&raw).unwrap();
+
+    let broken = section(&dump, "p2_coin_cnt");
+    assert_eq!(broken.slots[3].state, SlotState::BadChecksum);
+    assert_eq!(broken.newest, Some(0));
+
+    let report = dump.repair().unwrap();
+    assert_eq!(report.migrated, NvMigrateOk::UpToDate);
+    assert_eq!(report.healed, 1);
+
+    let healed = section(&dump, "p2_coin_cnt");
+    assert_eq!(healed.slots[3].state, SlotState::Valid);
+    assert_eq!(healed.slots[3].data, 77u32.to_le_bytes());
+}
+
+#[test]
+fn headerless_install_config_is_migrated() {
+    // development build before layout versioning, 36 bytes install config
+    let mut dump = Dump::blank();
+    let mut config = [0u8; 36];
+    config[16] = 0x01; // free-play
+    config[35] = 2; // shared credit cabinet
+    dump.set(kind("install_config"), &config).unwrap();
+
+    let before = section(&dump, "install_config");
+    assert_eq!(before.info.type_id, type_id::INSTALL_CONFIG_V0);
+    assert!(!fields(before.info.type_id, &before.data())
+        .iter()
+        .any(|(field, _)| field == "language"));
+
+    let report = dump.repair().unwrap();
+    assert!(matches!(report.migrated, NvMigrateOk::Migrated(1, 1)));
+
+    let after = section(&dump, "install_config");
+    assert_eq!(after.info.type_id, type_id::INSTALL_CONFIG);
+    assert_eq!(after.data()[..36], config);
+    assert_eq!(after.data()[36..], [0; 6]);
+
+    let fields = fields(after.info.type_id, &after.data());
+    assert!(fields.contains(&("flags".to_owned(), "0x01".to_owned())));
+    assert!(fields.contains(&("cabinet".to_owned(), "2".to_owned())));
+    assert!(fields.contains(&("language".to_owned(), "0".to_owned())));
+}
+
+#[test]
+fn rejected_dumps() {
+    assert_eq!(
+        Dump::from_bytes(&[0xFF; 256]).err(),
+        Some(DumpError::Size(256))
+    );
+
+    let mut header = NvLayoutHeader::of(&LAYOUT_CURRENT);
+    header.version = 0x7F;
+    let mut raw = *first_boot().as_bytes();
+    raw[HEADER_ADDR as usize..].copy_from_slice(&header.to_bytes());
+
+    let mut dump = Dump::from_bytes(&raw).unwrap();
+    assert!(matches!(dump.header(), DumpHeader::Unknown(x) if x.version == 0x7F));
+    assert_eq!(dump.repair(), Err(DumpError::UnknownLayout(0x7F)));
+    assert_eq!(dump.as_bytes(), &raw);
+}
+
+#[test]
+fn values() {
+    assert_eq!(section_name(kind("service_cnt")), "service_cnt");
+    assert_eq!(section_kind("p3_card_cnt"), None);
+    assert_eq!(section_name(kind("extra_player_cnt")), "extra_player_cnt");
+
+    assert_eq!(
+        parse_value(type_id::PAYOUT_CNT, "0x100"),
+        Some(vec![0, 1, 0, 0])
+    );
+    assert_eq!(parse_value(type_id::PAYOUT_CNT, "-1"), None);
+    assert_eq!(
+        parse_value(type_id::SERVICE_CNT, "0100000002000000"),
+        Some(vec![1, 0, 0, 0, 2, 0, 0, 0])
+    );
+
+    let service = fields(
+        type_id::SERVICE_CNT,
+        &[1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 4, 0, 0, 0],
+    );
+    assert_eq!(service[3], ("players[3]".to_owned(), "4".to_owned()));
+    let extra = fields(
+        type_id::EXTRA_PLAYER_CNT,
+        &[0, 0, 0, 0, 0, 0, 0, 0, 5, 0, 0, 0, 0, 0, 0, 0],
+    );
+    assert_eq!(extra[2], ("coin[0]".to_owned(), "5".to_owned()));
+
+    let terminal = fields(type_id::TERMINAL_ID, b"1234567890\x00\x01A");
+    assert_eq!(terminal[0].1, "\"1234567890\"");
+    assert_eq!(terminal[1].1, "\"\\x00\\x01A\"");
+    assert_eq!(format_uptime(TICK_HZ * 3723), "1:02:03");
+}